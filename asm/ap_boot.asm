@@ -0,0 +1,113 @@
+; ap_boot.asm
+; The trampoline of application processors (AP).
+; AP starts in real mode at the page given by Startup IPI,
+; the kernel copies the code below to TRAMPOLINE_BASE
+; and fills ApParams before sending Startup IPI.
+; The code switches to protected mode, enables paging
+; and calls the kernel entry with processor index on stack
+
+format ELF
+
+TRAMPOLINE_BASE equ 8000h
+;the offset of copied code relative to the linked one
+RELOC equ TRAMPOLINE_BASE - AP_TRAMPOLINE_START
+
+CR0_PROTECTED_MODE equ 1h
+CR0_PAGING equ 80000000h
+CR4_PAE equ 20h
+MSR_EFER equ 0C0000080h
+EFER_NXE equ 800h
+
+;the flags of ApParams.paging
+PAGING_PAE equ 1h
+PAGING_NX equ 2h
+
+;the same selectors as in kernel GDT
+KERNEL_CODE equ 08h
+KERNEL_DATA equ 10h
+
+section '.text' executable
+
+public AP_TRAMPOLINE_START
+public AP_TRAMPOLINE_PARAMS
+public AP_TRAMPOLINE_END
+
+use16
+AP_TRAMPOLINE_START:
+    cli
+    cld
+
+    xor ax, ax
+    mov ds, ax
+
+    lgdt [Gdt.handle + RELOC]
+
+    mov eax, cr0
+    or eax, CR0_PROTECTED_MODE
+    mov cr0, eax
+
+    jmp KERNEL_CODE:ProtectedMode + RELOC
+
+use32
+ProtectedMode:
+    mov ax, KERNEL_DATA
+    mov ds, ax
+    mov es, ax
+    mov fs, ax
+    mov gs, ax
+    mov ss, ax
+
+    ;the paging mode should match the one of bootstrap processor
+    test dword [ApParams.paging + RELOC], PAGING_PAE
+    jz .directory
+
+    mov eax, cr4
+    or eax, CR4_PAE
+    mov cr4, eax
+
+    test dword [ApParams.paging + RELOC], PAGING_NX
+    jz .directory
+
+    mov ecx, MSR_EFER
+    rdmsr
+    or eax, EFER_NXE
+    wrmsr
+
+.directory:
+    ;the page is identity mapped in kernel directory
+    mov eax, dword [ApParams.directory + RELOC]
+    mov cr3, eax
+
+    mov eax, cr0
+    or eax, CR0_PAGING
+    mov cr0, eax
+
+    mov esp, dword [ApParams.stack + RELOC]
+
+    push dword [ApParams.cpu + RELOC]
+    call dword [ApParams.entry + RELOC]
+
+    ;the entry never returns
+.halt:
+    hlt
+    jmp .halt
+
+;the temporary flat GDT until the kernel loads own one
+Gdt.start:
+    dq 0
+    dq 00CF9A000000FFFFh ;code, ring 0
+    dq 00CF92000000FFFFh ;data, ring 0
+Gdt.end:
+
+Gdt.handle:
+    dw Gdt.end - Gdt.start - 1
+    dd Gdt.start + RELOC
+
+;@Declare{struct=ApParams}
+AP_TRAMPOLINE_PARAMS:
+    ApParams.directory dd 0 ;physical address of page directory
+    ApParams.stack dd 0 ;the top of kernel stack
+    ApParams.entry dd 0 ;the kernel entry: fn(cpu: usize) -> !
+    ApParams.cpu dd 0 ;the index of processor
+    ApParams.paging dd 0 ;PAGING_PAE and PAGING_NX flags
+AP_TRAMPOLINE_END:
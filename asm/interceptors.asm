@@ -122,6 +122,110 @@ run_process_routine:
 
     iret
 
+public resume_user_routine
+
+;Input:
+;eax -> user instruction to resume
+;ecx -> user esp
+;edx -> user eflags
+;Notes: the task sees zero in eax (status) and edx
+;as returned from the interrupted syscall
+resume_user_routine:
+    mov bx, (4 shl 3) or 3
+    mov es, bx
+    mov ds, bx
+    mov fs, bx
+    mov gs, bx
+
+    push (4 shl 3) or 3 ;ss
+    push ecx
+    push edx ;eflags
+
+    push (3 shl 3) or 3
+    push eax
+
+    xor eax, eax
+    xor edx, edx
+
+    iret
+
+
+;Input:
+;[esp + 4] -> target address
+;[esp + 8] -> source address
+;[esp + 12] -> count of bytes to copy
+;Output:
+;eax -> zero on success, non-zero if page fault is caught
+;Notes: page fault on user_copy_fault_ip
+;is resumed by page fault handler on user_copy_fixup
+public user_copy
+public user_copy_fault_ip
+public user_copy_fixup
+
+user_copy:
+    push esi edi
+
+    mov edi, dword [esp + 12]
+    mov esi, dword [esp + 16]
+    mov ecx, dword [esp + 20]
+
+    cld
+user_copy_fault_ip:
+    rep movsb
+
+    xor eax, eax
+    pop edi esi
+    ret
+
+user_copy_fixup:
+    mov eax, 1
+    pop edi esi
+    ret
+
+
+public enable_pae_routine
+public ENABLE_PAE_ROUTINE_END
+
+CR0_PAGING equ 80000000h
+CR4_PAE equ 20h
+MSR_EFER equ 0C0000080h
+EFER_NXE equ 800h
+
+;Input:
+;eax -> physical address of page directory pointer table
+;ecx -> non-zero to enable no-execute bit
+;Notes: the routine is called at identity mapped address,
+;no stack is used while paging is off
+enable_pae_routine:
+    pushfd
+    cli
+
+    mov edx, cr0
+    and edx, not CR0_PAGING
+    mov cr0, edx
+
+    mov cr3, eax
+
+    mov eax, cr4
+    or eax, CR4_PAE
+    mov cr4, eax
+
+    jecxz .paging
+
+    mov ecx, MSR_EFER
+    rdmsr
+    or eax, EFER_NXE
+    wrmsr
+
+.paging:
+    mov edx, cr0
+    or edx, CR0_PAGING
+    mov cr0, edx
+
+    popfd
+    ret
+ENABLE_PAE_ROUTINE_END:
+
 
 public breakpoint
 
@@ -1,7 +1,13 @@
 #![allow(unused)]
+use core::{
+    sync::atomic::{AtomicU16, Ordering},
+    time::Duration,
+};
+
 use kernel_lib::{
-    io::{self, IoBatch},
+    io::{self, IoBatch, PortRange},
     object::{KernelBuf, KernelBufMut, UserBufMut},
+    time::Instant,
 };
 
 pub const ATA_PRIMARY: u8 = 0x0;
@@ -10,6 +16,11 @@ pub const ATA_SECONDARY: u8 = 0x01;
 pub const ATA_MASTER: u8 = 0x00;
 pub const ATA_SLAVE: u8 = 0x01;
 
+pub const SECTOR_SIZE: usize = 512;
+
+/// The drive may write back its whole cache on flush
+pub const FLUSH_TIMEOUT: Duration = Duration::from_secs(30);
+
 pub const ATA_PRIMARY_IO: u16 = 0x1F0;
 pub const ATA_SECONDARY_IO: u16 = 0x170;
 
@@ -84,6 +95,50 @@ pub const ATA_REG_DEVADDRESS: u16 = 0x0D;
 pub const ATA_READ: u16 = 0x00;
 pub const ATA_WRITE: u16 = 0x013;
 
+/// The ports of legacy channels requested by driver
+pub const ATA_LEGACY_PORTS: [PortRange; 4] = [
+    PortRange::new(ATA_PRIMARY_IO, 8),
+    PortRange::new(ATA_PRIMARY_DCR_AS, 1),
+    PortRange::new(ATA_SECONDARY_IO, 8),
+    PortRange::new(ATA_SECONDARY_DCR_AS, 1),
+];
+
+/// The command ports of channels. The PCI controller
+/// in native mode moves them from legacy addresses
+static PRIMARY_IO: AtomicU16 = AtomicU16::new(ATA_PRIMARY_IO);
+static SECONDARY_IO: AtomicU16 = AtomicU16::new(ATA_SECONDARY_IO);
+
+/// The alternate status ports of channels
+static PRIMARY_ALTSTATUS: AtomicU16 = AtomicU16::new(ATA_PRIMARY_DCR_AS);
+static SECONDARY_ALTSTATUS: AtomicU16 = AtomicU16::new(ATA_SECONDARY_DCR_AS);
+
+pub fn set_ports(bus: u8, io_base: u16, altstatus: u16) {
+    let (io, alt) = if bus == ATA_PRIMARY {
+        (&PRIMARY_IO, &PRIMARY_ALTSTATUS)
+    } else {
+        (&SECONDARY_IO, &SECONDARY_ALTSTATUS)
+    };
+
+    io.store(io_base, Ordering::Relaxed);
+    alt.store(altstatus, Ordering::Relaxed);
+}
+
+fn io_base(bus: u8) -> u16 {
+    if bus == ATA_PRIMARY {
+        PRIMARY_IO.load(Ordering::Relaxed)
+    } else {
+        SECONDARY_IO.load(Ordering::Relaxed)
+    }
+}
+
+fn altstatus(bus: u8) -> u16 {
+    if bus == ATA_PRIMARY {
+        PRIMARY_ALTSTATUS.load(Ordering::Relaxed)
+    } else {
+        SECONDARY_ALTSTATUS.load(Ordering::Relaxed)
+    }
+}
+
 // pub unsafe fn select_drive(bus: u8, drive: u8) {
 //     if bus == ATA_PRIMARY {
 //         if drive == ATA_MASTER {
@@ -104,11 +159,7 @@ pub fn read_sector(
     lba: u32,
     buffer: &mut KernelBufMut,
 ) -> io::Result<()> {
-    let io_base = if bus == ATA_PRIMARY {
-        ATA_PRIMARY_IO
-    } else {
-        ATA_SECONDARY_IO
-    };
+    let io_base = io_base(bus);
 
     let command = if drive == ATA_MASTER { 0xE0 } else { 0xF0 };
 
@@ -122,29 +173,99 @@ pub fn read_sector(
         .port_u8(io_base + ATA_REG_LBA0, lba as u8)
         .port_u8(io_base + ATA_REG_LBA1, (lba >> 8) as u8)
         .port_u8(io_base + ATA_REG_LBA2, (lba >> 16) as u8)
-        .port_u8(io_base + ATA_REG_LBA3, (lba >> 24) as u8)
         .port_u8(io_base + ATA_REG_COMMAND, ATA_CMD_READ_PIO)
         .commit()?;
 
-    poll(io_base)?;
+    poll(bus)?;
 
     IoBatch::new_read().port_u16_to_buf(io_base + ATA_REG_DATA, buffer)?;
 
-    delay(io_base)?;
+    delay(bus)?;
 
     Ok(())
 }
 
+/// Write one sector of `data`
 pub fn write_sector(
-    _bus: u8,
-    _drive: u8,
-    _lba: u32,
-    _buffer: &KernelBuf,
+    bus: u8,
+    drive: u8,
+    lba: u32,
+    data: &[u8; SECTOR_SIZE],
 ) -> io::Result<()> {
-    todo!()
+    let io_base = io_base(bus);
+
+    let command = if drive == ATA_MASTER { 0xE0 } else { 0xF0 };
+
+    IoBatch::new_write()
+        .port_u8(
+            io_base + ATA_REG_HDDEVSEL,
+            command | (lba >> 24 & 0x0F) as u8,
+        )
+        .port_u8(io_base + 1, 0x00)
+        .port_u8(io_base + ATA_REG_SECCOUNT0, 1)
+        .port_u8(io_base + ATA_REG_LBA0, lba as u8)
+        .port_u8(io_base + ATA_REG_LBA1, (lba >> 8) as u8)
+        .port_u8(io_base + ATA_REG_LBA2, (lba >> 16) as u8)
+        .port_u8(io_base + ATA_REG_COMMAND, ATA_CMD_WRITE_PIO as u8)
+        .commit()?;
+
+    poll(bus)?;
+
+    //the batch holds 32 words
+    let mut batch = IoBatch::new_write();
+
+    for chunk in data.chunks(64) {
+        for word in chunk.chunks(2) {
+            batch.port_u16(
+                io_base + ATA_REG_DATA,
+                u16::from_le_bytes([word[0], word[1]]),
+            );
+        }
+
+        batch.commit()?;
+    }
+
+    delay(bus)?;
+
+    Ok(())
 }
-fn poll(io_base: u16) -> io::Result<()> {
-    delay(io_base)?;
+/// Write the drive cache to the medium
+pub fn flush_cache(bus: u8, drive: u8) -> io::Result<()> {
+    let io_base = io_base(bus);
+
+    let select = if drive == ATA_MASTER { 0xA0 } else { 0xB0 };
+
+    IoBatch::new_write()
+        .port_u8(io_base + ATA_REG_HDDEVSEL, select)
+        .port_u8(io_base + ATA_REG_COMMAND, ATA_CMD_CACHE_FLUSH as u8)
+        .commit()?;
+
+    delay(bus)?;
+
+    let start = Instant::now();
+
+    //the command has no data phase
+    loop {
+        let status = IoBatch::new_read().port_u8(io_base + ATA_REG_STATUS)?;
+
+        if (status & ATA_SR_BSY) == 0 {
+            if (status & ATA_SR_ERR) != 0 {
+                return Err(io::IoError::DeviceFailure);
+            }
+
+            return Ok(());
+        }
+
+        if start.elapsed() > FLUSH_TIMEOUT {
+            return Err(io::IoError::Timeout);
+        }
+    }
+}
+
+fn poll(bus: u8) -> io::Result<()> {
+    let io_base = io_base(bus);
+
+    delay(bus)?;
 
     loop {
         let status = IoBatch::new_read().port_u8(io_base + ATA_REG_STATUS)?;
@@ -168,8 +289,8 @@ fn poll(io_base: u16) -> io::Result<()> {
 
     Ok(())
 }
-fn delay(io_base: u16) -> io::Result<()> {
-    let _ = IoBatch::new_read().port_u8(io_base + ATA_REG_ALTSTATUS)?;
+fn delay(bus: u8) -> io::Result<()> {
+    let _ = IoBatch::new_read().port_u8(altstatus(bus))?;
 
     Ok(())
 }
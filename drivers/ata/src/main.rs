@@ -4,9 +4,11 @@
 mod ide;
 
 use kernel_lib::{
+    drivers::pci::{PciBar, PciDeviceInfo, PciMatch},
     io::{
         self,
         block::{self, BlockDeviceInfo},
+        PortRange,
     },
     object::{KernelBufMut, UserBuf},
     KernelModule, ModuleError,
@@ -14,6 +16,16 @@ use kernel_lib::{
 
 const DEVICE_NAME: &str = "ata";
 
+const MASS_STORAGE: u8 = 0x01;
+const IDE_CONTROLLER: u8 = 0x01;
+
+/// The bits of programming interface: the channel is in native mode
+const PRIMARY_NATIVE: u8 = 1;
+const SECONDARY_NATIVE: u8 = 1 << 2;
+
+/// The alternate status in the control block of native channel
+const NATIVE_ALTSTATUS: u16 = 2;
+
 kernel_lib::module! {
     module: AtaDriver,
     name: "ata-disk",
@@ -21,34 +33,103 @@ kernel_lib::module! {
 
 pub struct AtaDriver;
 
-fn handle_read(sector: u32, mut buffer: KernelBufMut) -> io::Result<()> {
+/// The master and slave drives of both buses
+const DISKS_COUNT: usize = 4;
+
+/// The disk number of request: master and slave of primary bus,
+/// then the ones of secondary bus
+fn disk_drive(disk: usize) -> io::Result<(u8, u8)> {
+    match disk {
+        0 => Ok((ide::ATA_PRIMARY, ide::ATA_MASTER)),
+        1 => Ok((ide::ATA_PRIMARY, ide::ATA_SLAVE)),
+        2 => Ok((ide::ATA_SECONDARY, ide::ATA_MASTER)),
+        3 => Ok((ide::ATA_SECONDARY, ide::ATA_SLAVE)),
+        _ => Err(io::IoError::NotSupported),
+    }
+}
+
+fn handle_read(
+    disk: usize,
+    sector: u32,
+    mut buffer: KernelBufMut,
+) -> io::Result<()> {
     assert!(buffer.remaining_capacity() % 512 == 0);
 
+    let (bus, drive) = disk_drive(disk)?;
+
     let sector_count = buffer.remaining_capacity() / 512;
 
     for i in 0..sector_count {
         let sector = sector + i as u32;
-        ide::read_sector(0, 0, sector, &mut buffer)?;
+        ide::read_sector(bus, drive, sector, &mut buffer)?;
         // buffer.flush()?;
     }
 
     Ok(())
 }
 
-fn handle_write(_sector: u32, _buf: UserBuf) -> io::Result<()> {
-    todo!()
+fn handle_write(disk: usize, sector: u32, buf: UserBuf) -> io::Result<()> {
+    let (bus, drive) = disk_drive(disk)?;
+
+    for (i, chunk) in buf.as_slice().chunks(ide::SECTOR_SIZE).enumerate() {
+        //the tail of the last sector is zeroed
+        let mut data = [0u8; ide::SECTOR_SIZE];
+        data[..chunk.len()].copy_from_slice(chunk);
+
+        ide::write_sector(bus, drive, sector + i as u32, &data)?;
+    }
+
+    Ok(())
 }
 
-fn ioctl(_cmd: u32) -> io::Result<()> {
-    Err(io::IoError::NotSupported)
+fn ioctl(disk: usize, cmd: u32) -> io::Result<()> {
+    let (bus, drive) = disk_drive(disk)?;
+
+    match cmd {
+        block::CMD_FLUSH => ide::flush_cache(bus, drive),
+        _ => Err(io::IoError::NotSupported),
+    }
 }
 
 impl KernelModule for AtaDriver {
+    const PCI_DEVICES: &'static [PciMatch] =
+        &[PciMatch::class(MASS_STORAGE, IDE_CONTROLLER)];
+
+    const IO_PORTS: &'static [PortRange] = &ide::ATA_LEGACY_PORTS;
+
+    fn probe(device: PciDeviceInfo) -> Result<Self, ModuleError> {
+        let bars = device.bars;
+
+        let channels = [
+            (ide::ATA_PRIMARY, PRIMARY_NATIVE, bars[0], bars[1]),
+            (ide::ATA_SECONDARY, SECONDARY_NATIVE, bars[2], bars[3]),
+        ];
+
+        //the channel in compatibility mode uses legacy ports,
+        //the ports of native channel are granted by kernel on bind
+        for (bus, native, command, control) in channels {
+            if device.prog_if & native == 0 {
+                continue;
+            }
+
+            if let (
+                PciBar::Io { port: io_base, .. },
+                PciBar::Io { port: control, .. },
+            ) = (command, control)
+            {
+                ide::set_ports(bus, io_base, control + NATIVE_ALTSTATUS);
+            }
+        }
+
+        Self::init()
+    }
+
     fn init() -> Result<Self, ModuleError> {
         let _ = block::register_device(BlockDeviceInfo {
             name: DEVICE_NAME.into(),
             sector_size: 512,
             queue_size: 10,
+            disks: DISKS_COUNT,
         });
 
         log::info!("Ata driver is initialized");
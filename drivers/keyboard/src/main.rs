@@ -29,6 +29,8 @@ module! {
 }
 
 impl KernelModule for KeyboardDriver {
+    const IO_PORTS: &'static [io::PortRange] = &ps::PS2_PORTS;
+
     fn init() -> Result<Self, ModuleError> {
         let context = Box::<DriverContextLock>::try_new_uninit()?;
 
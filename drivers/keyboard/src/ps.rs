@@ -1,4 +1,11 @@
-use kernel_lib::io::{self, IoBatch};
+use kernel_lib::io::{self, IoBatch, PortRange};
+
+const PS2_DATA: u16 = 0x60;
+const PS2_STATUS: u16 = 0x64;
+
+/// The data and status/command ports of controller
+pub const PS2_PORTS: [PortRange; 2] =
+    [PortRange::new(PS2_DATA, 1), PortRange::new(PS2_STATUS, 1)];
 
 static QWERTYUIOP: &[u8] = b"qwertzuiop";
 static YXCVBNM: &[u8] = b"yxcvbnm";
@@ -12,7 +19,7 @@ const ONE_PRESSED: u8 = 0x2;
 const NINE_PRESSED: u8 = 0xA;
 
 pub fn read_scan_code() -> io::Result<Option<char>> {
-    let key = IoBatch::new_read().port_u8(0x60)?;
+    let key = IoBatch::new_read().port_u8(PS2_DATA)?;
 
     log::trace!("KEY TO FETCH: 0x{key:x}");
 
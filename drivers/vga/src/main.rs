@@ -24,6 +24,10 @@ const VGA_WIDTH: usize = 80;
 const VGA_HEIGHT: usize = 25;
 
 const VGA_BUFFER_OFFSET: usize = 0xB8_000;
+
+/// The index and data registers of CRT controller
+const CRTC_INDEX: u16 = 0x3D4;
+const CRTC_DATA: u16 = 0x3D5;
 const VGA_COLOR_BLACK: u8 = 0;
 const VGA_COLOR_WHITE: u8 = 0xF;
 const VGA_DEFAULT_COLOR: u8 = VGA_COLOR_WHITE | (VGA_COLOR_BLACK << 4);
@@ -90,10 +94,10 @@ impl VgaWriter {
         let pos = self.cursor_y * VGA_WIDTH + self.cursor_x;
 
         IoBatch::new_write()
-            .port_u8(0x3D4, 0x0F)
-            .port_u8(0x3D5, pos as u8)
-            .port_u8(0x3D4, 0x0E)
-            .port_u8(0x3D5, (pos >> 8) as u8)
+            .port_u8(CRTC_INDEX, 0x0F)
+            .port_u8(CRTC_DATA, pos as u8)
+            .port_u8(CRTC_INDEX, 0x0E)
+            .port_u8(CRTC_DATA, (pos >> 8) as u8)
             .commit()
             .expect("Failed to update cursor pos")
     }
@@ -152,6 +156,9 @@ pub fn ioctl(file: File, cmd: u32) -> fs::Result<()> {
 }
 
 impl KernelModule for VgaDriver {
+    const IO_PORTS: &'static [io::PortRange] =
+        &[io::PortRange::new(CRTC_INDEX, 2)];
+
     fn init() -> Result<Self, ModuleError> {
         let offset = unsafe { VGA_BUFFER.buffer.as_ptr() };
 
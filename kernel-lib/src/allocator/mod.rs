@@ -1,12 +1,47 @@
-use talc::{ClaimOnOom, Span, Talc, Talck};
+use core::alloc::Layout;
+
+use talc::{OomHandler, Span, Talc, Talck};
+
+use crate::memory::{self, Protection, PAGE_SIZE};
 
 #[cfg(feature = "driver")]
 mod driver;
 
+/// The least memory claimed from kernel at once
+const MIN_CLAIM_SIZE: usize = 16 * PAGE_SIZE;
+/// The allocator metadata placed in each claimed span
+const CLAIM_OVERHEAD: usize = 64;
+
 static mut ARENA: [u8; 10_000] = [0; 10_000];
 
-#[global_allocator]
-static ALLOCATOR: Talck<spin::Mutex<()>, ClaimOnOom> =
-    Talc::new(unsafe { ClaimOnOom::new(Span::from_array(&raw mut ARENA)) })
-        .lock();
+/// Claim the static arena first, then map anonymous memory
+/// each time the heap is exhausted
+pub struct ClaimFromKernel {
+    arena: Span,
+}
+
+impl OomHandler for ClaimFromKernel {
+    fn handle_oom(talc: &mut Talc<Self>, layout: Layout) -> Result<(), ()> {
+        let arena =
+            core::mem::replace(&mut talc.oom_handler.arena, Span::empty());
 
+        if !arena.is_empty() && unsafe { talc.claim(arena) }.is_ok() {
+            return Ok(());
+        }
+
+        let size = layout.size() + layout.align() + CLAIM_OVERHEAD;
+        let size = size.max(MIN_CLAIM_SIZE).next_multiple_of(PAGE_SIZE);
+
+        let base = memory::map_anonymous(size, Protection::READ_WRITE)
+            .map_err(|cause| log::error!("Failed to extend heap: {cause:?}"))?;
+
+        unsafe { talc.claim(Span::from_base_size(base, size)) }.map(|_| ())
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Talck<spin::Mutex<()>, ClaimFromKernel> =
+    Talc::new(ClaimFromKernel {
+        arena: Span::from_array(&raw mut ARENA),
+    })
+    .lock();
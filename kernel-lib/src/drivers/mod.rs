@@ -1,3 +1,5 @@
+pub mod pci;
+
 use kernel_types::{
     fs::{FileRequest, FsRequest},
     io::block,
@@ -0,0 +1,29 @@
+use kernel_types::{syscall, syscall::SyscallError};
+
+pub use kernel_types::drivers::pci::{
+    PciBar, PciDeviceInfo, PciMatch, RawPciMatch, PCI_BARS_COUNT,
+};
+
+/// Take the first unbound device matching any of `patterns`.
+/// The patterns are tried in order
+pub fn bind(patterns: &[PciMatch]) -> Result<PciDeviceInfo, SyscallError> {
+    for pattern in patterns {
+        let mut device = PciDeviceInfo::default();
+
+        let status = unsafe {
+            syscall! {
+                syscall::Request::PciBind,
+                ecx: &mut device,
+                edx: &RawPciMatch::from(pattern)
+            }
+        };
+
+        match status {
+            Ok(()) => return Ok(device),
+            Err(SyscallError::DeviceIsNotFound) => continue,
+            Err(cause) => return Err(cause),
+        }
+    }
+
+    Err(SyscallError::DeviceIsNotFound)
+}
@@ -6,7 +6,7 @@ use core::mem::MaybeUninit;
 pub use index_node::*;
 pub use kernel_types::fs::*;
 use kernel_types::{
-    object::{KernelObject, OpStatus, RawHandle},
+    object::{AnyObjectInfo, KernelObject, OpStatus, RawHandle},
     syscall,
 };
 
@@ -110,7 +110,7 @@ impl File {
 
 impl From<RawHandle> for File {
     fn from(value: RawHandle) -> Self {
-        let mut file_info = MaybeUninit::<FileInfo>::uninit();
+        let mut file_info = MaybeUninit::<AnyObjectInfo>::uninit();
 
         unsafe {
             syscall! {
@@ -121,7 +121,10 @@ impl From<RawHandle> for File {
             .unwrap();
         }
 
-        let file = unsafe { file_info.assume_init() };
+        let AnyObjectInfo::File(file) = (unsafe { file_info.assume_init() })
+        else {
+            panic!("The handle isn't file");
+        };
 
         Self {
             handle: value,
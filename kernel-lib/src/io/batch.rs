@@ -1,9 +1,12 @@
-use kernel_types::{
-    io::{IoOperation, PortOperation},
-    syscall,
+use kernel_types::io::{IoOperation, PortOperation};
+
+use crate::{
+    io::{self, port, IoError},
+    object::KernelBufMut,
 };
 
-use crate::{io, object::KernelBufMut};
+/// The bytes read from port before they're copied to kernel buffer
+const CHUNK_SIZE: usize = 512;
 
 #[repr(C)]
 #[must_use]
@@ -21,15 +24,39 @@ impl IoKind for Read {}
 impl IoKind for Write {}
 
 impl<const N: usize, K: IoKind> IoBatch<K, N> {
+    /// Execute operations in order. The ports are accessed
+    /// directly, thus they must be granted to module
     pub fn commit(&mut self) -> io::Result<()> {
-        unsafe {
-            syscall!(
-                syscall::Request::IoOperation,
-                ecx: self.ops.len(),
-                edx: self.ops.as_ptr(),
-            )?;
+        for op in self.ops.iter() {
+            let IoOperation::PortOperation(op) = op else {
+                return Err(IoError::NotSupported);
+            };
+
+            unsafe {
+                match *op {
+                    PortOperation::WriteByte { port, value } => {
+                        port::outb(port, value)
+                    }
+                    PortOperation::WriteWord { port, value } => {
+                        port::outw(port, value)
+                    }
+                    PortOperation::ReadByte { port, value } => {
+                        value.write_volatile(port::inb(port))
+                    }
+                    PortOperation::ReadWord { port, value } => {
+                        value.write_volatile(port::inw(port))
+                    }
+                    //the kernel buffer is filled by `*_to_buf` methods
+                    PortOperation::ReadBytesToBuf { .. }
+                    | PortOperation::ReadWordsToBuf { .. } => {
+                        return Err(IoError::NotSupported)
+                    }
+                }
+            }
         }
 
+        self.ops.clear();
+
         Ok(())
     }
 }
@@ -80,44 +107,31 @@ impl<const N: usize> IoBatch<Read, N> {
         Ok(v)
     }
 
+    /// Fill the remaining capacity of `buf` with bytes read from `port`
     pub fn port_u8_to_buf(
         &mut self,
         port: u16,
         buf: &mut KernelBufMut,
     ) -> io::Result<()> {
-        self.ops
-            .push(
-                PortOperation::ReadBytesToBuf {
-                    port,
-                    buf: unsafe { buf.handle().syscall() },
-                }
-                .into(),
-            )
-            .unwrap();
-
         self.commit()?;
 
-        Ok(())
+        read_to_buf(buf, 1, |bytes| {
+            bytes[0] = unsafe { port::inb(port) };
+        })
     }
 
+    /// Fill the remaining capacity of `buf` with words read from `port`
     pub fn port_u16_to_buf(
         &mut self,
         port: u16,
         buf: &mut KernelBufMut,
     ) -> io::Result<()> {
-        self.ops
-            .push(
-                PortOperation::ReadWordsToBuf {
-                    port,
-                    buf: unsafe { buf.handle().syscall() },
-                }
-                .into(),
-            )
-            .unwrap();
-
         self.commit()?;
 
-        Ok(())
+        read_to_buf(buf, 2, |bytes| {
+            let word = unsafe { port::inw(port) };
+            bytes.copy_from_slice(&word.to_le_bytes());
+        })
     }
 }
 
@@ -147,3 +161,24 @@ impl<const N: usize> IoBatch<Write, N> {
         self
     }
 }
+
+fn read_to_buf(
+    buf: &mut KernelBufMut,
+    width: usize,
+    mut read: impl FnMut(&mut [u8]),
+) -> io::Result<()> {
+    let mut chunk = [0u8; CHUNK_SIZE];
+    let mut remaining = buf.remaining_capacity() / width * width;
+
+    while remaining > 0 {
+        let len = usize::min(remaining, chunk.len());
+
+        chunk[..len].chunks_exact_mut(width).for_each(&mut read);
+
+        buf.write(&chunk[..len])?;
+
+        remaining -= len;
+    }
+
+    Ok(())
+}
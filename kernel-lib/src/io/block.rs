@@ -6,9 +6,9 @@ use kernel_types::{object::RawHandle, syscall};
 //separately
 #[derive(Debug, Clone)]
 pub struct Operations {
-    pub read: fn(sector: u32, buf: KernelBufMut) -> Result<()>,
-    pub write: fn(sector: u32, buf: UserBuf) -> Result<()>,
-    pub ioctl: fn(u32) -> Result<()>,
+    pub read: fn(disk: usize, sector: u32, buf: KernelBufMut) -> Result<()>,
+    pub write: fn(disk: usize, sector: u32, buf: UserBuf) -> Result<()>,
+    pub ioctl: fn(disk: usize, cmd: u32) -> Result<()>,
 }
 
 use crate::{
@@ -20,7 +20,7 @@ pub fn register_device(device: BlockDeviceInfo) -> Result<()> {
     unsafe {
         syscall! {
             syscall::Request::RegBlockDevice,
-            edx: &device
+            edx: &RawBlockDeviceInfo::from(&device)
         }?;
     }
     Ok(())
@@ -5,7 +5,7 @@ pub fn register_module(device: CharModuleInfo) -> syscall::Result<()> {
     unsafe {
         syscall! {
             syscall::Request::RegCharDevice,
-            edx: &device
+            edx: &RawCharModuleInfo::from(&device)
         }
     }
 }
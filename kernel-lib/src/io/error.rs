@@ -9,6 +9,10 @@ use kernel_types::{object::OpStatus, syscall::SyscallError};
 pub enum IoError {
     #[error("Not Supported Operation")]
     NotSupported,
+    #[error("Device reports failure")]
+    DeviceFailure,
+    #[error("Device is not responding")]
+    Timeout,
     #[error("Syscall is failed: {0:?}")]
     SyscallFailed(#[from] SyscallError),
 }
@@ -17,6 +21,8 @@ impl From<IoError> for OpStatus {
     fn from(value: IoError) -> Self {
         match value {
             IoError::NotSupported => OpStatus::NotSupported,
+            IoError::DeviceFailure => OpStatus::Failed,
+            IoError::Timeout => OpStatus::Failed,
             IoError::SyscallFailed(_) => OpStatus::Failed,
         }
     }
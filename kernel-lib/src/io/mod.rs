@@ -2,11 +2,15 @@ mod batch;
 pub mod block;
 pub mod char;
 mod error;
+pub mod port;
 pub mod spin;
 
 pub use batch::*;
 pub use kernel_types::io::op::*;
-pub use kernel_types::io::{IrqMessage, IrqHandler, MemoryRemap};
+pub use kernel_types::io::{
+    IrqHandler, IrqMessage, MemoryRemap, PortRange, PortsDeclaration,
+    RawIrqHandler,
+};
 use kernel_types::object::{Queue, RawHandle};
 use kernel_types::syscall;
 
@@ -23,7 +27,7 @@ pub fn set_irq(line: u8, hook: Option<IoOperation>) -> Result<Queue<IrqMessage>>
         syscall!(
             syscall::Request::SetIrqHandler,
             ecx: &mut queue,
-            edx: &handler,
+            edx: &RawIrqHandler::from(&handler),
         )?;
     }
 
@@ -34,6 +38,24 @@ pub fn set_irq(line: u8, hook: Option<IoOperation>) -> Result<Queue<IrqMessage>>
     Ok(queue.into())
 }
 
+/// Allow module to execute `in`/`out` on `ranges`.
+/// The ports must not be owned by another module
+pub fn request_ports(ranges: &[PortRange]) -> Result<()> {
+    if ranges.is_empty() {
+        return Ok(());
+    }
+
+    unsafe {
+        syscall!(
+            syscall::Request::RequestIoPorts,
+            ecx: ranges.len(),
+            edx: ranges.as_ptr(),
+        )?;
+    }
+
+    Ok(())
+}
+
 pub fn remap(
     physical_memory: usize,
     virtual_memory: *mut u8,
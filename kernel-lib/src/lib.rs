@@ -17,11 +17,14 @@ extern crate alloc;
 mod allocator;
 
 pub mod drivers;
+pub mod memory;
 pub mod object;
+pub mod power;
 pub mod process;
 mod rt;
 pub mod string;
 pub mod task;
+pub mod time;
 
 #[cfg(not(test))]
 #[panic_handler]
@@ -37,11 +40,31 @@ macro_rules! module {
         name: $name:literal$(,)?
     ) => {
         static mut MODULE: Option<$ty> = None;
+
+        /// Read by kernel on load, the module can't request other ports
+        #[export_name = "io_ports"]
+        static IO_PORTS: $crate::io::PortsDeclaration =
+            $crate::io::PortsDeclaration::new(
+                <$ty as $crate::KernelModule>::IO_PORTS,
+            );
+
         #[export_name = "init"]
         extern "C" fn init() {
             $crate::logging::init().unwrap();
 
-            match $ty::init() {
+            let ports = <$ty as $crate::KernelModule>::IO_PORTS;
+            let devices = <$ty as $crate::KernelModule>::PCI_DEVICES;
+
+            //the legacy resources are used if no device is found
+            let module = match $crate::io::request_ports(ports) {
+                Ok(()) => match $crate::drivers::pci::bind(devices) {
+                    Ok(device) => $ty::probe(device),
+                    Err(_) => $ty::init(),
+                },
+                Err(cause) => Err(cause.into()),
+            };
+
+            match module {
                 Ok(data) => {
                     unsafe { MODULE = Some(data) };
                     $crate::complete($ty::ops());
@@ -54,8 +77,9 @@ macro_rules! module {
             }
         }
 
+        /// Invoked by kernel in a new task before shutdown
         #[export_name = "exit"]
-        extern "C" fn exit() {
+        extern "C" fn exit(_args: *const ()) {
             let module = unsafe { MODULE.take() };
 
             if core::mem::needs_drop::<$ty>() {
@@ -63,6 +87,8 @@ macro_rules! module {
                     let _ = module;
                 }
             }
+
+            $crate::task::terminate(0);
         }
     };
 }
@@ -101,6 +127,21 @@ pub fn complete(ops: ModuleOperations) -> ! {
 }
 
 pub trait KernelModule: Sized {
+    /// The PCI devices served by module
+    const PCI_DEVICES: &'static [drivers::pci::PciMatch] = &[];
+
+    /// The legacy ports driven by module. The I/O BARs
+    /// of bound PCI device are granted by kernel
+    const IO_PORTS: &'static [io::PortRange] = &[];
+
     fn init() -> Result<Self, ModuleError>;
+
+    /// Invoked instead of `init` when one of `PCI_DEVICES` is bound
+    fn probe(
+        _device: drivers::pci::PciDeviceInfo,
+    ) -> Result<Self, ModuleError> {
+        Self::init()
+    }
+
     fn ops() -> ModuleOperations;
 }
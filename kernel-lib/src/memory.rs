@@ -0,0 +1,84 @@
+use kernel_types::{
+    memory::{AnonymousMap, FileMap, ProtectRange},
+    syscall,
+    syscall::SyscallError,
+};
+
+pub use kernel_types::memory::{MapFlags, Protection};
+
+pub const PAGE_SIZE: usize = 4096;
+
+/// Map `len` bytes of zeroed memory (rounded up to pages)
+pub fn map_anonymous(
+    len: usize,
+    protection: Protection,
+) -> Result<*mut u8, SyscallError> {
+    let map = AnonymousMap { len, protection };
+    let mut address: usize = 0;
+
+    unsafe {
+        syscall! {
+            syscall::Request::MapAnonymous,
+            ecx: &mut address,
+            edx: &map
+        }?;
+    }
+
+    Ok(address as *mut u8)
+}
+
+/// Map `len` bytes of file opened by task at index `file`.
+/// `offset` should be aligned to pages, the pages are read on the first access
+pub fn map_file(
+    file: usize,
+    offset: usize,
+    len: usize,
+    protection: Protection,
+    flags: MapFlags,
+) -> Result<*mut u8, SyscallError> {
+    let map = FileMap {
+        file,
+        offset,
+        len,
+        protection,
+        flags,
+    };
+    let mut address: usize = 0;
+
+    unsafe {
+        syscall! {
+            syscall::Request::MapFile,
+            ecx: &mut address,
+            edx: &map
+        }?;
+    }
+
+    Ok(address as *mut u8)
+}
+
+/// Change the access to `len` bytes (rounded up to pages) at page aligned
+/// `address`. The range should lie in one mapping
+pub unsafe fn protect(
+    address: *mut u8,
+    len: usize,
+    protection: Protection,
+) -> Result<(), SyscallError> {
+    let range = ProtectRange {
+        address: address as usize,
+        len,
+        protection,
+    };
+
+    syscall! {
+        syscall::Request::Protect,
+        edx: &range
+    }
+}
+
+/// Return the whole mapping starting at `address` to kernel
+pub unsafe fn unmap(address: *mut u8) -> Result<(), SyscallError> {
+    syscall! {
+        syscall::Request::Unmap,
+        edx: address as usize
+    }
+}
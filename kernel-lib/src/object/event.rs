@@ -48,6 +48,10 @@ impl Event {
         }
     }
 
+    pub fn handle(&self) -> &RawHandle {
+        &self.handle
+    }
+
     pub fn try_clone(&self) -> syscall::Result<Self> {
         Ok(Self {
             handle: self.handle.try_clone()?,
@@ -1,6 +1,10 @@
 use core::mem::MaybeUninit;
 
-use kernel_types::{io::MemBuf, object::RawHandle, syscall};
+use kernel_types::{
+    io::MemBuf,
+    object::{AnyObjectInfo, RawHandle},
+    syscall,
+};
 
 use super::UserBuf;
 
@@ -10,6 +14,8 @@ pub struct KernelBuf {
     pub len: usize,
     pub capacity: usize,
     pub handle: RawHandle,
+    //buffer pages lent by kernel, null when buffer should be copied
+    pub ptr: *mut u8,
 }
 
 #[derive(Debug)]
@@ -18,11 +24,13 @@ pub struct KernelBufMut {
     pub len: usize,
     pub capacity: usize,
     pub handle: RawHandle,
+    pub ptr: *mut u8,
+    written: usize,
 }
 
 impl From<RawHandle> for KernelBuf {
     fn from(value: RawHandle) -> Self {
-        let mut buf_info = MaybeUninit::<MemBuf>::uninit();
+        let mut buf_info = MaybeUninit::<AnyObjectInfo>::uninit();
 
         unsafe {
             syscall!(
@@ -33,12 +41,16 @@ impl From<RawHandle> for KernelBuf {
             .unwrap();
         }
 
-        let buf_info = unsafe { buf_info.assume_init() };
+        let AnyObjectInfo::Buf(buf_info) = (unsafe { buf_info.assume_init() })
+        else {
+            panic!("The handle isn't kernel buffer");
+        };
 
         Self {
             len: buf_info.len,
             capacity: buf_info.capacity,
             handle: value,
+            ptr: buf_info.ptr,
         }
     }
 }
@@ -57,10 +69,36 @@ impl KernelBuf {
         self.len() == 0
     }
 
+    pub fn is_lent(&self) -> bool {
+        !self.ptr.is_null()
+    }
+
+    /// The buffer content if kernel pages are mapped to the module
+    pub fn as_slice(&self) -> Option<&[u8]> {
+        self.is_lent()
+            .then(|| unsafe { core::slice::from_raw_parts(self.ptr, self.len) })
+    }
+
     pub fn copy_to(
         &self,
         buf: &mut UserBuf,
     ) -> Result<(), syscall::SyscallError> {
+        if let Some(bytes) = self.as_slice() {
+            let len = usize::min(bytes.len(), buf.capacity());
+
+            unsafe {
+                core::ptr::copy_nonoverlapping(
+                    bytes.as_ptr(),
+                    buf.as_mut_ptr(),
+                    len,
+                );
+
+                buf.set_len(len);
+            }
+
+            return Ok(());
+        }
+
         let mem_buf = MemBuf {
             len: 0,
             capacity: buf.capacity(),
@@ -89,6 +127,8 @@ impl From<RawHandle> for KernelBufMut {
             len: buf.len,
             capacity: buf.capacity,
             handle: buf.handle,
+            ptr: buf.ptr,
+            written: buf.len,
         }
     }
 }
@@ -105,10 +145,27 @@ impl KernelBufMut {
     pub fn write(&mut self, bytes: &[u8]) -> Result<(), syscall::SyscallError> {
         assert!(bytes.len() <= self.remaining_capacity());
 
+        //the kernel only accounts bytes written to lent pages
+        let ptr = if self.ptr.is_null() {
+            bytes.as_ptr() as *mut u8
+        } else {
+            unsafe {
+                let ptr = self.ptr.add(self.written);
+
+                core::ptr::copy_nonoverlapping(
+                    bytes.as_ptr(),
+                    ptr,
+                    bytes.len(),
+                );
+
+                ptr
+            }
+        };
+
         let buf = MemBuf {
             len: bytes.len(),
             capacity: bytes.len(),
-            ptr: bytes.as_ptr() as *mut u8,
+            ptr,
         };
 
         unsafe {
@@ -120,6 +177,7 @@ impl KernelBufMut {
         }
 
         self.len -= bytes.len();
+        self.written += bytes.len();
 
         Ok(())
     }
@@ -15,6 +15,9 @@ pub struct UserBufMut {
     pushed_count: usize,
     buf: alloc::vec::Vec<u8>,
     handle: RawHandle,
+    //kernel pages mapped to module, bytes are pushed there directly
+    lent: *mut u8,
+    flushed_count: usize,
 }
 
 impl UserBufMut {
@@ -28,6 +31,15 @@ impl UserBufMut {
     }
 
     pub fn push(&mut self, v: u8) {
+        if !self.lent.is_null() {
+            assert!(self.pushed_count < self.k_buf_capacity);
+
+            unsafe { self.lent.add(self.pushed_count).write(v) };
+            self.pushed_count += 1;
+
+            return;
+        }
+
         self.pushed_count += 1;
         self.buf.push(v);
 
@@ -37,6 +49,10 @@ impl UserBufMut {
     pub fn flush(&mut self) -> syscall::Result<()> {
         assert!(self.pushed_count <= self.k_buf_capacity);
 
+        if !self.lent.is_null() {
+            return self.flush_lent();
+        }
+
         if self.buf.is_empty() {
             return Ok(());
         }
@@ -61,16 +77,48 @@ impl UserBufMut {
 
         Ok(())
     }
+
+    fn flush_lent(&mut self) -> syscall::Result<()> {
+        if self.flushed_count == self.pushed_count {
+            return Ok(());
+        }
+
+        let mem_buf = MemBuf {
+            ptr: unsafe { self.lent.add(self.flushed_count) },
+            len: self.pushed_count - self.flushed_count,
+            capacity: 0,
+        };
+
+        unsafe {
+            syscall! {
+                syscall::Request::UserCopy,
+                ecx: &mem_buf,
+                edx: self.handle.syscall()
+            }?;
+        }
+
+        self.flushed_count = self.pushed_count;
+
+        Ok(())
+    }
 }
 
 impl From<RawHandle> for UserBufMut {
     fn from(value: RawHandle) -> Self {
         let kernel_buf = KernelBuf::from(value);
 
+        let buf_capacity = if kernel_buf.is_lent() {
+            0
+        } else {
+            kernel_buf.capacity
+        };
+
         Self {
             pushed_count: 0,
             k_buf_capacity: kernel_buf.capacity,
-            buf: Vec::with_capacity(kernel_buf.capacity),
+            buf: Vec::with_capacity(buf_capacity),
+            lent: kernel_buf.ptr,
+            flushed_count: 0,
             handle: kernel_buf.handle,
         }
     }
@@ -1,8 +1,10 @@
 mod event;
 mod kernel_buf;
 mod mutex;
+mod timer;
 
 pub use event::*;
 pub use kernel_buf::*;
 pub use kernel_types::object::*;
 pub use mutex::*;
+pub use timer::*;
@@ -0,0 +1,72 @@
+use core::time::Duration;
+
+use kernel_types::{
+    object::RawHandle,
+    syscall,
+    time::{TimeSpec, TimerSpec},
+};
+
+use super::Event;
+
+/// The kernel timer notifying the event on each fire
+#[derive(Debug)]
+pub struct Timer {
+    handle: RawHandle,
+}
+
+impl Timer {
+    pub fn new(event: &Event) -> syscall::Result<Self> {
+        let mut handle: usize = 0;
+
+        unsafe {
+            syscall! {
+                syscall::Request::TimerNew,
+                ecx: &mut handle,
+                edx: event.handle().syscall(),
+            }?;
+
+            Ok(RawHandle::new_unchecked(handle).into())
+        }
+    }
+
+    /// Fire once after `delay`
+    pub fn one_shot(&self, delay: Duration) -> syscall::Result<()> {
+        self.start(delay, Duration::ZERO)
+    }
+
+    /// Fire after `delay` and then each `period`.
+    /// Zero period means one-shot timer
+    pub fn start(
+        &self,
+        delay: Duration,
+        period: Duration,
+    ) -> syscall::Result<()> {
+        let spec = TimerSpec {
+            delay: TimeSpec::from(delay),
+            period: TimeSpec::from(period),
+        };
+
+        unsafe {
+            syscall! {
+                syscall::Request::TimerStart,
+                ecx: &spec,
+                edx: self.handle.syscall(),
+            }
+        }
+    }
+
+    pub fn cancel(&self) -> syscall::Result<()> {
+        unsafe {
+            syscall! {
+                syscall::Request::TimerCancel,
+                edx: self.handle.syscall()
+            }
+        }
+    }
+}
+
+impl From<RawHandle> for Timer {
+    fn from(value: RawHandle) -> Self {
+        Self { handle: value }
+    }
+}
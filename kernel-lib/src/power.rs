@@ -0,0 +1,17 @@
+use kernel_types::{syscall, syscall::SyscallError};
+
+/// Stop modules, sync file systems and power off the machine.
+/// Return only if the kernel rejects the request
+pub fn shutdown() -> SyscallError {
+    let status = unsafe { syscall!(syscall::Request::Shutdown) };
+
+    status.err().unwrap_or(SyscallError::Failed)
+}
+
+/// Stop modules, sync file systems and reset the machine.
+/// Return only if the kernel rejects the request
+pub fn reboot() -> SyscallError {
+    let status = unsafe { syscall!(syscall::Request::Reboot) };
+
+    status.err().unwrap_or(SyscallError::Failed)
+}
@@ -1,4 +1,4 @@
-use kernel_types::syscall;
+use kernel_types::syscall::{self, SyscallError};
 
 pub fn exit(code: i32) -> ! {
     log::info!("Terminating with code: {code}");
@@ -12,3 +12,35 @@ pub fn exit(code: i32) -> ! {
         }
     }
 }
+
+/// Clone the calling task into new process sharing memory copy-on-write.
+/// The parent gets id of child process, the child gets `None`
+pub fn fork() -> Result<Option<usize>, SyscallError> {
+    let mut child: usize = 0;
+    let status: u32;
+
+    unsafe {
+        //the child resumes with fresh registers,
+        //so callee-saved ones are kept on the stack
+        core::arch::asm! {
+            "push ebx",
+            "push esi",
+            "push edi",
+            "push ebp",
+            "int 80h",
+            "pop ebp",
+            "pop edi",
+            "pop esi",
+            "pop ebx",
+            inlateout("eax") syscall::Request::Fork as u32 => status,
+            inlateout("ecx") &mut child as *mut usize => _,
+            lateout("edx") _,
+        }
+    }
+
+    if status != 0 {
+        return Err(SyscallError::from(status));
+    }
+
+    Ok((child != 0).then_some(child))
+}
@@ -109,12 +109,12 @@ pub fn handle_char_module(
                 (ops.ioctl)(file.into(), command)
             }
 
-            FileRequest::Read { file, buf } => {
+            FileRequest::Read { file, buf, .. } => {
                 let user_buf = UserBufMut::from(buf);
 
                 (ops.read)(file.into(), user_buf)
             }
-            FileRequest::Write { buf, file } => {
+            FileRequest::Write { buf, file, .. } => {
                 log::debug!("write operation");
 
                 let buf = KernelBuf::from(buf);
@@ -164,7 +164,7 @@ pub fn handle_block_module(
             block::Work::Read { sector, buffer } => {
                 let buf = KernelBufMut::from(buffer);
 
-                (ops.read)(sector, buf)
+                (ops.read)(req.disk, sector, buf)
             }
             block::Work::Write { sector, buffer } => {
                 let buf = KernelBuf::from(buffer);
@@ -172,9 +172,9 @@ pub fn handle_block_module(
                 let mut user_buf = UserBuf::new(buf.capacity());
                 buf.copy_to(&mut user_buf).unwrap();
 
-                (ops.write)(sector, user_buf)
+                (ops.write)(req.disk, sector, user_buf)
             }
-            block::Work::Passthrough { cmd } => (ops.ioctl)(cmd),
+            block::Work::Passthrough { cmd } => (ops.ioctl)(req.disk, cmd),
         };
 
         match status {
@@ -0,0 +1,67 @@
+use core::{ops::Sub, time::Duration};
+
+use kernel_types::{syscall, syscall::SyscallError, time::TimeSpec};
+
+pub use kernel_types::time::ClockType;
+
+/// Read the kernel clock
+pub fn now(clock: ClockType) -> Result<Duration, SyscallError> {
+    let mut time = TimeSpec::default();
+
+    unsafe {
+        syscall! {
+            syscall::Request::GetTime,
+            ecx: &mut time,
+            edx: clock as u32
+        }?;
+    }
+
+    Ok(Duration::new(time.secs, time.nanos))
+}
+
+/// The processor time consumed by current task
+pub fn thread_time() -> Duration {
+    now(ClockType::ThreadConsumed).expect("Failed to read thread clock")
+}
+
+/// The monotonic clock measured from system boot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Instant(Duration);
+
+impl Instant {
+    pub fn now() -> Self {
+        Self(now(ClockType::Boot).expect("Failed to read boot clock"))
+    }
+
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        self.0.saturating_sub(earlier.0)
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Self::now().duration_since(*self)
+    }
+}
+
+impl Sub for Instant {
+    type Output = Duration;
+
+    fn sub(self, other: Instant) -> Self::Output {
+        self.duration_since(other)
+    }
+}
+
+/// The wall clock time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SystemTime(Duration);
+
+impl SystemTime {
+    pub const UNIX_EPOCH: SystemTime = SystemTime(Duration::ZERO);
+
+    pub fn now() -> Self {
+        Self(now(ClockType::Epoch).expect("Failed to read epoch clock"))
+    }
+
+    pub fn duration_since_epoch(&self) -> Duration {
+        self.0
+    }
+}
@@ -0,0 +1,156 @@
+//! Fixed ACPI Description Table: power management registers
+
+use super::{read_u16, read_u32, GenericAddress, SdtHeader};
+
+pub const SIGNATURE: &[u8; 4] = b"FACP";
+
+/// The offsets of fields in table content (after the header)
+const FIRMWARE_CONTROL: usize = 0;
+const DSDT: usize = 4;
+const SCI_INTERRUPT: usize = 10;
+const SMI_COMMAND: usize = 12;
+const ACPI_ENABLE: usize = 16;
+const ACPI_DISABLE: usize = 17;
+const PM1A_EVENT_BLOCK: usize = 20;
+const PM1B_EVENT_BLOCK: usize = 24;
+const PM1A_CONTROL_BLOCK: usize = 28;
+const PM1B_CONTROL_BLOCK: usize = 32;
+const PM_TIMER_BLOCK: usize = 40;
+const PM1_CONTROL_LENGTH: usize = 53;
+const CENTURY: usize = 72;
+const BOOT_ARCHITECTURE: usize = 73;
+const FLAGS: usize = 76;
+const RESET_REGISTER: usize = 80;
+const RESET_VALUE: usize = 92;
+
+/// The content of ACPI 1.0 table ends with flags
+const MIN_SIZE: usize = FLAGS + 4;
+
+/// The reset register is supported (ACPI 2.0+)
+const RESET_REG_SUP: u32 = 1 << 10;
+/// The system has no 8042 keyboard controller
+const NO_8042: u16 = 1 << 1;
+
+#[derive(Debug)]
+pub struct Fadt {
+    pub firmware_control: u32,
+    pub dsdt: u32,
+    pub sci_interrupt: u16,
+    /// The port to switch between legacy and ACPI modes
+    pub smi_command: u32,
+    pub acpi_enable: u8,
+    pub acpi_disable: u8,
+    pub pm1a_event_block: u32,
+    pub pm1b_event_block: u32,
+    pub pm1a_control_block: u32,
+    pub pm1b_control_block: u32,
+    pub pm1_control_length: u8,
+    pub pm_timer_block: u32,
+    /// The index of century in CMOS (0 if not supported)
+    pub century: u8,
+    pub has_8042: bool,
+    pub flags: u32,
+    /// The register and value to reset the system
+    pub reset: Option<(GenericAddress, u8)>,
+}
+
+impl Fadt {
+    /// `None` if table is shorter than ACPI 1.0 one
+    pub fn parse(table: &SdtHeader) -> Option<Self> {
+        Self::parse_data(table.revision, table.data())
+    }
+
+    fn parse_data(revision: u8, data: &[u8]) -> Option<Self> {
+        if data.len() < MIN_SIZE {
+            log::warn!("FADT is too short: {} bytes", data.len());
+            return None;
+        }
+
+        let flags = read_u32(data, FLAGS);
+
+        //ACPI 1.0 table ends before reset register
+        let reset = (flags & RESET_REG_SUP != 0 && data.len() > RESET_VALUE)
+            .then(|| {
+                (
+                    GenericAddress::parse(data, RESET_REGISTER),
+                    data[RESET_VALUE],
+                )
+            });
+
+        Some(Self {
+            firmware_control: read_u32(data, FIRMWARE_CONTROL),
+            dsdt: read_u32(data, DSDT),
+            sci_interrupt: read_u16(data, SCI_INTERRUPT),
+            smi_command: read_u32(data, SMI_COMMAND),
+            acpi_enable: data[ACPI_ENABLE],
+            acpi_disable: data[ACPI_DISABLE],
+            pm1a_event_block: read_u32(data, PM1A_EVENT_BLOCK),
+            pm1b_event_block: read_u32(data, PM1B_EVENT_BLOCK),
+            pm1a_control_block: read_u32(data, PM1A_CONTROL_BLOCK),
+            pm1b_control_block: read_u32(data, PM1B_CONTROL_BLOCK),
+            pm1_control_length: data[PM1_CONTROL_LENGTH],
+            pm_timer_block: read_u32(data, PM_TIMER_BLOCK),
+            century: data[CENTURY],
+            //the field is reserved in ACPI 1.0
+            has_8042: revision < 2
+                || read_u16(data, BOOT_ARCHITECTURE) & NO_8042 == 0,
+            flags,
+            reset,
+        })
+    }
+}
+
+/// `None` if ACPI has no FADT or it is malformed
+pub fn find() -> Option<Fadt> {
+    super::find_table(SIGNATURE).and_then(Fadt::parse)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+    extern crate std;
+
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn short_table_test() {
+        assert!(Fadt::parse_data(2, &[]).is_none());
+        assert!(Fadt::parse_data(2, &[0; MIN_SIZE - 1]).is_none());
+    }
+
+    #[test]
+    fn acpi1_table_test() {
+        let mut data = vec![0; MIN_SIZE];
+        data[ACPI_ENABLE] = 0xF0;
+        data[PM1A_CONTROL_BLOCK..PM1A_CONTROL_BLOCK + 4]
+            .copy_from_slice(&0x404u32.to_le_bytes());
+        //the reset register is claimed, but the table ends before it
+        data[FLAGS..FLAGS + 4].copy_from_slice(&RESET_REG_SUP.to_le_bytes());
+
+        let fadt = Fadt::parse_data(1, &data).unwrap();
+
+        assert_eq!(fadt.acpi_enable, 0xF0);
+        assert_eq!(fadt.pm1a_control_block, 0x404);
+        assert!(fadt.has_8042);
+        assert!(fadt.reset.is_none());
+    }
+
+    #[test]
+    fn reset_register_test() {
+        let mut data = vec![0; RESET_VALUE + 1];
+        data[FLAGS..FLAGS + 4].copy_from_slice(&RESET_REG_SUP.to_le_bytes());
+        data[BOOT_ARCHITECTURE] = NO_8042 as u8;
+        data[RESET_REGISTER + 4] = 0xCF;
+        data[RESET_VALUE] = 0x06;
+
+        let fadt = Fadt::parse_data(2, &data).unwrap();
+
+        let (register, value) = fadt.reset.unwrap();
+
+        assert_eq!(register.address, 0xCF);
+        assert_eq!(value, 0x06);
+        assert!(!fadt.has_8042);
+    }
+}
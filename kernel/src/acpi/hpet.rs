@@ -0,0 +1,48 @@
+//! High Precision Event Timer description table
+
+use super::{read_u16, read_u32, GenericAddress, SdtHeader};
+
+pub const SIGNATURE: &[u8; 4] = b"HPET";
+
+const COUNTER_64BIT: u32 = 1 << 13;
+const LEGACY_REPLACEMENT: u32 = 1 << 15;
+
+#[derive(Debug)]
+pub struct Hpet {
+    pub hardware_revision: u8,
+    /// The count of comparators in the first timer block
+    pub comparators: u8,
+    pub is_64bit: bool,
+    /// The timer can replace PIT and RTC interrupts
+    pub legacy_replacement: bool,
+    pub pci_vendor_id: u16,
+    /// The registers of the timer block
+    pub base: GenericAddress,
+    pub number: u8,
+    /// The minimal period in periodic mode (in counter ticks)
+    pub minimum_tick: u16,
+}
+
+impl Hpet {
+    pub fn parse(table: &SdtHeader) -> Self {
+        let data = table.data();
+
+        let block_id = read_u32(data, 0);
+
+        Self {
+            hardware_revision: block_id as u8,
+            comparators: ((block_id >> 8) & 0x1F) as u8 + 1,
+            is_64bit: block_id & COUNTER_64BIT != 0,
+            legacy_replacement: block_id & LEGACY_REPLACEMENT != 0,
+            pci_vendor_id: (block_id >> 16) as u16,
+            base: GenericAddress::parse(data, 4),
+            number: data[16],
+            minimum_tick: read_u16(data, 17),
+        }
+    }
+}
+
+/// `None` if there is no HPET
+pub fn find() -> Option<Hpet> {
+    super::find_table(SIGNATURE).map(Hpet::parse)
+}
@@ -0,0 +1,228 @@
+//! Multiple APIC Description Table
+
+use alloc::vec::Vec;
+
+use crate::memory::PhysicalAddress;
+
+use super::{read_u32, read_u64, SdtHeader};
+
+pub const SIGNATURE: &[u8; 4] = b"APIC";
+
+/// The system has dual 8259 PIC as well
+const PCAT_COMPAT: u32 = 1;
+
+const LOCAL_APIC: u8 = 0;
+const IO_APIC: u8 = 1;
+const SOURCE_OVERRIDE: u8 = 2;
+const LOCAL_APIC_OVERRIDE: u8 = 5;
+
+/// The local APIC address and flags precede the entries
+const HEADER_SIZE: usize = 8;
+
+/// The processor is ready to use
+const PROCESSOR_ENABLED: u32 = 1;
+/// The processor can be enabled at runtime
+const PROCESSOR_ONLINE_CAPABLE: u32 = 1 << 1;
+
+#[derive(Debug, Clone, Copy)]
+pub struct LocalApicEntry {
+    pub processor_id: u8,
+    pub apic_id: u8,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct IoApicEntry {
+    pub id: u8,
+    pub address: PhysicalAddress,
+    /// The first global system interrupt served by IO-APIC
+    pub gsi_base: u32,
+}
+
+/// The ISA interrupt connected to another input of IO-APIC
+#[derive(Debug, Clone, Copy)]
+pub struct SourceOverride {
+    pub source: u8,
+    pub gsi: u32,
+    pub flags: u16,
+}
+
+impl SourceOverride {
+    pub fn is_active_low(&self) -> bool {
+        self.flags & 0b11 == 0b11
+    }
+
+    pub fn is_level_triggered(&self) -> bool {
+        (self.flags >> 2) & 0b11 == 0b11
+    }
+}
+
+#[derive(Debug)]
+pub struct Madt {
+    pub local_apic: PhysicalAddress,
+    pub has_pic: bool,
+    pub processors: Vec<LocalApicEntry>,
+    pub io_apics: Vec<IoApicEntry>,
+    pub overrides: Vec<SourceOverride>,
+}
+
+/// The size of entry fields read by parser
+fn entry_size(kind: u8) -> usize {
+    match kind {
+        LOCAL_APIC => 8,
+        IO_APIC => 12,
+        SOURCE_OVERRIDE => 10,
+        LOCAL_APIC_OVERRIDE => 12,
+        _ => 2,
+    }
+}
+
+impl Madt {
+    /// `None` if table is too short for the local APIC address
+    pub fn parse(table: &SdtHeader) -> Option<Self> {
+        Self::parse_data(table.data())
+    }
+
+    fn parse_data(data: &[u8]) -> Option<Self> {
+        if data.len() < HEADER_SIZE {
+            log::warn!("MADT is too short: {} bytes", data.len());
+            return None;
+        }
+
+        let local_apic = read_u32(data, 0) as PhysicalAddress;
+        let flags = read_u32(data, 4);
+
+        let mut madt = Self {
+            local_apic,
+            has_pic: flags & PCAT_COMPAT != 0,
+            processors: Vec::new(),
+            io_apics: Vec::new(),
+            overrides: Vec::new(),
+        };
+
+        let mut offset = HEADER_SIZE;
+
+        while offset + 2 <= data.len() {
+            let kind = data[offset];
+            let len = data[offset + 1] as usize;
+
+            if len < 2 || offset + len > data.len() {
+                log::warn!("Malformed MADT entry at {offset}");
+                break;
+            }
+
+            let entry = &data[offset..offset + len];
+
+            offset += len;
+
+            if len < entry_size(kind) {
+                log::warn!("Short MADT entry of kind {kind}: {len} bytes");
+                continue;
+            }
+
+            match kind {
+                LOCAL_APIC => {
+                    let flags = read_u32(entry, 4);
+
+                    madt.processors.push(LocalApicEntry {
+                        processor_id: entry[2],
+                        apic_id: entry[3],
+                        enabled: flags
+                            & (PROCESSOR_ENABLED | PROCESSOR_ONLINE_CAPABLE)
+                            != 0,
+                    });
+                }
+                IO_APIC => madt.io_apics.push(IoApicEntry {
+                    id: entry[2],
+                    address: read_u32(entry, 4) as PhysicalAddress,
+                    gsi_base: read_u32(entry, 8),
+                }),
+                SOURCE_OVERRIDE => madt.overrides.push(SourceOverride {
+                    source: entry[3],
+                    gsi: read_u32(entry, 4),
+                    flags: u16::from_le_bytes([entry[8], entry[9]]),
+                }),
+                LOCAL_APIC_OVERRIDE => {
+                    let address = read_u64(entry, 4);
+
+                    //the physical address above 4GB is not reachable
+                    if let Ok(address) = PhysicalAddress::try_from(address) {
+                        madt.local_apic = address;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Some(madt)
+    }
+
+    /// The global system interrupt of ISA irq
+    pub fn isa_override(&self, irq: u8) -> Option<&SourceOverride> {
+        self.overrides.iter().find(|entry| entry.source == irq)
+    }
+}
+
+/// `None` if ACPI has no MADT or it is malformed
+pub fn find() -> Option<Madt> {
+    super::find_table(SIGNATURE).and_then(Madt::parse)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+    extern crate std;
+
+    use alloc::vec;
+
+    use super::*;
+
+    const TABLE_HEADER: [u8; 8] = [0x00, 0x00, 0xE0, 0xFE, 1, 0, 0, 0];
+
+    #[test]
+    fn short_table_test() {
+        assert!(Madt::parse_data(&[0x00, 0x00, 0xE0]).is_none());
+
+        let madt = Madt::parse_data(&TABLE_HEADER).unwrap();
+
+        assert_eq!(madt.local_apic, 0xFEE0_0000);
+        assert!(madt.has_pic);
+        assert!(madt.processors.is_empty());
+    }
+
+    #[test]
+    fn short_entry_test() {
+        let mut data = vec![];
+        data.extend_from_slice(&TABLE_HEADER);
+        //the local APIC entry without flags is skipped
+        data.extend_from_slice(&[LOCAL_APIC, 4, 0, 1]);
+        data.extend_from_slice(&[LOCAL_APIC, 8, 1, 2, 1, 0, 0, 0]);
+        //the override without flags is skipped
+        data.extend_from_slice(&[SOURCE_OVERRIDE, 8, 0, 0, 2, 0, 0, 0]);
+        data.extend_from_slice(&[IO_APIC, 12, 3, 0]);
+        data.extend_from_slice(&[0x00, 0x00, 0xC0, 0xFE, 0, 0, 0, 0]);
+
+        let madt = Madt::parse_data(&data).unwrap();
+
+        assert_eq!(madt.processors.len(), 1);
+        assert_eq!(madt.processors[0].apic_id, 2);
+        assert!(madt.processors[0].enabled);
+        assert!(madt.overrides.is_empty());
+        assert_eq!(madt.io_apics.len(), 1);
+        assert_eq!(madt.io_apics[0].address, 0xFEC0_0000);
+    }
+
+    #[test]
+    fn truncated_entry_test() {
+        let mut data = vec![];
+        data.extend_from_slice(&TABLE_HEADER);
+        data.extend_from_slice(&[LOCAL_APIC, 8, 1, 2, 1, 0, 0, 0]);
+        //the entry length exceeds the table
+        data.extend_from_slice(&[IO_APIC, 12, 3, 0]);
+
+        let madt = Madt::parse_data(&data).unwrap();
+
+        assert_eq!(madt.processors.len(), 1);
+        assert!(madt.io_apics.is_empty());
+    }
+}
@@ -0,0 +1,320 @@
+//! Discovery of ACPI tables provided by firmware.
+//! RSDP is taken from multiboot2 information or found in BIOS area.
+//! The tables are mapped once and live until shutdown
+
+pub mod fadt;
+pub mod hpet;
+pub mod madt;
+pub mod power;
+
+use core::{mem, slice};
+
+use alloc::vec::Vec;
+
+use crate::{
+    common::atomics::UnsafeLazyCell,
+    memory::{self, AllocError, PhysicalAddress, VirtualAddress},
+};
+
+pub use fadt::Fadt;
+pub use madt::Madt;
+
+/// The BIOS area scanned for RSDP
+const BIOS_AREA: core::ops::Range<PhysicalAddress> = 0xE0000..0x100000;
+/// The location of EBDA segment in BIOS data area
+const EBDA_POINTER: PhysicalAddress = 0x40E;
+const EBDA_SCAN_SIZE: usize = 1024;
+
+const RSDP_SIGNATURE: &[u8; 8] = b"RSD PTR ";
+/// The size of RSDP of ACPI 1.0 covered by the first checksum
+const RSDP_V1_SIZE: usize = 20;
+
+#[derive(Debug, thiserror_no_std::Error)]
+pub enum AcpiError {
+    #[error("RSDP is not found")]
+    NoRsdp,
+    #[error("XSDT is located above 4GB")]
+    UnreachableXsdt,
+    #[error("Invalid checksum of {0:?} table")]
+    InvalidChecksum([u8; 4]),
+    #[error("{0:?} table is not found")]
+    NoTable([u8; 4]),
+    #[error("Sleep state S5 is not defined")]
+    NoSleepState,
+    #[error("Reset register is not supported")]
+    NoResetRegister,
+    #[error("Register in {0:?} is not supported")]
+    UnsupportedRegister(AddressSpace),
+    #[error("Failed to map table: {0}")]
+    MappingFailed(#[from] AllocError),
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C, packed)]
+struct Rsdp {
+    signature: [u8; 8],
+    checksum: u8,
+    oem_id: [u8; 6],
+    revision: u8,
+    rsdt: u32,
+    //ACPI 2.0+ fields
+    length: u32,
+    xsdt: u64,
+    extended_checksum: u8,
+    reserved: [u8; 3],
+}
+
+impl Rsdp {
+    fn has_xsdt(&self) -> bool {
+        self.revision >= 2 && self.xsdt != 0
+    }
+}
+
+/// The address of register in one of address spaces
+#[derive(Debug, Clone, Copy)]
+pub struct GenericAddress {
+    pub space: AddressSpace,
+    pub bit_width: u8,
+    pub bit_offset: u8,
+    pub access_size: u8,
+    pub address: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, num_enum::FromPrimitive)]
+#[repr(u8)]
+pub enum AddressSpace {
+    SystemMemory = 0,
+    SystemIo = 1,
+    PciConfig = 2,
+    #[num_enum(default)]
+    Other,
+}
+
+impl GenericAddress {
+    fn parse(bytes: &[u8], offset: usize) -> Self {
+        Self {
+            space: AddressSpace::from(bytes[offset]),
+            bit_width: bytes[offset + 1],
+            bit_offset: bytes[offset + 2],
+            access_size: bytes[offset + 3],
+            address: read_u64(bytes, offset + 4),
+        }
+    }
+}
+
+/// The common header of system description tables
+#[derive(Debug, Clone, Copy)]
+#[repr(C, packed)]
+pub struct SdtHeader {
+    pub signature: [u8; 4],
+    pub length: u32,
+    pub revision: u8,
+    pub checksum: u8,
+    pub oem_id: [u8; 6],
+    pub oem_table_id: [u8; 8],
+    pub oem_revision: u32,
+    pub creator_id: u32,
+    pub creator_revision: u32,
+}
+
+impl SdtHeader {
+    pub fn signature(&self) -> &str {
+        core::str::from_utf8(&self.signature).unwrap_or("????")
+    }
+
+    /// The table content following the header
+    pub fn data(&self) -> &[u8] {
+        let header_size = mem::size_of::<SdtHeader>();
+        let len = (self.length as usize).saturating_sub(header_size);
+
+        unsafe {
+            let start =
+                (self as *const SdtHeader as *const u8).add(header_size);
+
+            slice::from_raw_parts(start, len)
+        }
+    }
+
+    fn bytes(&self) -> &[u8] {
+        unsafe {
+            slice::from_raw_parts(
+                self as *const SdtHeader as *const u8,
+                self.length as usize,
+            )
+        }
+    }
+}
+
+pub struct AcpiTables {
+    pub revision: u8,
+    pub oem_id: [u8; 6],
+    /// `true` if tables are listed by 64-bit XSDT
+    pub is_extended: bool,
+    pub tables: Vec<&'static SdtHeader>,
+}
+
+static TABLES: UnsafeLazyCell<AcpiTables> = UnsafeLazyCell::empty();
+
+/// RSDP copied from multiboot2 information
+static mut BOOT_RSDP: Option<Rsdp> = None;
+
+/// Remember RSDP given by bootloader.
+///
+/// # Safety
+/// The method is invoked before paging is enabled,
+/// thus the static is written by its physical address.
+/// `rsdp` should point to RSDP copy of multiboot2 tag
+pub unsafe fn set_boot_rsdp(rsdp: *const u8) {
+    let boot_rsdp = ((&raw mut BOOT_RSDP) as VirtualAddress
+        - memory::kernel_virtual_offset())
+        as *mut Option<Rsdp>;
+
+    boot_rsdp.write(Some((rsdp as *const Rsdp).read_unaligned()));
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(bytes[offset..offset + 2].try_into().unwrap())
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+fn checksum(bytes: &[u8]) -> bool {
+    bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) == 0
+}
+
+/// The low memory is mapped to kernel space at boot
+fn low_memory(physical: PhysicalAddress) -> VirtualAddress {
+    physical + memory::kernel_virtual_offset()
+}
+
+fn is_valid_rsdp(rsdp: &Rsdp) -> bool {
+    let bytes = unsafe {
+        slice::from_raw_parts(rsdp as *const Rsdp as *const u8, RSDP_V1_SIZE)
+    };
+
+    &rsdp.signature == RSDP_SIGNATURE && checksum(bytes)
+}
+
+fn scan_rsdp(range: core::ops::Range<PhysicalAddress>) -> Option<Rsdp> {
+    range.step_by(16).find_map(|physical| {
+        let rsdp =
+            unsafe { (low_memory(physical) as *const Rsdp).read_unaligned() };
+
+        is_valid_rsdp(&rsdp).then_some(rsdp)
+    })
+}
+
+fn find_rsdp() -> Option<Rsdp> {
+    let boot_rsdp = unsafe { BOOT_RSDP };
+
+    if let Some(rsdp) = boot_rsdp.filter(is_valid_rsdp) {
+        log::debug!("RSDP is given by bootloader");
+        return Some(rsdp);
+    }
+
+    let ebda_segment =
+        unsafe { (low_memory(EBDA_POINTER) as *const u16).read_unaligned() };
+
+    let ebda = (ebda_segment as PhysicalAddress) << 4;
+
+    if ebda != 0 {
+        if let Some(rsdp) = scan_rsdp(ebda..ebda + EBDA_SCAN_SIZE) {
+            return Some(rsdp);
+        }
+    }
+
+    scan_rsdp(BIOS_AREA)
+}
+
+/// Map the whole table which header is located at `physical`
+fn map_table(
+    physical: PhysicalAddress,
+) -> Result<&'static SdtHeader, AcpiError> {
+    let header = memory::map_device(physical, mem::size_of::<SdtHeader>())?;
+    let length = unsafe { (*(header as *const SdtHeader)).length } as usize;
+
+    let table = unsafe {
+        &*(memory::map_device(physical, length)? as *const SdtHeader)
+    };
+
+    if !checksum(table.bytes()) {
+        return Err(AcpiError::InvalidChecksum(table.signature));
+    }
+
+    Ok(table)
+}
+
+/// The physical addresses of tables listed by RSDT or XSDT
+fn table_addresses(rsdp: &Rsdp) -> Result<Vec<u64>, AcpiError> {
+    if rsdp.has_xsdt() {
+        let xsdt = PhysicalAddress::try_from(rsdp.xsdt)
+            .map_err(|_| AcpiError::UnreachableXsdt)?;
+
+        let xsdt = map_table(xsdt)?;
+
+        Ok(xsdt
+            .data()
+            .chunks_exact(mem::size_of::<u64>())
+            .map(|entry| read_u64(entry, 0))
+            .collect())
+    } else {
+        let rsdt = map_table(rsdp.rsdt as PhysicalAddress)?;
+
+        Ok(rsdt
+            .data()
+            .chunks_exact(mem::size_of::<u32>())
+            .map(|entry| read_u32(entry, 0) as u64)
+            .collect())
+    }
+}
+
+pub fn init() -> Result<(), AcpiError> {
+    let rsdp = find_rsdp().ok_or(AcpiError::NoRsdp)?;
+
+    let tables = table_addresses(&rsdp)?
+        .into_iter()
+        .filter_map(|physical| {
+            //the physical address above 4GB is not reachable
+            let Ok(physical) = PhysicalAddress::try_from(physical) else {
+                log::warn!("Skipping ACPI table at 0x{physical:x}");
+                return None;
+            };
+
+            map_table(physical)
+                .inspect_err(|cause| log::warn!("Skipping ACPI table: {cause}"))
+                .ok()
+        })
+        .collect::<Vec<_>>();
+
+    for table in tables.iter() {
+        log::info!("ACPI table: {}", table.signature());
+    }
+
+    TABLES.set(AcpiTables {
+        revision: rsdp.revision,
+        oem_id: rsdp.oem_id,
+        is_extended: rsdp.has_xsdt(),
+        tables,
+    });
+
+    Ok(())
+}
+
+/// `None` if ACPI is not initialized
+pub fn tables() -> Option<&'static AcpiTables> {
+    TABLES.try_get()
+}
+
+pub fn find_table(signature: &[u8; 4]) -> Option<&'static SdtHeader> {
+    tables()?
+        .tables
+        .iter()
+        .find(|table| &table.signature == signature)
+        .copied()
+}
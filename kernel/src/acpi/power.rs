@@ -0,0 +1,163 @@
+//! Power off via sleep state S5 and reset via FADT reset register
+
+use crate::{
+    common::io,
+    memory::{self, PhysicalAddress},
+    task::clocks,
+};
+
+use super::{fadt, map_table, AcpiError, AddressSpace, Fadt};
+
+const S5_NAME: &[u8; 4] = b"_S5_";
+
+/// AML opcodes met in `\_S5` package
+const NAME_OP: u8 = 0x08;
+const ROOT_PREFIX: u8 = b'\\';
+const PACKAGE_OP: u8 = 0x12;
+const BYTE_PREFIX: u8 = 0x0A;
+const ZERO_OP: u8 = 0x00;
+const ONE_OP: u8 = 0x01;
+
+/// PM1 control register bits
+const SCI_EN: u16 = 1;
+const SLP_TYP_SHIFT: u16 = 10;
+const SLP_TYP_MASK: u16 = 0b111 << SLP_TYP_SHIFT;
+const SLP_EN: u16 = 1 << 13;
+
+/// How long firmware may switch to ACPI mode
+const ENABLE_TIMEOUT_MS: u32 = 300;
+
+/// Enter sleep state S5.
+/// The method returns only if the machine is still running
+pub fn power_off() -> Result<(), AcpiError> {
+    let fadt = fadt::find().ok_or(AcpiError::NoTable(*fadt::SIGNATURE))?;
+
+    let dsdt = map_table(fadt.dsdt as PhysicalAddress)?;
+
+    let (slp_typa, slp_typb) =
+        s5_sleep_types(dsdt.bytes()).ok_or(AcpiError::NoSleepState)?;
+
+    enable_acpi_mode(&fadt);
+
+    unsafe {
+        write_sleep_type(fadt.pm1a_control_block, slp_typa);
+
+        if fadt.pm1b_control_block != 0 {
+            write_sleep_type(fadt.pm1b_control_block, slp_typb);
+        }
+    }
+
+    Ok(())
+}
+
+/// Write reset value to FADT reset register.
+/// The method returns only if the machine is still running
+pub fn reset() -> Result<(), AcpiError> {
+    let fadt = fadt::find().ok_or(AcpiError::NoTable(*fadt::SIGNATURE))?;
+
+    let (register, value) = fadt.reset.ok_or(AcpiError::NoResetRegister)?;
+
+    match register.space {
+        AddressSpace::SystemIo => unsafe {
+            io::outb(register.address as u16, value)
+        },
+        AddressSpace::SystemMemory => {
+            let physical = PhysicalAddress::try_from(register.address)
+                .map_err(|_| AcpiError::UnsupportedRegister(register.space))?;
+
+            let register = memory::map_device(physical, 1)?;
+
+            unsafe { (register as *mut u8).write_volatile(value) };
+        }
+        space => return Err(AcpiError::UnsupportedRegister(space)),
+    }
+
+    Ok(())
+}
+
+/// Firmware may start in legacy mode where
+/// PM1 registers are owned by SMI handler
+fn enable_acpi_mode(fadt: &Fadt) {
+    let control = fadt.pm1a_control_block as u16;
+
+    let is_enabled = || unsafe { io::inw(control) } & SCI_EN != 0;
+
+    if is_enabled() || fadt.smi_command == 0 || fadt.acpi_enable == 0 {
+        return;
+    }
+
+    unsafe { io::outb(fadt.smi_command as u16, fadt.acpi_enable) };
+
+    for _ in 0..ENABLE_TIMEOUT_MS {
+        if is_enabled() {
+            return;
+        }
+
+        clocks::pit_wait(1);
+    }
+
+    log::warn!("Firmware doesn't switch to ACPI mode");
+}
+
+unsafe fn write_sleep_type(control_block: u32, sleep_type: u8) {
+    let port = control_block as u16;
+
+    let control = io::inw(port) & !SLP_TYP_MASK;
+    let sleep_type = ((sleep_type as u16) << SLP_TYP_SHIFT) & SLP_TYP_MASK;
+
+    io::outw(port, control | sleep_type | SLP_EN);
+}
+
+/// Find `Name(\_S5, Package() {SLP_TYPa, SLP_TYPb, ...})` in DSDT.
+/// The full AML interpreter is not required for the only package
+fn s5_sleep_types(aml: &[u8]) -> Option<(u8, u8)> {
+    let position = aml
+        .windows(S5_NAME.len())
+        .position(|name| name == S5_NAME)?;
+
+    let is_named = match position {
+        0 => false,
+        1 => aml[0] == NAME_OP,
+        _ => {
+            aml[position - 1] == NAME_OP
+                || (aml[position - 2] == NAME_OP
+                    && aml[position - 1] == ROOT_PREFIX)
+        }
+    };
+
+    if !is_named {
+        return None;
+    }
+
+    let mut offset = position + S5_NAME.len();
+
+    if *aml.get(offset)? != PACKAGE_OP {
+        return None;
+    }
+
+    offset += 1;
+
+    //the upper bits of PkgLength lead byte is the count of following bytes
+    offset += ((*aml.get(offset)? >> 6) & 0b11) as usize + 1;
+
+    //NumElements
+    offset += 1;
+
+    let slp_typa = read_integer(aml, &mut offset)?;
+    let slp_typb = read_integer(aml, &mut offset)?;
+
+    Some((slp_typa, slp_typb))
+}
+
+fn read_integer(aml: &[u8], offset: &mut usize) -> Option<u8> {
+    let (value, len) = match *aml.get(*offset)? {
+        BYTE_PREFIX => (*aml.get(*offset + 1)?, 2),
+        ZERO_OP => (0, 1),
+        ONE_OP => (1, 1),
+        _ => return None,
+    };
+
+    *offset += len;
+
+    Some(value)
+}
@@ -3,14 +3,18 @@ pub mod properties;
 use core::arch::asm;
 
 use kernel_types::{get_eax, set_eax};
-use multiboot2::BootInformation;
+use multiboot2::{BootInformation, RsdpV1Tag, RsdpV2Tag};
 use properties::KernelProperties;
 
+use crate::acpi;
 use crate::memory::{
     self, CaptureMemRec, DirEntry, TableEntry, DIRECTORY_ENTRIES_COUNT,
     DIRECTORY_PAGES_COUNT, TABLE_ENTRIES_COUNT,
 };
 
+/// The size of multiboot2 tag header (type and size)
+const TAG_HEADER_SIZE: usize = 8;
+
 #[repr(u32)]
 pub enum BootStatus {
     InvalidBootInfo = 1,
@@ -59,6 +63,15 @@ pub unsafe fn parse_grub_args_native(
         interpret_command_line(cmd_line, properties)
     }
 
+    //the tags hold the copy of RSDP
+    if let Some(tag) = mbi.rsdp_v2_tag() {
+        let rsdp = (tag as *const RsdpV2Tag as *const u8).add(TAG_HEADER_SIZE);
+        acpi::set_boot_rsdp(rsdp);
+    } else if let Some(tag) = mbi.rsdp_v1_tag() {
+        let rsdp = (tag as *const RsdpV1Tag as *const u8).add(TAG_HEADER_SIZE);
+        acpi::set_boot_rsdp(rsdp);
+    }
+
     let Some(memory_map) = mbi.memory_map_tag() else {
         return BootStatus::MemoryMapNotPresent;
     };
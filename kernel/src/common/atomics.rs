@@ -3,6 +3,8 @@ use core::ops::{Deref, DerefMut};
 use core::ptr::NonNull;
 use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
+use crate::smp;
+
 #[repr(transparent)]
 pub struct RefCounter(AtomicUsize);
 
@@ -46,6 +48,10 @@ impl SpinLock {
             if is_acquired {
                 break;
             }
+
+            //the holder may wait for our shootdown with interrupts disabled
+            smp::serve_shootdown(smp::cpu_id());
+            core::hint::spin_loop();
         }
     }
 
@@ -63,6 +69,62 @@ impl SpinLock {
     }
 }
 
+/// The spinlock that can be taken again by the processor already holding it.
+/// Interrupts should be disabled while the lock is held,
+/// otherwise an interrupt handler would enter the critical section
+#[derive(Debug)]
+pub struct RecursiveSpinLock {
+    owner: AtomicUsize,
+    depth: AtomicUsize,
+}
+
+impl RecursiveSpinLock {
+    const NO_OWNER: usize = usize::MAX;
+
+    pub const fn new() -> Self {
+        Self {
+            owner: AtomicUsize::new(Self::NO_OWNER),
+            depth: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.owner.load(Ordering::Acquire) != Self::NO_OWNER
+    }
+
+    pub fn acquire(&self) {
+        let cpu = smp::cpu_id();
+
+        if self.owner.load(Ordering::Acquire) == cpu {
+            self.depth.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+
+        while self
+            .owner
+            .compare_exchange_weak(
+                Self::NO_OWNER,
+                cpu,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            )
+            .is_err()
+        {
+            //the holder may wait for our shootdown with interrupts disabled
+            smp::serve_shootdown(cpu);
+            core::hint::spin_loop();
+        }
+
+        self.depth.store(1, Ordering::Relaxed);
+    }
+
+    pub fn release(&self) {
+        if self.depth.fetch_sub(1, Ordering::Relaxed) == 1 {
+            self.owner.store(Self::NO_OWNER, Ordering::Release);
+        }
+    }
+}
+
 #[repr(transparent)]
 pub struct UnsafeLazyCell<T> {
     cell: UnsafeCell<Option<T>>,
@@ -93,6 +155,10 @@ impl<T> UnsafeLazyCell<T> {
             );
         }
     }
+
+    pub fn try_get(&self) -> Option<&T> {
+        unsafe { &*self.cell.get() }.as_ref()
+    }
 }
 
 unsafe impl<T> Send for UnsafeLazyCell<T> where T: Send {}
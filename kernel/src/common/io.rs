@@ -18,6 +18,12 @@ pub unsafe fn outw(port: u16, value: u16) {
     options(preserves_flags, nostack));
 }
 
+pub unsafe fn outl(port: u16, value: u32) {
+    asm!(
+    "out dx, eax", in("dx") port, in("eax") value,
+    options(preserves_flags, nostack));
+}
+
 pub unsafe fn inb(port: u16) -> u8 {
     let value: u8;
     asm!(
@@ -33,3 +39,11 @@ pub unsafe fn inw(port: u16) -> u16 {
     options(preserves_flags, nostack));
     value
 }
+
+pub unsafe fn inl(port: u16) -> u32 {
+    let value: u32;
+    asm!(
+    "in eax, dx", in("dx") port, out("eax") value,
+    options(preserves_flags, nostack));
+    value
+}
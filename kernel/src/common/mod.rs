@@ -3,5 +3,6 @@ pub use spin_box::SpinBox;
 pub mod atomics;
 pub mod io;
 pub mod logging;
+pub mod rtc;
 mod spin_box;
 pub mod time;
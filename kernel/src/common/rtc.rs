@@ -87,3 +87,27 @@ pub unsafe fn read_rtc_time() -> RtcTime {
         year,
     }
 }
+
+impl RtcTime {
+    /// The seconds since 1970-01-01 00:00:00 UTC
+    pub fn to_unix_secs(&self) -> u64 {
+        //days from civil algorithm (proleptic Gregorian calendar)
+        let year = self.year as i64 - (self.month <= 2) as i64;
+        let month = self.month as i64;
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month_index = (month + 9) % 12;
+        let day_of_year = (153 * month_index + 2) / 5 + self.day as i64 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4
+            - year_of_era / 100
+            + day_of_year;
+        let days = era * 146_097 + day_of_era - 719_468;
+
+        let secs = days * 86_400
+            + self.hours as i64 * 3_600
+            + self.minutes as i64 * 60
+            + self.seconds as i64;
+
+        secs.max(0) as u64
+    }
+}
@@ -1,24 +1,67 @@
-use kernel_types::declare_constants;
+use core::ops::Add;
 
-#[derive(Default)]
+use kernel_types::{declare_constants, time::TimeSpec};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Timestamp {
     secs: u64,
+    nanos: u32,
 }
 declare_constants!(
     pub u64,
     MILLIS_PER_SEC = 1_000;
     MICROS_PER_SEC = 1_000_000;
+    NANOS_PER_SEC = 1_000_000_000;
 );
 impl Timestamp {
     pub fn from_secs(secs: u64) -> Self {
-        Self { secs }
+        Self { secs, nanos: 0 }
     }
     pub fn from_millis(millis: u64) -> Self {
-        let secs = millis / MILLIS_PER_SEC;
-        Self::from_secs(secs)
+        Self::from_nanos(millis * (NANOS_PER_SEC / MILLIS_PER_SEC))
     }
     pub fn from_micros(micros: u64) -> Self {
-        let secs = micros / MICROS_PER_SEC;
-        Self::from_secs(secs)
+        Self::from_nanos(micros * (NANOS_PER_SEC / MICROS_PER_SEC))
+    }
+    pub fn from_nanos(nanos: u64) -> Self {
+        Self {
+            secs: nanos / NANOS_PER_SEC,
+            nanos: (nanos % NANOS_PER_SEC) as u32,
+        }
+    }
+    pub fn secs(&self) -> u64 {
+        self.secs
+    }
+    pub fn subsec_nanos(&self) -> u32 {
+        self.nanos
+    }
+    pub fn as_millis(&self) -> u64 {
+        self.secs * MILLIS_PER_SEC
+            + self.nanos as u64 / (NANOS_PER_SEC / MILLIS_PER_SEC)
+    }
+    pub fn as_nanos(&self) -> u64 {
+        self.secs * NANOS_PER_SEC + self.nanos as u64
+    }
+}
+
+impl Add for Timestamp {
+    type Output = Timestamp;
+
+    fn add(self, other: Timestamp) -> Self::Output {
+        let nanos = self.nanos as u64 + other.nanos as u64;
+
+        Self {
+            secs: self.secs + other.secs + nanos / NANOS_PER_SEC,
+            nanos: (nanos % NANOS_PER_SEC) as u32,
+        }
+    }
+}
+
+impl From<Timestamp> for TimeSpec {
+    fn from(value: Timestamp) -> Self {
+        Self {
+            secs: value.secs,
+            nanos: value.nanos,
+        }
     }
 }
@@ -4,18 +4,14 @@ use kernel_types::{
     syscall::SyscallError,
 };
 
-use super::init_module;
+use super::{init_block_module, init_module};
 
 pub fn reg_blk_module(dev: &BlockDeviceInfo) -> Result<(), SyscallError> {
-    init_module(
-        &dev.name,
-        core::ptr::null_mut(),
-        ModuleKind::Block,
-        dev.queue_size,
-    )
-    .inspect_err(|cause| {
-        log::warn!("Failed to init new module: {cause}");
-    })?;
+    init_block_module(&dev.name, dev.disks, dev.queue_size).inspect_err(
+        |cause| {
+            log::warn!("Failed to init new module: {cause}");
+        },
+    )?;
 
     Ok(())
 }
@@ -83,7 +83,10 @@ extern "C" fn fs_task(raw_handle: *const ()) {
 
                 work.send_response(sb_info.into());
             }
-            FsRequest::Unmount { .. } => todo!(),
+            //devices are served by modules, nothing to write back
+            FsRequest::Unmount { .. } => {
+                work.send_response(FsResponse::Completed);
+            }
             FsRequest::FsQueue { queue } => {
                 let init_message =
                     Box::try_new(InitMessage { work, queue }).unwrap();
@@ -249,6 +249,7 @@ extern "C" fn fs_task(arg: *const ()) {
                 let mut files = Box::new(Files::new());
                 files.create_dir("/kernel").unwrap();
                 files.create_dir("/dev").unwrap();
+                files.create_dir("/sys").unwrap();
                 files.create_file("/test.txt").unwrap();
                 files.create_file("/kernel/io.sys").unwrap();
 
@@ -260,7 +261,10 @@ extern "C" fn fs_task(arg: *const ()) {
 
                 work.send_response(sb_info.into());
             }
-            FsRequest::Unmount { .. } => todo!(),
+            //the files are kept in memory only
+            FsRequest::Unmount { .. } => {
+                work.send_response(FsResponse::Completed);
+            }
             FsRequest::FsQueue { queue } => {
                 let msg = Box::new(InitMessage {
                     work,
@@ -8,7 +8,10 @@ use elf::{
 };
 use fallible_collections::{FallibleVec, TryCollect};
 use kernel_types::{
-    collections::LinkedList, get_eax, io::MemoryRemap, string::MutString,
+    collections::LinkedList,
+    get_eax,
+    io::{MemoryRemap, PortRange, PortsDeclaration},
+    string::MutString,
     task::TaskParams,
 };
 
@@ -24,6 +27,14 @@ use crate::{
     task,
 };
 
+/// The symbols exported by `kernel_lib::module!`
+const EXIT_SYMBOL: &str = "exit";
+const IO_PORTS_SYMBOL: &str = "io_ports";
+
+/// The area of user stacks for tasks spawned in process
+const TASK_STACKS: core::ops::Range<VirtualAddress> = 0xA_000_000..0xB_000_000;
+const TASK_STACK_PAGES: usize = 4;
+
 #[derive(Debug, thiserror_no_std::Error)]
 pub enum LoadError {
     #[error("Failed to parse elf file: {0}")]
@@ -40,6 +51,8 @@ pub enum LoadError {
     Kernel(#[from] KernelError),
     #[error("Module has no entry point")]
     NoEntryPoint,
+    #[error("Declared I/O ports are out of module")]
+    InvalidPorts,
 }
 
 fn check_elf_format(
@@ -215,6 +228,74 @@ impl<'a> Loader<'a> {
         })
     }
 
+    /// The address of symbol defined by module
+    fn find_symbol(&self, name: &str) -> Option<VirtualAddress> {
+        let (symbols, strings) = self.elf_file.symbol_table().ok()??;
+
+        symbols
+            .iter()
+            .filter(|symbol| !symbol.is_undefined())
+            .find(|symbol| {
+                strings
+                    .get(symbol.st_name as usize)
+                    .is_ok_and(|symbol_name| symbol_name == name)
+            })
+            .map(|symbol| symbol.st_value as VirtualAddress)
+    }
+
+    /// The ports declared by module. The address space
+    /// of `process` should be active, they are read from its memory
+    fn declared_ports(
+        &self,
+        process: &Process,
+    ) -> Result<Vec<PortRange>, LoadError> {
+        let Some(address) = self.find_symbol(IO_PORTS_SYMBOL) else {
+            return Ok(Vec::new());
+        };
+
+        let state = process.state.lock();
+
+        let is_loaded = |start: VirtualAddress, len: usize| {
+            state.find_region(start).is_some_and(|region| {
+                start
+                    .checked_add(len)
+                    .is_some_and(|end| end <= region.range.end)
+            })
+        };
+
+        if !is_loaded(address, core::mem::size_of::<PortsDeclaration>()) {
+            return Err(LoadError::InvalidPorts);
+        }
+
+        let declaration = unsafe {
+            core::ptr::read_unaligned(address as *const PortsDeclaration)
+        };
+
+        if declaration.count == 0 {
+            return Ok(Vec::new());
+        }
+
+        let ranges_size = declaration
+            .count
+            .checked_mul(core::mem::size_of::<PortRange>())
+            .ok_or(LoadError::InvalidPorts)?;
+
+        if !is_loaded(declaration.ranges as VirtualAddress, ranges_size) {
+            return Err(LoadError::InvalidPorts);
+        }
+
+        let mut ranges: Vec<PortRange> =
+            FallibleVec::try_with_capacity(declaration.count)?;
+
+        for index in 0..declaration.count {
+            ranges.push(unsafe {
+                core::ptr::read_unaligned(declaration.ranges.add(index))
+            });
+        }
+
+        Ok(ranges)
+    }
+
     fn relocate_symbols(&mut self) -> Result<(), LoadError> {
         for header in self.sections.iter() {
             //relocated section
@@ -353,6 +434,15 @@ impl<'a> Loader<'a> {
         assert!(entry_point != 0);
         let process = builder.build(entry_point)?;
 
+        let io_ports = self.declared_ports(&process)?;
+
+        let mut state = process.state.lock();
+
+        state.exit_point = self.find_symbol(EXIT_SYMBOL);
+        state.io_ports = Some(io_ports);
+
+        drop(state);
+
         let task = task::new_task(
             run_process,
             core::ptr::null_mut(),
@@ -420,20 +510,33 @@ pub extern "C" fn run_process_task(raw_params: *const ()) {
 
     unsafe { io::disable() }; //disable interrupts to configure kernel task
 
-    let mut pages = memory::physical_alloc(4 * Page::SIZE)
+    let mut pages = memory::physical_alloc(TASK_STACK_PAGES * Page::SIZE)
         .expect("Failed to alloc memory for page stack");
 
+    let process = current_task!().process.clone().unwrap();
+
+    let mut state = process.state.lock();
+
+    //several tasks may run in the same process
+    let guard_start = state
+        .find_free_range(TASK_STACKS, (TASK_STACK_PAGES + 1) * Page::SIZE)
+        .expect("No space for task stack");
+
+    //the stack overflow faults on the guard page below
+    let stack_start = guard_start + Page::SIZE;
+
+    let guard = MemoryRegion::guard(guard_start..stack_start)
+        .expect("Failed to reserve stack guard");
+
+    state.add_region(guard.into_node());
+
     let map_region = MemoryMappingRegion {
         flags: MemoryMappingFlag::USER_DATA,
-        page_count: 4,
-        virtual_offset: 0xA_000_000,
+        page_count: TASK_STACK_PAGES,
+        virtual_offset: stack_start,
         physical_offset: pages.first().unwrap().as_physical(),
     };
 
-    let process = current_task!().process.clone().unwrap();
-
-    let mut state = process.state.lock();
-
     state.marker.map_user_range(&map_region).unwrap();
 
     let region = MemoryRegion::new_allocated(
@@ -1,15 +1,22 @@
+use core::{mem, ptr};
+
 use crate::common::atomics::UnsafeLazyCell;
 use crate::current_task;
 use crate::error::KernelError;
-use crate::io::InterruptableLazyCell;
+use crate::io::block::{self, BlockWork};
+use crate::io::{self, InterruptableLazyCell};
+use crate::memory::{Process, VirtualAddress};
 use crate::object::Handle;
-use crate::task::Event;
+use crate::task::{self, clocks, Event, TaskId, TaskPriority};
+use crate::user::queue::Queue;
+use alloc::boxed::Box;
 use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use kernel_types::collections::LinkedList;
 pub use kernel_types::drivers::ModuleId;
 use kernel_types::drivers::ModuleKind;
+use kernel_types::task::{FnTask, TaskParams};
 
 pub mod api;
 mod auto_load;
@@ -19,15 +26,20 @@ mod fat_fs;
 mod generated;
 mod loader;
 mod module_info;
+mod sys_fs;
 
 pub use error::*;
 pub use loader::run_process_task;
 pub use module_info::*;
+pub use sys_fs::show_meminfo;
 
 use generated::STATIC_DRIVERS;
 
 pub const KERNEL_MODULE: usize = 0;
 
+/// How often the completion of module exit is checked (in milliseconds)
+const EXIT_POLL_MS: usize = 10;
+
 pub struct ModuleManager {
     modules: InterruptableLazyCell<LinkedList<'static, ModuleItem>>,
     mount: Handle<Event>,
@@ -104,6 +116,152 @@ pub fn modules() -> Vec<ModuleEntry> {
         .collect()
 }
 
+/// Run `exit` routine of each module process in a new task
+/// and wait at most `timeout` milliseconds until they are completed
+pub fn exit_modules(timeout: usize) {
+    let mut processes = MODULES
+        .get()
+        .modules
+        .lock()
+        .iter()
+        .filter_map(|m| m.state.process.clone())
+        .collect::<Vec<_>>();
+
+    //a process may register several modules
+    processes.sort_by_key(|process| process.id);
+    processes.dedup_by_key(|process| process.id);
+
+    let exit_tasks = processes
+        .into_iter()
+        .filter_map(|process| {
+            let exit_point = process.state.lock().exit_point?;
+            let pid = process.id;
+
+            spawn_exit_task(process, exit_point)
+                .inspect_err(|cause| {
+                    log::warn!("Failed to stop process#{pid}: {cause}")
+                })
+                .ok()
+        })
+        .collect::<Vec<_>>();
+
+    let deadline = clocks::monotonic_millis() + timeout;
+
+    while exit_tasks.iter().any(|id| task::is_alive(*id)) {
+        if clocks::monotonic_millis() >= deadline {
+            log::warn!("Modules are not stopped in {timeout}ms");
+            break;
+        }
+
+        task::sleep(EXIT_POLL_MS);
+    }
+}
+
+fn spawn_exit_task(
+    process: Process,
+    exit_point: VirtualAddress,
+) -> Result<TaskId, KernelError> {
+    let params = Box::try_new(TaskParams {
+        args: ptr::null(),
+        routine: unsafe {
+            mem::transmute::<VirtualAddress, FnTask>(exit_point)
+        },
+        nice: 0,
+    })?;
+
+    let exit_task = task::new_task(
+        run_process_task,
+        Box::into_raw(params) as *const (),
+        TaskPriority::Module(0),
+    )?;
+
+    exit_task.set_process(process);
+
+    let id = exit_task.id;
+
+    task::submit_task(exit_task);
+
+    Ok(id)
+}
+
+/// Ask each block device to write back its cache. The device served
+/// by calling module is skipped, the module can't respond while waiting
+pub fn flush_block_devices() {
+    let caller = current_module().map(|module| module.id);
+
+    let devices = MODULES
+        .get()
+        .modules
+        .lock()
+        .iter()
+        .filter(|m| Some(m.state.id) != caller)
+        .filter_map(|m| match &m.state.queue {
+            ModuleQueue::Block(queue, _) => {
+                Some((m.state.name.clone(), queue.clone(), m.state.disks))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    for (name, queue, disks) in devices {
+        for disk in 0..disks {
+            flush_disk(&name, &queue, disk);
+        }
+    }
+}
+
+fn flush_disk(name: &str, queue: &Handle<Queue<BlockWork>>, disk: usize) {
+    let request = block::Request {
+        disk,
+        work: block::Work::Passthrough {
+            cmd: block::CMD_FLUSH,
+        },
+    };
+
+    let Ok(work) = (unsafe { BlockWork::new_boxed(request, queue) }) else {
+        log::warn!("No memory to flush {name}#{disk}");
+        return;
+    };
+
+    let work = queue.push(work);
+
+    match work.wait().map(|response| response.status()) {
+        Some(Ok(())) => log::info!("{name}#{disk} is flushed"),
+        Some(Err(status)) => {
+            log::warn!("Failed to flush {name}#{disk}: {status:?}")
+        }
+        None => log::warn!("{name}#{disk} is not responding"),
+    }
+}
+
+/// The queue of block device registered as `name`
+pub fn block_queue(name: &str) -> Option<Handle<Queue<BlockWork>>> {
+    let module = MODULES.get().find_module_by_name(name)?;
+
+    match &module.queue {
+        ModuleQueue::Block(queue, _) => Some(queue.clone()),
+        _ => None,
+    }
+}
+
+/// `true` if the process serves a device or file system
+pub fn is_module(id: ModuleId) -> bool {
+    MODULES.get().find_module(id).is_some()
+}
+
+/// Remove irq handler of module served by exiting `process`
+pub fn release_irq(process: &Process) {
+    if let Some(module) = MODULES.get().find_module(process.id) {
+        io::release_irq(&module);
+    }
+}
+
+/// `true` if process is loaded as module. The module binds its device
+/// before it's registered, thus `current_module` doesn't find it yet
+pub fn is_module_process(process: &Process) -> bool {
+    process.state.lock().io_ports.is_some()
+}
+
 pub fn current_module() -> Option<Arc<Module>> {
     let module_id = current_task!()
         .process
@@ -131,6 +289,25 @@ pub fn init_module(
     Ok(())
 }
 
+/// Register block device serving `disks` disks
+pub fn init_block_module(
+    name: &str,
+    disks: usize,
+    capacity: usize,
+) -> Result<(), ModuleError> {
+    log::debug!("new block module detected: {name}");
+
+    let mut module =
+        Module::new(name, ptr::null(), ModuleKind::Block, capacity)?;
+    module.disks = disks;
+
+    MODULES.get().add_module(module)?;
+
+    log::debug!("new module added: {name}");
+
+    Ok(())
+}
+
 pub fn ready_event() -> Handle<Event> {
     MODULES.get().mount.clone()
 }
@@ -166,6 +343,8 @@ pub fn init() {
     dev_fs::spawn_task().expect("Failed to init dev fs");
 
     fat_fs::spawn_task().expect("Failed to init fat fs");
+
+    sys_fs::spawn_task().expect("Failed to init sys fs");
 }
 
 // extern "Rust" {
@@ -14,7 +14,7 @@ use crate::{
         block::{self, BlockWork},
         InterruptableLazyCell, ModuleIrqContext,
     },
-    memory::{self, AllocError, Slab, SlabBox},
+    memory::{self, AllocError, Process, Slab, SlabBox},
     object::{Handle, ObjectContainer},
     task,
     user::{kernel_buf::KernelBuf, queue::Queue},
@@ -72,6 +72,10 @@ pub struct Module {
     pub name: heapless::String<MAX_MODULE_NAME_LEN>,
     pub queue: ModuleQueue,
     pub ctx: *const (),
+    /// `None` if module is served by kernel
+    pub process: Option<Process>,
+    /// The count of disks served by block device
+    pub disks: usize,
     irq_ctx: InterruptableLazyCell<Option<Arc<ModuleIrqContext>>>,
 }
 
@@ -84,9 +88,16 @@ impl Module {
         }
     }
 
-    pub fn set_irq_ctx(&self, irq_ctx: Arc<ModuleIrqContext>) {
-        let mut lock = self.irq_ctx.lock();
-        *lock = Some(irq_ctx);
+    /// Returns the replaced context
+    pub fn set_irq_ctx(
+        &self,
+        irq_ctx: Arc<ModuleIrqContext>,
+    ) -> Option<Arc<ModuleIrqContext>> {
+        self.irq_ctx.lock().replace(irq_ctx)
+    }
+
+    pub fn take_irq_ctx(&self) -> Option<Arc<ModuleIrqContext>> {
+        self.irq_ctx.lock().take()
     }
 
     pub fn irq_ctx(&self) -> Option<Arc<ModuleIrqContext>> {
@@ -157,7 +168,7 @@ pub extern "C" fn blk_exchange(ctx: *const ()) {
                 }
             }
 
-            FileRequest::Read { file, buf } => {
+            FileRequest::Read { file, buf, .. } => {
                 let file = Handle::<IndexNode>::from_raw(file);
                 let buf = Handle::<KernelBuf>::from_raw(buf);
 
@@ -196,7 +207,7 @@ pub extern "C" fn blk_exchange(ctx: *const ()) {
                 ctx.sector += 1;
             }
 
-            FileRequest::Write { file, buf } => {
+            FileRequest::Write { file, buf, .. } => {
                 let file = Handle::<IndexNode>::from_raw(file);
                 let buf = Handle::<KernelBuf>::from_raw(buf);
 
@@ -286,9 +297,10 @@ impl Module {
             }
         };
 
-        let id = current_task!()
-            .process
-            .clone()
+        let process = current_task!().process.clone();
+
+        let id = process
+            .as_ref()
             .map(|proc| proc.id)
             .unwrap_or(KERNEL_MODULE);
 
@@ -299,7 +311,9 @@ impl Module {
             id,
             ctx,
             queue,
+            process,
             name: concated_name.into(),
+            disks: 0,
             irq_ctx: InterruptableLazyCell::new(None),
         })
     }
@@ -0,0 +1,528 @@
+use core::fmt::{self, Write};
+
+use alloc::{boxed::Box, string::String, string::ToString, vec::Vec};
+use kernel_types::{
+    fs::{
+        DirEntriesInfo, FileLookupRequest, FilePermissions, FileRequest,
+        FileResponse, FileSystem, FileSystemKind, FsRequest, FsResponse,
+        IndexNodeInfo, NodeKind, SuperBlockInfo,
+    },
+    object::{OpStatus, RawHandle},
+};
+
+use crate::{
+    acpi, current_task,
+    drivers::MODULES,
+    fs::{self, FileLookupWork, FileWork, FsWork, IndexNode, SuperBlock},
+    memory::{self, Page, ProcessId, VirtualAddress},
+    object::{tracking, Handle, Kind},
+    pci::{self, PciBar},
+    task,
+    user::{kernel_buf::KernelBuf, queue::Queue},
+};
+
+/// The pseudo file rendered by kernel on each read
+pub struct SysFile {
+    pub name: &'static str,
+    pub show: fn(&mut String) -> fmt::Result,
+}
+
+static SYS_FILES: &[SysFile] = &[
+    SysFile {
+        name: "acpi",
+        show: show_acpi,
+    },
+    SysFile {
+        name: "pci",
+        show: show_pci,
+    },
+    SysFile {
+        name: "objects",
+        show: show_objects,
+    },
+    SysFile {
+        name: "tasks",
+        show: show_tasks,
+    },
+    SysFile {
+        name: "meminfo",
+        show: show_meminfo,
+    },
+];
+
+pub fn spawn_task() -> fs::Result<()> {
+    let fs_info = FileSystem {
+        name: "sys-fs".into(),
+        kind: FileSystemKind::READ_ONLY,
+    };
+
+    let fs_id = fs::register_fs(fs_info)?;
+
+    let queue = fs::fs_queue(fs_id).expect("Fs is not created");
+
+    let fs_task = task::new_task(
+        fs_task,
+        unsafe { queue.into_addr() as _ },
+        task::TaskPriority::Module(1),
+    )
+    .expect("Failed to spawn sys-fs task");
+
+    task::submit_task(fs_task);
+
+    Ok(())
+}
+
+pub struct InitMessage {
+    work: Handle<FsWork>,
+    queue: RawHandle,
+}
+
+extern "C" fn fs_task(raw_handle: *const ()) {
+    let queue = unsafe {
+        Handle::<Queue<FsWork>>::from_addr_unchecked(
+            raw_handle as VirtualAddress,
+        )
+    };
+
+    log::debug!("sys-fs task #{}", current_task!().id);
+
+    loop {
+        let Some(work) = queue.blocking_pop() else {
+            break;
+        };
+
+        match work.take_request() {
+            FsRequest::Mount { .. } => {
+                let sb_info = SuperBlockInfo {
+                    block_size: 512,
+                    queue_size: 3,
+                    context: core::ptr::null(),
+                };
+
+                work.send_response(sb_info.into());
+            }
+            FsRequest::Unmount { .. } => {
+                work.send_response(FsResponse::Completed);
+            }
+            FsRequest::FsQueue { queue } => {
+                let init_message =
+                    Box::try_new(InitMessage { work, queue }).unwrap();
+
+                let arg = Box::into_raw(init_message) as *mut ();
+
+                let sb_task =
+                    task::new_task(sb_task, arg, task::TaskPriority::Module(0))
+                        .unwrap();
+
+                task::submit_task(sb_task);
+            }
+        }
+    }
+}
+
+extern "C" fn sb_task(ptr: *const ()) {
+    let message = unsafe { Box::from_raw(ptr as *mut InitMessage) };
+
+    let message = *message;
+
+    let files = Queue::<FileWork>::new_bounded(3)
+        .expect("Failed to alloc sys-fs file queue");
+
+    let file_task = task::new_task(
+        file_task,
+        unsafe { files.clone().into_addr() as _ },
+        task::TaskPriority::Module(0),
+    )
+    .expect("Failed to spawn sys-fs file task");
+
+    task::submit_task(file_task);
+
+    message.work.send_response(FsResponse::Completed);
+
+    let queue = Handle::<Queue<FileLookupWork>>::from_raw(message.queue);
+
+    log::debug!("sys sb task is started #{}", current_task!().id);
+
+    loop {
+        let Some(work) = queue.blocking_pop() else {
+            break;
+        };
+
+        match work.take_request() {
+            FileLookupRequest::LookupNode { name, sb } => {
+                let _ = Handle::<SuperBlock>::from_raw(sb);
+
+                let Some((id, file)) = SYS_FILES
+                    .iter()
+                    .enumerate()
+                    .find(|(_, file)| file.name == name)
+                else {
+                    work.send_response(OpStatus::NotFound.into());
+                    continue;
+                };
+
+                let inode_info = IndexNodeInfo {
+                    id: id as _,
+                    size: 0,
+                    ctx: file as *const SysFile as *const (),
+                    kind: NodeKind::File,
+                    queue: files.clone().into_raw(),
+                    permissions: FilePermissions::READ,
+                };
+
+                work.send_response(inode_info.into());
+            }
+            FileLookupRequest::DirectoryEnries { sb, .. } => {
+                let _ = Handle::<SuperBlock>::from_raw(sb);
+
+                let entries = SYS_FILES
+                    .iter()
+                    .map(|file| file.name.to_string())
+                    .collect::<Vec<_>>();
+
+                work.send_response(DirEntriesInfo { entries }.into());
+            }
+            FileLookupRequest::FlushNode { .. }
+            | FileLookupRequest::DestroyNode { .. }
+            | FileLookupRequest::CreateFile { .. }
+            | FileLookupRequest::CreateDirectory { .. } => {
+                work.send_response(OpStatus::NotSupported.into());
+            }
+        }
+    }
+}
+
+extern "C" fn file_task(raw_handle: *const ()) {
+    let queue = unsafe {
+        Handle::<Queue<FileWork>>::from_addr_unchecked(
+            raw_handle as VirtualAddress,
+        )
+    };
+
+    loop {
+        let Some(work) = queue.blocking_pop() else {
+            break;
+        };
+
+        match work.take_request() {
+            FileRequest::Read { file, buf, offset } => {
+                let file = Handle::<IndexNode>::from_raw(file);
+                let buf = Handle::<KernelBuf>::from_raw(buf);
+
+                let sys_file = unsafe { &*(file.ctx as *const SysFile) };
+
+                let mut text = String::new();
+
+                if (sys_file.show)(&mut text).is_err() {
+                    work.send_response(OpStatus::Failed.into());
+                    continue;
+                }
+
+                let bytes = text.as_bytes().get(offset..).unwrap_or_default();
+
+                //the content which is not fit is dropped
+                let len = usize::min(bytes.len(), buf.remaining_capacity());
+
+                let response = match buf.copy_from(&bytes[..len]) {
+                    Ok(()) => FileResponse::Completed,
+                    Err(_) => OpStatus::NoSpace.into(),
+                };
+
+                work.send_response(response);
+            }
+            FileRequest::Write { .. } | FileRequest::Command { .. } => {
+                work.send_response(OpStatus::NotSupported.into());
+            }
+        }
+    }
+}
+
+fn owner_name(process: Option<ProcessId>) -> String {
+    let Some(id) = process else {
+        return "kernel".to_string();
+    };
+
+    MODULES
+        .get()
+        .find_module(id)
+        .map(|module| module.name.to_string())
+        .unwrap_or_else(|| alloc::format!("pid {id}"))
+}
+
+fn show_acpi(out: &mut String) -> fmt::Result {
+    let Some(acpi_tables) = acpi::tables() else {
+        return writeln!(out, "ACPI is not available");
+    };
+
+    writeln!(
+        out,
+        "revision {}, OEM {}, {}",
+        acpi_tables.revision,
+        String::from_utf8_lossy(&acpi_tables.oem_id),
+        if acpi_tables.is_extended {
+            "XSDT"
+        } else {
+            "RSDT"
+        }
+    )?;
+
+    writeln!(out)?;
+    writeln!(
+        out,
+        "{:<6}{:<10}{:>5}{:>8}",
+        "table", "oem", "rev", "length"
+    )?;
+
+    for table in acpi_tables.tables.iter() {
+        writeln!(
+            out,
+            "{:<6}{:<10}{:>5}{:>8}",
+            table.signature(),
+            String::from_utf8_lossy(&table.oem_table_id),
+            table.revision,
+            { table.length },
+        )?;
+    }
+
+    if let Some(madt) = acpi::madt::find() {
+        writeln!(out)?;
+        writeln!(out, "MADT: LAPIC 0x{:x}", madt.local_apic)?;
+
+        for processor in madt.processors.iter() {
+            writeln!(
+                out,
+                "  processor {} LAPIC#{}{}",
+                processor.processor_id,
+                processor.apic_id,
+                if processor.enabled { "" } else { " (disabled)" }
+            )?;
+        }
+
+        for io_apic in madt.io_apics.iter() {
+            writeln!(
+                out,
+                "  IO-APIC#{} 0x{:x} GSI {}",
+                io_apic.id, io_apic.address, io_apic.gsi_base
+            )?;
+        }
+
+        for source in madt.overrides.iter() {
+            writeln!(out, "  IRQ{} -> GSI {}", source.source, source.gsi)?;
+        }
+    }
+
+    if let Some(fadt) = acpi::fadt::find() {
+        writeln!(out)?;
+        writeln!(
+            out,
+            "FADT: SCI {}, PM1a control 0x{:x}, 8042 {}",
+            fadt.sci_interrupt, fadt.pm1a_control_block, fadt.has_8042
+        )?;
+
+        if let Some((register, value)) = fadt.reset {
+            writeln!(
+                out,
+                "  reset {:?} 0x{:x} <- 0x{value:x}",
+                register.space, register.address
+            )?;
+        }
+    }
+
+    if let Some(hpet) = acpi::hpet::find() {
+        writeln!(out)?;
+        writeln!(
+            out,
+            "HPET: 0x{:x}, {} comparator(s), 64-bit {}",
+            hpet.base.address, hpet.comparators, hpet.is_64bit
+        )?;
+    }
+
+    Ok(())
+}
+
+fn show_pci(out: &mut String) -> fmt::Result {
+    writeln!(
+        out,
+        "{:<10}{:<11}{:<9}{:>5}  {:<14}bars",
+        "address", "id", "class", "irq", "owner"
+    )?;
+
+    for device in pci::devices() {
+        let info = &device.info;
+
+        write!(
+            out,
+            "{:<10}{:<11}{:<9}{:>5}  {:<14}",
+            alloc::format!("{}", device.address),
+            alloc::format!("{:04x}:{:04x}", info.vendor_id, info.device_id),
+            alloc::format!(
+                "{:02x}.{:02x}.{:02x}",
+                info.class,
+                info.subclass,
+                info.prog_if
+            ),
+            info.irq_line,
+            device
+                .owner
+                .map(|owner| owner_name(Some(owner)))
+                .unwrap_or_default(),
+        )?;
+
+        for bar in info.bars.iter() {
+            match bar {
+                PciBar::None => {}
+                PciBar::Io { port, size } => {
+                    write!(out, " io 0x{port:x}+0x{size:x}")?
+                }
+                PciBar::Memory { address, size, .. } => {
+                    write!(out, " mem 0x{address:x}+0x{size:x}")?
+                }
+            }
+        }
+
+        writeln!(out)?;
+    }
+
+    Ok(())
+}
+
+fn show_objects(out: &mut String) -> fmt::Result {
+    writeln!(out, "{:<16}{:>6}", "kind", "live")?;
+
+    for kind in Kind::ALL {
+        let live = tracking::live_count(kind);
+
+        writeln!(out, "{:<16}{:>6}", alloc::format!("{kind:?}"), live)?;
+    }
+
+    writeln!(out)?;
+    writeln!(out, "{:<16}{:<14}{:>6}  site", "kind", "owner", "live")?;
+
+    for site in tracking::live_sites() {
+        writeln!(
+            out,
+            "{:<16}{:<14}{:>6}  {}",
+            alloc::format!("{:?}", site.kind),
+            owner_name(site.origin.process),
+            site.live,
+            site.origin.location,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Render physical memory, slabs, kernel heap and process usage
+pub fn show_meminfo(out: &mut String) -> fmt::Result {
+    const KB_IN_PAGE: usize = Page::SIZE / 1024;
+
+    let Ok(physical) = memory::physical_stat() else {
+        return writeln!(out, "Physical allocator is busy");
+    };
+
+    writeln!(
+        out,
+        "total {} KB, free {} KB, used {} KB",
+        physical.total_pages * KB_IN_PAGE,
+        physical.free_pages * KB_IN_PAGE,
+        physical.used_pages() * KB_IN_PAGE,
+    )?;
+
+    write!(out, "free batches by power:")?;
+
+    for (power, count) in physical.free_batches.iter().enumerate() {
+        write!(out, " {power}:{count}")?;
+    }
+
+    writeln!(out)?;
+
+    let heap = memory::heap_stat();
+
+    writeln!(
+        out,
+        "heap 0x{:x}-0x{:x}, {} KB committed, {} KB released, {} guard page(s)",
+        heap.start,
+        heap.end,
+        heap.committed_pages() * KB_IN_PAGE,
+        heap.released_pages * KB_IN_PAGE,
+        heap.guard_pages,
+    )?;
+
+    if let Some(swap) = memory::swap_stat() {
+        writeln!(
+            out,
+            "swap {} KB, used {} KB",
+            swap.total_pages * KB_IN_PAGE,
+            swap.used_pages * KB_IN_PAGE,
+        )?;
+    }
+
+    writeln!(out)?;
+    writeln!(
+        out,
+        "{:<20}{:>6}{:>8}{:>8}{:>8}{:>6}{:>8}",
+        "slab", "size", "entries", "objects", "total", "pages", "wasted"
+    )?;
+
+    for slab in memory::slab_stats() {
+        writeln!(
+            out,
+            "{:<20}{:>6}{:>8}{:>8}{:>8}{:>6}{:>8}",
+            slab.name,
+            slab.object_size,
+            slab.entries,
+            slab.objects,
+            slab.capacity,
+            slab.pages,
+            slab.wasted_bytes(),
+        )?;
+    }
+
+    writeln!(out)?;
+    writeln!(
+        out,
+        "{:>5}  {:<14}{:>8}{:>10}{:>10}{:>10}",
+        "pid", "owner", "regions", "virt(KB)", "res(KB)", "own(KB)"
+    )?;
+
+    for process in task::processes() {
+        let stat = process.state.lock().mem_stat();
+
+        writeln!(
+            out,
+            "{:>5}  {:<14}{:>8}{:>10}{:>10}{:>10}",
+            process.id,
+            owner_name(Some(process.id)),
+            stat.regions,
+            stat.virtual_pages * KB_IN_PAGE,
+            stat.resident_pages * KB_IN_PAGE,
+            stat.owned_pages * KB_IN_PAGE,
+        )?;
+    }
+
+    Ok(())
+}
+
+fn show_tasks(out: &mut String) -> fmt::Result {
+    writeln!(
+        out,
+        "{:>5}{:>5}  {:<12}{:<10}{:>10}{:>10}{:>8}",
+        "id", "cpu#", "priority", "status", "cpu(ms)", "vrun(ms)", "runs"
+    )?;
+
+    for task in task::stats() {
+        writeln!(
+            out,
+            "{:>5}{:>5}  {:<12}{:<10}{:>10}{:>10}{:>8}",
+            task.id,
+            task.cpu,
+            alloc::format!("{}", task.priority),
+            task.status,
+            task.cpu_time,
+            task.vruntime / 1_000_000,
+            task.exec_count,
+        )?;
+    }
+
+    Ok(())
+}
@@ -1,11 +1,14 @@
-mod rtc;
-
 use core::arch::asm;
 
-use rtc::{read_rtc_time, RtcTime};
 use volatile::Volatile;
 
-use crate::{common::io, memory::PhysicalAddress};
+use crate::{
+    common::{
+        io,
+        rtc::{read_rtc_time, RtcTime},
+    },
+    memory::PhysicalAddress,
+};
 
 const VGA_WIDTH: usize = 80;
 const VGA_HEIGHT: usize = 25;
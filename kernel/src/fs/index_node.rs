@@ -3,15 +3,15 @@ use kernel_macro::ListNode;
 use kernel_types::{
     collections::{BoxedNode, ListNode},
     container_of,
-    fs::{FileRequest, IndexNodeInfo, NodeId, NodeKind},
+    fs::{FileInfo, FileRequest, IndexNodeInfo, NodeId, NodeKind},
 };
 
 use crate::{
     common::time::Timestamp,
     impl_container,
     io::block::BlockWork,
-    memory::{self, AllocError, Slab, SlabBox},
-    object::{self, Handle, Object, ObjectContainer, UserHandle},
+    memory::{self, AllocError, ProcessId, Slab, SlabBox},
+    object::{self, Handle, Object, ObjectContainer, ObjectInfo, UserHandle},
     user::queue::Queue,
 };
 
@@ -43,6 +43,7 @@ pub struct IndexNode {
 }
 
 impl IndexNode {
+    #[track_caller]
     pub fn new(
         inode: IndexNodeInfo,
         parent: &Handle<SuperBlock>,
@@ -86,10 +87,23 @@ impl IndexNode {
 
         Ok(handle)
     }
+
+    /// The size of file in bytes
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    fn describe(&self, _process: Option<ProcessId>) -> ObjectInfo {
+        ObjectInfo::File(FileInfo {
+            ctx: self.ctx,
+            offset: 0,
+        })
+    }
 }
 
 impl_container! {
     IndexNode,
     obj_kind: File,
-    slab: "inode"
+    slab: "inode",
+    info: IndexNode::describe
 }
@@ -8,7 +8,7 @@ pub use index_node::*;
 use kernel_types::fs::{
     FileLookupRequest, FileRequest, FileSystem, FsId, FsRequest,
 };
-use kernel_types::object::RawHandle;
+use kernel_types::object::{OpStatus, RawHandle};
 use kernel_types::string::QuickString;
 pub use mount_point::*;
 pub use path::*;
@@ -63,6 +63,9 @@ pub enum FsError {
 
     #[error("Invalid file name")]
     InvalidFileName,
+
+    #[error("File operation is failed: {0:?}")]
+    OpFailed(OpStatus),
 }
 
 declare_constants!(
@@ -108,7 +111,9 @@ pub unsafe fn mount_root_fs() -> Result<()> {
     let sb_info = work.wait().unwrap().super_block().unwrap();
     log::debug!("fat fs sb is taken");
 
-    let mount_point = MountPoint::new_boxed(sb_info, "/")?;
+    let fs_queue = FILE_SYSTEMS.fs_by_name("fat-fs", |fs| Ok(fs.queue()))?;
+
+    let mount_point = MountPoint::new_boxed(sb_info, "/", fs_queue)?;
 
     let queue = mount_point.queue().into_raw();
 
@@ -132,37 +137,59 @@ pub fn mkdir(path: &str) -> Result<Handle<FileLookupWork>> {
 }
 
 pub unsafe fn mount_dev_fs() -> Result<()> {
+    let mount_point = mount_kernel_fs("dev-fs", "/dev")?;
+
+    FILE_SYSTEMS.set_dev_fs(mount_point.into_node());
+
+    Ok(())
+}
+
+pub unsafe fn mount_sys_fs() -> Result<()> {
+    let mount_point = mount_kernel_fs("sys-fs", "/sys")?;
+
+    FILE_SYSTEMS.set_sys_fs(mount_point.into_node());
+
+    Ok(())
+}
+
+/// Mount file system served by kernel task without device
+unsafe fn mount_kernel_fs(fs_name: &str, path: &str) -> Result<MountPointBox> {
     let work = FILE_SYSTEMS
-        .fs_by_name("dev-fs", move |fs| {
-            log::debug!("sending dev-fs mount");
+        .fs_by_name(fs_name, move |fs| {
+            log::debug!("sending {fs_name} mount");
 
             fs.send_request(FsRequest::Mount {
                 device: RawHandle::null(),
             })
         })
-        .expect("Failed to mount dev-fs");
+        .unwrap_or_else(|_| panic!("Failed to mount {fs_name}"));
 
     let sb_info = work.wait().unwrap().super_block().unwrap();
-    log::debug!("dev fs sb is taken");
+    log::debug!("{fs_name} sb is taken");
 
-    let mount_point = MountPoint::new_boxed(sb_info, "/dev")?;
+    let fs_queue = FILE_SYSTEMS.fs_by_name(fs_name, |fs| Ok(fs.queue()))?;
+
+    let mount_point = MountPoint::new_boxed(sb_info, path, fs_queue)?;
 
     let queue = mount_point.queue().into_raw();
 
     let work = FILE_SYSTEMS
-        .fs_by_name("dev-fs", |fs| {
-            log::debug!("Sending dev-fs queue");
+        .fs_by_name(fs_name, |fs| {
+            log::debug!("Sending {fs_name} queue");
             fs.send_request(FsRequest::FsQueue { queue })
         })
         .unwrap();
 
     work.wait().unwrap().status().unwrap();
 
-    log::info!("Mounting dev-fs");
+    log::info!("Mounting {fs_name}");
 
-    FILE_SYSTEMS.set_dev_fs(mount_point.into_node());
+    Ok(mount_point)
+}
 
-    Ok(())
+/// Unmount all file systems in reverse order of mounting
+pub fn unmount_all() {
+    FILE_SYSTEMS.unmount_all();
 }
 
 pub fn mount(path: &str, fs_name: &str, dev_name: &str) -> Result<()> {
@@ -222,9 +249,11 @@ pub fn read(
         return Err(FsError::InvalidFileHandle);
     };
 
+    //the opened files have no position, so reading starts at the beginning
     let res = FileRequest::Read {
         file: file.handle().into_raw(),
         buf: buf.into_raw(),
+        offset: 0,
     };
 
     file.send_request(res)
@@ -241,11 +270,52 @@ pub fn write(
     let req = FileRequest::Write {
         buf: buf.into_raw(),
         file: file.handle().into_raw(),
+        offset: 0,
     };
 
     file.send_request(req)
 }
 
+/// Read the part of `node` starting at `offset` into `buf`.
+/// The task is blocked until file system responds
+pub fn read_at(
+    node: &Handle<IndexNode>,
+    offset: usize,
+    buf: Handle<KernelBuf>,
+) -> Result<()> {
+    let work = node.send_request(FileRequest::Read {
+        file: node.handle().into_raw(),
+        buf: buf.into_raw(),
+        offset,
+    })?;
+
+    complete(work)
+}
+
+/// Write `buf` to `node` starting at `offset`.
+/// The task is blocked until file system responds
+pub fn write_at(
+    node: &Handle<IndexNode>,
+    offset: usize,
+    buf: Handle<KernelBuf>,
+) -> Result<()> {
+    let work = node.send_request(FileRequest::Write {
+        file: node.handle().into_raw(),
+        buf: buf.into_raw(),
+        offset,
+    })?;
+
+    complete(work)
+}
+
+fn complete(work: Handle<FileWork>) -> Result<()> {
+    let Some(response) = work.wait() else {
+        return Err(FsError::FsIsDead);
+    };
+
+    response.status().map_err(FsError::OpFailed)
+}
+
 pub fn close(file_handle: usize) -> Result<object::RawHandle> {
     let Some(_file) = current_task!().opened_files.get(file_handle) else {
         return Err(FsError::InvalidFileHandle);
@@ -3,7 +3,7 @@ use elf::hash;
 use kernel_macro::ListNode;
 use kernel_types::{
     collections::{BoxedNode, ListNode},
-    fs::{FileLookupRequest, SuperBlockInfo},
+    fs::{FileLookupRequest, FsRequest, SuperBlockInfo},
 };
 
 use crate::{
@@ -13,7 +13,7 @@ use crate::{
     user::queue::Queue,
 };
 
-use super::{File, FileLookupWork, FileSystemItem, Result, SuperBlock};
+use super::{File, FileLookupWork, FileSystemItem, FsWork, Result, SuperBlock};
 
 pub struct MountPointBox {
     mount_point: SlabBox<MountPoint>,
@@ -35,6 +35,8 @@ pub struct MountPoint {
 
     path_node: alloc::string::String,
     sb: Handle<SuperBlock>,
+    /// The queue of file system serving the mount point
+    fs_queue: Handle<Queue<FsWork>>,
     // parent_mount: Option<NonNull<MountPoint>>,
     // //the vfs' root doesn't have parent
     // //child_mounts: LinkedList<'static, MountPoint>,
@@ -51,11 +53,13 @@ impl MountPoint {
     pub fn new_boxed(
         sb_info: SuperBlockInfo,
         mount_point: &str,
+        fs_queue: Handle<Queue<FsWork>>,
     ) -> Result<MountPointBox> {
         let super_block = SuperBlock::new(sb_info)?;
 
         let mount_point = crate::memory::slab_alloc(Self {
             sb: super_block,
+            fs_queue,
             path_node: mount_point.to_string(),
             node: ListNode::empty(),
         })?;
@@ -71,6 +75,17 @@ impl MountPoint {
         self.sb.queue.clone()
     }
 
+    /// Ask file system to write back and release the super block
+    pub fn unmount(&self) -> Result<Handle<FsWork>> {
+        let request = FsRequest::Unmount {
+            fs: self.sb.handle().into_raw(),
+        };
+
+        let work = unsafe { FsWork::new_boxed(request, &self.fs_queue)? };
+
+        Ok(self.fs_queue.push(work))
+    }
+
     pub fn mkdir(&self, name: &str) -> Result<Handle<FileLookupWork>> {
         let req = FileLookupRequest::CreateDirectory {
             sb: self.sb.handle().into_raw(),
@@ -70,6 +70,7 @@ pub struct SuperBlock {
 }
 
 impl SuperBlock {
+    #[track_caller]
     pub fn new(info: SuperBlockInfo) -> Result<Handle<SuperBlock>, AllocError> {
         let queue = Queue::new_bounded(info.queue_size)?;
 
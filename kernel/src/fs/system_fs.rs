@@ -3,7 +3,7 @@ use core::{
     sync::atomic::{AtomicUsize, Ordering},
 };
 
-use alloc::{boxed::Box, string::ToString};
+use alloc::{boxed::Box, string::ToString, vec::Vec};
 use kernel_types::{
     collections::{LinkedList, ListNode},
     fs::{FileSystem, FsId},
@@ -65,6 +65,42 @@ impl SystemMountPoints {
         self.mounts.lock().push_back(fs);
     }
 
+    pub unsafe fn set_sys_fs(&self, fs: &'static mut ListNode<MountPoint>) {
+        self.mounts.lock().push_back(fs);
+    }
+
+    pub fn unmount_all(&self) {
+        let mut mounted = Vec::new();
+
+        let mut mounts = self.mounts.lock();
+
+        while let Some(mount) = mounts.remove_first() {
+            mounted.push(mount.into_boxed());
+        }
+
+        drop(mounts);
+
+        //the root file system is mounted first
+        for mount in mounted.into_iter().rev() {
+            let status = mount
+                .unmount()
+                .and_then(|work| work.wait().ok_or(FsError::FsIsDead))
+                .map(|response| response.status());
+
+            match status {
+                Ok(Ok(())) => log::info!("{} is unmounted", mount.path_node()),
+                Ok(Err(status)) => log::warn!(
+                    "Failed to unmount {}: {status:?}",
+                    mount.path_node()
+                ),
+                Err(cause) => log::warn!(
+                    "Failed to unmount {}: {cause}",
+                    mount.path_node()
+                ),
+            }
+        }
+    }
+
     pub fn fs_by_name<F, T>(&self, name: &str, mut action: F) -> fs::Result<T>
     where
         F: FnOnce(&FileSystemItem) -> fs::Result<T>,
@@ -118,6 +154,24 @@ impl SystemMountPoints {
             return action(device_name, dev_fs);
         }
 
+        if let Some(sys_path) = path.strip_prefix("sys") {
+            let Some(file_name) = sys_path.strip_prefix("/") else {
+                return Err(fs::FsError::NotFound);
+            };
+
+            let Some(sys_fs) = self
+                .mounts
+                .try_lock()
+                .unwrap()
+                .iter()
+                .find(|mount| mount.path_node().eq("/sys"))
+            else {
+                return Err(fs::FsError::NotFound);
+            };
+
+            return action(file_name, sys_fs);
+        }
+
         let root_fs = self
             .mounts
             .try_lock()
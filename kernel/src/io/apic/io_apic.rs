@@ -0,0 +1,117 @@
+use core::ptr;
+
+use crate::memory::VirtualAddress;
+
+const REGISTER_SELECT: usize = 0x00;
+const REGISTER_WINDOW: usize = 0x10;
+
+const VERSION: u32 = 0x01;
+const REDIRECTION_TABLE: u32 = 0x10;
+
+const ACTIVE_LOW: u32 = 1 << 13;
+const LEVEL_TRIGGERED: u32 = 1 << 15;
+const MASKED: u32 = 1 << 16;
+
+/// The routing of interrupt input to local APIC
+#[derive(Debug, Clone, Copy)]
+pub struct Redirection {
+    pub vector: u8,
+    pub destination: u8,
+    pub active_low: bool,
+    pub level_triggered: bool,
+}
+
+impl Redirection {
+    fn low(&self) -> u32 {
+        let mut value = self.vector as u32;
+
+        if self.active_low {
+            value |= ACTIVE_LOW;
+        }
+
+        if self.level_triggered {
+            value |= LEVEL_TRIGGERED;
+        }
+
+        value
+    }
+}
+
+pub struct IoApic {
+    base: VirtualAddress,
+    /// The first global system interrupt served by controller
+    gsi_base: u32,
+    inputs: u32,
+}
+
+impl IoApic {
+    /// # Safety
+    /// `base` should be the mapped register page of IO-APIC
+    pub unsafe fn new(base: VirtualAddress, gsi_base: u32) -> Self {
+        let mut io_apic = Self {
+            base,
+            gsi_base,
+            inputs: 0,
+        };
+
+        io_apic.inputs = ((io_apic.read(VERSION) >> 16) & 0xFF) + 1;
+
+        for input in 0..io_apic.inputs {
+            io_apic.set_masked(input, true);
+        }
+
+        io_apic
+    }
+
+    fn read(&self, register: u32) -> u32 {
+        unsafe {
+            ptr::write_volatile(
+                (self.base + REGISTER_SELECT) as *mut u32,
+                register,
+            );
+            ptr::read_volatile((self.base + REGISTER_WINDOW) as *const u32)
+        }
+    }
+
+    fn write(&self, register: u32, value: u32) {
+        unsafe {
+            ptr::write_volatile(
+                (self.base + REGISTER_SELECT) as *mut u32,
+                register,
+            );
+            ptr::write_volatile(
+                (self.base + REGISTER_WINDOW) as *mut u32,
+                value,
+            )
+        }
+    }
+
+    /// The input of controller for global system interrupt
+    pub fn input_of(&self, gsi: u32) -> Option<u32> {
+        let input = gsi.checked_sub(self.gsi_base)?;
+
+        (input < self.inputs).then_some(input)
+    }
+
+    /// The input is masked until it is explicitly unmasked
+    pub fn redirect(&self, input: u32, redirection: Redirection) {
+        let register = REDIRECTION_TABLE + input * 2;
+
+        self.write(register + 1, (redirection.destination as u32) << 24);
+        self.write(register, redirection.low() | MASKED);
+    }
+
+    pub fn set_masked(&self, input: u32, masked: bool) {
+        let register = REDIRECTION_TABLE + input * 2;
+
+        let value = self.read(register);
+
+        let value = if masked {
+            value | MASKED
+        } else {
+            value & !MASKED
+        };
+
+        self.write(register, value);
+    }
+}
@@ -0,0 +1,128 @@
+use core::ptr;
+
+use kernel_types::declare_constants;
+
+use crate::{memory::VirtualAddress, task::clocks};
+
+declare_constants!(
+    usize,
+    ID = 0x20;
+    TASK_PRIORITY = 0x80;
+    EOI = 0xB0;
+    SPURIOUS = 0xF0;
+    INTERRUPT_COMMAND_LOW = 0x300;
+    INTERRUPT_COMMAND_HIGH = 0x310;
+    LVT_TIMER = 0x320;
+    TIMER_INITIAL_COUNT = 0x380;
+    TIMER_CURRENT_COUNT = 0x390;
+    TIMER_DIVIDE = 0x3E0;
+);
+
+declare_constants!(
+    u32,
+    APIC_ENABLE = 1 << 8, "The software enable flag of spurious register";
+    LVT_MASKED = 1 << 16;
+    TIMER_PERIODIC = 1 << 17;
+    DIVIDE_BY_16 = 0x3;
+    DELIVERY_INIT = 0b101 << 8;
+    DELIVERY_STARTUP = 0b110 << 8;
+    DELIVERY_PENDING = 1 << 12, "The previous IPI is not accepted yet";
+    LEVEL_ASSERT = 1 << 14;
+);
+
+/// The vector of spurious interrupts. They require no EOI
+pub const SPURIOUS_VECTOR: u8 = 0xFF;
+
+/// The interrupt controller of current processor
+pub struct LocalApic {
+    base: VirtualAddress,
+}
+
+impl LocalApic {
+    /// # Safety
+    /// `base` should be the mapped register page of local APIC
+    pub unsafe fn new(base: VirtualAddress) -> Self {
+        Self { base }
+    }
+
+    fn read(&self, register: usize) -> u32 {
+        unsafe { ptr::read_volatile((self.base + register) as *const u32) }
+    }
+
+    fn write(&self, register: usize, value: u32) {
+        unsafe {
+            ptr::write_volatile((self.base + register) as *mut u32, value)
+        }
+    }
+
+    pub fn id(&self) -> u8 {
+        (self.read(ID) >> 24) as u8
+    }
+
+    pub fn enable(&self) {
+        //accept all interrupts
+        self.write(TASK_PRIORITY, 0);
+        self.write(SPURIOUS, APIC_ENABLE | SPURIOUS_VECTOR as u32);
+    }
+
+    pub fn eoi(&self) {
+        self.write(EOI, 0);
+    }
+
+    fn wait_delivery(&self) {
+        while self.read(INTERRUPT_COMMAND_LOW) & DELIVERY_PENDING != 0 {
+            core::hint::spin_loop();
+        }
+    }
+
+    fn send_command(&self, destination: u8, command: u32) {
+        self.wait_delivery();
+
+        self.write(INTERRUPT_COMMAND_HIGH, (destination as u32) << 24);
+        self.write(INTERRUPT_COMMAND_LOW, command);
+
+        self.wait_delivery();
+    }
+
+    /// Raise `vector` on processor with given LAPIC id
+    pub fn send_ipi(&self, destination: u8, vector: u8) {
+        self.send_command(destination, vector as u32 | LEVEL_ASSERT);
+    }
+
+    /// Reset the processor to wait for Startup IPI
+    pub fn send_init(&self, destination: u8) {
+        self.send_command(destination, DELIVERY_INIT | LEVEL_ASSERT);
+    }
+
+    /// Start the processor in real mode at `page` * 4096
+    pub fn send_startup(&self, destination: u8, page: u8) {
+        self.send_command(
+            destination,
+            DELIVERY_STARTUP | LEVEL_ASSERT | page as u32,
+        );
+    }
+
+    /// The count of timer ticks per millisecond measured by PIT
+    pub fn calibrate_timer(&self) -> u32 {
+        self.write(TIMER_DIVIDE, DIVIDE_BY_16);
+        self.write(LVT_TIMER, LVT_MASKED);
+        self.write(TIMER_INITIAL_COUNT, u32::MAX);
+
+        clocks::pit_wait(clocks::CALIBRATION_MS);
+
+        let elapsed = u32::MAX - self.read(TIMER_CURRENT_COUNT);
+
+        self.write(TIMER_INITIAL_COUNT, 0);
+
+        elapsed / clocks::CALIBRATION_MS
+    }
+
+    /// Fire `vector` each `period` milliseconds.
+    /// The timers of all processors run at the same rate,
+    /// so they are calibrated only once
+    pub fn start_timer(&self, vector: u8, period: usize, ticks_per_ms: u32) {
+        self.write(TIMER_DIVIDE, DIVIDE_BY_16);
+        self.write(LVT_TIMER, vector as u32 | TIMER_PERIODIC);
+        self.write(TIMER_INITIAL_COUNT, ticks_per_ms * period as u32);
+    }
+}
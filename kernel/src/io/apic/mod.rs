@@ -0,0 +1,238 @@
+//! Local APIC and IO-APIC discovered via ACPI MADT.
+//! When they are present, ISA interrupts are routed through IO-APIC
+//! to the same vectors as with 8259 PIC and LAPIC timer drives the scheduler
+
+mod io_apic;
+mod local;
+
+use core::arch::x86::__cpuid;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use alloc::vec::Vec;
+
+use crate::{
+    acpi::{self, Madt},
+    common::atomics::UnsafeLazyCell,
+    memory::{self, Page},
+    task::clocks,
+};
+
+use super::{
+    pic::{self, PicLine, LINES_COUNT},
+    IrqLine,
+};
+
+use io_apic::{IoApic, Redirection};
+use local::LocalApic;
+
+/// The input of IO-APIC serving ISA line
+#[derive(Debug, Clone, Copy)]
+struct Route {
+    io_apic: usize,
+    input: u32,
+}
+
+struct Apic {
+    local: LocalApic,
+    io_apics: Vec<IoApic>,
+    routes: [Option<Route>; LINES_COUNT],
+    /// The count of LAPIC timer ticks per millisecond
+    timer_rate: u32,
+}
+
+static APIC: UnsafeLazyCell<Apic> = UnsafeLazyCell::empty();
+static IS_ENABLED: AtomicBool = AtomicBool::new(false);
+
+fn has_apic() -> bool {
+    const APIC_FLAG: u32 = 1 << 9;
+
+    let features = unsafe { __cpuid(1) };
+
+    features.edx & APIC_FLAG != 0
+}
+
+/// `true` if interrupts are served by APIC instead of 8259 PIC
+pub fn is_enabled() -> bool {
+    IS_ENABLED.load(Ordering::SeqCst)
+}
+
+/// Switch to APIC if it is available.
+/// Return `false` if 8259 PIC should be used.
+/// Interrupts should be disabled
+pub fn init() -> bool {
+    if !has_apic() {
+        log::info!("No APIC. 8259 PIC is used");
+        return false;
+    }
+
+    let Some(madt) = acpi::madt::find() else {
+        log::warn!("No MADT. 8259 PIC is used");
+        return false;
+    };
+
+    if madt.io_apics.is_empty() {
+        log::warn!("No IO-APIC. 8259 PIC is used");
+        return false;
+    }
+
+    match setup(&madt) {
+        Ok(mut apic) => {
+            apic.timer_rate = apic.local.calibrate_timer();
+
+            log::info!("LAPIC timer: {} ticks per ms", apic.timer_rate);
+
+            APIC.set(apic);
+            IS_ENABLED.store(true, Ordering::SeqCst);
+
+            //the LAPIC timer replaces PIT
+            start_timer();
+
+            true
+        }
+        Err(cause) => {
+            log::warn!("Failed to init APIC: {cause}. 8259 PIC is used");
+            false
+        }
+    }
+}
+
+fn setup(madt: &Madt) -> Result<Apic, memory::AllocError> {
+    let local_base = memory::map_device(madt.local_apic, Page::SIZE)?;
+    let local = unsafe { LocalApic::new(local_base) };
+
+    let mut io_apics = Vec::new();
+
+    for entry in madt.io_apics.iter() {
+        let base = memory::map_device(entry.address, Page::SIZE)?;
+
+        io_apics.push(unsafe { IoApic::new(base, entry.gsi_base) });
+    }
+
+    if madt.has_pic {
+        unsafe { pic::disable() };
+    }
+
+    local.enable();
+
+    let mut apic = Apic {
+        routes: [None; LINES_COUNT],
+        timer_rate: 0,
+        local,
+        io_apics,
+    };
+
+    for line in 0..LINES_COUNT as u8 {
+        let line = PicLine::try_from(line).unwrap();
+
+        apic.route(madt, line);
+    }
+
+    log::info!(
+        "APIC is enabled. LAPIC#{}, {} IO-APIC(s)",
+        apic.local.id(),
+        apic.io_apics.len()
+    );
+
+    Ok(apic)
+}
+
+impl Apic {
+    fn route(&mut self, madt: &Madt, line: PicLine) {
+        let irq = u8::from(line);
+
+        let (gsi, active_low, level_triggered) = match madt.isa_override(irq) {
+            Some(entry) => {
+                (entry.gsi, entry.is_active_low(), entry.is_level_triggered())
+            }
+            //ISA interrupts are edge triggered and active high
+            None => (irq as u32, false, false),
+        };
+
+        let Some((index, input)) = self
+            .io_apics
+            .iter()
+            .enumerate()
+            .find_map(|(index, io_apic)| Some((index, io_apic.input_of(gsi)?)))
+        else {
+            log::warn!("No IO-APIC input for {line:?} (GSI {gsi})");
+            return;
+        };
+
+        self.io_apics[index].redirect(
+            input,
+            Redirection {
+                vector: IrqLine::from(line).interrupt,
+                destination: self.local.id(),
+                active_low,
+                level_triggered,
+            },
+        );
+
+        //the system timer is served by LAPIC,
+        //the cascade line has no meaning
+        if !matches!(line, PicLine::IRQ0 | PicLine::IRQ2) {
+            self.io_apics[index].set_masked(input, false);
+        }
+
+        self.routes[irq as usize] = Some(Route {
+            io_apic: index,
+            input,
+        });
+    }
+
+    fn set_masked(&self, line: PicLine, masked: bool) {
+        let Some(route) = self.routes[u8::from(line) as usize] else {
+            return;
+        };
+
+        self.io_apics[route.io_apic].set_masked(route.input, masked);
+    }
+}
+
+fn start_timer() {
+    let apic = APIC.get();
+
+    apic.local.start_timer(
+        IrqLine::SYS_TIMER.interrupt,
+        clocks::TIMEOUT,
+        apic.timer_rate,
+    );
+}
+
+/// Enable LAPIC of application processor.
+/// The ISA interrupts are still delivered to the bootstrap processor
+pub fn init_cpu() {
+    APIC.get().local.enable();
+
+    start_timer();
+}
+
+/// The LAPIC id of current processor
+pub fn local_id() -> u8 {
+    APIC.try_get().map(|apic| apic.local.id()).unwrap_or(0)
+}
+
+/// Raise `vector` on processor with given LAPIC id
+pub fn send_ipi(apic_id: u8, vector: u8) {
+    APIC.get().local.send_ipi(apic_id, vector);
+}
+
+pub fn send_init(apic_id: u8) {
+    APIC.get().local.send_init(apic_id);
+}
+
+pub fn send_startup(apic_id: u8, page: u8) {
+    APIC.get().local.send_startup(apic_id, page);
+}
+
+pub fn set_mask(line: PicLine) {
+    APIC.get().set_masked(line, true);
+}
+
+pub fn clear_mask(line: PicLine) {
+    APIC.get().set_masked(line, false);
+}
+
+pub fn complete() {
+    APIC.get().local.eoi();
+}
@@ -2,5 +2,5 @@
 mod device;
 mod work;
 
-pub use kernel_types::io::block::{Request, Response, Work};
+pub use kernel_types::io::block::{Request, Response, Work, CMD_FLUSH};
 pub use work::BlockWork;
@@ -1,7 +1,7 @@
 use kernel_types::collections::LinkedList;
 
 use crate::io::pic::PicLine;
-use crate::io::{pic, CallbackInfo};
+use crate::io::{self, pic, CallbackInfo};
 use crate::memory::{self, Slab, SlabBox};
 use crate::ticks_now;
 
@@ -10,7 +10,8 @@ use crate::task::TaskContext;
 ///The manager struct that handle all request for given interrupt.
 #[derive(Debug)]
 pub struct IrqChain {
-    callbacks: spin::Mutex<LinkedList<'static, CallbackInfo>>,
+    //the chain is dispatched by several processors at the same time
+    callbacks: spin::RwLock<LinkedList<'static, CallbackInfo>>,
     //the interrupt number
     line: PicLine,
 }
@@ -25,7 +26,7 @@ unsafe impl Send for IrqChain {}
 
 impl IrqChain {
     pub fn new(line: PicLine) -> Self {
-        let callbacks = spin::Mutex::new(Default::default());
+        let callbacks = spin::RwLock::new(Default::default());
 
         Self { callbacks, line }
     }
@@ -36,7 +37,7 @@ impl IrqChain {
 
         let mut is_dispatched = false;
 
-        let callbacks = self.callbacks.try_lock().unwrap();
+        let callbacks = self.callbacks.read();
 
         for callback in callbacks.iter() {
             is_dispatched |= callback.invoke(is_dispatched, frame); //if first is already dispatch the interrupt then other can only check
@@ -59,11 +60,37 @@ impl IrqChain {
 
         let leaked_info = SlabBox::leak(boxed_info);
 
-        let mut list = self.callbacks.try_lock().unwrap();
+        let mut list = self.callbacks.write();
 
         list.push_back(leaked_info.as_next());
     }
 
+    /// Unlink the callback registered with `context`. No callback
+    /// is running after the return, so its context can be released
+    pub fn remove(&self, context: *const ()) -> bool {
+        let should_restore = unsafe { io::status() };
+
+        //the chain is read by interrupt on this processor
+        unsafe { io::disable() };
+
+        let mut callbacks = self.callbacks.write();
+
+        let removed = callbacks.remove_by(|info| info.context == context);
+        let is_removed = removed.is_some();
+
+        if let Some(info) = removed {
+            drop(info.into_boxed());
+        }
+
+        drop(callbacks);
+
+        if should_restore {
+            unsafe { io::enable() };
+        }
+
+        is_removed
+    }
+
     pub fn line(&self) -> PicLine {
         self.line
     }
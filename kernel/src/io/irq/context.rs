@@ -2,7 +2,9 @@ use core::ops::DerefMut;
 
 use alloc::sync::Arc;
 use kernel_types::{
-    collections::LinkedList, drivers::ModuleId, io::IoOperation,
+    collections::LinkedList,
+    drivers::ModuleId,
+    io::{IoOperation, PortOperation},
 };
 
 use crate::{
@@ -10,15 +12,46 @@ use crate::{
     error::KernelError,
     io::{InterruptableLazyCell, IrqLine},
     memory::{self, SlabBox},
-    object::{Handle, Object, ObjectContainer},
+    object::{self, Handle, Object, ObjectContainer},
     user::queue::Queue,
 };
 
 use super::IrqEvent;
 
+/// The operation run by irq handler. The hook owns reference to
+/// its kernel buffer, so the module can't free it under the handler
+pub struct IrqHook {
+    op: IoOperation,
+}
+
+impl IrqHook {
+    /// # Safety
+    /// The buffer of `op` should be kernel object referenced by caller.
+    /// The reference is released with the hook
+    pub unsafe fn from_raw(op: IoOperation) -> Self {
+        Self { op }
+    }
+
+    pub fn op(&self) -> &IoOperation {
+        &self.op
+    }
+}
+
+impl Drop for IrqHook {
+    fn drop(&mut self) {
+        if let IoOperation::PortOperation(
+            PortOperation::ReadBytesToBuf { buf, .. }
+            | PortOperation::ReadWordsToBuf { buf, .. },
+        ) = self.op
+        {
+            unsafe { object::drop_raw(buf) };
+        }
+    }
+}
+
 pub struct ModuleIrqContext {
     pub module_id: ModuleId,
-    pub hook_op: Option<IoOperation>,
+    pub hook: Option<IrqHook>,
     pub line: IrqLine,
     //that's safe to handle in interrupt
     //as nested interrupts are not allowed
@@ -43,7 +76,7 @@ const RESERVED_EVENTS_COUNT: usize = 7;
 impl ModuleIrqContext {
     pub fn new(
         line: IrqLine,
-        hook: Option<IoOperation>,
+        hook: Option<IrqHook>,
         queue: Handle<Queue<IrqEvent>>,
     ) -> Result<Arc<ModuleIrqContext>, KernelError> {
         let Some(module) = current_module() else {
@@ -62,7 +95,7 @@ impl ModuleIrqContext {
             line,
             reserved_events: InterruptableLazyCell::new(reserved_events),
             queue,
-            hook_op: hook,
+            hook,
             module_id: module.id,
         })?;
 
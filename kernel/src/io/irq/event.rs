@@ -15,12 +15,15 @@ pub struct IrqEvent {
 }
 
 impl IrqEvent {
+    #[track_caller]
     pub fn new_detached(line: IrqLine) -> Result<SlabBox<Self>, AllocError> {
         crate::memory::slab_alloc(Self {
             line,
             object: Self::new_root_object(),
         })
     }
+
+    #[track_caller]
     pub fn new_boxed(
         line: IrqLine,
         parent: &Handle<Queue<IrqEvent>>,
@@ -41,7 +44,7 @@ impl_container! {
 impl<'a> From<&'a IrqEvent> for IrqMessage {
     fn from(value: &'a IrqEvent) -> Self {
         Self {
-            line: value.line.line.into()
+            line: value.line.line.into(),
         }
     }
 }
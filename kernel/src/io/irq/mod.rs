@@ -5,7 +5,7 @@ mod event;
 use core::sync::atomic::AtomicUsize;
 
 pub use chain::*;
-pub use context::ModuleIrqContext;
+pub use context::{IrqHook, ModuleIrqContext};
 pub use event::IrqEvent;
 
 use crate::{
@@ -43,8 +43,8 @@ pub fn module_irq(
         log::warn!("Failed to notify process via irq: {cause}");
     }
 
-    if let Some(op) = context.hook_op.as_ref() {
-        unsafe { interpretate_op(op) };
+    if let Some(hook) = context.hook.as_ref() {
+        unsafe { interpretate_op(hook.op()) };
     }
 
     pic::complete(context.line.into());
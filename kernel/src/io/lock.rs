@@ -3,11 +3,14 @@ use core::marker::PhantomData;
 use core::ops::{Deref, DerefMut};
 use core::ptr::NonNull;
 
+use crate::common::atomics::RecursiveSpinLock;
 use crate::io;
 
-///should be used only in single cpu architectures
+///disable interrupts on current cpu and spin until other cpus release the cell.
+///The cpu holding the lock can take it again (as in single cpu case)
 pub struct InterruptableLazyCell<T> {
     cell: UnsafeCell<Option<T>>,
+    lock: RecursiveSpinLock,
 }
 
 unsafe impl<T> Send for InterruptableLazyCell<T> {}
@@ -18,12 +21,14 @@ impl<T> InterruptableLazyCell<T> {
     pub const fn empty() -> Self {
         Self {
             cell: UnsafeCell::new(None),
+            lock: RecursiveSpinLock::new(),
         }
     }
 
     pub const fn new(value: T) -> Self {
         Self {
             cell: UnsafeCell::new(Some(value)),
+            lock: RecursiveSpinLock::new(),
         }
     }
 
@@ -39,7 +44,7 @@ impl<T> InterruptableLazyCell<T> {
     pub fn lock(&self) -> InterruptableLock<T> {
         let cell = unsafe { &mut *self.cell.get() };
         if let Some(data) = cell {
-            InterruptableLock::new(data)
+            InterruptableLock::new(data, &self.lock)
         } else {
             unreachable!("Failed to fetch from int lazy cell");
         }
@@ -48,18 +53,22 @@ impl<T> InterruptableLazyCell<T> {
 
 pub struct InterruptableLock<'a, T> {
     data: NonNull<T>,
+    lock: &'a RecursiveSpinLock,
     should_restore: bool,
     _marker: PhantomData<&'a mut T>,
 }
 
 impl<'a, T> InterruptableLock<'a, T> {
-    fn new(data: &'a mut T) -> Self {
+    fn new(data: &'a mut T, lock: &'a RecursiveSpinLock) -> Self {
         let should_restore = unsafe { io::status() };
 
         unsafe { io::disable() };
 
+        lock.acquire();
+
         Self {
             should_restore,
+            lock,
             data: NonNull::from(data),
             _marker: PhantomData,
         }
@@ -82,6 +91,8 @@ impl<'a, T> DerefMut for InterruptableLock<'a, T> {
 
 impl<'a, T> Drop for InterruptableLock<'a, T> {
     fn drop(&mut self) {
+        self.lock.release();
+
         if self.should_restore {
             unsafe { io::enable() }
         }
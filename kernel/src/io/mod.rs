@@ -3,7 +3,7 @@ use core::sync::atomic::AtomicUsize;
 use core::{mem, ptr};
 
 use alloc::sync::Arc;
-pub use irq::{module_irq, ModuleIrqContext};
+pub use irq::{module_irq, IrqHook, ModuleIrqContext};
 use kernel_macro::ListNode;
 use kernel_types::collections::{BoxedNode, ListNode};
 use kernel_types::io::IoOperation;
@@ -13,7 +13,7 @@ use kernel_types::{
 
 use crate::common::io::{inb, inw, outb, outw};
 use crate::current_task;
-use crate::drivers::current_module;
+use crate::drivers::{current_module, Module};
 use crate::error::KernelError;
 use crate::io;
 use crate::io::irq::IrqChain;
@@ -29,12 +29,15 @@ use crate::user::queue::Queue;
 
 pub use irq::IrqEvent;
 pub use lock::InterruptableLazyCell;
+pub use system::double_fault_task;
 
+pub(crate) mod apic;
 pub mod block;
 pub mod char;
 mod irq;
 mod lock;
 pub(crate) mod pic;
+pub mod ports;
 mod system;
 
 #[derive(Debug)]
@@ -49,6 +52,21 @@ pub struct InterruptStackFrame {
     ss: usize,
 }
 
+impl InterruptStackFrame {
+    pub const fn ip(&self) -> usize {
+        self.ip
+    }
+
+    pub const fn flags(&self) -> usize {
+        self.flags
+    }
+
+    /// The stack of interrupted task (valid only for user space)
+    pub const fn esp(&self) -> usize {
+        self.esp
+    }
+}
+
 pub type NakedExceptionHandler = extern "x86-interrupt" fn(InterruptStackFrame);
 pub type SyscallHandler = unsafe extern "x86-interrupt" fn(InterruptStackFrame);
 pub type ErrorExceptionHandler =
@@ -250,7 +268,7 @@ const MAX_INTERRUPTS_COUNT: usize = 256;
 
 pub fn set_irq(
     line: IrqLine,
-    hook: Option<IoOperation>,
+    hook: Option<IrqHook>,
 ) -> Result<Handle<Queue<IrqEvent>>, KernelError> {
     let queue = Queue::new_bounded(10)?;
 
@@ -260,33 +278,50 @@ pub fn set_irq(
 
     let ctx = ModuleIrqContext::new(line, hook, queue.clone())?;
 
-    module.set_irq_ctx(ctx.clone());
+    let replaced = module.set_irq_ctx(ctx.clone());
 
     let info = CallbackInfo::new(module_irq, Arc::into_raw(ctx).cast());
 
     let index = u8::from(line.line) as usize;
-    let interceptors = INTERCEPTORS.try_lock().unwrap().unwrap();
+    let interceptors = INTERCEPTORS.read().unwrap();
 
     let manager = interceptors[index];
 
     manager.append(info);
 
+    drop(interceptors);
+
+    if let Some(replaced) = replaced {
+        unlink_irq_ctx(&replaced);
+    }
+
     Ok(queue)
 }
 
+/// Remove irq handler of module, so its hook is released
+pub fn release_irq(module: &Module) {
+    if let Some(ctx) = module.take_irq_ctx() {
+        unlink_irq_ctx(&ctx);
+    }
+}
+
+fn unlink_irq_ctx(ctx: &Arc<ModuleIrqContext>) {
+    let index = u8::from(ctx.line.line) as usize;
+    let manager = INTERCEPTORS.read().unwrap()[index];
+
+    let raw_ctx = Arc::as_ptr(ctx);
+
+    if manager.remove(raw_ctx.cast()) {
+        //the reference passed to chain in `set_irq`
+        drop(unsafe { Arc::from_raw(raw_ctx) });
+    }
+}
+
 //the red zone in thread kernel size:
 //all segment registers + all base registers + InterStackFrame + error code + user-mode switching ― the worst case
 pub const KERNEL_TRAP_SIZE: usize =
     4 * 2 + 8 * 4 + mem::size_of::<InterruptStackFrame>() + 4 + 4 * 2;
 
-pub fn start_op_tx() {
-    log::debug!("JIT started");
-}
-
-pub fn end_op_tx() {
-    log::debug!("JIT ended");
-}
-
 pub unsafe fn interpretate_op(op: &IoOperation) {
     use kernel_types::io::PortOperation;
 
@@ -367,16 +402,17 @@ pub fn init() {
 
     init_interceptors(&mut table);
 
+    //the timer is already set up by PIT,
+    //LAPIC timer replaces it if APIC is available
+    apic::init();
+
     unsafe {
         INTERRUPT_TABLE = table;
         INTERRUPT_TABLE_HANDLE = IDTHandle::new(&raw const INTERRUPT_TABLE);
-
-        asm! {
-        "lidt [eax]",
-        in("eax") &raw const INTERRUPT_TABLE_HANDLE
-        }
     }
 
+    load_table();
+
     unsafe {
         if syscall!(syscall::RESERVED, ecx: 2, edx: 1).is_ok() {
             let code: usize = get_edx!();
@@ -389,14 +425,24 @@ pub fn init() {
     };
 }
 
+/// Load the interrupt table shared by all processors
+pub fn load_table() {
+    unsafe {
+        asm! {
+        "lidt [eax]",
+        in("eax") &raw const INTERRUPT_TABLE_HANDLE
+        }
+    }
+}
+
 extern "C" {
     static INTERCEPTOR_STUB_ARRAY: [NakedExceptionHandler; pic::LINES_COUNT];
 }
 
 #[no_mangle]
-static INTERCEPTORS: spin::Mutex<
+static INTERCEPTORS: spin::RwLock<
     Option<[&'static IrqChain; pic::LINES_COUNT]>,
-> = spin::Mutex::new(None);
+> = spin::RwLock::new(None);
 
 #[inline(never)]
 pub unsafe fn validate_stack() {
@@ -430,10 +476,8 @@ pub unsafe extern "C" fn interceptor_stub(
         options(preserves_flags)
     };
 
-    let chain = &mut INTERCEPTORS
-        .try_lock()
-        .expect("Busy loop on interrupts")
-        .unwrap()[index];
+    //each processor can serve interrupts at the same time
+    let chain = INTERCEPTORS.read().expect("Interceptors are not set")[index];
 
     memory::start_irq(|| {
         chain.dispatch(&mut frame_ptr);
@@ -462,7 +506,7 @@ fn init_interceptors(table: &mut IDTable) {
         object
     });
 
-    *INTERCEPTORS.try_lock().unwrap() = objects.into();
+    *INTERCEPTORS.write() = objects.into();
 }
 /// set custom interrupt handler
 pub fn set(index: usize, descriptor: InterruptGate) -> InterruptGate {
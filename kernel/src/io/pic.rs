@@ -5,6 +5,8 @@ use num_enum::{IntoPrimitive, TryFromPrimitive};
 
 use crate::common::io;
 
+use super::apic;
+
 declare_constants!(
     pub u16,
     PIC1 = 0x20;
@@ -60,6 +62,10 @@ pub enum PicLine {
 }
 
 pub unsafe fn set_mask(pic_line: PicLine) {
+    if apic::is_enabled() {
+        return apic::set_mask(pic_line);
+    }
+
     let mut line = u8::from(pic_line);
 
     let port = if line <= LAST_MASTER_IRQ_LINE {
@@ -73,6 +79,10 @@ pub unsafe fn set_mask(pic_line: PicLine) {
 }
 
 pub unsafe fn clear_mask(pic_line: PicLine) {
+    if apic::is_enabled() {
+        return apic::clear_mask(pic_line);
+    }
+
     let mut line = u8::from(pic_line);
 
     let port = if line <= LAST_MASTER_IRQ_LINE {
@@ -114,7 +124,7 @@ pub unsafe fn remap(master_offset: u8, slave_offset: u8) {
     outb(PIC2_DATA, old_pic2);
 }
 
-//Migration to APIC
+///Mask all lines of 8259 PIC as APIC is serving interrupts
 pub unsafe fn disable() {
     asm!(
     "out 0xa1, al",
@@ -125,6 +135,10 @@ pub unsafe fn disable() {
 
 ///Send signal by irq line that interrupt is processed
 pub fn complete(pic_line: PicLine) {
+    if apic::is_enabled() {
+        return apic::complete();
+    }
+
     let line = pic_line as u8;
 
     if line >= 8 {
@@ -0,0 +1,301 @@
+//! I/O permission bitmaps of module processes. A module requests
+//! its ports once and executes `in`/`out` itself: the processor
+//! checks each access against the bitmap of the running process
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use alloc::{alloc::AllocError, boxed::Box, vec::Vec};
+use kernel_types::syscall::SyscallError;
+
+pub use kernel_types::io::PortRange;
+
+use crate::{
+    common::logging::{BOCHS_HACK_PORT, COM_1},
+    memory::{Process, ProcessId},
+};
+
+use super::pic::{PIC1, PIC2};
+
+pub const IO_PORTS_COUNT: usize = u16::MAX as usize + 1;
+pub const IO_BITMAP_SIZE: usize = IO_PORTS_COUNT / u8::BITS as usize;
+
+/// The ports driven by kernel itself
+const RESERVED_PORTS: &[PortRange] = &[
+    PortRange::new(PIC1, 2),
+    PortRange::new(PIC2, 2),
+    PortRange::new(0x40, 4),  //PIT
+    PortRange::new(0x70, 2),  //CMOS
+    PortRange::new(0xCF8, 8), //PCI configuration space
+    PortRange::new(COM_1, 8),
+    PortRange::new(BOCHS_HACK_PORT, 1),
+];
+
+#[derive(Debug, thiserror_no_std::Error)]
+pub enum PortError {
+    #[error("Ports {0:X?} are empty or out of bounds")]
+    InvalidRange(PortRange),
+
+    #[error("Ports {0:X?} are used by kernel")]
+    Reserved(PortRange),
+
+    #[error("Ports {range:X?} are owned by process#{owner}")]
+    Busy { range: PortRange, owner: ProcessId },
+
+    #[error("Process#{0} is not a module")]
+    NotModule(ProcessId),
+
+    #[error("Ports {0:X?} are not declared by module")]
+    Undeclared(PortRange),
+
+    #[error("Failed to alloc I/O bitmap")]
+    Alloc(#[from] AllocError),
+}
+
+impl From<PortError> for SyscallError {
+    fn from(value: PortError) -> Self {
+        log::warn!("Failed to grant ports: {value}");
+
+        match value {
+            PortError::InvalidRange(_) => SyscallError::InvalidData,
+            PortError::Reserved(_) => SyscallError::AccessDenied,
+            PortError::Busy { .. } => SyscallError::BusyResource,
+            PortError::NotModule(_) => SyscallError::AccessDenied,
+            PortError::Undeclared(_) => SyscallError::AccessDenied,
+            PortError::Alloc(_) => SyscallError::NoMemory,
+        }
+    }
+}
+
+/// The port is allowed if its bit is clear
+#[repr(C)]
+pub struct IoBitmap {
+    bits: [u8; IO_BITMAP_SIZE],
+}
+
+impl IoBitmap {
+    pub const fn deny_all() -> Self {
+        Self {
+            bits: [u8::MAX; IO_BITMAP_SIZE],
+        }
+    }
+
+    /// The bitmap is larger than kernel stack,
+    /// thus it's initialized in place
+    pub fn new_boxed() -> Result<Box<Self>, AllocError> {
+        let mut bitmap = Box::<Self>::try_new_uninit()?;
+
+        unsafe {
+            bitmap.as_mut_ptr().write_bytes(u8::MAX, 1);
+
+            Ok(bitmap.assume_init())
+        }
+    }
+
+    pub fn allow(&mut self, range: &PortRange) {
+        for port in range.base as usize..range.end() as usize {
+            self.bits[port / 8] &= !(1 << (port % 8));
+        }
+    }
+
+    /// Check `width` ports starting from `port`
+    pub fn is_allowed(&self, port: u16, width: u16) -> bool {
+        let start = port as usize;
+
+        (start..start + width as usize).all(|port| {
+            port < IO_PORTS_COUNT
+                && self.bits[port / 8] & (1 << (port % 8)) == 0
+        })
+    }
+
+    pub fn copy_from(&mut self, other: &Self) {
+        self.bits.copy_from_slice(&other.bits);
+    }
+}
+
+/// The ports given to modules
+static OWNERS: spin::Mutex<Vec<(PortRange, ProcessId)>> =
+    spin::Mutex::new(Vec::new());
+
+/// Changed on each grant, thus CPUs reload cached bitmaps
+static GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+pub fn generation() -> usize {
+    GENERATION.load(Ordering::Acquire)
+}
+
+/// Give `process` exclusive access to `ranges`.
+/// No port is granted if any range is rejected
+pub fn grant(process: &Process, ranges: &[PortRange]) -> Result<(), PortError> {
+    let mut owners = OWNERS.lock();
+
+    for range in ranges {
+        check_range(range)?;
+
+        let busy = owners.iter().find(|(owned, owner)| {
+            *owner != process.id && owned.overlaps(range)
+        });
+
+        if let Some((_, owner)) = busy {
+            return Err(PortError::Busy {
+                range: *range,
+                owner: *owner,
+            });
+        }
+    }
+
+    owners.try_reserve(ranges.len()).map_err(|_| AllocError)?;
+
+    let mut state = process.state.lock();
+
+    let bitmap = match state.io_bitmap {
+        Some(ref mut bitmap) => bitmap,
+        None => state.io_bitmap.insert(IoBitmap::new_boxed()?),
+    };
+
+    for range in ranges {
+        bitmap.allow(range);
+        owners.push((*range, process.id));
+
+        log::debug!("Ports {range:X?} are granted to process#{}", process.id);
+    }
+
+    GENERATION.fetch_add(1, Ordering::AcqRel);
+
+    Ok(())
+}
+
+/// Give module the requested `ranges`. Each range
+/// should lie in the ports declared by module on load
+pub fn grant_declared(
+    process: &Process,
+    ranges: &[PortRange],
+) -> Result<(), PortError> {
+    {
+        let state = process.state.lock();
+
+        let Some(declared) = state.io_ports.as_ref() else {
+            return Err(PortError::NotModule(process.id));
+        };
+
+        if let Some(range) = find_undeclared(declared, ranges) {
+            return Err(PortError::Undeclared(range));
+        }
+    }
+
+    grant(process, ranges)
+}
+
+/// The range is not empty, addressable and not used by kernel
+fn check_range(range: &PortRange) -> Result<(), PortError> {
+    if range.count == 0 || range.end() > IO_PORTS_COUNT as u32 {
+        return Err(PortError::InvalidRange(*range));
+    }
+
+    if RESERVED_PORTS
+        .iter()
+        .any(|reserved| reserved.overlaps(range))
+    {
+        return Err(PortError::Reserved(*range));
+    }
+
+    Ok(())
+}
+
+/// The first range which doesn't lie in one of `declared` ranges
+fn find_undeclared(
+    declared: &[PortRange],
+    ranges: &[PortRange],
+) -> Option<PortRange> {
+    ranges.iter().copied().find(|range| {
+        !declared.iter().any(|declared| {
+            declared.base <= range.base && range.end() <= declared.end()
+        })
+    })
+}
+
+/// Take back all ports of terminated process
+pub fn release(process: &Process) {
+    OWNERS.lock().retain(|(_, owner)| *owner != process.id);
+
+    let bitmap = process.state.lock().io_bitmap.take();
+
+    if bitmap.is_some() {
+        log::debug!("Ports of process#{} are released", process.id);
+
+        GENERATION.fetch_add(1, Ordering::AcqRel);
+    }
+}
+
+pub fn is_granted(process: &Process, port: u16, width: u16) -> bool {
+    process
+        .state
+        .lock()
+        .io_bitmap
+        .as_ref()
+        .is_some_and(|bitmap| bitmap.is_allowed(port, width))
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+    extern crate std;
+
+    use super::*;
+
+    #[test]
+    fn check_range_test() {
+        assert!(check_range(&PortRange::new(0x1F0, 8)).is_ok());
+        assert!(check_range(&PortRange::new(u16::MAX, 1)).is_ok());
+
+        assert!(matches!(
+            check_range(&PortRange::new(0x1F0, 0)),
+            Err(PortError::InvalidRange(_))
+        ));
+        assert!(matches!(
+            check_range(&PortRange::new(u16::MAX, 2)),
+            Err(PortError::InvalidRange(_))
+        ));
+        assert!(matches!(
+            check_range(&PortRange::new(0x3E, 4)),
+            Err(PortError::Reserved(_))
+        ));
+        assert!(matches!(
+            check_range(&PortRange::new(COM_1 + 7, 1)),
+            Err(PortError::Reserved(_))
+        ));
+    }
+
+    #[test]
+    fn undeclared_test() {
+        let declared = [PortRange::new(0x1F0, 8), PortRange::new(0x3F6, 1)];
+
+        assert_eq!(find_undeclared(&declared, &[]), None);
+        assert_eq!(
+            find_undeclared(
+                &declared,
+                &[PortRange::new(0x1F0, 8), PortRange::new(0x3F6, 1)]
+            ),
+            None
+        );
+        assert_eq!(
+            find_undeclared(&declared, &[PortRange::new(0x1F2, 2)]),
+            None
+        );
+
+        //the range should lie in one declared range
+        let crossing = PortRange::new(0x1F4, 8);
+
+        assert_eq!(find_undeclared(&declared, &[crossing]), Some(crossing));
+
+        let outside = PortRange::new(0x170, 8);
+
+        assert_eq!(
+            find_undeclared(&declared, &[PortRange::new(0x1F0, 1), outside]),
+            Some(outside)
+        );
+        assert_eq!(
+            find_undeclared(&[], &[PortRange::new(0x1F0, 1)]),
+            Some(PortRange::new(0x1F0, 1))
+        );
+    }
+}
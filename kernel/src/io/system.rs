@@ -4,9 +4,11 @@ use crate::io::irq::IrqChain;
 use crate::io::{
     self, IDTable, InterruptStackFrame, IrqLine, MAX_INTERRUPTS_COUNT,
 };
-use crate::memory::{Page, SlabBox, VirtualAddress};
+use crate::memory::{
+    Page, PageFault, SegmentSelector, SlabBox, TaskGate, VirtualAddress,
+};
 use crate::{
-    current_task, error_trap, log_module, memory, naked_trap, task, user,
+    current_task, error_trap, log_module, memory, naked_trap, smp, task, user,
 };
 
 //the common handlers
@@ -39,7 +41,13 @@ pub fn init_traps(table: &mut IDTable) {
         IDTable::DEVICE_NOT_AVAILABLE,
         naked_trap!(device_not_available),
     );
-    table.set(IDTable::DOUBLE_FAULT, error_trap!(double_fault));
+    //the overflowed kernel stack can't take the frame,
+    //so the handler runs as separate task on own stack
+    let mut double_fault =
+        TaskGate::default(SegmentSelector::DOUBLE_FAULT_TASK);
+    double_fault.flags.set_present(true);
+
+    table.set(IDTable::DOUBLE_FAULT, double_fault.into());
     table.set(IDTable::INVALID_TSS, error_trap!(invalid_tss));
     table.set(IDTable::SEGMENT_NOT_PRESENT, error_trap!(invalid_segment));
     table.set(IDTable::STACK_FAULT, error_trap!(stack_fault));
@@ -83,6 +91,8 @@ pub struct SyscallParams {
 #[no_mangle]
 pub extern "C" fn handle_syscall(params: *mut SyscallParams) {
     let SyscallParams { code, edx, ecx } = unsafe { &mut *params }.clone();
+    //the frame pushed by processor lies right above the params
+    let frame = unsafe { &*params.add(1).cast::<InterruptStackFrame>() };
     let params = unsafe { &mut *params };
 
     if code == syscall::RESERVED {
@@ -99,8 +109,10 @@ pub extern "C" fn handle_syscall(params: *mut SyscallParams) {
 
     let old_esp = current_task!().context().esp;
 
-    let code = match user::syscall::handle(request, edx as usize, ecx as usize)
-    {
+    let result =
+        user::syscall::handle(request, edx as usize, ecx as usize, frame);
+
+    let code = match result {
         Ok(_) => 0,
         Err(cause) => cause as u32,
     };
@@ -158,12 +170,17 @@ pub extern "x86-interrupt" fn device_not_available(
     log_module!("device not available");
 }
 
-//we can do nothing
-pub extern "x86-interrupt" fn double_fault(
-    _frame: InterruptStackFrame,
-    _code: usize,
-) {
-    panic!("The double fault exception occurs. We can nothing...(");
+/// The task entered through task gate of double fault.
+/// The faulted task can't be resumed, so the kernel panics
+pub extern "C" fn double_fault_task() -> ! {
+    //the task switch sets TS flag
+    unsafe { core::arch::asm!("clts", options(nomem, nostack)) };
+
+    panic!(
+        "The double fault exception occurs on CPU#{}. \
+         The kernel stack may be overflowed",
+        smp::cpu_id()
+    );
 }
 
 pub extern "x86-interrupt" fn invalid_tss(
@@ -201,7 +218,7 @@ pub extern "x86-interrupt" fn general_protection(
 }
 
 pub extern "x86-interrupt" fn page_fault(
-    frame: InterruptStackFrame,
+    mut frame: InterruptStackFrame,
     code: usize,
 ) {
     let access_address: VirtualAddress;
@@ -214,6 +231,9 @@ pub extern "x86-interrupt" fn page_fault(
         }
     }
 
+    let code = PageFaultError(code as u32);
+    let mut fault = PageFault::NotMapped;
+
     if let Some(process) = current_task!().process.clone() {
         let mut state = process.state.lock();
 
@@ -234,9 +254,31 @@ pub extern "x86-interrupt" fn page_fault(
 
             return;
         }
+
+        drop(state);
+
+        //the page of file mapping is read by module, so the task may block
+        match memory::resolve_fault(
+            &process,
+            access_address,
+            code.present(),
+            code.write(),
+        ) {
+            Ok(()) => return,
+            Err(cause) => fault = cause,
+        }
     }
 
-    let code = PageFaultError(code as u32);
+    if let Some(fixup_ip) = user::access::fault_fixup(frame.ip) {
+        //syscall has touched invalid user memory,
+        //the copy routine returns error instead
+        unsafe {
+            let frame = &raw mut frame;
+            (&raw mut (*frame).ip).write_volatile(fixup_ip);
+        }
+
+        return;
+    }
 
     log_module! {
         "[Task#{}] Page Fault ({access_address:X}): {code:?} at IP={:X} CS={:X}\n",
@@ -251,6 +293,12 @@ pub extern "x86-interrupt" fn page_fault(
 
         log_module!("Lookuped value: {lookuped:?}");
     }
+
+    if code.user() {
+        user::fault(access_address, fault);
+    }
+
+    panic!("Kernel page fault at 0x{access_address:X}: {fault}");
 }
 
 pub extern "x86-interrupt" fn alignment_check(
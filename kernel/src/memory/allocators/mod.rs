@@ -1,7 +1,7 @@
-pub use physical::PhysicalAllocator;
+pub use physical::{PhysicalAllocator, MAX_UNIT_POWER};
 pub use system::{
-    classify_slab_by_size, Alignment, MemoryAllocationFlag, Slab, SlabAlloc,
-    SystemAllocator,
+    classify_slab_by_size, Alignment, HeapStat, MemoryAllocationFlag, Slab,
+    SlabAlloc, SlabStat, SystemAllocator, MAX_SLAB_STATS,
 };
 
 mod physical;
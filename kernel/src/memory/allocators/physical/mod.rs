@@ -58,6 +58,25 @@ fn is_even_in_memory_map(page: &Page) -> bool {
     page.index() % 2 == 0
 }
 
+/// Put `count` free pages starting with page `index` to buddy lists.
+/// Like in [`collect_buddies`], only even page heads the batch of many pages
+fn push_run(buddies: &mut BuddyArray, mut index: usize, mut count: usize) {
+    while count > 0 {
+        let batch_size = if index % 2 == 0 {
+            (1 << count.ilog2()).min(MAX_BUDDY_BATCH_SIZE)
+        } else {
+            1
+        };
+
+        let head = unsafe { &mut *Page::take_unchecked(index << Page::SHIFT) };
+
+        buddies[buddy_index(batch_size)].push_front(head.as_node());
+
+        index += batch_size;
+        count -= batch_size;
+    }
+}
+
 #[no_mangle]
 unsafe fn collect_buddies(mut boot_allocator: BootAllocator) -> BuddyArray {
     unsafe fn reset_pages(offset: VirtualAddress, count: usize) {
@@ -250,6 +269,15 @@ impl PhysicalAllocator {
         }
     }
 
+    /// The count of free batches in each buddy list
+    pub fn free_batches(
+        &self,
+    ) -> Result<[usize; MAX_UNIT_POWER + 1], AllocError> {
+        let lock = self.buddies.lock()?;
+
+        Ok(core::array::from_fn(|power| lock[power].len()))
+    }
+
     /// Be careful with such method: it should, theoretically, batch page in solid memory region, but, truly, doesn't
     pub fn dealloc_page(&self, page: &'static mut Page) {
         assert_ne!(page.flags, PageFlag::DMA);
@@ -263,6 +291,41 @@ impl PhysicalAllocator {
         }
     }
 
+    /// Put back the pages taken by [`Self::alloc_continuous_pages`].
+    /// Unlike [`Self::dealloc_page`], the physically continuous runs of
+    /// pages are split into buddy batches. The pages are expected in order
+    /// of physical addresses, otherwise each gap ends the run
+    pub fn dealloc_batch(&self, mut pages: LinkedList<'static, Page>) {
+        let mut lock = self.buddies.lock().unwrap();
+
+        //the index of first page and the length of continuous run
+        let mut run: Option<(usize, usize)> = None;
+
+        while let Some(page) = pages.remove_first() {
+            page.release();
+            debug_assert!(!page.is_used());
+
+            let index = page.index();
+
+            match run {
+                Some((start, count)) if start + count == index => {
+                    run = Some((start, count + 1));
+                }
+                _ => {
+                    if let Some((start, count)) = run {
+                        push_run(&mut lock, start, count);
+                    }
+
+                    run = Some((index, 1));
+                }
+            }
+        }
+
+        if let Some((start, count)) = run {
+            push_run(&mut lock, start, count);
+        }
+    }
+
     /// Remove requested pages from allocation proccess
     pub fn reserve_pages(
         &self,
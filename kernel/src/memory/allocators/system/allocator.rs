@@ -13,7 +13,8 @@ use crate::memory::{
 };
 
 use super::{
-    slab_entry::SlabEntry, slab_head::SlabHead, MemoryAllocationFlag, SlabAlloc,
+    slab_entry::SlabEntry, slab_head::SlabHead, HeapStat, MemoryAllocationFlag,
+    SlabAlloc,
 };
 
 #[derive(ListNode)]
@@ -33,6 +34,11 @@ pub struct SlabAllocator {
 
     //holds GlobalPageDirectory
     heap_offset: VirtualAddress, //the next free virtual address
+    heap_start: VirtualAddress,
+    /// The count of heap pages reserved around guarded allocations
+    guard_pages: usize,
+    /// The count of heap pages returned to physical allocator
+    released_pages: usize,
 }
 
 impl SlabAllocator {
@@ -43,6 +49,9 @@ impl SlabAllocator {
         Self {
             allocator,
             heap_offset,
+            heap_start: heap_offset,
+            guard_pages: 0,
+            released_pages: 0,
 
             cached_entries: LinkedList::empty(),
             cached_heads: LinkedList::empty(),
@@ -53,13 +62,24 @@ impl SlabAllocator {
     pub fn virtual_alloc(
         &mut self,
         pages_count: usize,
-        _flags: MemoryAllocationFlag,
+        flags: MemoryAllocationFlag,
     ) -> Result<*mut u8, AllocError> {
         assert!(pages_count > 0);
 
         let mut pages = self.allocator.alloc_zeroed_pages(pages_count)?;
 
-        let heap_start_offset = self.move_heap_offset(pages_count);
+        //the guard pages are reserved in heap, but never committed
+        let guard_pages = if flags.contains(MemoryAllocationFlag::GUARDED) {
+            1
+        } else {
+            0
+        };
+
+        let heap_start_offset = self
+            .move_heap_offset(pages_count + 2 * guard_pages)
+            + guard_pages * Page::SIZE;
+
+        self.guard_pages += 2 * guard_pages;
 
         assert_eq!(pages.len(), pages_count);
 
@@ -68,11 +88,21 @@ impl SlabAllocator {
         Ok(heap_start_offset as *mut u8)
     }
 
+    #[cfg_attr(not(feature = "kasan"), allow(unused_variables))]
     pub fn virtual_dealloc(
         &mut self,
-        _offset: VirtualAddress,
-        _pages_count: usize,
+        offset: VirtualAddress,
+        pages_count: usize,
     ) {
+        //the pages stay mapped, so stale pointers read poison.
+        //the page marker passes physical addresses out of heap
+        #[cfg(feature = "kasan")]
+        if (self.heap_start..self.heap_offset).contains(&offset) {
+            let end = offset + pages_count * Page::SIZE;
+
+            super::sanitizer::poison(offset..end);
+        }
+
         //page marker can call this method
         //todo: prevent deadlock
         // let mut virt_offset = offset;
@@ -187,6 +217,41 @@ impl SlabAllocator {
         Ok(entries)
     }
 
+    /// Return pages of empty entries to physical allocator, the entries
+    /// are cached. The heap range of entry is unmapped, but never reused.
+    /// Return the count of released pages.
+    ///
+    /// It's reached from the global deallocation, so the heap objects must
+    /// not be freed while the locks taken here are held: the kernel page
+    /// marker lock (unmapping also waits for TLB shootdown of other
+    /// processors) and the buddy lock of physical allocator
+    pub fn release_slab_entries(
+        &mut self,
+        mut entries: LinkedList<'static, SlabEntry>,
+    ) -> usize {
+        let mut released = 0;
+
+        while let Some(entry) = entries.remove_first() {
+            let heap_range = entry.heap_range();
+            let mut pages = entry.take_pages();
+
+            memory::kernel_uncommit(heap_range);
+
+            for page in pages.iter_mut() {
+                let _ = page.reset_virtual();
+            }
+
+            released += pages.len();
+
+            self.allocator.dealloc_batch(pages);
+            self.cached_entries.push_back(entry);
+        }
+
+        self.released_pages += released;
+
+        released
+    }
+
     //alloc at least desireable count of pages
     fn commit_new_entries(
         &mut self,
@@ -224,6 +289,15 @@ impl SlabAllocator {
         Ok(list)
     }
 
+    pub fn heap_stat(&self) -> HeapStat {
+        HeapStat {
+            start: self.heap_start,
+            end: self.heap_offset,
+            guard_pages: self.guard_pages,
+            released_pages: self.released_pages,
+        }
+    }
+
     //take heap offset available for requested pages count
     fn move_heap_offset(&mut self, pages_count: usize) -> VirtualAddress {
         let old_heap_offset = self.heap_offset;
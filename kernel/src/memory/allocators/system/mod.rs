@@ -1,12 +1,14 @@
 mod allocator;
 mod object;
+#[cfg(feature = "kasan")]
+mod sanitizer;
 mod slab_entry;
 mod slab_head;
 mod tree;
 
 use allocator::SlabAllocator;
 
-use kernel_types::declare_constants;
+use kernel_types::{collections::LinkedList, declare_constants};
 use tree::SlabTree;
 
 use crate::memory::{
@@ -43,6 +45,9 @@ bitflags::bitflags! {
         const WRITE = 0x04;
         const READ = 0x08;
         const READ_WRITE = Self::WRITE.bits() | Self::READ.bits();
+        /// The pages around allocation are left unmapped,
+        /// so overruns fault (used for kernel stacks)
+        const GUARDED = 0x10;
     }
 }
 
@@ -53,9 +58,56 @@ pub struct SlabAlloc {
     pub alignment: Alignment,
 }
 
+/// The usage of objects of one slab
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SlabStat {
+    pub name: SlabName,
+    pub object_size: usize,
+    pub entries: usize,
+    /// The count of taken objects
+    pub objects: usize,
+    pub capacity: usize,
+    pub pages: usize,
+}
+
+impl SlabStat {
+    /// The bytes of slab pages which never hold any object
+    pub fn wasted_bytes(&self) -> usize {
+        self.pages * Page::SIZE - self.capacity * self.object_size
+    }
+}
+
+/// The usage of kernel heap, the addresses are never returned to heap
+#[derive(Debug, Clone, Copy)]
+pub struct HeapStat {
+    pub start: VirtualAddress,
+    /// The next free address
+    pub end: VirtualAddress,
+    /// The reserved pages which are never mapped
+    pub guard_pages: usize,
+    /// The pages unmapped from heap and returned to physical allocator
+    pub released_pages: usize,
+}
+
+impl HeapStat {
+    /// The count of heap pages backed by physical memory
+    pub fn committed_pages(&self) -> usize {
+        (self.end - self.start) / Page::SIZE
+            - self.guard_pages
+            - self.released_pages
+    }
+}
+
+declare_constants!(
+    pub usize,
+    MAX_SLAB_STATS = 32, "The maximal count of slabs in stats";
+);
+
 pub struct SystemAllocator {
     tree: spin::Mutex<SlabTree>,
     allocator: spin::Mutex<SlabAllocator>,
+    #[cfg(feature = "kasan")]
+    sanitizer: spin::Mutex<sanitizer::Sanitizer>,
 }
 
 unsafe impl Send for SystemAllocator {}
@@ -81,6 +133,8 @@ impl SystemAllocator {
         Ok(Self {
             tree: spin::Mutex::new(tree),
             allocator: spin::Mutex::new(allocator),
+            #[cfg(feature = "kasan")]
+            sanitizer: spin::Mutex::new(sanitizer::Sanitizer::new()),
         })
     }
 
@@ -95,6 +149,15 @@ impl SystemAllocator {
     ) -> Result<*mut u8, AllocError> {
         log::debug!("Allocating slab {:?}", allocation);
 
+        #[cfg(feature = "kasan")]
+        let redzone = sanitizer::redzone_size(allocation.size);
+
+        #[cfg(feature = "kasan")]
+        let allocation = SlabAlloc {
+            size: sanitizer::padded_size(allocation.size),
+            ..allocation
+        };
+
         let mut tree = self.tree.try_lock().unwrap();
 
         let head = tree.find_head_or_alloc(allocation.name, || {
@@ -103,8 +166,8 @@ impl SystemAllocator {
             allocator.alloc_slab_head(&allocation)
         })?;
 
-        match head.try_alloc() {
-            Some(offset) => Ok(offset as *mut u8),
+        let offset = match head.try_alloc() {
+            Some(offset) => offset,
             None => {
                 log::debug!("Stage 3");
                 let entries = {
@@ -113,26 +176,98 @@ impl SystemAllocator {
                     allocator.alloc_slab_entries(1, allocation.size)?
                 };
 
-                head.extend_with_free_entries(entries);
+                #[cfg(feature = "kasan")]
+                for entry in entries.iter() {
+                    sanitizer::poison(entry.heap_range());
+                }
 
-                let offset =
-                    head.try_alloc().expect("New entries are submitted");
+                head.extend_with_free_entries(entries);
 
-                Ok(offset as *mut u8)
+                head.try_alloc().expect("New entries are submitted")
             }
-        }
+        };
+
+        #[cfg(feature = "kasan")]
+        let offset = sanitizer::unpoison(
+            allocation.name,
+            offset,
+            allocation.size.into(),
+            redzone,
+        );
+
+        Ok(offset as *mut u8)
     }
 
+    #[track_caller]
     pub fn dealloc_slab(&self, name: SlabName, ptr: *mut u8) {
         let offset = ptr as VirtualAddress;
 
         let mut tree = self.tree.try_lock().unwrap();
 
+        //the object is freed in slab when it leaves quarantine
+        #[cfg(feature = "kasan")]
+        let Some((name, offset)) = self.quarantine(&mut tree, name, offset) else {
+            return;
+        };
+
         let Some(head) = tree.find_head(name) else {
             panic!("Unknown slab with name = {name}");
         };
 
-        head.dealloc(offset);
+        let Some(entry) = head.dealloc(offset) else {
+            return;
+        };
+
+        //the pages go back to physical allocator,
+        //see `release_slab_entries` for the locks taken
+        let mut entries = LinkedList::empty();
+        entries.push_back(entry);
+
+        let mut allocator = self.allocator.try_lock().unwrap();
+
+        allocator.release_slab_entries(entries);
+    }
+
+    /// Put object in quarantine, return the object leaving it
+    #[cfg(feature = "kasan")]
+    #[track_caller]
+    fn quarantine(
+        &self,
+        tree: &mut SlabTree,
+        name: SlabName,
+        offset: VirtualAddress,
+    ) -> Option<(SlabName, VirtualAddress)> {
+        let caller = core::panic::Location::caller();
+
+        //the slab object starts with redzone
+        let Some((object, size)) = tree
+            .find_head(name)
+            .and_then(|head| head.find_object(offset))
+        else {
+            log::error!(
+                "Slab {name}: free of unknown object at 0x{offset:X} by {caller}"
+            );
+
+            return None;
+        };
+
+        let mut sanitizer = self.sanitizer.try_lock().unwrap();
+
+        let redzone = offset - object;
+
+        let evicted = sanitizer.release(name, object, size, redzone, caller)?;
+
+        Some((evicted.name, evicted.offset))
+    }
+
+    /// Release empty entries of all slabs.
+    /// Return the count of pages returned to physical allocator
+    pub fn shrink(&self) -> usize {
+        let entries = self.tree.try_lock().unwrap().shrink();
+
+        let mut allocator = self.allocator.try_lock().unwrap();
+
+        allocator.release_slab_entries(entries)
     }
 
     pub fn virtual_alloc(
@@ -149,6 +284,16 @@ impl SystemAllocator {
         allocator.virtual_alloc(pages_count, flags)
     }
 
+    /// The stats are collected without allocations,
+    /// as the global allocator is locked meanwhile
+    pub fn slab_stats(&self) -> heapless::Vec<SlabStat, MAX_SLAB_STATS> {
+        self.tree.try_lock().unwrap().stats()
+    }
+
+    pub fn heap_stat(&self) -> HeapStat {
+        self.allocator.try_lock().unwrap().heap_stat()
+    }
+
     pub fn virtual_dealloc(
         &self,
         offset: VirtualAddress,
@@ -0,0 +1,172 @@
+//! The debug mode of slab allocator (the `kasan` feature).
+//! Each object is surrounded by redzones, the freed objects are poisoned
+//! and kept in quarantine before they are returned to slab.
+//! The redzones are verified on free and the poison on reuse.
+//! The caller of free is precise for direct calls of allocator only,
+//! the objects dropped by `Box` are reported with allocator location
+
+use core::{ops::Range, panic::Location};
+
+use kernel_types::declare_constants;
+
+use crate::memory::VirtualAddress;
+
+use super::SlabName;
+
+declare_constants!(
+    pub usize,
+    REDZONE_SIZE = 16, "The minimal bytes guarding each side of slab object";
+    QUARANTINE_SIZE = 64, "The count of freed objects delayed from reuse";
+);
+
+/// The pattern of freed (or never allocated) memory
+const POISON: u8 = 0x6B;
+/// The pattern of redzones around taken object
+const REDZONE: u8 = 0xCC;
+
+/// The object freed by kernel, but not returned to slab yet
+#[derive(Debug, Clone, Copy)]
+pub struct Quarantined {
+    pub name: SlabName,
+    /// The start of object including redzone
+    pub offset: VirtualAddress,
+    pub size: usize,
+    pub redzone: usize,
+    pub freed_at: &'static Location<'static>,
+}
+
+pub struct Sanitizer {
+    quarantine: heapless::Deque<Quarantined, QUARANTINE_SIZE>,
+}
+
+/// The redzone of object keeps its alignment. The objects are placed
+/// by their size, so the alignment is the largest power of two
+/// dividing the size
+pub const fn redzone_size(size: u16) -> usize {
+    let align = 1 << size.trailing_zeros();
+
+    if align > REDZONE_SIZE {
+        align
+    } else {
+        REDZONE_SIZE
+    }
+}
+
+/// The object size with redzones
+pub const fn padded_size(size: u16) -> u16 {
+    size + 2 * redzone_size(size) as u16
+}
+
+/// Fill memory in `range` which is not given to kernel
+pub fn poison(range: Range<VirtualAddress>) {
+    bytes(range.start, range.len()).fill(POISON);
+}
+
+/// Verify poison of object taken from slab and set its redzones.
+/// Return the address given to kernel
+pub fn unpoison(
+    name: SlabName,
+    offset: VirtualAddress,
+    size: usize,
+    redzone: usize,
+) -> VirtualAddress {
+    let object = bytes(offset, size);
+
+    if let Some(index) = object.iter().position(|&byte| byte != POISON) {
+        log::error!(
+            "Slab {name}: object at 0x{:X} is written at +{} while free",
+            offset + redzone,
+            index as isize - redzone as isize,
+        );
+    }
+
+    object[..redzone].fill(REDZONE);
+    object[size - redzone..].fill(REDZONE);
+
+    offset + redzone
+}
+
+impl Sanitizer {
+    pub const fn new() -> Self {
+        Self {
+            quarantine: heapless::Deque::new(),
+        }
+    }
+
+    /// Verify redzones of object freed by `caller`, poison and put it in
+    /// quarantine. Return the object leaving quarantine to be freed in slab
+    pub fn release(
+        &mut self,
+        name: SlabName,
+        offset: VirtualAddress,
+        size: usize,
+        redzone: usize,
+        caller: &'static Location<'static>,
+    ) -> Option<Quarantined> {
+        let address = offset + redzone;
+
+        let freed = self
+            .quarantine
+            .iter()
+            .find(|object| object.offset == offset);
+
+        if let Some(freed) = freed {
+            log::error!(
+                "Slab {name}: object at 0x{address:X} is freed again by {caller}, the first free by {}",
+                freed.freed_at,
+            );
+
+            return None;
+        }
+
+        let object = bytes(offset, size);
+
+        let (head, rest) = object.split_at(redzone);
+        let tail = &rest[rest.len() - redzone..];
+
+        if head.iter().chain(tail).any(|&byte| byte != REDZONE) {
+            log::error!(
+                "Slab {name}: redzone of object at 0x{address:X} is overwritten, freed by {caller}"
+            );
+        }
+
+        object.fill(POISON);
+
+        let evicted = if self.quarantine.is_full() {
+            self.quarantine.pop_front()
+        } else {
+            None
+        };
+
+        let _ = self.quarantine.push_back(Quarantined {
+            name,
+            offset,
+            size,
+            redzone,
+            freed_at: caller,
+        });
+
+        let evicted = evicted?;
+
+        let object = bytes(evicted.offset, evicted.size);
+
+        if let Some(index) = object.iter().position(|&byte| byte != POISON) {
+            log::error!(
+                "Slab {}: object at 0x{:X} is written at +{} after free by {}",
+                evicted.name,
+                evicted.offset + evicted.redzone,
+                index as isize - evicted.redzone as isize,
+                evicted.freed_at,
+            );
+
+            //the write is reported once, not on reuse again
+            object.fill(POISON);
+        }
+
+        Some(evicted)
+    }
+}
+
+fn bytes(offset: VirtualAddress, size: usize) -> &'static mut [u8] {
+    unsafe { core::slice::from_raw_parts_mut(offset as *mut u8, size) }
+}
@@ -1,4 +1,8 @@
-use core::{mem::MaybeUninit, ptr};
+use core::{
+    mem::{self, MaybeUninit},
+    ops::Range,
+    ptr,
+};
 
 use bitvec::{order::Lsb0, view::BitView};
 use kernel_macro::ListNode;
@@ -78,7 +82,9 @@ impl SlabEntry {
     }
 
     pub fn holds(&self, offset: VirtualAddress) -> bool {
-        if offset < self.base_offset as usize {
+        //the objects of other entries are not aligned with this one,
+        //if the object size doesn't divide page size
+        if !self.heap_range().contains(&offset) {
             return false;
         }
 
@@ -95,6 +101,24 @@ impl SlabEntry {
             .expect("Valid bit index")
     }
 
+    /// The start and size of taken object containing `address`
+    #[cfg(feature = "kasan")]
+    pub fn find_object(
+        &self,
+        address: VirtualAddress,
+    ) -> Option<(VirtualAddress, usize)> {
+        if !self.heap_range().contains(&address) {
+            return None;
+        }
+
+        let distance = address - self.base_offset as VirtualAddress;
+        let object_size = self.object_size as usize;
+
+        let object = address - distance % object_size;
+
+        self.holds(object).then_some((object, object_size))
+    }
+
     pub fn take_object(&mut self) -> VirtualAddress {
         assert!(self.capacity != 0);
 
@@ -152,6 +176,41 @@ impl SlabEntry {
         (self.heap_mask as usize).count_zeros() as usize
     }
 
+    pub const fn object_size(&self) -> usize {
+        self.object_size as usize
+    }
+
+    pub const fn capacity(&self) -> usize {
+        self.capacity as usize
+    }
+
+    /// The count of taken objects
+    pub const fn used(&self) -> usize {
+        self.heap_mask.count_ones() as usize
+    }
+
+    pub fn pages_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// The heap addresses where objects are placed
+    pub fn heap_range(&self) -> Range<VirtualAddress> {
+        let start = self.base_offset as VirtualAddress;
+
+        start..start + self.pages_count() * Page::SIZE
+    }
+
+    /// Detach pages of empty entry, so it can be reused by [`Self::set`]
+    pub fn take_pages(&mut self) -> LinkedList<'static, Page> {
+        assert!(self.is_empty());
+
+        self.base_offset = ptr::null_mut();
+        self.object_size = 0;
+        self.capacity = 0;
+
+        mem::take(&mut self.pages)
+    }
+
     pub fn is_empty(&self) -> bool {
         self.heap_mask == 0
     }
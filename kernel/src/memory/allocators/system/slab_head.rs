@@ -3,7 +3,7 @@ use kernel_types::collections::{LinkedList, ListNode};
 
 use crate::memory::VirtualAddress;
 
-use super::{slab_entry::SlabEntry, SlabName};
+use super::{slab_entry::SlabEntry, SlabName, SlabStat};
 
 // use slab entries as child for slab head
 #[derive(Debug, ListNode)]
@@ -33,7 +33,12 @@ impl SlabHead {
         }
     }
 
-    pub fn dealloc(&mut self, offset: VirtualAddress) {
+    /// Release object at `offset`. The entry left empty is unlinked and
+    /// returned if the slab keeps another empty entry for allocations
+    pub fn dealloc(
+        &mut self,
+        offset: VirtualAddress,
+    ) -> Option<&'static mut ListNode<SlabEntry>> {
         log::debug!("Dealloc {} entry at {offset:X}", self.name);
 
         let mut full_iter = self.full.iter_mut();
@@ -48,17 +53,45 @@ impl SlabHead {
 
                 let partial = full_iter.unlink_watched().unwrap();
                 self.partial.push_front(partial);
-                return;
+                return None;
             }
         }
 
-        let entry = self
-            .partial
-            .iter_mut()
-            .find(|entry| entry.holds(offset))
-            .expect("Failed to find slab entry by ");
+        let has_spare = self.partial.iter().any(|entry| entry.is_empty());
+
+        let mut partial_iter = self.partial.iter_mut();
 
-        entry.release(offset);
+        loop {
+            let entry = partial_iter
+                .next()
+                .expect("Failed to find slab entry by offset");
+
+            if entry.holds(offset) {
+                entry.release(offset);
+
+                if !(entry.is_empty() && has_spare) {
+                    return None;
+                }
+
+                return partial_iter.unlink_watched();
+            }
+        }
+    }
+
+    /// Unlink all empty entries, their pages can be released
+    pub fn shrink(&mut self) -> LinkedList<'static, SlabEntry> {
+        let mut empty = LinkedList::empty();
+
+        let mut partial_iter = self.partial.iter_mut();
+
+        while let Some(entry) = partial_iter.next() {
+            if entry.is_empty() {
+                let entry = partial_iter.unlink_watched().unwrap();
+                empty.push_back(entry);
+            }
+        }
+
+        empty
     }
 
     pub fn try_alloc(&mut self) -> Option<VirtualAddress> {
@@ -76,6 +109,35 @@ impl SlabHead {
         Some(offset)
     }
 
+    /// The start and size of taken object containing `address`
+    #[cfg(feature = "kasan")]
+    pub fn find_object(
+        &self,
+        address: VirtualAddress,
+    ) -> Option<(VirtualAddress, usize)> {
+        self.full
+            .iter()
+            .chain(self.partial.iter())
+            .find_map(|entry| entry.find_object(address))
+    }
+
+    pub fn stat(&self) -> SlabStat {
+        let mut stat = SlabStat {
+            name: self.name,
+            ..SlabStat::default()
+        };
+
+        for entry in self.full.iter().chain(self.partial.iter()) {
+            stat.object_size = entry.object_size();
+            stat.entries += 1;
+            stat.objects += entry.used();
+            stat.capacity += entry.capacity();
+            stat.pages += entry.pages_count();
+        }
+
+        stat
+    }
+
     pub fn extend_with_free_entries(
         &mut self,
         mut entries: LinkedList<'static, SlabEntry>,
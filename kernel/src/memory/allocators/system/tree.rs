@@ -2,7 +2,10 @@ use kernel_types::collections::LinkedList;
 
 use crate::memory::AllocError;
 
-use super::{slab_head::SlabHead, SlabName};
+use super::{
+    slab_entry::SlabEntry, slab_head::SlabHead, SlabName, SlabStat,
+    MAX_SLAB_STATS,
+};
 
 #[derive(Default)]
 pub struct SlabTree {
@@ -41,6 +44,26 @@ impl SlabTree {
         Ok(head)
     }
 
+    /// The stats of slabs, the ones above [`MAX_SLAB_STATS`] are skipped
+    pub fn stats(&self) -> heapless::Vec<SlabStat, MAX_SLAB_STATS> {
+        self.heads
+            .iter()
+            .take(MAX_SLAB_STATS)
+            .map(|head| head.stat())
+            .collect()
+    }
+
+    /// Unlink empty entries of all slabs
+    pub fn shrink(&mut self) -> LinkedList<'static, SlabEntry> {
+        let mut empty = LinkedList::empty();
+
+        for head in self.heads.iter_mut() {
+            empty.splice(&mut head.shrink());
+        }
+
+        empty
+    }
+
     pub fn find_head(&mut self, slab_name: SlabName) -> Option<&mut SlabHead> {
         let head =
             self.heads.iter_mut().find(|head| head.name.eq(slab_name))?;
@@ -28,6 +28,7 @@ impl SegmentSelector {
         USER_CODE = SegmentSelector::new(3, PrivilegeLevel::USER, SelectorType::Gdt); //3
         USER_DATA = SegmentSelector::new(4, PrivilegeLevel::USER, SelectorType::Gdt);//4
         TASK = SegmentSelector(0x28);//6
+        DOUBLE_FAULT_TASK = SegmentSelector(0x30);//7
 
     );
 
@@ -328,6 +329,13 @@ impl TaskGate {
     }
 }
 
+impl From<TaskGate> for InterruptGate {
+    fn from(gate: TaskGate) -> Self {
+        //both gates have the same layout of selector and flags
+        unsafe { mem::transmute(gate) }
+    }
+}
+
 ///the common struct for both TaskGate and InterruptGate
 #[repr(C)]
 #[derive(Copy, Clone)]
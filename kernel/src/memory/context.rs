@@ -1,5 +1,6 @@
 use core::{cell::UnsafeCell, sync::atomic::Ordering};
 
+use crate::smp::{self, MAX_CPUS};
 use crate::task::{Mutex, MutexGuard};
 
 use super::AllocError;
@@ -13,22 +14,30 @@ pub enum Context {
     Kernel,
 }
 
-static CONTEXT: AtomicContext = AtomicContext::new(Context::Boot);
+//each cpu serves own interrupts
+static CONTEXTS: [AtomicContext; MAX_CPUS] =
+    [const { AtomicContext::new(Context::Boot) }; MAX_CPUS];
+
+fn current() -> &'static AtomicContext {
+    &CONTEXTS[smp::cpu_id()]
+}
 
 pub unsafe fn init() {
-    CONTEXT.store(Context::Kernel, Ordering::SeqCst);
+    current().store(Context::Kernel, Ordering::SeqCst);
 }
 
 pub fn start_irq<F: FnOnce()>(f: F) {
-    CONTEXT.store(Context::Irq, Ordering::SeqCst);
+    let context = current();
+
+    context.store(Context::Irq, Ordering::SeqCst);
 
     f();
 
-    CONTEXT.store(Context::Kernel, Ordering::SeqCst);
+    context.store(Context::Kernel, Ordering::SeqCst);
 }
 
 pub fn context() -> Context {
-    CONTEXT.load(Ordering::SeqCst)
+    current().load(Ordering::SeqCst)
 }
 
 pub fn is_irq_context() -> bool {
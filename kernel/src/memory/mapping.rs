@@ -1,6 +1,7 @@
 use kernel_types::io::MemoryRemap;
 
 use super::{
+    paging::{pae::PaeEntry, PagingFeatures},
     DirEntryFlag, MemoryRegionFlag, Page, PhysicalAddress, TableEntryFlag,
     VirtualAddress,
 };
@@ -13,8 +14,10 @@ bitflags::bitflags! {
         const USER_LAYOUT = Self::NO_PRIVILEGE.bits() | Self::PRESENT.bits();
         const USER_CODE = Self::NO_PRIVILEGE.bits() | Self::PRESENT.bits();
 
-        const USER_DATA = Self::WRITABLE.bits() | Self::PRESENT.bits() | Self::NO_PRIVILEGE.bits();
+        const USER_DATA = Self::WRITABLE.bits() | Self::PRESENT.bits() | Self::NO_PRIVILEGE.bits() | Self::NO_EXECUTE.bits();
 
+        /// Software bit, enforced only by PAE paging with NX support
+        const NO_EXECUTE = 0b10_0000_0000;
         const CACHE_DISABLED = 0b10_000;
         const WRITE_THROUGH = 0b1000;
         const NO_PRIVILEGE = 0b100;
@@ -24,14 +27,24 @@ bitflags::bitflags! {
     }
 }
 
+/// Any present user page is readable. Without NX bit it's executable as well,
+/// so only the pages without any access are kept not present
 impl From<MemoryRegionFlag> for MemoryMappingFlag {
     fn from(value: MemoryRegionFlag) -> Self {
+        if !value.intersects(MemoryRegionFlag::ACCESS) {
+            return MemoryMappingFlag::NO_PRIVILEGE;
+        }
+
         let mut flags = MemoryMappingFlag::USER_LAYOUT;
 
         if value.contains(MemoryRegionFlag::WRITE) {
             flags |= Self::WRITABLE;
         }
 
+        if !value.contains(MemoryRegionFlag::EXEC) {
+            flags |= Self::NO_EXECUTE;
+        }
+
         flags
     }
 }
@@ -42,8 +55,26 @@ impl MemoryMappingFlag {
         unsafe { TableEntryFlag::wrap(self.bits() | TableEntryFlag::PRESENT) }
     }
 
+    /// The directory entry only selects privilege, the access
+    /// is checked by table entries (one table maps pages of several regions)
     pub fn as_directory_flag(&self) -> DirEntryFlag {
-        unsafe { DirEntryFlag::wrap(self.bits() | DirEntryFlag::PRESENT) }
+        let flags = self.bits() & !Self::NO_EXECUTE.bits();
+
+        unsafe {
+            DirEntryFlag::wrap(
+                flags | DirEntryFlag::PRESENT | DirEntryFlag::WRITABLE,
+            )
+        }
+    }
+
+    pub fn as_pae_flag(&self) -> u64 {
+        let mut flags = (self.bits() as u64) | PaeEntry::PRESENT;
+
+        if self.contains(Self::NO_EXECUTE) && PagingFeatures::enabled().nx {
+            flags |= PaeEntry::NO_EXECUTE;
+        }
+
+        flags
     }
 }
 
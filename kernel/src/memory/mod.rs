@@ -1,25 +1,37 @@
 use core::alloc::{Allocator, GlobalAlloc};
+use core::arch::asm;
+use core::ops::Range;
 use core::ptr::NonNull;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use core::{mem, ptr};
 
 use alloc::boxed::Box;
 use allocators::SlabAlloc;
 
-pub use allocators::{Alignment, MemoryAllocationFlag, PhysicalAllocator};
+pub use allocators::{
+    Alignment, HeapStat, MemoryAllocationFlag, PhysicalAllocator, SlabStat,
+    MAX_SLAB_STATS, MAX_UNIT_POWER,
+};
 pub use arch::*;
 use kernel_types::collections::LinkedList;
 use kernel_types::declare_constants;
+use kernel_types::object::OpStatus;
 pub use paging::PagingProperties;
-use paging::{GDTHandle, PageDirectoryEntries};
-use talc::{ClaimOnOom, Span, Talc, Talck};
+use paging::{
+    GDTHandle, PaeDirectory, PageDirectoryEntries, TABLE_PAGES_COUNT,
+};
 
 use crate::common::atomics::{SpinLockLazyCell, UnsafeLazyCell};
 use crate::current_task;
+use crate::fs::{self, FsError, IndexNode};
 use crate::io::InterruptableLazyCell;
 use crate::memory::allocators::SystemAllocator;
 use crate::memory::paging::GDTTable;
+use crate::object::Handle;
+use crate::smp::{self, MAX_CPUS};
 
-use crate::task::{Task, TaskState};
+use crate::task::{self, Task, TaskPriority, TaskState, TaskStateSegment};
+use crate::user::kernel_buf::{KernelBuf, LEND_AREA};
 
 mod allocators;
 mod arch;
@@ -29,17 +41,21 @@ mod page;
 mod paging;
 mod process;
 mod region;
+mod shrinker;
+mod swap;
 
 pub use context::{is_irq_context, start_irq, ContextLock};
 pub use mapping::*;
 pub use page::*;
 pub use process::*;
 pub use region::*;
+pub use shrinker::*;
+pub use swap::*;
 
 pub use paging::table::{DirEntry, DirEntryFlag, TableEntry, TableEntryFlag};
 pub use paging::{
-    CaptureMemRec, PageMarker, PageMarkerError, DIRECTORY_ENTRIES_COUNT,
-    DIRECTORY_PAGES_COUNT, TABLE_ENTRIES_COUNT,
+    CaptureMemRec, PageMarker, PageMarkerError, PagingFeatures,
+    DIRECTORY_ENTRIES_COUNT, DIRECTORY_PAGES_COUNT, TABLE_ENTRIES_COUNT,
 };
 
 pub use allocators::Slab;
@@ -63,6 +79,8 @@ pub enum AllocError {
     InvalidAlignment(Alignment),
     #[error("Several overlaping regions")]
     OverlappingRegions,
+    #[error("No region starts at 0x{0:X}")]
+    NoRegion(VirtualAddress),
     #[error("Rust allocator failed: {0}")]
     AllocationFailed(#[from] alloc::alloc::AllocError),
     #[error("Rust collection allocator failed: {0}")]
@@ -90,9 +108,9 @@ pub type VirtualAddress = usize;
 #[no_mangle]
 pub fn init_kernel_space(boot_config: &mut PagingProperties) {
     unsafe {
-        GDT = boot_config.gdt().as_ref().clone();
-        GDT_HANDLE = GDTHandle::new(&raw const GDT);
-        GDT_HANDLE.load();
+        GDTS[0] = GDTTable::from_boot(boot_config.gdt().as_ptr());
+        GDT_HANDLES[0] = GDTHandle::new(&raw const GDTS[0]);
+        GDT_HANDLES[0].load();
 
         //page directory is comming without any reference
         fn init_page(ph_offset: PhysicalAddress) {
@@ -151,27 +169,147 @@ pub fn init_kernel_space(boot_config: &mut PagingProperties) {
 
     unsafe { PHYSICAL_ALLOCATOR.get().init() };
     unsafe { SYSTEM_ALLOCATOR.lock().init() };
+
+    let features = PagingFeatures::detect();
+
+    if features.pae {
+        enable_pae(features).expect("Failed to enable PAE paging");
+
+        log::info!("PAE paging is enabled (NX: {})", features.nx);
+    }
+}
+
+extern "C" {
+    /// Input: eax -> physical address of page directory pointer table,
+    /// ecx -> non-zero to enable no-execute bit.
+    /// The routine turns paging off, so it runs only at identity mapping
+    fn enable_pae_routine();
+    static ENABLE_PAE_ROUTINE_END: u8;
+}
+
+/// Replace the boot page directory by PAE tables mapping the same pages
+fn enable_pae(features: PagingFeatures) -> Result<(), AllocError> {
+    let routine = enable_pae_routine as VirtualAddress;
+    let routine_end = unsafe { &raw const ENABLE_PAE_ROUTINE_END };
+
+    let start = routine - kernel_virtual_offset();
+    let end = routine_end as VirtualAddress - kernel_virtual_offset();
+
+    let identity = Page::SIZE * (start / Page::SIZE)..end;
+
+    map_identity(identity.clone())?;
+
+    let pages_count = KERNEL_MARKER.get().pae_pages_count();
+
+    let mut pages = alloc::vec::Vec::new();
+    pages.try_reserve_exact(pages_count)?;
+
+    for _ in 0..pages_count {
+        pages.push(
+            alloc_physical_pages(TABLE_PAGES_COUNT)
+                .ok_or(AllocError::NoMemory)?,
+        );
+    }
+
+    let mut marker = KERNEL_MARKER.get();
+
+    let directory = marker.mirror_to_pae(&mut || pages.pop())?;
+
+    unsafe {
+        asm!(
+            "call {routine}",
+            routine = in(reg) start,
+            inout("eax") directory.physical_offset => _,
+            inout("ecx") features.nx as usize => _,
+            out("edx") _,
+        );
+    }
+
+    //the pages of boot directory stay used by PAE tables
+    mem::forget(mem::replace(&mut *marker, PageMarker::new_pae(directory)));
+
+    features.enable();
+
+    drop(marker);
+
+    unmap_identity(identity);
+
+    pages.into_iter().for_each(dealloc_physical_page);
+
+    Ok(())
 }
 
-#[allow(static_mut_refs)]
 pub fn enable_task_switching() {
+    load_task_state(0);
+
+    unsafe { context::init() };
+}
+
+/// Load own GDT and TSS on application processor
+#[allow(static_mut_refs)]
+pub fn init_cpu(cpu: usize) {
+    unsafe {
+        GDTS[cpu] = GDTS[0].clone();
+        GDT_HANDLES[cpu] = GDTHandle::new(&raw const GDTS[cpu]);
+        GDT_HANDLES[cpu].load();
+    }
+
+    load_task_state(cpu);
+
+    unsafe { context::init() };
+}
+
+#[allow(static_mut_refs)]
+fn load_task_state(cpu: usize) {
     let mut state = TaskState::null();
-    state.set_io_map(0xFFFF);
+    state.set_io_map(task::NO_IO_BITMAP);
     state.set_stack_selector(SegmentSelector::KERNEL_DATA);
     state.set_code_selector(SegmentSelector::KERNEL_CODE);
 
-    unsafe { TASK_STATE = state };
+    //the bitmap is too large to be moved through stack
+    unsafe { TASK_STATES[cpu].state = state };
+
+    let task_state = unsafe { &raw const TASK_STATES[cpu] };
 
     let task = TaskStateDescriptor::active(
-        &raw const TASK_STATE as VirtualAddress,
-        mem::size_of::<TaskState>() - 1,
+        task_state as VirtualAddress,
+        TaskStateSegment::LIMIT,
     );
 
-    log::debug!("Task state: {task:?}");
+    log::debug!("Task state of CPU#{cpu}: {task:?}");
 
-    unsafe { GDT.load_task(task) };
+    load_double_fault_task(cpu);
 
-    unsafe { context::init() };
+    unsafe { GDTS[cpu].load_task(task) };
+}
+
+/// The stack of double fault handler. The handler runs as separate task,
+/// so the kernel stack overflow doesn't cause another fault
+#[repr(C, align(16))]
+struct DoubleFaultStack([u8; Page::SIZE]);
+
+#[allow(static_mut_refs)]
+fn load_double_fault_task(cpu: usize) {
+    let stack = unsafe { &raw const DOUBLE_FAULT_STACKS[cpu] };
+    let stack_top =
+        stack as VirtualAddress + mem::size_of::<DoubleFaultStack>();
+
+    let state = TaskState::kernel_entry(
+        crate::io::double_fault_task,
+        stack_top,
+        KERNEL_MARKER.get().physical_offset(),
+    );
+
+    unsafe { DOUBLE_FAULT_STATES[cpu] = state };
+
+    let task_state = unsafe { &raw const DOUBLE_FAULT_STATES[cpu] };
+
+    let task = TaskStateDescriptor::active(
+        task_state as VirtualAddress,
+        mem::size_of::<TaskState>() - 1,
+    );
+
+    unsafe { GDTS[cpu].set_double_fault_task(task) };
 }
 
 fn alloc_physical_pages(page_count: usize) -> Option<PhysicalAddress> {
@@ -337,6 +475,19 @@ pub fn new_page_marker() -> Result<PageMarker, AllocError> {
         mem::size_of::<PageDirectoryEntries>()
     );
 
+    if PagingFeatures::enabled().pae {
+        let directory =
+            PaeDirectory::new(paging::pae::kernel_pointer(), &mut || {
+                alloc_physical_pages(TABLE_PAGES_COUNT)
+            })?;
+
+        let mut marker = PageMarker::new_pae(directory);
+
+        KERNEL_MARKER.get().share_kernel(&mut marker);
+
+        return Ok(marker);
+    }
+
     let raw_entries = SYSTEM_ALLOCATOR
         .lock()
         .virtual_alloc(
@@ -349,14 +500,6 @@ pub fn new_page_marker() -> Result<PageMarker, AllocError> {
 
     let entries = unsafe { &mut *raw_entries };
 
-    //essential data structures will be copied
-    //but we must ensure integrity kernel space
-    //in all processes
-    unsafe {
-        entries
-            .copy_from_slice(KERNEL_MARKER.get().directory().share_entries());
-    }
-
     let physical_offset = KERNEL_MARKER
         .get()
         .lookup_physical(raw_entries as VirtualAddress)
@@ -367,7 +510,14 @@ pub fn new_page_marker() -> Result<PageMarker, AllocError> {
         physical_offset,
     };
 
-    Ok(PageMarker::new(directory))
+    let mut marker = PageMarker::new(directory);
+
+    //essential data structures will be copied
+    //but we must ensure integrity kernel space
+    //in all processes
+    KERNEL_MARKER.get().share_kernel(&mut marker);
+
+    Ok(marker)
 }
 
 pub struct PhysicalAllocation {
@@ -444,16 +594,452 @@ pub fn remap(
     Ok(())
 }
 
+/// Map kernel pages of `[kernel_offset; kernel_offset + len)` into the first free
+/// window of `area` in the process address space. The pages are still owned by kernel,
+/// the process only borrows them until [`reclaim_pages`]
+pub fn lend_pages(
+    process: &Process,
+    kernel_offset: VirtualAddress,
+    len: usize,
+    area: Range<VirtualAddress>,
+) -> Result<VirtualAddress, AllocError> {
+    if kernel_offset % Page::SIZE != 0 {
+        return Err(AllocError::InvalidAlignment(Alignment::Page));
+    }
+
+    let page_count = Page::upper_bound(len);
+    let size = page_count * Page::SIZE;
+
+    let mut state = process.state.lock();
+
+    let Some(user_offset) = state.find_free_range(area, size) else {
+        return Err(AllocError::NoMemory);
+    };
+
+    let region = unsafe {
+        MemoryRegion::empty(
+            user_offset..(user_offset + size),
+            MemoryRegionFlag::READ_WRITE | MemoryRegionFlag::SHARED,
+        )
+    }?;
+
+    for index in 0..page_count {
+        let virt_offset = user_offset + index * Page::SIZE;
+
+        let mapped =
+            lookup_kernel_physical_page(kernel_offset + index * Page::SIZE)
+                .ok_or(AllocError::NoMemory)
+                .and_then(|physical_offset| {
+                    state
+                        .marker
+                        .map_user_range(&MemoryMappingRegion {
+                            flags: MemoryMappingFlag::USER_DATA,
+                            virtual_offset: virt_offset,
+                            physical_offset,
+                            page_count: 1,
+                        })
+                        .map_err(AllocError::from)
+                });
+
+        if let Err(cause) = mapped {
+            state.marker.unmap_lent_range(user_offset..virt_offset);
+
+            return Err(cause);
+        }
+    }
+
+    state.add_region(region.into_node());
+
+    Ok(user_offset)
+}
+
+/// Unmap pages lent by [`lend_pages`] starting at `user_offset`
+pub fn reclaim_pages(process: &Process, user_offset: VirtualAddress) {
+    let mut state = process.state.lock();
+
+    let Some(node) = state
+        .regions
+        .remove_by(|region| region.range.start == user_offset)
+    else {
+        log::warn!("No lent region at 0x{user_offset:X}");
+        return;
+    };
+
+    //the region has no pages, so nothing is deallocated
+    let region = node.into_boxed();
+
+    state.marker.unmap_lent_range(region.range.clone());
+}
+
+/// Reserve `len` bytes in the first free window of [`ANONYMOUS_AREA`].
+/// The zeroed pages are allocated on the first access and
+/// owned by region until [`unmap_region`]
+pub fn map_anonymous(
+    process: &Process,
+    len: usize,
+    flag: MemoryRegionFlag,
+) -> Result<VirtualAddress, AllocError> {
+    reserve_demand_region(&mut process.state.lock(), len, flag, None)
+}
+
+/// Reserve `len` bytes of `node` starting at `offset` in [`ANONYMOUS_AREA`].
+/// The pages are read from file on the first access. There is no page cache,
+/// so each process reads own copy and writes of private mapping never reach
+/// the file. The shared mapping is written back by [`sync_file_map`]
+pub fn map_file(
+    process: &Process,
+    node: Handle<IndexNode>,
+    offset: usize,
+    len: usize,
+    flag: MemoryRegionFlag,
+) -> Result<VirtualAddress, AllocError> {
+    if offset % Page::SIZE != 0 {
+        return Err(AllocError::InvalidAlignment(Alignment::Page));
+    }
+
+    let file = MappedFile { node, offset };
+
+    reserve_demand_region(&mut process.state.lock(), len, flag, Some(file))
+}
+
+fn reserve_demand_region(
+    state: &mut ProcessState,
+    len: usize,
+    flag: MemoryRegionFlag,
+    file: Option<MappedFile>,
+) -> Result<VirtualAddress, AllocError> {
+    if len == 0 {
+        return Err(AllocError::NoMemory);
+    }
+
+    let size = Page::upper_bound(len) * Page::SIZE;
+
+    let Some(user_offset) = state.find_free_range(ANONYMOUS_AREA, size) else {
+        return Err(AllocError::NoMemory);
+    };
+
+    let mut region = unsafe {
+        MemoryRegion::empty(
+            user_offset..(user_offset + size),
+            flag | MemoryRegionFlag::DEMAND,
+        )
+    }?;
+
+    region.file = file;
+
+    state.add_region(region.into_node());
+
+    Ok(user_offset)
+}
+
+/// Resolve the fault at `address` of `process`. The page of file
+/// mapping or swap is read with unlocked state, so the task may block.
+/// The file page is read only where blocking on fs is allowed,
+/// see [`can_block_on_fs`].
+/// Without free memory the cold pages are swapped out first
+pub fn resolve_fault(
+    process: &Process,
+    address: VirtualAddress,
+    is_present: bool,
+    is_write: bool,
+) -> Result<(), PageFault> {
+    match fault_once(process, address, is_present, is_write) {
+        Err(PageFault::Alloc(AllocError::NoMemory))
+            if swap_out_pages(SWAP_BATCH) > 0 =>
+        {
+            fault_once(process, address, is_present, is_write)
+        }
+        resolved => resolved,
+    }
+}
+
+fn fault_once(
+    process: &Process,
+    address: VirtualAddress,
+    is_present: bool,
+    is_write: bool,
+) -> Result<(), PageFault> {
+    let resolved = process
+        .state
+        .lock()
+        .handle_fault(address, is_present, is_write);
+
+    match resolved {
+        Err(PageFault::FileBacked) => fault_in_file(process, address),
+        Err(PageFault::Swapped) => swap::swap_in(process, address),
+        resolved => resolved,
+    }
+}
+
+/// The file pages are read and written by the module serving file system,
+/// so the task waits for it. The interrupt handlers and module tasks
+/// (they may serve the file system themselves) never wait
+fn can_block_on_fs() -> bool {
+    !is_irq_context()
+        && !matches!(current_task!().priority, TaskPriority::Module(_))
+}
+
+fn fault_in_file(
+    process: &Process,
+    address: VirtualAddress,
+) -> Result<(), PageFault> {
+    if !can_block_on_fs() {
+        return Err(PageFault::WouldBlock);
+    }
+
+    let (node, file_offset) = {
+        let state = process.state.lock();
+
+        let region = state.find_region(address).ok_or(PageFault::NotMapped)?;
+
+        let (Some(file), Some(file_offset)) =
+            (region.file.as_ref(), region.file_offset(address))
+        else {
+            return Err(PageFault::NotMapped);
+        };
+
+        (file.node.clone(), file_offset)
+    };
+
+    let buf = KernelBuf::new(Page::SIZE)?;
+
+    fs::read_at(&node, file_offset, buf.clone())?;
+
+    process.state.lock().map_file_page(address, &buf)
+}
+
+/// Write the pages of shared file mapping at `user_offset` back to file.
+/// The pages never touched are skipped. The process address space
+/// should be active, the pages are read through it
+pub fn sync_file_map(
+    process: &Process,
+    user_offset: VirtualAddress,
+) -> Result<(), FsError> {
+    if !can_block_on_fs() {
+        return Err(FsError::OpFailed(OpStatus::Failed));
+    }
+
+    let (node, file_start, range) = {
+        let state = process.state.lock();
+
+        let Some(region) = state
+            .regions
+            .iter()
+            .find(|region| region.range.start == user_offset)
+        else {
+            return Ok(());
+        };
+
+        let is_shared = region
+            .flag
+            .contains(MemoryRegionFlag::SHARED | MemoryRegionFlag::WRITE);
+
+        let Some(file) = region.file.as_ref().filter(|_| is_shared) else {
+            return Ok(());
+        };
+
+        (file.node.clone(), file.offset, region.range.clone())
+    };
+
+    for page_offset in range.clone().step_by(Page::SIZE) {
+        let file_offset = file_start + (page_offset - range.start);
+
+        //the file isn't extended by the tail of last page
+        let len =
+            usize::min(Page::SIZE, node.size().saturating_sub(file_offset));
+
+        if len == 0 {
+            break;
+        }
+
+        if process
+            .state
+            .lock()
+            .marker
+            .lookup_physical(page_offset)
+            .is_none()
+        {
+            continue;
+        }
+
+        let page = unsafe {
+            core::slice::from_raw_parts(page_offset as *const u8, len)
+        };
+
+        let buf = KernelBuf::new(len)?;
+
+        if buf.copy_from(page).is_err() {
+            return Err(FsError::OpFailed(OpStatus::NoSpace));
+        }
+
+        fs::write_at(&node, file_offset, buf)?;
+    }
+
+    Ok(())
+}
+
+/// Write back all shared file mappings of `process` before its teardown.
+/// The process address space should be active as for [`sync_file_map`]
+pub fn sync_file_maps(process: &Process) {
+    let mut next = 0;
+
+    loop {
+        let start = process
+            .state
+            .lock()
+            .regions
+            .iter()
+            .filter(|region| region.file.is_some())
+            .map(|region| region.range.start)
+            .filter(|&start| start >= next)
+            .min();
+
+        let Some(start) = start else {
+            break;
+        };
+
+        if let Err(cause) = sync_file_map(process, start) {
+            log::warn!("Failed to write back file mapping: {cause}");
+        }
+
+        next = start + 1;
+    }
+}
+
+/// Unmap the region mapped by [`map_anonymous`] or [`map_file`]
+/// at `user_offset` and return its pages to physical allocator
+pub fn unmap_region(
+    process: &Process,
+    user_offset: VirtualAddress,
+) -> Result<(), AllocError> {
+    if !ANONYMOUS_AREA.contains(&user_offset) {
+        return Err(AllocError::NoRegion(user_offset));
+    }
+
+    let mut state = process.state.lock();
+
+    let Some(node) = state
+        .regions
+        .remove_by(|region| region.range.start == user_offset)
+    else {
+        return Err(AllocError::NoRegion(user_offset));
+    };
+
+    let region = node.into_boxed();
+
+    //the region releases own pages, the pages shared
+    //with forked process stay used by its mappings
+    state.marker.unmap_user_range(region.range.clone());
+
+    drop(region);
+
+    Ok(())
+}
+
 /// allocate physical memory
-/// not continuous
+/// not continuous. The shrinkers are asked for memory before failure
 pub fn physical_alloc(bytes: usize) -> Result<PhysicalAllocation, AllocError> {
     let pages_count = Page::upper_bound(bytes);
 
-    let list = PHYSICAL_ALLOCATOR.get().alloc_zeroed_pages(pages_count)?;
+    let allocator = PHYSICAL_ALLOCATOR.get();
+
+    let list = match allocator.alloc_zeroed_pages(pages_count) {
+        Err(AllocError::NoMemory) if shrink() > 0 => {
+            allocator.alloc_zeroed_pages(pages_count)?
+        }
+        result => result?,
+    };
 
     Ok(list.into())
 }
 
+/// Change the access to pages in `address..address + len` of process.
+/// The range should lie in one region which is split on the range bounds.
+/// The anonymous neighbours with the same access are merged back
+pub fn protect(
+    process: &Process,
+    address: VirtualAddress,
+    len: usize,
+    access: MemoryRegionFlag,
+) -> Result<(), AllocError> {
+    if address % Page::SIZE != 0 {
+        return Err(AllocError::InvalidAlignment(Alignment::Page));
+    }
+
+    //the kernel buffers are lent with fixed access
+    if len == 0 || LEND_AREA.contains(&address) {
+        return Err(AllocError::NoRegion(address));
+    }
+
+    let Some(end) = address.checked_add(Page::upper_bound(len) * Page::SIZE)
+    else {
+        return Err(AllocError::NoRegion(address));
+    };
+
+    let mut state = process.state.lock();
+    let ProcessState {
+        regions, marker, ..
+    } = &mut *state;
+
+    let region = regions
+        .iter_mut()
+        .find(|region| region.range.contains(&address))
+        .ok_or(AllocError::NoRegion(address))?;
+
+    if end > region.range.end {
+        return Err(AllocError::OverlappingRegions);
+    }
+
+    let flag = region.flag.difference(MemoryRegionFlag::ACCESS) | access;
+
+    let tail = if end < region.range.end {
+        Some(region.split_at(end, marker)?)
+    } else {
+        None
+    };
+
+    let middle = if address > region.range.start {
+        Some(region.split_at(address, marker)?)
+    } else {
+        region.flag = flag;
+        None
+    };
+
+    let middle = middle.map(|mut middle| {
+        middle.flag = flag;
+        middle
+    });
+
+    for part in [middle, tail].into_iter().flatten() {
+        regions.push_back(part.into_node());
+    }
+
+    //the private pages can be shared after fork,
+    //so they get write access on the first write fault
+    let mapping = if flag.contains(MemoryRegionFlag::SHARED) {
+        MemoryMappingFlag::from(flag)
+    } else {
+        MemoryMappingFlag::from(flag.difference(MemoryRegionFlag::WRITE))
+    };
+
+    marker.protect_range(address..end, mapping);
+
+    if ANONYMOUS_AREA.contains(&address) {
+        state.merge_at(end);
+        state.merge_at(address);
+    }
+
+    Ok(())
+}
+
+/// Drop the reference of mapping to physical page.
+/// The page is freed when nothing uses it
+pub fn release_page(page: PhysicalAddress) {
+    let page = unsafe { &mut *Page::take_unchecked(page) };
+
+    PHYSICAL_ALLOCATOR.get().dealloc_page(page);
+}
+
 pub fn physical_dealloc(mut pages: LinkedList<'static, Page>) {
     let mut iter = pages.iter_mut();
 
@@ -478,18 +1064,38 @@ pub fn physical_dealloc(mut pages: LinkedList<'static, Page>) {
 pub unsafe fn switch_to_task(task: &mut Task) {
     let stack = task.stack_start();
 
-    let task_state = &raw mut TASK_STATE;
-    unsafe { (*task_state).set_kernel_stack(stack) };
+    let cpu = smp::cpu_id();
+
+    let task_state = &raw mut TASK_STATES[cpu];
+    unsafe { (*task_state).state.set_kernel_stack(stack) };
+
+    let process_id = task.process.as_ref().map_or(0, |process| process.id);
+    RUNNING_PROCESSES[cpu].store(process_id, Ordering::SeqCst);
 
     if let Some(process) = task.process.as_ref() {
         let state = process.state.lock();
 
         state.marker.load();
+
+        let bitmap = state.io_bitmap.as_deref();
+        unsafe {
+            (*task_state).load_io_bitmap(bitmap.map(|map| (process.id, map)))
+        };
     } else {
         KERNEL_MARKER.get().load();
+
+        unsafe { (*task_state).load_io_bitmap(None) };
     };
 }
 
+/// The process of task running on current processor.
+/// Unlike `current_task!`, the scheduler isn't locked
+pub fn running_process() -> Option<ProcessId> {
+    let id = RUNNING_PROCESSES[smp::cpu_id()].load(Ordering::SeqCst);
+
+    (id != 0).then_some(id)
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum AddressSpace {
     Kernel,
@@ -511,10 +1117,10 @@ pub fn address_space() -> AddressSpace {
         .process
         .clone()
         .map(|proc| {
-            let kernel_dir = KERNEL_MARKER.get().directory() as *const _;
-            let user_dir = proc.state.lock().marker.directory() as *const _;
+            let kernel_dir = KERNEL_MARKER.get().physical_offset();
+            let user_dir = proc.state.lock().marker.physical_offset();
 
-            if ptr::eq(kernel_dir, user_dir) {
+            if kernel_dir == user_dir {
                 AddressSpace::Kernel
             } else {
                 AddressSpace::User
@@ -528,6 +1134,43 @@ pub unsafe fn switch_to_kernel() {
     marker.load();
 }
 
+/// The kernel address space loaded for the syscall. The address space
+/// of current task is loaded back on drop, so an early return with
+/// error doesn't leave the user code running with kernel directory
+pub struct KernelSpaceGuard {
+    _private: (),
+}
+
+impl KernelSpaceGuard {
+    pub unsafe fn enter() -> Self {
+        unsafe { switch_to_kernel() };
+
+        Self { _private: () }
+    }
+
+    /// Load the task address space back, e.g. to write the result
+    pub fn leave(self) {}
+}
+
+impl Drop for KernelSpaceGuard {
+    fn drop(&mut self) {
+        let active: PhysicalAddress;
+
+        unsafe {
+            asm! {
+                "mov {}, cr3",
+                out(reg) active,
+                options(nostack, preserves_flags)
+            }
+        }
+
+        //the work handlers load task address space themselves
+        if active == KERNEL_MARKER.get().physical_offset() {
+            unsafe { switch_to_task(current_task!()) };
+        }
+    }
+}
+
 //duplicates in kernel.ld script
 declare_constants!(
     pub usize,
@@ -574,13 +1217,132 @@ pub fn kernel_commit(
     marker.map_kernel_range(&region)
 }
 
+/// Unmap kernel memory committed by [`kernel_commit`].
+/// The pages are not deallocated, only the mapping releases them
+pub fn kernel_uncommit(range: Range<VirtualAddress>) {
+    let mut marker = KERNEL_MARKER.get();
+    marker.unmap_lent_range(range);
+}
+
+/// The kernel window where device memory is mapped
+const DEVICE_WINDOW: Range<VirtualAddress> = 0xFF80_0000..0xFFC0_0000;
+
+/// The area of anonymous mappings in process address space
+pub const ANONYMOUS_AREA: Range<VirtualAddress> = 0x10_000_000..0x80_000_000;
+
+static DEVICE_WINDOW_NEXT: AtomicUsize = AtomicUsize::new(DEVICE_WINDOW.start);
+
+/// Map `len` bytes of device memory (MMIO, firmware tables)
+/// starting at `physical` into kernel space.
+/// The mapping is never released
+pub fn map_device(
+    physical: PhysicalAddress,
+    len: usize,
+) -> Result<VirtualAddress, AllocError> {
+    let page_offset = physical % Page::SIZE;
+    let page_count = Page::upper_bound(page_offset + len);
+    let size = page_count * Page::SIZE;
+
+    let virtual_offset = DEVICE_WINDOW_NEXT
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |next| {
+            (next + size <= DEVICE_WINDOW.end).then_some(next + size)
+        })
+        .map_err(|_| AllocError::NoMemory)?;
+
+    KERNEL_MARKER.get().map_device_range(&MemoryMappingRegion {
+        flags: MemoryMappingFlag::KERNEL_LAYOUT
+            | MemoryMappingFlag::CACHE_DISABLED,
+        virtual_offset,
+        physical_offset: physical - page_offset,
+        page_count,
+    })?;
+
+    Ok(virtual_offset + page_offset)
+}
+
+/// The usage of physical memory
+#[derive(Debug, Clone)]
+pub struct PhysicalStat {
+    /// The pages of RAM described by memory map
+    pub total_pages: usize,
+    pub free_pages: usize,
+    /// The count of free batches in each buddy list,
+    /// the batch of power N holds up to 2^N pages
+    pub free_batches: [usize; MAX_UNIT_POWER + 1],
+}
+
+impl PhysicalStat {
+    pub fn used_pages(&self) -> usize {
+        self.total_pages - self.free_pages
+    }
+}
+
+pub fn physical_stat() -> Result<PhysicalStat, AllocError> {
+    let free_batches = PHYSICAL_ALLOCATOR.get().free_batches()?;
+
+    //the pages outside of RAM are never initialized
+    let pages = unsafe { (*(&raw const MEMORY_MAP)).iter() }
+        .filter(|page| page.flags.contains(PageFlag::UNUSED));
+
+    let (total_pages, free_pages) = pages
+        .fold((0, 0), |(total, free), page| {
+            (total + 1, free + !page.is_used() as usize)
+        });
+
+    Ok(PhysicalStat {
+        total_pages,
+        free_pages,
+        free_batches,
+    })
+}
+
+pub fn slab_stats() -> heapless::Vec<SlabStat, MAX_SLAB_STATS> {
+    SYSTEM_ALLOCATOR.lock().slab_stats()
+}
+
+pub fn heap_stat() -> HeapStat {
+    SYSTEM_ALLOCATOR.lock().heap_stat()
+}
+
+/// The physical address of kernel page directory
+pub fn kernel_directory() -> PhysicalAddress {
+    KERNEL_MARKER.get().physical_offset()
+}
+
+/// Map low memory to the same virtual addresses in kernel space.
+/// It is used by code enabling paging (the startup of processors)
+pub fn map_identity(range: Range<PhysicalAddress>) -> Result<(), AllocError> {
+    KERNEL_MARKER.get().map_device_range(&MemoryMappingRegion {
+        flags: MemoryMappingFlag::KERNEL_LAYOUT,
+        virtual_offset: range.start,
+        physical_offset: range.start,
+        page_count: Page::upper_bound(range.len()),
+    })?;
+
+    Ok(())
+}
+
+pub fn unmap_identity(range: Range<PhysicalAddress>) {
+    KERNEL_MARKER.get().unmap_device_range(range);
+}
+
 extern "C" {
     static mut MEMORY_MAP: MemoryMap;
 }
 
-static mut TASK_STATE: TaskState = TaskState::null();
-static mut GDT: GDTTable = GDTTable::null();
-static mut GDT_HANDLE: GDTHandle = GDTHandle::null();
+static mut TASK_STATES: [TaskStateSegment; MAX_CPUS] =
+    [const { TaskStateSegment::null() }; MAX_CPUS];
+/// The process loaded by [`switch_to_task`] on each processor.
+/// Zero for kernel tasks, the process ids start from one
+static RUNNING_PROCESSES: [AtomicUsize; MAX_CPUS] =
+    [const { AtomicUsize::new(0) }; MAX_CPUS];
+static mut DOUBLE_FAULT_STATES: [TaskState; MAX_CPUS] =
+    [const { TaskState::null() }; MAX_CPUS];
+static mut DOUBLE_FAULT_STACKS: [DoubleFaultStack; MAX_CPUS] =
+    [const { DoubleFaultStack([0; Page::SIZE]) }; MAX_CPUS];
+static mut GDTS: [GDTTable; MAX_CPUS] = [const { GDTTable::null() }; MAX_CPUS];
+static mut GDT_HANDLES: [GDTHandle; MAX_CPUS] =
+    [const { GDTHandle::null() }; MAX_CPUS];
 
 static PHYSICAL_ALLOCATOR: UnsafeLazyCell<PhysicalAllocator> =
     UnsafeLazyCell::empty();
@@ -594,7 +1356,10 @@ static KERNEL_ALLOCATOR: VirtualAllocator = VirtualAllocator;
 
 unsafe impl GlobalAlloc for VirtualAllocator {
     unsafe fn alloc(&self, layout: core::alloc::Layout) -> *mut u8 {
-        if let Some(slab) = allocators::classify_slab_by_size(layout.size()) {
+        //the objects of slab are aligned on their size
+        let slab_size = usize::max(layout.size(), layout.align());
+
+        if let Some(slab) = allocators::classify_slab_by_size(slab_size) {
             let ptr = SYSTEM_ALLOCATOR
                 .lock()
                 .alloc_slab(slab)
@@ -618,7 +1383,9 @@ unsafe impl GlobalAlloc for VirtualAllocator {
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, layout: core::alloc::Layout) {
-        if let Some(slab) = allocators::classify_slab_by_size(layout.size()) {
+        let slab_size = usize::max(layout.size(), layout.align());
+
+        if let Some(slab) = allocators::classify_slab_by_size(slab_size) {
             SYSTEM_ALLOCATOR.lock().dealloc_slab(slab.name, ptr);
 
             return;
@@ -3,25 +3,125 @@ use core::{arch::asm, ops::Range};
 use crate::{
     memory::{
         self, MemoryMappingFlag, MemoryMappingRegion, Page, PhysicalAddress,
-        VirtualAddress,
+        TableEntryFlag, VirtualAddress,
     },
-    page_index, table_index,
+    page_index, smp, table_index,
 };
 
-use super::{PageDirectory, PageMarkerError, TABLE_PAGES_COUNT};
+use super::{
+    pae::{PaeDirectory, PaeEntry, TableAlloc, POINTERS_COUNT},
+    table::{DirEntryFlag, TableEntry},
+    PageDirectory, PageMarkerError, TABLE_ENTRIES_COUNT, TABLE_PAGES_COUNT,
+};
 
 /// The struct is simply used to transfer physical layout for page allcoator
 #[derive(Debug)]
 pub struct PageMarker {
     // directory: &'a mut [DirEntry<'a>; DIRECTORY_ENTRIES_COUNT],
-    directory: PageDirectory<'static, 'static>,
+    layout: Layout,
+}
+
+/// The paging structures selected at boot
+#[derive(Debug)]
+enum Layout {
+    Legacy(PageDirectory<'static, 'static>),
+    Pae(PaeDirectory),
+}
+
+/// The entry mapping one page in any layout
+enum PageEntry<'a> {
+    Legacy(&'a mut TableEntry<'static>),
+    Pae(&'a mut PaeEntry),
+}
+
+impl PageEntry<'_> {
+    fn ph_offset(&self) -> Option<PhysicalAddress> {
+        match self {
+            Self::Legacy(entry) => entry.ph_offset(),
+            Self::Pae(entry) => entry.ph_offset(),
+        }
+    }
+
+    fn clear(&mut self) -> Option<PhysicalAddress> {
+        match self {
+            Self::Legacy(entry) => entry.clear(),
+            Self::Pae(entry) => entry.clear(),
+        }
+    }
+
+    fn swap_slot(&self) -> Option<usize> {
+        match self {
+            Self::Legacy(entry) => entry.swap_slot(),
+            Self::Pae(entry) => entry.swap_slot(),
+        }
+    }
+
+    fn set_swap_slot(&mut self, slot: usize) {
+        match self {
+            Self::Legacy(entry) => entry.set_swap_slot(slot),
+            Self::Pae(entry) => entry.set_swap_slot(slot),
+        }
+    }
+
+    /// Clear the accessed bit set by processor, return its value
+    fn take_accessed(&mut self) -> bool {
+        match self {
+            Self::Legacy(entry) => {
+                let flags = entry.flags().bits();
+
+                entry.set_flags(unsafe {
+                    TableEntryFlag::wrap(flags & !TableEntryFlag::ACCESSED)
+                });
+
+                flags & TableEntryFlag::ACCESSED != 0
+            }
+            Self::Pae(entry) => {
+                let flags = entry.flags();
+
+                entry.set_flags(flags & !PaeEntry::ACCESSED);
+
+                flags & PaeEntry::ACCESSED != 0
+            }
+        }
+    }
+
+    /// Map present page at `ph_offset`
+    fn set(&mut self, ph_offset: PhysicalAddress, flags: MemoryMappingFlag) {
+        match self {
+            Self::Legacy(entry) => {
+                **entry = TableEntry::new(ph_offset, flags.as_table_flag())
+            }
+            Self::Pae(entry) => {
+                **entry = PaeEntry::new(ph_offset, flags.as_pae_flag())
+            }
+        }
+    }
+
+    /// Change the flags keeping the frame, the page stays
+    /// not present if `flags` has no [`MemoryMappingFlag::PRESENT`]
+    fn protect(&mut self, flags: MemoryMappingFlag) {
+        let is_present = flags.contains(MemoryMappingFlag::PRESENT);
+
+        match self {
+            Self::Legacy(entry) if is_present => {
+                entry.set_flags(flags.as_table_flag())
+            }
+            Self::Legacy(entry) => {
+                entry.set_flags(unsafe { TableEntryFlag::wrap(flags.bits()) })
+            }
+            Self::Pae(entry) if is_present => {
+                entry.set_flags(flags.as_pae_flag())
+            }
+            Self::Pae(entry) => entry.set_flags(flags.bits() as u64),
+        }
+    }
 }
 
 impl PageMarker {
     ///load underlying directory table to cpu
     #[inline(always)]
     pub fn load(&self) {
-        let directory: PhysicalAddress = self.directory.physical_offset;
+        let directory: PhysicalAddress = self.physical_offset();
 
         unsafe {
             asm! {
@@ -32,12 +132,27 @@ impl PageMarker {
         }
     }
 
+    /// The physical address loaded to CR3
+    pub fn physical_offset(&self) -> PhysicalAddress {
+        match &self.layout {
+            Layout::Legacy(directory) => directory.physical_offset,
+            Layout::Pae(directory) => directory.physical_offset,
+        }
+    }
+
     pub fn lookup_physical(
         &self,
         offset: VirtualAddress,
     ) -> Option<PhysicalAddress> {
+        let directory = match &self.layout {
+            Layout::Legacy(directory) => directory,
+            Layout::Pae(directory) => {
+                return directory.entry(offset)?.ph_offset()
+            }
+        };
+
         let table_index = table_index!(offset);
-        let table_entry = self.directory.entries[table_index];
+        let table_entry = directory.entries[table_index];
 
         let page_table = table_entry.page_table()?;
 
@@ -45,6 +160,133 @@ impl PageMarker {
 
         page_entry.ph_offset()
     }
+
+    /// The count of pages mapped in `range` (including not present ones)
+    pub fn mapped_pages(&self, range: Range<VirtualAddress>) -> usize {
+        range
+            .step_by(Page::SIZE)
+            .filter(|&offset| self.peek_physical(offset).is_some())
+            .count()
+    }
+
+    /// Unlike [`Self::lookup_physical`], the page table is not acquired
+    fn peek_physical(&self, offset: VirtualAddress) -> Option<PhysicalAddress> {
+        let directory = match &self.layout {
+            Layout::Legacy(directory) => directory,
+            Layout::Pae(directory) => {
+                return directory.entry(offset)?.ph_offset()
+            }
+        };
+
+        let table_offset =
+            directory.entries[table_index!(offset)].ph_offset()?;
+
+        let virt_offset =
+            unsafe { &*Page::take_unchecked(table_offset) }.as_virtual()?;
+
+        let page_table = unsafe {
+            &*(virt_offset as *const [TableEntry; TABLE_ENTRIES_COUNT])
+        };
+
+        page_table[page_index!(offset)].ph_offset()
+    }
+
+    /// The entry of page at `offset`, `None` if there is no page table
+    fn entry(&mut self, offset: VirtualAddress) -> Option<PageEntry<'_>> {
+        match &mut self.layout {
+            Layout::Legacy(directory) => {
+                let dir_entry = &mut directory.entries[table_index!(offset)];
+                let page_table = dir_entry.page_table_mut()?;
+
+                Some(PageEntry::Legacy(&mut page_table[page_index!(offset)]))
+            }
+            Layout::Pae(directory) => {
+                directory.entry(offset).map(PageEntry::Pae)
+            }
+        }
+    }
+
+    /// The entry of page at `offset`, the missing page table
+    /// is allocated only if `can_allocate` is set
+    fn entry_or_alloc(
+        &mut self,
+        offset: VirtualAddress,
+        flags: MemoryMappingFlag,
+        can_allocate: bool,
+    ) -> Result<PageEntry<'_>, PageMarkerError> {
+        let mut alloc = || memory::alloc_physical_pages(TABLE_PAGES_COUNT);
+
+        let directory = match &mut self.layout {
+            Layout::Legacy(directory) => directory,
+            Layout::Pae(directory) => {
+                let is_user = flags.contains(MemoryMappingFlag::NO_PRIVILEGE);
+                let alloc: Option<TableAlloc> =
+                    if can_allocate { Some(&mut alloc) } else { None };
+
+                return directory
+                    .entry_or_alloc(offset, is_user, alloc)
+                    .map(PageEntry::Pae);
+            }
+        };
+
+        let table_index = table_index!(offset);
+        let dir_entry = &mut directory.entries[table_index];
+
+        dir_entry.set_flags(flags.as_directory_flag());
+
+        if !dir_entry.has_page_table() {
+            log::debug!("Table index: {table_index}");
+
+            if !can_allocate {
+                return Err(PageMarkerError::EmptyDirEntry(offset as _));
+            }
+
+            let Some(ph_offset) = alloc() else {
+                return Err(PageMarkerError::OutOfMemory);
+            };
+
+            dir_entry.set_ph_offset(ph_offset);
+        }
+
+        let page_table = dir_entry.page_table_mut().unwrap();
+
+        Ok(PageEntry::Legacy(&mut page_table[page_index!(offset)]))
+    }
+
+    /// Detach the page table covering `offset` if it has no pages
+    fn detach_empty_table(&mut self, offset: VirtualAddress) {
+        let directory = match &mut self.layout {
+            Layout::Legacy(directory) => directory,
+            Layout::Pae(directory) => {
+                return directory.detach_empty_table(offset);
+            }
+        };
+
+        let dir_entry = &mut directory.entries[table_index!(offset)];
+
+        if let Some(page_table) = dir_entry.page_table_mut() {
+            let is_empty = page_table
+                .iter()
+                .all(|table_entry| table_entry.ph_offset().is_none());
+
+            if is_empty {
+                dir_entry.clear();
+            }
+        }
+    }
+
+    /// Detach the page table covering `offset`, return its frame
+    fn clear_table(
+        &mut self,
+        offset: VirtualAddress,
+    ) -> Option<PhysicalAddress> {
+        match &mut self.layout {
+            Layout::Legacy(directory) => {
+                directory.entries[table_index!(offset)].clear()
+            }
+            Layout::Pae(directory) => directory.clear_table(offset),
+        }
+    }
 }
 
 impl PageMarker {
@@ -78,28 +320,89 @@ impl PageMarker {
     //     Ok(marker)
     // }
 
-    pub fn directory(&self) -> &PageDirectory<'static, 'static> {
-        &self.directory
+    pub fn new(directory: PageDirectory<'static, 'static>) -> Self {
+        Self {
+            layout: Layout::Legacy(directory),
+        }
     }
 
-    pub fn new(directory: PageDirectory<'static, 'static>) -> Self {
-        Self { directory }
+    pub fn new_pae(directory: PaeDirectory) -> Self {
+        Self {
+            layout: Layout::Pae(directory),
+        }
     }
 
-    fn mark_pageg(
-        &mut self,
-        virtual_offset: VirtualAddress,
-        flags: MemoryMappingFlag,
-    ) {
-        let dir_entry =
-            &mut self.directory.entries[table_index!(virtual_offset)];
-        dir_entry.set_flags(flags.as_directory_flag());
+    /// Make kernel space of `marker` the same as in this one.
+    /// The legacy directory copies the kernel entries,
+    /// while PAE directories share the kernel page directories
+    pub fn share_kernel(&self, marker: &mut PageMarker) {
+        match (&self.layout, &mut marker.layout) {
+            (Layout::Legacy(kernel), Layout::Legacy(directory)) => unsafe {
+                directory.entries.copy_from_slice(kernel.share_entries());
+            },
+            (Layout::Pae(kernel), Layout::Pae(directory)) => {
+                directory.share_from(kernel)
+            }
+            _ => unreachable!("Page markers of different layouts"),
+        }
+    }
 
-        if let Some(page_table) = dir_entry.page_table_mut() {
-            for table_entry in page_table.iter_mut() {
-                table_entry.set_flags(flags.as_table_flag());
+    /// The count of pages [`Self::mirror_to_pae`] can use for tables
+    pub fn pae_pages_count(&self) -> usize {
+        let Layout::Legacy(directory) = &self.layout else {
+            return 0;
+        };
+
+        let tables_count = directory
+            .entries
+            .iter()
+            .filter(|dir_entry| dir_entry.ph_offset().is_some())
+            .count();
+
+        //each legacy table covers two PAE tables
+        1 + POINTERS_COUNT + 2 * tables_count
+    }
+
+    /// Build PAE tables mapping the same pages as the legacy directory.
+    /// The pages are mapped again without taking new references,
+    /// as the legacy directory is never used after switching
+    pub fn mirror_to_pae(
+        &self,
+        alloc: TableAlloc,
+    ) -> Result<PaeDirectory, PageMarkerError> {
+        let Layout::Legacy(legacy) = &self.layout else {
+            return Err(PageMarkerError::InvalidTableAddress);
+        };
+
+        let mut directory = PaeDirectory::new(POINTERS_COUNT, alloc)?;
+
+        for (table_index, dir_entry) in legacy.entries.iter().enumerate() {
+            let Some(page_table) = dir_entry.page_table() else {
+                continue;
+            };
+
+            let is_user =
+                dir_entry.flags().test_with(DirEntryFlag::NO_PRIVILEGE);
+
+            for (page_index, page_entry) in page_table.iter().enumerate() {
+                let Some(ph_offset) = page_entry.ph_offset() else {
+                    continue;
+                };
+
+                let virt_offset = (table_index << 22) | (page_index << 12);
+
+                let entry = directory.entry_or_alloc(
+                    virt_offset,
+                    is_user,
+                    Some(&mut *alloc),
+                )?;
+
+                *entry =
+                    PaeEntry::new(ph_offset, page_entry.flags().bits() as u64);
             }
         }
+
+        Ok(directory)
     }
 
     #[inline(never)]
@@ -132,44 +435,239 @@ impl PageMarker {
         let mut virt_offset = map_region.virtual_offset;
 
         for _ in 0..map_region.page_count {
-            let table_index = table_index!(virt_offset);
-            let entry_index = page_index!(virt_offset);
+            let mut page_entry =
+                self.entry_or_alloc(virt_offset, flags, can_allocate)?;
 
-            let dir_entry = &mut self.directory.entries[table_index];
+            let page = Page::take(ph_offset);
 
-            dir_entry.set_flags(flags.as_directory_flag());
+            page_entry.set(page.as_physical(), flags);
 
-            if !dir_entry.has_page_table() {
-                log::debug!("Table index: {table_index}");
+            virt_offset += Page::SIZE;
+            ph_offset += Page::SIZE;
+        }
 
-                if !can_allocate {
-                    return Err(PageMarkerError::EmptyDirEntry(
-                        virt_offset as _,
-                    ));
-                }
+        Ok(())
+    }
 
-                let Some(ph_offset) =
-                    memory::alloc_physical_pages(TABLE_PAGES_COUNT)
-                else {
-                    return Err(PageMarkerError::OutOfMemory);
-                };
+    /// Map device memory (MMIO) into kernel space.
+    /// The physical pages are not accounted as they can be out of RAM
+    pub fn map_device_range(
+        &mut self,
+        map_region: &MemoryMappingRegion,
+    ) -> Result<(), PageMarkerError> {
+        let flags = map_region.flags;
+
+        let mut ph_offset = map_region.physical_offset;
+        let mut virt_offset = map_region.virtual_offset;
+
+        for _ in 0..map_region.page_count {
+            self.entry_or_alloc(virt_offset, flags, true)?
+                .set(ph_offset, flags);
 
-                dir_entry.set_ph_offset(ph_offset);
+            invalidate_page(virt_offset);
+
+            virt_offset += Page::SIZE;
+            ph_offset += Page::SIZE;
+        }
+
+        Ok(())
+    }
+
+    /// Unmap device memory mapped by [`Self::map_device_range`].
+    /// The page table is detached when it has no more pages,
+    /// so address spaces copying kernel directory never share it.
+    /// The pages aren't released, so user regions are unmapped
+    /// by [`Self::unmap_user_range`]
+    pub fn unmap_device_range(&mut self, range: Range<VirtualAddress>) {
+        debug_assert!(
+            range.end <= memory::ANONYMOUS_AREA.start
+                || range.start >= memory::ANONYMOUS_AREA.end,
+            "Anonymous range {range:X?} is unmapped as device memory"
+        );
+
+        let mut virt_offset = range.start;
+
+        while virt_offset < range.end {
+            if let Some(mut page_entry) = self.entry(virt_offset) {
+                page_entry.clear();
+
+                self.detach_empty_table(virt_offset);
             }
 
-            let page_table = dir_entry.page_table_mut().unwrap();
+            invalidate_page(virt_offset);
 
-            let page_entry = &mut page_table[entry_index];
+            virt_offset += Page::SIZE;
+        }
 
-            let page = Page::take(ph_offset);
+        smp::flush_tlb(range);
+    }
+
+    /// Unmap pages borrowed from another address space.
+    /// Unlike [`Self::unmap_range`], the pages are only released, not deallocated
+    pub fn unmap_lent_range(&mut self, range: Range<VirtualAddress>) {
+        let mut virt_offset = range.start;
+
+        while virt_offset < range.end {
+            let ph_offset = self
+                .entry(virt_offset)
+                .and_then(|mut page_entry| page_entry.clear());
+
+            if let Some(ph_offset) = ph_offset {
+                unsafe { (*Page::take_unchecked(ph_offset)).release() };
+            }
 
-            page_entry.set_ph_offset(page.as_physical());
-            page_entry.set_flags(flags.as_table_flag());
+            invalidate_page(virt_offset);
 
             virt_offset += Page::SIZE;
-            ph_offset += Page::SIZE;
         }
 
+        smp::flush_tlb(range);
+    }
+
+    /// Unmap pages of user region. Each mapping holds a reference to page,
+    /// so the page is freed once no region or mapping uses it
+    pub fn unmap_user_range(&mut self, range: Range<VirtualAddress>) {
+        let mut virt_offset = range.start;
+
+        while virt_offset < range.end {
+            let ph_offset =
+                self.entry(virt_offset).and_then(|mut page_entry| {
+                    if let Some(slot) = page_entry.swap_slot() {
+                        memory::release_swap_slot(slot);
+                    }
+
+                    page_entry.clear()
+                });
+
+            if let Some(ph_offset) = ph_offset {
+                memory::release_page(ph_offset);
+            }
+
+            invalidate_page(virt_offset);
+
+            virt_offset += Page::SIZE;
+        }
+
+        smp::flush_tlb(range);
+    }
+
+    /// Whether the page at `offset` is mapped writable
+    pub fn is_writable(&self, offset: VirtualAddress) -> bool {
+        let directory = match &self.layout {
+            Layout::Legacy(directory) => directory,
+            Layout::Pae(directory) => {
+                return directory.entry(offset).is_some_and(|page_entry| {
+                    page_entry.ph_offset().is_some()
+                        && page_entry.flags() & PaeEntry::WRITABLE != 0
+                });
+            }
+        };
+
+        let table_entry = directory.entries[table_index!(offset)];
+
+        let Some(page_table) = table_entry.page_table() else {
+            return false;
+        };
+
+        let page_entry = &page_table[page_index!(offset)];
+
+        page_entry.ph_offset().is_some()
+            && page_entry.flags().test_with(TableEntryFlag::WRITABLE)
+    }
+
+    /// Change the flags of pages mapped in `range`, the page
+    /// keeps its frame even if `flags` make it not present.
+    /// The entries of pages never touched stay empty
+    pub fn protect_range(
+        &mut self,
+        range: Range<VirtualAddress>,
+        flags: MemoryMappingFlag,
+    ) {
+        let mut virt_offset = range.start;
+
+        while virt_offset < range.end {
+            if let Some(mut page_entry) = self.entry(virt_offset) {
+                if page_entry.ph_offset().is_some() {
+                    page_entry.protect(flags);
+                }
+            }
+
+            invalidate_page(virt_offset);
+
+            virt_offset += Page::SIZE;
+        }
+
+        smp::flush_tlb(range);
+    }
+
+    /// The swap slot kept by entry of page at `offset`
+    pub fn swap_slot(&mut self, offset: VirtualAddress) -> Option<usize> {
+        self.entry(offset)?.swap_slot()
+    }
+
+    /// Clear the accessed bit of page at `offset`, return whether the page
+    /// was used since the last call. Only the local TLB is flushed,
+    /// so the access on another processor can be missed
+    pub fn take_accessed(&mut self, offset: VirtualAddress) -> bool {
+        let is_accessed = self
+            .entry(offset)
+            .is_some_and(|mut page_entry| page_entry.take_accessed());
+
+        invalidate_page(offset);
+
+        is_accessed
+    }
+
+    /// Replace the present page at `offset` with swap `slot`.
+    /// Return the frame, its mapping reference is not released
+    pub fn swap_out_page(
+        &mut self,
+        offset: VirtualAddress,
+        slot: usize,
+    ) -> Option<PhysicalAddress> {
+        let mut page_entry = self.entry(offset)?;
+        let ph_offset = page_entry.ph_offset()?;
+
+        page_entry.set_swap_slot(slot);
+
+        invalidate_page(offset);
+
+        smp::flush_tlb(offset..offset + Page::SIZE);
+
+        Some(ph_offset)
+    }
+
+    /// Keep swap `slot` in the entry of page at `offset`
+    pub fn map_swapped(
+        &mut self,
+        offset: VirtualAddress,
+        slot: usize,
+    ) -> Result<(), PageMarkerError> {
+        self.entry_or_alloc(offset, MemoryMappingFlag::USER_DATA, true)?
+            .set_swap_slot(slot);
+
+        Ok(())
+    }
+
+    /// Map `physical` at `offset` in place of the current page.
+    /// The replaced page is not released
+    pub fn remap_user_page(
+        &mut self,
+        offset: VirtualAddress,
+        physical: PhysicalAddress,
+        flags: MemoryMappingFlag,
+    ) -> Result<(), PageMarkerError> {
+        self.map_user_range(&MemoryMappingRegion {
+            flags,
+            virtual_offset: offset,
+            physical_offset: physical,
+            page_count: 1,
+        })?;
+
+        invalidate_page(offset);
+
+        smp::flush_tlb(offset..offset + Page::SIZE);
+
         Ok(())
     }
 
@@ -181,22 +679,19 @@ impl PageMarker {
         let mut virt_offset = range.start;
 
         while virt_offset < range.end {
-            let table_index = table_index!(virt_offset);
-            let page_index = page_index!(virt_offset);
-
-            let dir_entry = &mut self.directory.entries[table_index];
-
-            if let Some(page_table) = dir_entry.page_table_mut() {
-                let table_entry = &mut page_table[page_index];
+            let ph_offset = self
+                .entry(virt_offset)
+                .and_then(|mut page_entry| page_entry.clear());
 
-                if let Some(ph_offset) = table_entry.clear() {
-                    memory::dealloc_physical_page(ph_offset);
-                }
+            if let Some(ph_offset) = ph_offset {
+                memory::dealloc_physical_page(ph_offset);
             }
 
             virt_offset += Page::SIZE;
         }
 
+        smp::flush_tlb(range.clone());
+
         if !unmap_all {
             return;
         }
@@ -204,10 +699,7 @@ impl PageMarker {
         let mut virt_offset = range.start;
 
         while virt_offset < range.end {
-            let table_index = table_index!(virt_offset);
-            let dir_entry = &mut self.directory.entries[table_index];
-
-            if let Some(ph_offset) = dir_entry.clear() {
+            if let Some(ph_offset) = self.clear_table(virt_offset) {
                 memory::dealloc_physical_page(ph_offset);
             }
 
@@ -220,12 +712,26 @@ impl Drop for PageMarker {
     fn drop(&mut self) {
         log::debug!("Deallocation page marker");
 
-        for dir_entry in self.directory.entries.iter_mut() {
+        let directory = match &mut self.layout {
+            Layout::Legacy(directory) => directory,
+            Layout::Pae(directory) => {
+                return directory.clear_owned(
+                    &mut memory::dealloc_physical_page,
+                    &mut memory::release_swap_slot,
+                );
+            }
+        };
+
+        for dir_entry in directory.entries.iter_mut() {
             let Some(page_table) = dir_entry.page_table_mut() else {
                 continue;
             };
 
             for table_entry in page_table.iter_mut() {
+                if let Some(slot) = table_entry.swap_slot() {
+                    memory::release_swap_slot(slot);
+                }
+
                 if let Some(ph_offset) = table_entry.clear() {
                     memory::dealloc_physical_page(ph_offset);
                 }
@@ -235,9 +741,19 @@ impl Drop for PageMarker {
                 memory::dealloc_physical_page(ph_offset);
             }
 
-            memory::dealloc_physical_page(self.directory.physical_offset);
+            memory::dealloc_physical_page(directory.physical_offset);
+
+            directory.physical_offset = 0;
+        }
+    }
+}
 
-            self.directory.physical_offset = 0;
+fn invalidate_page(virt_offset: VirtualAddress) {
+    unsafe {
+        asm! {
+            "invlpg [{0}]",
+            in(reg) virt_offset,
+            options(nostack, preserves_flags)
         }
     }
 }
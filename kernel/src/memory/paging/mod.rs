@@ -1,6 +1,8 @@
 use core::arch::asm;
-use core::mem::MaybeUninit;
-use core::ptr::NonNull;
+use core::arch::x86::__cpuid;
+use core::mem::{self, MaybeUninit};
+use core::ptr::{self, NonNull};
+use core::sync::atomic::{AtomicBool, Ordering};
 
 use static_assertions::assert_eq_size;
 
@@ -14,10 +16,12 @@ use crate::memory::{
 
 mod directory;
 mod marker;
+pub(crate) mod pae;
 pub(crate) mod table;
 
 pub use directory::*;
 pub use marker::*;
+pub use pae::PaeDirectory;
 
 use super::{kernel_virtual_offset, PhysicalAddress};
 
@@ -32,6 +36,60 @@ declare_constants!(
 
 pub type PageDirectoryEntries<'a> = [DirEntry<'a>; DIRECTORY_ENTRIES_COUNT];
 
+/// PAE paging is enabled at boot if the CPU supports it
+static PAE_ENABLED: AtomicBool = AtomicBool::new(false);
+/// The no-execute bit of PAE entries is enforced
+static NX_ENABLED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy)]
+pub struct PagingFeatures {
+    pub pae: bool,
+    pub nx: bool,
+}
+
+impl PagingFeatures {
+    const PAE_BIT: u32 = 1 << 6;
+    const NX_BIT: u32 = 1 << 20;
+    const EXTENDED_FEATURES: u32 = 0x8000_0001;
+
+    /// Detect the paging features from CPUID.
+    /// Without `pae` feature the legacy paging is always used
+    pub fn detect() -> Self {
+        if !cfg!(feature = "pae") {
+            return Self {
+                pae: false,
+                nx: false,
+            };
+        }
+
+        let features = unsafe { __cpuid(1) };
+        let pae = features.edx & Self::PAE_BIT != 0;
+
+        let max_extended = unsafe { __cpuid(0x8000_0000) }.eax;
+
+        let nx = pae
+            && max_extended >= Self::EXTENDED_FEATURES
+            && unsafe { __cpuid(Self::EXTENDED_FEATURES) }.edx & Self::NX_BIT
+                != 0;
+
+        Self { pae, nx }
+    }
+
+    /// The features enabled on bootstrap processor
+    pub fn enabled() -> Self {
+        Self {
+            pae: PAE_ENABLED.load(Ordering::Relaxed),
+            nx: NX_ENABLED.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Remember the features enabled after switching page tables
+    pub(super) fn enable(&self) {
+        PAE_ENABLED.store(self.pae, Ordering::SeqCst);
+        NX_ENABLED.store(self.nx, Ordering::SeqCst);
+    }
+}
+
 pub enum CommonError {
     OutOfBounds,
 }
@@ -69,15 +127,40 @@ pub struct GDTTable {
     user_code: MemoryDescriptor,
     user_data: MemoryDescriptor,
     task: TaskStateDescriptor,
+    double_fault_task: TaskStateDescriptor,
 }
 
-assert_eq_size!(GDTTable, [usize; 2 * 6]);
+assert_eq_size!(GDTTable, [usize; 2 * 7]);
 
 impl GDTTable {
+    /// The boot loader sets the descriptors up to the task state
+    pub const BOOT_SIZE: usize = mem::offset_of!(GDTTable, double_fault_task);
+
     pub const fn null() -> Self {
         unsafe { MaybeUninit::zeroed().assume_init() }
     }
 
+    /// Copy the descriptors of boot table, the others are empty
+    pub unsafe fn from_boot(boot_table: *const GDTTable) -> Self {
+        let mut table = Self::null();
+
+        unsafe {
+            ptr::copy_nonoverlapping(
+                boot_table.cast::<u8>(),
+                (&raw mut table).cast::<u8>(),
+                Self::BOOT_SIZE,
+            )
+        };
+
+        table
+    }
+
+    /// The task state switched to on double fault.
+    /// The task gate selects it, so it's never loaded by `ltr`
+    pub fn set_double_fault_task(&mut self, task: TaskStateDescriptor) {
+        self.double_fault_task = task;
+    }
+
     pub fn load_task(&mut self, task: TaskStateDescriptor) {
         self.task = task;
 
@@ -0,0 +1,280 @@
+//! PAE paging: three levels of tables with 64-bit entries.
+//! The top table (page directory pointer table) has 4 entries,
+//! each of them covers 1 GB of address space. Unlike 32-bit paging,
+//! the entries have no-execute bit (if the processor supports it)
+
+use kernel_types::declare_constants;
+
+use crate::memory::{Page, PhysicalAddress, VirtualAddress};
+
+use super::PageMarkerError;
+
+declare_constants!(
+    pub usize,
+    PAE_ENTRIES_COUNT = 512, "The count of entries in directory or table";
+    POINTERS_COUNT = 4, "The count of entries in page directory pointer table";
+);
+
+pub type PaeTable = [PaeEntry; PAE_ENTRIES_COUNT];
+
+/// The page provider for tables (it should return zeroed page)
+pub type TableAlloc<'a> = &'a mut dyn FnMut() -> Option<PhysicalAddress>;
+
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy)]
+pub struct PaeEntry {
+    entry: u64,
+}
+
+impl PaeEntry {
+    const ADDRESS_MASK: u64 = 0x000F_FFFF_FFFF_F000;
+
+    pub const PRESENT: u64 = 0b1;
+    pub const WRITABLE: u64 = 0b10;
+    pub const NO_PRIVILEGE: u64 = 0b100;
+    pub const ACCESSED: u64 = 0b10_0000;
+    /// The software bit of not present entry keeping swap slot
+    /// instead of frame (the dirty bit is unused while not present)
+    pub const SWAPPED: u64 = 0b100_0000;
+    /// Reserved until EFER.NXE is enabled
+    pub const NO_EXECUTE: u64 = 1 << 63;
+
+    pub fn new(ph_offset: PhysicalAddress, flags: u64) -> Self {
+        Self {
+            entry: (ph_offset as u64 & Self::ADDRESS_MASK) | flags,
+        }
+    }
+
+    pub const fn flags(&self) -> u64 {
+        self.entry & !Self::ADDRESS_MASK
+    }
+
+    pub fn set_flags(&mut self, flags: u64) {
+        self.entry = (self.entry & Self::ADDRESS_MASK) | flags;
+    }
+
+    /// The frame of entry, the entry can keep it while not present
+    pub fn ph_offset(&self) -> Option<PhysicalAddress> {
+        if self.swap_slot().is_some() {
+            return None;
+        }
+
+        let offset = self.entry & Self::ADDRESS_MASK;
+        (offset != 0).then_some(offset as PhysicalAddress)
+    }
+
+    pub fn clear(&mut self) -> Option<PhysicalAddress> {
+        let ph_offset = self.ph_offset();
+        self.entry = 0;
+        ph_offset
+    }
+
+    /// The slot of swapped out page
+    pub fn swap_slot(&self) -> Option<usize> {
+        let is_swapped =
+            self.entry & (Self::SWAPPED | Self::PRESENT) == Self::SWAPPED;
+
+        is_swapped.then_some(
+            ((self.entry & Self::ADDRESS_MASK) >> Page::SHIFT) as usize,
+        )
+    }
+
+    /// Make entry not present keeping swap `slot`
+    pub fn set_swap_slot(&mut self, slot: usize) {
+        self.entry = (((slot as u64) << Page::SHIFT) & Self::ADDRESS_MASK)
+            | Self::SWAPPED;
+    }
+
+    /// The next level table referenced by entry
+    pub fn table(&self) -> Option<&'static mut PaeTable> {
+        let ph_offset = self.ph_offset()?;
+
+        //unlike Page::take, the lookup doesn't acquire the page
+        let virt_offset =
+            unsafe { &*Page::take_unchecked(ph_offset) }.as_virtual()?;
+
+        Some(unsafe { &mut *(virt_offset as *mut PaeTable) })
+    }
+}
+
+/// The paging structures of one address space. All page directories
+/// are allocated with the pointer table, as the processor reads pointers
+/// only on loading CR3. The kernel directories are shared by all spaces
+#[derive(Debug)]
+pub struct PaeDirectory {
+    pointers: &'static mut PaeTable,
+    /// The count of leading directories owned by address space
+    owned: usize,
+    pub physical_offset: PhysicalAddress,
+}
+
+impl PaeDirectory {
+    /// Allocate the pointer table with `owned` leading directories,
+    /// the rest of pointers should be filled by [`Self::share_from`]
+    pub fn new(
+        owned: usize,
+        alloc: TableAlloc,
+    ) -> Result<Self, PageMarkerError> {
+        assert!(owned <= POINTERS_COUNT);
+
+        let physical_offset = alloc().ok_or(PageMarkerError::OutOfMemory)?;
+
+        let pointers = PaeEntry::new(physical_offset, 0)
+            .table()
+            .ok_or(PageMarkerError::InvalidTableAddress)?;
+
+        for pointer in pointers.iter_mut().take(owned) {
+            let directory = alloc().ok_or(PageMarkerError::OutOfMemory)?;
+
+            //the access bits of pointers are reserved
+            *pointer = PaeEntry::new(directory, PaeEntry::PRESENT);
+        }
+
+        Ok(Self {
+            pointers,
+            owned,
+            physical_offset,
+        })
+    }
+
+    /// Share the directories of `source` not owned by this space
+    pub fn share_from(&mut self, source: &PaeDirectory) {
+        self.pointers[self.owned..POINTERS_COUNT]
+            .copy_from_slice(&source.pointers[self.owned..POINTERS_COUNT]);
+    }
+
+    fn directory_entry(
+        &self,
+        offset: VirtualAddress,
+    ) -> Option<&'static mut PaeEntry> {
+        let directory = self.pointers[pointer_index(offset)].table()?;
+
+        Some(&mut directory[directory_index(offset)])
+    }
+
+    /// The entry mapping page at `offset` (`None` without page table)
+    pub fn entry(
+        &self,
+        offset: VirtualAddress,
+    ) -> Option<&'static mut PaeEntry> {
+        let table = self.directory_entry(offset)?.table()?;
+
+        Some(&mut table[page_index(offset)])
+    }
+
+    /// The entry mapping page at `offset`, the missing table is allocated.
+    /// The directory entry only selects privilege, access is kept in pages
+    pub fn entry_or_alloc(
+        &mut self,
+        offset: VirtualAddress,
+        is_user: bool,
+        alloc: Option<TableAlloc>,
+    ) -> Result<&'static mut PaeEntry, PageMarkerError> {
+        let dir_entry = self
+            .directory_entry(offset)
+            .ok_or(PageMarkerError::InvalidTableAddress)?;
+
+        let mut flags = PaeEntry::PRESENT | PaeEntry::WRITABLE;
+
+        if is_user {
+            flags |= PaeEntry::NO_PRIVILEGE;
+        }
+
+        if dir_entry.ph_offset().is_none() {
+            let Some(alloc) = alloc else {
+                return Err(PageMarkerError::EmptyDirEntry(offset as _));
+            };
+
+            let table = alloc().ok_or(PageMarkerError::OutOfMemory)?;
+
+            *dir_entry = PaeEntry::new(table, flags);
+        } else {
+            dir_entry.set_flags(dir_entry.flags() | flags);
+        }
+
+        let table = dir_entry
+            .table()
+            .ok_or(PageMarkerError::InvalidTableAddress)?;
+
+        Ok(&mut table[page_index(offset)])
+    }
+
+    /// Detach the table of `offset` if it has no pages
+    pub fn detach_empty_table(&mut self, offset: VirtualAddress) {
+        let Some(dir_entry) = self.directory_entry(offset) else {
+            return;
+        };
+
+        let Some(table) = dir_entry.table() else {
+            return;
+        };
+
+        if table.iter().all(|entry| entry.ph_offset().is_none()) {
+            dir_entry.clear();
+        }
+    }
+
+    /// Detach the table of `offset`, return its frame
+    pub fn clear_table(
+        &mut self,
+        offset: VirtualAddress,
+    ) -> Option<PhysicalAddress> {
+        self.directory_entry(offset)?.clear()
+    }
+
+    /// Clear all entries of owned directories, the frames
+    /// of pages and tables are passed to `release`,
+    /// the slots of swapped out pages to `release_swap`
+    pub fn clear_owned(
+        &mut self,
+        release: &mut dyn FnMut(PhysicalAddress),
+        release_swap: &mut dyn FnMut(usize),
+    ) {
+        for pointer in self.pointers.iter_mut().take(self.owned) {
+            let Some(directory) = pointer.table() else {
+                continue;
+            };
+
+            for dir_entry in directory.iter_mut() {
+                if let Some(table) = dir_entry.table() {
+                    for entry in table.iter_mut() {
+                        if let Some(slot) = entry.swap_slot() {
+                            release_swap(slot);
+                        }
+
+                        if let Some(ph_offset) = entry.clear() {
+                            release(ph_offset);
+                        }
+                    }
+                }
+
+                if let Some(ph_offset) = dir_entry.clear() {
+                    release(ph_offset);
+                }
+            }
+
+            if let Some(ph_offset) = pointer.clear() {
+                release(ph_offset);
+            }
+        }
+
+        release(self.physical_offset);
+    }
+}
+
+fn pointer_index(offset: VirtualAddress) -> usize {
+    (offset >> 30) & 0b11
+}
+
+fn directory_index(offset: VirtualAddress) -> usize {
+    (offset >> 21) & 0x1FF
+}
+
+fn page_index(offset: VirtualAddress) -> usize {
+    (offset >> 12) & 0x1FF
+}
+
+/// The first pointer shared by process spaces with kernel
+pub fn kernel_pointer() -> usize {
+    pointer_index(crate::memory::kernel_virtual_offset())
+}
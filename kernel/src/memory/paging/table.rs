@@ -51,6 +51,9 @@ macro_rules! entry_impl {
     ) => {
         impl<'a> $struct_name<'a> {
             const ADDRESS_MASK: usize = $address_mask;
+            /// The software bit of not present entry keeping swap slot
+            /// instead of frame (the dirty bit is unused while not present)
+            const SWAPPED: usize = 0b100_0000;
 
             pub fn new(ph_offset: PhysicalAddress, flags: $flag_type) -> Self {
                 let entry: usize =
@@ -78,17 +81,25 @@ macro_rules! entry_impl {
             }
 
             pub fn clear(&mut self) -> Option<PhysicalAddress> {
-                let offset = self.entry & Self::ADDRESS_MASK;
-                let ph_offset = (offset != 0).then_some(offset);
+                let ph_offset = self.ph_offset();
                 self.entry = 0;
                 ph_offset
             }
 
             pub fn ph_offset(&self) -> Option<PhysicalAddress> {
+                if self.is_swapped() {
+                    return None;
+                }
+
                 let offset = self.entry & Self::ADDRESS_MASK;
                 (offset != 0).then_some(offset)
             }
 
+            pub const fn is_swapped(&self) -> bool {
+                self.entry & (Self::SWAPPED | $flag_type::PRESENT)
+                    == Self::SWAPPED
+            }
+
             pub fn set_ph_offset(&mut self, ph_offset: PhysicalAddress) {
                 assert!(ph_offset != 0);
                 let flags = self.flags();
@@ -153,6 +164,18 @@ impl<'a> DirEntry<'a> {
 }
 
 impl<'a> TableEntry<'a> {
+    /// The slot of swapped out page
+    pub fn swap_slot(&self) -> Option<usize> {
+        self.is_swapped()
+            .then_some((self.entry & Self::ADDRESS_MASK) >> Page::SHIFT)
+    }
+
+    /// Make entry not present keeping swap `slot`
+    pub fn set_swap_slot(&mut self, slot: usize) {
+        self.entry =
+            ((slot << Page::SHIFT) & Self::ADDRESS_MASK) | Self::SWAPPED;
+    }
+
     pub fn has_page(&self) -> bool {
         self.as_bytes().is_some()
     }
@@ -1,15 +1,14 @@
 use core::marker::PhantomData;
 
-use alloc::sync::Arc;
 use kernel_types::collections::LinkedList;
 
 use crate::{
     error::KernelError,
-    io::InterruptableLazyCell,
     memory::{
         self, new_proccess_id, physical_alloc, AllocError, MemoryMappingRegion,
         MemoryRegion, MemoryRegionFlag, Page, PageMarker, VirtualAddress,
     },
+    object::HandleTable,
 };
 
 use super::{Process, ProcessState};
@@ -111,13 +110,14 @@ impl ProcessBuilder<ProcessSpace> {
         let state = ProcessState {
             stack,
             entry_point,
+            exit_point: None,
             regions: self.regions,
             marker: self.marker,
+            handles: HandleTable::new(),
+            io_bitmap: None,
+            io_ports: None,
         };
 
-        Ok(Process {
-            id,
-            state: Arc::new(InterruptableLazyCell::new(state)),
-        })
+        Process::new(id, state)
     }
 }
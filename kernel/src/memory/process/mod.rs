@@ -3,21 +3,29 @@ mod segment;
 
 use core::{
     ops::Range,
+    slice,
     sync::atomic::{AtomicUsize, Ordering},
 };
 
-use alloc::sync::Arc;
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
 use builder::{KernelSpace, ProcessBuilder};
 use kernel_types::collections::LinkedList;
 
 use crate::{
     error::KernelError,
-    io::InterruptableLazyCell,
-    memory::{self, MemoryMappingRegion, MemoryRegionFlag},
+    fs::FsError,
+    io::{
+        ports::{IoBitmap, PortRange},
+        InterruptableLazyCell,
+    },
+    memory::{self, MemoryMappingFlag, MemoryMappingRegion, MemoryRegionFlag},
+    object::HandleTable,
+    user::kernel_buf::{KernelBuf, LEND_AREA},
 };
 
 use super::{
-    paging::PageMarker, AllocError, MemoryRegion, Page, VirtualAddress,
+    paging::PageMarker, AllocError, MemoryRegion, Page, SwapError,
+    VirtualAddress,
 };
 
 pub type ProcessId = usize;
@@ -42,17 +50,72 @@ pub fn new_proccess_id() -> Option<ProcessId> {
 pub struct Process {
     pub id: ProcessId,
     pub state: Arc<InterruptableLazyCell<ProcessState>>,
+    /// The count of tasks running in process
+    tasks: Arc<AtomicUsize>,
 }
 
 impl Process {
+    fn new(id: ProcessId, state: ProcessState) -> Result<Self, KernelError> {
+        Ok(Self {
+            id,
+            state: Arc::try_new(InterruptableLazyCell::new(state))?,
+            tasks: Arc::try_new(AtomicUsize::new(0))?,
+        })
+    }
+
     pub fn builder() -> Result<ProcessBuilder<KernelSpace>, KernelError> {
         Ok(ProcessBuilder::new()?)
     }
+
+    /// Create new process with the copy of address space,
+    /// see [`ProcessState::fork`]
+    pub fn fork(&self) -> Result<Process, KernelError> {
+        let state = self.state.lock().fork()?;
+
+        let Some(id) = new_proccess_id() else {
+            return Err(KernelError::NoPidAvailable);
+        };
+
+        Process::new(id, state)
+    }
+
+    /// Count the task which is started in process
+    pub fn attach_task(&self) {
+        self.tasks.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// `true` if the last task of process is stopped,
+    /// so the process is released by the caller
+    pub fn detach_task(&self) -> bool {
+        self.tasks.fetch_sub(1, Ordering::SeqCst) == 1
+    }
+}
+
+#[derive(Debug, thiserror_no_std::Error)]
+pub enum PageFault {
+    #[error("No region contains address")]
+    NotMapped,
+    #[error("Access violates region protection")]
+    Protection,
+    #[error("Failed to populate page: {0}")]
+    Alloc(#[from] AllocError),
+    #[error("The page should be read from file")]
+    FileBacked,
+    #[error("Failed to read file page: {0}")]
+    File(#[from] FsError),
+    #[error("The file page can't be read without blocking")]
+    WouldBlock,
+    #[error("The page should be read from swap")]
+    Swapped,
+    #[error("Failed to read swapped page: {0}")]
+    Swap(#[from] SwapError),
 }
 
 ///Alternative to linux mm_struct
 pub struct ProcessState {
     pub entry_point: VirtualAddress,
+    /// The routine notifying module about shutdown
+    pub exit_point: Option<VirtualAddress>,
     ///offset of data segment
     ///It's redundant to store any information about last page ― it's can be easily calculated from heap_offset as:<br>
     ///<code> heap_offset % Page::SIZE </code> <br>
@@ -69,6 +132,14 @@ pub struct ProcessState {
     pub stack: Range<VirtualAddress>,
 
     pub regions: LinkedList<'static, MemoryRegion>,
+
+    ///kernel objects available to the process
+    pub handles: HandleTable,
+
+    /// The ports granted to module (`None` if no port is granted)
+    pub io_bitmap: Option<Box<IoBitmap>>,
+    /// The ports module may request (`None` if process isn't module)
+    pub io_ports: Option<Vec<PortRange>>,
     // last_touched_region: Option<&'static MemoryRegion>,
 }
 
@@ -76,6 +147,17 @@ impl super::Slab for ProcessState {
     const NAME: &str = "process";
 }
 
+/// The memory used by process
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ProcessMemStat {
+    pub regions: usize,
+    pub virtual_pages: usize,
+    /// The pages mapped in regions (the shared ones as well)
+    pub resident_pages: usize,
+    /// The pages allocated for regions
+    pub owned_pages: usize,
+}
+
 impl ProcessState {
     // pub fn new_boxed(segments: Segments) -> Result<Self, AllocError> {
     //     assert!((segments.data_start - segments.code_start) % Page::SIZE == 0);
@@ -215,6 +297,242 @@ impl ProcessState {
         Ok(())
     }
 
+    /// The usage of memory by process regions
+    pub fn mem_stat(&self) -> ProcessMemStat {
+        let mut stat = ProcessMemStat::default();
+
+        for region in self.regions.iter() {
+            stat.regions += 1;
+            stat.virtual_pages += Page::upper_bound(region.size());
+            stat.resident_pages +=
+                self.marker.mapped_pages(region.range.clone());
+            stat.owned_pages += region.pages.len();
+        }
+
+        stat
+    }
+
+    /// Clone the address space for forked process. The private writable
+    /// pages are shared read-only until one of processes writes them
+    pub fn fork(&mut self) -> Result<ProcessState, AllocError> {
+        let mut marker = memory::new_page_marker()?;
+        let mut regions = LinkedList::empty();
+
+        for region in self.regions.iter() {
+            //the kernel buffers are lent only for the running work
+            if LEND_AREA.contains(&region.range.start) {
+                continue;
+            }
+
+            let is_private = region.flag.contains(MemoryRegionFlag::WRITE)
+                && !region.flag.contains(MemoryRegionFlag::SHARED);
+
+            let flags: MemoryMappingFlag = if is_private {
+                region.flag.difference(MemoryRegionFlag::WRITE).into()
+            } else {
+                region.flag.into()
+            };
+
+            //the pages stay owned by parent region
+            let mut copy = unsafe {
+                MemoryRegion::empty(region.range.clone(), region.flag)
+            }?;
+            copy.file = region.file.clone();
+
+            let start = region.range.start - region.range.start % Page::SIZE;
+
+            for page_offset in (start..region.range.end).step_by(Page::SIZE) {
+                let Some(physical_offset) =
+                    self.marker.lookup_physical(page_offset)
+                else {
+                    //the slot is shared until one of processes reads it
+                    if let Some(slot) = self.marker.swap_slot(page_offset) {
+                        marker.map_swapped(page_offset, slot)?;
+                        memory::duplicate_swap_slot(slot);
+                    }
+
+                    continue;
+                };
+
+                marker.map_user_range(&MemoryMappingRegion {
+                    flags,
+                    virtual_offset: page_offset,
+                    physical_offset,
+                    page_count: 1,
+                })?;
+            }
+
+            if is_private {
+                self.marker.protect_range(start..region.range.end, flags);
+            }
+
+            //the mapping always makes the page present
+            if !flags.contains(MemoryMappingFlag::PRESENT) {
+                marker.protect_range(start..region.range.end, flags);
+            }
+
+            regions.push_back(copy.into_node());
+        }
+
+        Ok(ProcessState {
+            entry_point: self.entry_point,
+            exit_point: self.exit_point,
+            marker,
+            stack: self.stack.clone(),
+            regions,
+            handles: self.handles.try_clone()?,
+            io_bitmap: None,
+            io_ports: None,
+        })
+    }
+
+    /// Resolve the fault at `address` by mapping a page of demand region
+    /// or copying the page shared with forked process.
+    /// Any other fault on present page is a protection violation
+    pub fn handle_fault(
+        &mut self,
+        address: VirtualAddress,
+        is_present: bool,
+        is_write: bool,
+    ) -> Result<(), PageFault> {
+        let region = self
+            .regions
+            .iter_mut()
+            .find(|region| region.range.contains(&address))
+            .ok_or(PageFault::NotMapped)?;
+
+        if !region.flag.intersects(MemoryRegionFlag::ACCESS)
+            || (is_write && !region.flag.contains(MemoryRegionFlag::WRITE))
+        {
+            return Err(PageFault::Protection);
+        }
+
+        //the present page of writable region is read-only
+        //only while it's shared after fork
+        if is_present {
+            if !is_write || region.flag.contains(MemoryRegionFlag::SHARED) {
+                return Err(PageFault::Protection);
+            }
+
+            return copy_on_write(&mut self.marker, region, address);
+        }
+
+        //the swap device is served by module, so the caller unlocks state
+        if self
+            .marker
+            .swap_slot(address - address % Page::SIZE)
+            .is_some()
+        {
+            return Err(PageFault::Swapped);
+        }
+
+        if !region.flag.contains(MemoryRegionFlag::DEMAND) {
+            return Err(PageFault::NotMapped);
+        }
+
+        //the file is read by module, so the caller unlocks state first
+        if region.file.is_some() {
+            return Err(PageFault::FileBacked);
+        }
+
+        let physical_offset = region.no_page()?;
+
+        self.marker
+            .map_user_range(&MemoryMappingRegion {
+                flags: region.flag.into(),
+                virtual_offset: address - address % Page::SIZE,
+                physical_offset,
+                page_count: 1,
+            })
+            .map_err(AllocError::from)?;
+
+        Ok(())
+    }
+
+    /// Map the page of file region containing `address` filled with `content`.
+    /// The process address space should be active, the page is written through it
+    pub fn map_file_page(
+        &mut self,
+        address: VirtualAddress,
+        content: &KernelBuf,
+    ) -> Result<(), PageFault> {
+        let page_offset = address - address % Page::SIZE;
+
+        //another task of process has already read the page
+        if self.marker.lookup_physical(page_offset).is_some() {
+            return Ok(());
+        }
+
+        let region = self
+            .regions
+            .iter_mut()
+            .find(|region| region.range.contains(&address))
+            .ok_or(PageFault::NotMapped)?;
+
+        let physical_offset = region.no_page()?;
+
+        self.marker
+            .map_user_range(&MemoryMappingRegion {
+                flags: region.flag.into(),
+                virtual_offset: page_offset,
+                physical_offset,
+                page_count: 1,
+            })
+            .map_err(AllocError::from)?;
+
+        let page = unsafe {
+            core::slice::from_raw_parts_mut(page_offset as *mut u8, Page::SIZE)
+        };
+
+        //the tail after the end of file is zeroed
+        page.fill(0);
+        let _ = content.copy_to(page);
+
+        Ok(())
+    }
+
+    /// Map the page at `page_offset` swapped out to `slot` filled
+    /// with `content`, then release the slot. The process address
+    /// space should be active, the page is written through it
+    pub fn map_swapped_page(
+        &mut self,
+        page_offset: VirtualAddress,
+        slot: usize,
+        content: &KernelBuf,
+    ) -> Result<(), PageFault> {
+        //another task of process has already read the page
+        if self.marker.swap_slot(page_offset) != Some(slot) {
+            return Ok(());
+        }
+
+        let region = self
+            .regions
+            .iter_mut()
+            .find(|region| region.range.contains(&page_offset))
+            .ok_or(PageFault::NotMapped)?;
+
+        let physical_offset = region.no_page()?;
+
+        self.marker
+            .map_user_range(&MemoryMappingRegion {
+                flags: region.flag.into(),
+                virtual_offset: page_offset,
+                physical_offset,
+                page_count: 1,
+            })
+            .map_err(AllocError::from)?;
+
+        let page = unsafe {
+            core::slice::from_raw_parts_mut(page_offset as *mut u8, Page::SIZE)
+        };
+
+        let _ = content.copy_to(page);
+
+        memory::release_swap_slot(slot);
+
+        Ok(())
+    }
+
     pub fn find_region_mut(
         &mut self,
         address: VirtualAddress,
@@ -235,10 +553,72 @@ impl ProcessState {
             .map(|region| region as &MemoryRegion)
     }
 
+    /// Merge the region starting at `address` into the previous one.
+    /// Only anonymous regions with the same flags are merged
+    pub fn merge_at(&mut self, address: VirtualAddress) {
+        let Some(next) = self.find_region(address) else {
+            return;
+        };
+
+        if next.range.start != address || next.file.is_some() {
+            return;
+        }
+
+        let flag = next.flag;
+
+        let has_prev = self.regions.iter().any(|region| {
+            region.range.end == address
+                && region.flag.bits() == flag.bits()
+                && region.file.is_none()
+        });
+
+        if !has_prev {
+            return;
+        }
+
+        let next = self
+            .regions
+            .remove_by(|region| region.range.start == address)
+            .unwrap()
+            .into_boxed();
+
+        let prev = self
+            .regions
+            .iter_mut()
+            .find(|region| region.range.end == address)
+            .unwrap();
+
+        prev.merge(next);
+    }
+
     pub fn add_region(&mut self, region: &'static mut MemoryRegion) {
         self.regions.push_back(region.as_node());
     }
 
+    /// Find the lowest page aligned offset in `area`
+    /// where `size` bytes don't overlap any region
+    pub fn find_free_range(
+        &self,
+        area: Range<VirtualAddress>,
+        size: usize,
+    ) -> Option<VirtualAddress> {
+        let mut offset = area.start;
+
+        while offset + size <= area.end {
+            let overlapped = self.regions.iter().find(|region| {
+                region.range.start < offset + size && offset < region.range.end
+            });
+
+            let Some(region) = overlapped else {
+                return Some(offset);
+            };
+
+            offset = Page::upper_bound(region.range.end) * Page::SIZE;
+        }
+
+        None
+    }
+
     pub fn find_prev_region(
         &mut self,
         address: VirtualAddress,
@@ -270,6 +650,70 @@ impl ProcessState {
     }
 }
 
+/// Give the process own copy of the page shared after fork.
+/// The last process using the page takes it without copying.
+/// The process address space should be active
+fn copy_on_write(
+    marker: &mut PageMarker,
+    region: &mut MemoryRegion,
+    address: VirtualAddress,
+) -> Result<(), PageFault> {
+    let page_offset = address - address % Page::SIZE;
+    let flags = MemoryMappingFlag::from(region.flag);
+
+    let shared_offset = marker
+        .lookup_physical(page_offset)
+        .ok_or(PageFault::NotMapped)?;
+
+    let shared_page = unsafe { &*Page::take_unchecked(shared_offset) };
+
+    //the region holds one reference, each mapping holds another one
+    let is_owned = region
+        .pages
+        .iter()
+        .any(|page| page.as_physical() == shared_offset);
+
+    if shared_page.use_count() <= 1 + is_owned as usize {
+        marker.protect_range(page_offset..page_offset + Page::SIZE, flags);
+
+        return Ok(());
+    }
+
+    let shared =
+        unsafe { slice::from_raw_parts(page_offset as *const u8, Page::SIZE) };
+
+    //kernel stack is too small to keep the page
+    let mut content = Vec::new();
+    content
+        .try_reserve_exact(Page::SIZE)
+        .map_err(AllocError::from)?;
+    content.extend_from_slice(shared);
+
+    let physical_offset = region.no_page()?;
+
+    marker
+        .remap_user_page(page_offset, physical_offset, flags)
+        .map_err(AllocError::from)?;
+
+    let page = unsafe {
+        slice::from_raw_parts_mut(page_offset as *mut u8, Page::SIZE)
+    };
+
+    page.copy_from_slice(&content);
+
+    //the region gives up the shared page, the other process keeps it
+    if let Some(owned) = region
+        .pages
+        .remove_by(|page| page.as_physical() == shared_offset)
+    {
+        owned.release();
+    }
+
+    memory::release_page(shared_offset);
+
+    Ok(())
+}
+
 pub struct AddressSpace {
     clean_pages: LinkedList<'static, Page>,
     dirty_pages: LinkedList<'static, Page>,
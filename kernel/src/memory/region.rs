@@ -1,11 +1,17 @@
-use core::{ops::Range, ptr::NonNull};
+use core::{fmt, ops::Range};
 
 use kernel_macro::ListNode;
-use kernel_types::collections::{BoxedNode, LinkedList, ListNode};
+use kernel_types::{
+    collections::{BoxedNode, LinkedList, ListNode},
+    memory::Protection,
+};
+
+use crate::{fs::IndexNode, object::Handle};
 
 use super::{
-    physical_dealloc, slab_alloc, AllocError, MemoryAllocationFlag,
-    MemoryMappingRegion, Page, Slab, SlabBox, VirtualAddress,
+    paging::PageMarker, physical_alloc, physical_dealloc, slab_alloc,
+    AllocError, MemoryAllocationFlag, MemoryMappingRegion, Page,
+    PhysicalAddress, Slab, SlabBox, VirtualAddress,
 };
 
 pub struct MemoryRegionBox {
@@ -22,11 +28,28 @@ pub struct MemoryRegion {
     // parent: NonNull<ProcessState>,
     pub range: Range<VirtualAddress>,
     pub flag: MemoryRegionFlag,
-    //mapped_file: MemoryMappedFile,
-    //file_offset: usize
+    /// The file read on faults (`None` for anonymous memory)
+    pub file: Option<MappedFile>,
     pub pages: LinkedList<'static, Page>,
 }
 
+/// The file backing the region
+#[derive(Clone)]
+pub struct MappedFile {
+    pub node: Handle<IndexNode>,
+    /// The position in file of the region start
+    pub offset: usize,
+}
+
+impl fmt::Debug for MappedFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MappedFile")
+            .field("node", &self.node.id)
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
 bitflags::bitflags! {
     #[derive(Debug, Clone, Copy)]
     pub struct MemoryRegionFlag: u8 {
@@ -35,11 +58,37 @@ bitflags::bitflags! {
         const EXEC = 0x04;
 
         const READ_WRITE = Self::READ.bits() | Self::WRITE.bits();
+        /// The region without any access bit is a guard, its pages fault
+        const ACCESS = Self::READ_WRITE.bits() | Self::EXEC.bits();
 
         const SHARED = 0x08;
 
         const SEQ_READ = 0x10;
         const RAND_READ = 0x20;
+
+        /// The pages are allocated on the first access,
+        /// so they don't follow the virtual order
+        const DEMAND = 0x40;
+    }
+}
+
+impl From<Protection> for MemoryRegionFlag {
+    fn from(value: Protection) -> Self {
+        let mut flag = MemoryRegionFlag::empty();
+
+        if value.contains(Protection::READ) {
+            flag |= MemoryRegionFlag::READ;
+        }
+
+        if value.contains(Protection::WRITE) {
+            flag |= MemoryRegionFlag::WRITE;
+        }
+
+        if value.contains(Protection::EXEC) {
+            flag |= MemoryRegionFlag::EXEC;
+        }
+
+        flag
     }
 }
 
@@ -78,11 +127,20 @@ impl MemoryRegion {
             pages: LinkedList::empty(),
             flag,
             range,
+            file: None,
         })?;
 
         Ok(MemoryRegionBox { region })
     }
 
+    /// Create inaccessible region reserving `range`, so overruns
+    /// of neighbour fault instead of touching another region
+    pub fn guard(
+        range: Range<VirtualAddress>,
+    ) -> Result<MemoryRegionBox, AllocError> {
+        unsafe { Self::empty(range, MemoryRegionFlag::empty()) }
+    }
+
     pub fn new_allocated(
         map_region: MemoryMappingRegion,
         flag: MemoryRegionFlag,
@@ -111,9 +169,25 @@ impl MemoryRegion {
     ///invoked when MemoryRegion is removed from address space
     pub fn close() {}
 
-    //it's simple callback to find page during page_fault_exception
-    pub fn no_page(&mut self) -> NonNull<Page> {
-        todo!()
+    /// Allocate the zeroed page for a faulted address of demand region.
+    /// The returned page is owned by region and should be mapped by caller
+    pub fn no_page(&mut self) -> Result<PhysicalAddress, AllocError> {
+        let mut pages = physical_alloc(Page::SIZE)?;
+
+        let physical = pages.first().ok_or(AllocError::NoMemory)?.as_physical();
+
+        self.pages.splice(&mut pages);
+
+        Ok(physical)
+    }
+
+    /// The position in mapped file of the page containing `address`
+    pub fn file_offset(&self, address: VirtualAddress) -> Option<usize> {
+        let file = self.file.as_ref()?;
+
+        let page_offset = address - address % Page::SIZE;
+
+        Some(file.offset + (page_offset - self.range.start))
     }
 
     pub fn populate(&mut self) -> usize {
@@ -160,6 +234,50 @@ impl MemoryRegion {
         Ok(buddy_region.into())
     }
 
+    /// Split the region on page aligned `offset`. The returned region
+    /// takes the rest of range with the pages mapped there by `marker`
+    pub fn split_at(
+        &mut self,
+        offset: VirtualAddress,
+        marker: &PageMarker,
+    ) -> Result<MemoryRegionBox, AllocError> {
+        assert!(offset % Page::SIZE == 0);
+        assert!(offset > self.range.start && offset < self.range.end);
+
+        let mut rest =
+            unsafe { Self::empty(offset..self.range.end, self.flag) }?;
+
+        rest.file = self.file.clone().map(|file| MappedFile {
+            offset: file.offset + (offset - self.range.start),
+            ..file
+        });
+
+        for page_offset in (offset..self.range.end).step_by(Page::SIZE) {
+            let Some(physical) = marker.lookup_physical(page_offset) else {
+                continue;
+            };
+
+            let page =
+                self.pages.remove_by(|page| page.as_physical() == physical);
+
+            if let Some(page) = page {
+                rest.pages.push_back(page);
+            }
+        }
+
+        self.range.end = offset;
+
+        Ok(rest)
+    }
+
+    /// Append the region following this one, its pages are moved
+    pub fn merge(&mut self, mut next: MemoryRegionBox) {
+        assert_eq!(self.range.end, next.range.start);
+
+        self.range.end = next.range.end;
+        self.pages.splice(&mut next.pages);
+    }
+
     //update the start of the region
     pub fn reduce_to(&mut self, offset: VirtualAddress) {
         assert!(offset > self.range.start);
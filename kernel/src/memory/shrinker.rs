@@ -0,0 +1,61 @@
+//! The callbacks releasing memory when physical allocator runs out of pages.
+//! The swap registers its shrinker moving user pages out once it's enabled.
+//! The path nodes are owned by opened files and tasks, so there is no
+//! dentry cache to shrink. Empty slab pages are released after shrinkers
+
+use kernel_types::declare_constants;
+
+use super::{AllocError, SYSTEM_ALLOCATOR};
+
+declare_constants!(
+    pub usize,
+    MAX_SHRINKERS = 16, "The maximal count of registered shrinkers";
+);
+
+/// The callback returns the count of released pages.
+/// It's invoked by allocating code, so it must not allocate
+/// and take locks which can be held while allocating
+pub type ShrinkFn = fn() -> usize;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Shrinker {
+    pub name: &'static str,
+    pub shrink: ShrinkFn,
+}
+
+//the shrinkers are invoked on allocation failure, so no heap is used
+static SHRINKERS: spin::Mutex<heapless::Vec<Shrinker, MAX_SHRINKERS>> =
+    spin::Mutex::new(heapless::Vec::new());
+
+pub fn register_shrinker(shrinker: Shrinker) -> Result<(), AllocError> {
+    log::debug!("Shrinker {} is registered", shrinker.name);
+
+    SHRINKERS
+        .lock()
+        .push(shrinker)
+        .map_err(|_| AllocError::NoMemory)
+}
+
+pub fn unregister_shrinker(name: &str) {
+    SHRINKERS.lock().retain(|shrinker| shrinker.name != name);
+}
+
+/// Ask all shrinkers to release memory, then return empty slab pages.
+/// Return the count of released pages
+pub fn shrink() -> usize {
+    //the shrinker can (un)register another one
+    let shrinkers = SHRINKERS.lock().clone();
+
+    let mut released = 0;
+
+    for shrinker in shrinkers.iter() {
+        let pages = (shrinker.shrink)();
+
+        log::debug!("Shrinker {} released {pages} page(s)", shrinker.name);
+
+        released += pages;
+    }
+
+    //the caches free objects, so slabs get empty entries
+    released + SYSTEM_ALLOCATOR.lock().shrink()
+}
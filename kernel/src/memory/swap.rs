@@ -0,0 +1,519 @@
+//! Swapping of anonymous pages to a block device. The pages of private
+//! demand regions not accessed since the last scan (second chance)
+//! are written to slots of swap area, the page entry keeps the slot
+//! until the page faults back in
+
+use core::{
+    arch::asm,
+    slice,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use alloc::vec::Vec;
+use kernel_types::{declare_constants, object::OpStatus};
+
+use crate::{
+    current_task, drivers,
+    error::KernelError,
+    io::block::{BlockWork, Request, Work},
+    object::{Handle, ObjectContainer},
+    task::{self, TaskPriority},
+    user::{kernel_buf::KernelBuf, queue::Queue},
+};
+
+use super::{
+    paging::PageMarker, register_shrinker, release_page, AllocError,
+    MemoryMappingFlag, MemoryRegion, MemoryRegionFlag, Page, PageFault,
+    PhysicalAddress, Process, ProcessState, Shrinker, VirtualAddress,
+};
+
+declare_constants!(
+    pub usize,
+    SECTOR_SIZE = 512, "The sector size of swap device";
+    SLOT_SECTORS = Page::SIZE / SECTOR_SIZE, "The sectors keeping one page";
+    MAX_SWAP_SLOTS = 1 << 20, "The count of slots addressable by page entry";
+    SWAP_BATCH = 8, "The pages swapped out when fault finds no memory";
+    SWAP_DAEMON_PERIOD = 100, "The period of swap daemon in milliseconds";
+);
+
+#[derive(Debug, thiserror_no_std::Error)]
+pub enum SwapError {
+    #[error("Swap is not enabled")]
+    Disabled,
+    #[error("Swap is already enabled")]
+    Enabled,
+    #[error("Invalid swap size: {0} page(s)")]
+    InvalidSize(usize),
+    #[error("Swap area has no free slot")]
+    Full,
+    #[error("Another page is being swapped out")]
+    Busy,
+    #[error("Swap device failed: {0:?}")]
+    Device(OpStatus),
+    #[error("Swap device is not responding")]
+    NoResponse,
+    #[error("Failed to allocate swap buffer: {0}")]
+    Alloc(#[from] AllocError),
+}
+
+/// The usage of swap area
+#[derive(Debug, Clone, Copy)]
+pub struct SwapStat {
+    pub total_pages: usize,
+    pub used_pages: usize,
+}
+
+struct SwapArea {
+    queue: Handle<Queue<BlockWork>>,
+    disk: usize,
+    start_sector: u32,
+    /// The count of page entries keeping each slot
+    slots: Vec<u16>,
+    /// All slots below are used
+    free_hint: usize,
+    used: usize,
+    /// The page is copied here before it's written, so swapping
+    /// out doesn't allocate. `None` while a page is being written
+    bounce: Option<Handle<KernelBuf>>,
+}
+
+//the queue and buffer handles are only used under `SWAP` lock
+//or taken out of it, so the area is never accessed concurrently
+unsafe impl Send for SwapArea {}
+
+impl SwapArea {
+    fn alloc_slot(&mut self) -> Option<usize> {
+        let slot = self.slots[self.free_hint..]
+            .iter()
+            .position(|&count| count == 0)?
+            + self.free_hint;
+
+        self.slots[slot] = 1;
+        self.free_hint = slot + 1;
+        self.used += 1;
+
+        Some(slot)
+    }
+
+    fn release_slot(&mut self, slot: usize) {
+        let Some(count) = self.slots.get_mut(slot).filter(|count| **count > 0)
+        else {
+            log::warn!("The free swap slot {slot} is released");
+            return;
+        };
+
+        *count -= 1;
+
+        if *count == 0 {
+            self.used -= 1;
+            self.free_hint = usize::min(self.free_hint, slot);
+        }
+    }
+
+    fn sector(&self, slot: usize) -> u32 {
+        self.start_sector + (slot * SLOT_SECTORS) as u32
+    }
+}
+
+static SWAP: spin::Mutex<Option<SwapArea>> = spin::Mutex::new(None);
+
+/// Use `pages` slots of `disk` starting at `start_sector` as swap area.
+/// The area is never released
+pub fn swap_on(
+    queue: Handle<Queue<BlockWork>>,
+    disk: usize,
+    start_sector: u32,
+    pages: usize,
+) -> Result<(), SwapError> {
+    if pages == 0 || pages > MAX_SWAP_SLOTS {
+        return Err(SwapError::InvalidSize(pages));
+    }
+
+    //the last sector of area should be addressable by request
+    if start_sector
+        .checked_add((pages * SLOT_SECTORS) as u32)
+        .is_none()
+    {
+        return Err(SwapError::InvalidSize(pages));
+    }
+
+    let mut slots = Vec::new();
+    slots.try_reserve_exact(pages).map_err(AllocError::from)?;
+    slots.resize(pages, 0);
+
+    let bounce = KernelBuf::new(Page::SIZE)?;
+
+    let mut swap = SWAP.lock();
+
+    if swap.is_some() {
+        return Err(SwapError::Enabled);
+    }
+
+    *swap = Some(SwapArea {
+        queue,
+        disk,
+        start_sector,
+        slots,
+        free_hint: 0,
+        used: 0,
+        bounce: Some(bounce),
+    });
+
+    drop(swap);
+
+    if let Err(cause) = spawn_swap_daemon() {
+        log::warn!("Swap is used only on page fault: {cause}");
+    } else if let Err(cause) = register_shrinker(Shrinker {
+        name: "swap",
+        shrink: shrink_swap,
+    }) {
+        log::warn!("Swap is used only on page fault: {cause}");
+    }
+
+    log::info!(
+        "Swap of {pages} page(s) at disk {disk} sector {start_sector} is enabled"
+    );
+
+    Ok(())
+}
+
+pub fn swap_stat() -> Option<SwapStat> {
+    SWAP.lock().as_ref().map(|swap| SwapStat {
+        total_pages: swap.slots.len(),
+        used_pages: swap.used,
+    })
+}
+
+/// Drop the reference of page entry to swap slot.
+/// The slot is free when no entry keeps it
+pub fn release_swap_slot(slot: usize) {
+    if let Some(swap) = SWAP.lock().as_mut() {
+        swap.release_slot(slot);
+    }
+}
+
+/// Take another reference to swap slot for the entry of forked process
+pub fn duplicate_swap_slot(slot: usize) {
+    let mut swap = SWAP.lock();
+
+    if let Some(count) = swap.as_mut().and_then(|swap| swap.slots.get_mut(slot))
+    {
+        *count += 1;
+    }
+}
+
+/// Write up to `count` pages not accessed recently to swap.
+/// The modules are skipped, as the module serving swap device
+/// can't wait for own pages. Return the count of freed pages
+pub fn swap_out_pages(count: usize) -> usize {
+    if SWAP.lock().is_none() {
+        return 0;
+    }
+
+    let mut processes = task::processes();
+
+    processes.retain(|process| !drivers::is_module(process.id));
+
+    let mut swapped = 0;
+
+    //the first pass clears accessed bits, so the second
+    //one finds the pages not used since the first
+    for _ in 0..2 {
+        for process in processes.iter() {
+            while swapped < count {
+                match swap_out(process) {
+                    Ok(true) => swapped += 1,
+                    //no cold page is left or it's written during I/O
+                    Ok(false) => break,
+                    //another task is writing, the memory comes from it
+                    Err(SwapError::Busy) => return swapped,
+                    Err(cause) => {
+                        log::warn!(
+                            "Failed to swap out page of process {}: {cause}",
+                            process.id
+                        );
+
+                        return swapped;
+                    }
+                }
+            }
+        }
+
+        if swapped >= count {
+            break;
+        }
+    }
+
+    log::debug!("{swapped} page(s) are swapped out");
+
+    swapped
+}
+
+/// The allocation has failed and the swap daemon should free memory
+static IS_SWAP_WANTED: AtomicBool = AtomicBool::new(false);
+
+/// The shrinker asking swap daemon for memory. Swapping allocates, locks
+/// process state and blocks on device I/O, so it's never done by allocating
+/// code. Nothing is released now, the later allocations get the memory
+fn shrink_swap() -> usize {
+    IS_SWAP_WANTED.store(true, Ordering::SeqCst);
+
+    0
+}
+
+fn spawn_swap_daemon() -> Result<(), KernelError> {
+    let daemon = task::new_task(
+        swap_daemon,
+        core::ptr::null_mut(),
+        TaskPriority::Kernel,
+    )?;
+
+    task::submit_task(daemon);
+
+    Ok(())
+}
+
+extern "C" fn swap_daemon(_arg: *const ()) {
+    log::debug!("Swap daemon task#{} started", current_task!().id);
+
+    loop {
+        task::sleep(SWAP_DAEMON_PERIOD);
+
+        if IS_SWAP_WANTED.swap(false, Ordering::SeqCst) {
+            swap_out_pages(SWAP_BATCH);
+        }
+    }
+}
+
+/// Write one cold page of `process` to swap. The page is read-only while
+/// it's written, so the write fault of process keeps the page in memory
+fn swap_out(process: &Process) -> Result<bool, SwapError> {
+    let buf = SWAP
+        .lock()
+        .as_mut()
+        .ok_or(SwapError::Disabled)?
+        .bounce
+        .take()
+        .ok_or(SwapError::Busy)?;
+
+    buf.reset();
+
+    let swapped = swap_out_with(process, &buf);
+
+    if let Some(swap) = SWAP.lock().as_mut() {
+        swap.bounce = Some(buf);
+    }
+
+    swapped
+}
+
+fn swap_out_with(
+    process: &Process,
+    buf: &Handle<KernelBuf>,
+) -> Result<bool, SwapError> {
+    let slot = SWAP
+        .lock()
+        .as_mut()
+        .ok_or(SwapError::Disabled)?
+        .alloc_slot()
+        .ok_or(SwapError::Full)?;
+
+    let victim = {
+        let mut state = process.state.lock();
+
+        let victim = find_cold_page(&mut state);
+
+        if let Some((page_offset, _, flag)) = victim {
+            let flags = flag.difference(MemoryRegionFlag::WRITE);
+
+            state.marker.protect_range(
+                page_offset..page_offset + Page::SIZE,
+                MemoryMappingFlag::from(flags),
+            );
+
+            with_address_space(&state.marker, || {
+                let page = unsafe {
+                    slice::from_raw_parts(page_offset as *const u8, Page::SIZE)
+                };
+
+                let _ = buf.copy_from(page);
+            });
+        }
+
+        victim
+    };
+
+    let Some((page_offset, physical, _)) = victim else {
+        release_swap_slot(slot);
+        return Ok(false);
+    };
+
+    //the device is served by module, so the state is unlocked
+    if let Err(cause) = exchange(slot, buf, true) {
+        release_swap_slot(slot);
+        return Err(cause);
+    }
+
+    let mut state = process.state.lock();
+
+    //the page written or unmapped during I/O stays in memory
+    let is_kept = state.marker.lookup_physical(page_offset) != Some(physical)
+        || state.marker.is_writable(page_offset);
+
+    if is_kept {
+        release_swap_slot(slot);
+        return Ok(false);
+    }
+
+    state.marker.swap_out_page(page_offset, slot);
+
+    //the region gives up the page, the mapping reference is dropped below
+    if let Some(owned) = state.find_region_mut(page_offset).and_then(|region| {
+        region
+            .pages
+            .remove_by(|page| page.as_physical() == physical)
+    }) {
+        owned.release();
+    }
+
+    release_page(physical);
+
+    Ok(true)
+}
+
+/// Read the swapped page containing `address` back to memory.
+/// The process address space should be active, the page is written through it
+pub(super) fn swap_in(
+    process: &Process,
+    address: VirtualAddress,
+) -> Result<(), PageFault> {
+    let page_offset = address - address % Page::SIZE;
+
+    //another task of process has already read the page
+    let Some(slot) = process.state.lock().marker.swap_slot(page_offset) else {
+        return Ok(());
+    };
+
+    let buf = KernelBuf::new(Page::SIZE)?;
+
+    exchange(slot, &buf, false)?;
+
+    process
+        .state
+        .lock()
+        .map_swapped_page(page_offset, slot, &buf)
+}
+
+/// The page of swappable region which isn't accessed since the last scan.
+/// Only the page owned by region and mapped once (not shared after fork
+/// or lent to kernel) is taken. Return the page with its region flag
+fn find_cold_page(
+    state: &mut ProcessState,
+) -> Option<(VirtualAddress, PhysicalAddress, MemoryRegionFlag)> {
+    let ProcessState {
+        regions, marker, ..
+    } = state;
+
+    for region in regions.iter().filter(|region| is_swappable(region)) {
+        for page_offset in region.range.clone().step_by(Page::SIZE) {
+            let Some(physical) = marker.lookup_physical(page_offset) else {
+                continue;
+            };
+
+            let frame = unsafe { &*Page::take_unchecked(physical) };
+
+            let is_owned = region
+                .pages
+                .iter()
+                .any(|page| page.as_physical() == physical);
+
+            if !is_owned || frame.use_count() != 2 {
+                continue;
+            }
+
+            if !marker.take_accessed(page_offset) {
+                return Some((page_offset, physical, region.flag));
+            }
+        }
+    }
+
+    None
+}
+
+/// The anonymous private region with accessible pages
+fn is_swappable(region: &MemoryRegion) -> bool {
+    region.file.is_none()
+        && region.flag.contains(MemoryRegionFlag::DEMAND)
+        && region.flag.intersects(MemoryRegionFlag::ACCESS)
+        && !region.flag.contains(MemoryRegionFlag::SHARED)
+}
+
+/// Read or write the page kept in `slot` through `buf`
+fn exchange(
+    slot: usize,
+    buf: &Handle<KernelBuf>,
+    is_write: bool,
+) -> Result<(), SwapError> {
+    let (queue, disk, sector) = {
+        let swap = SWAP.lock();
+        let swap = swap.as_ref().ok_or(SwapError::Disabled)?;
+
+        (swap.queue.clone(), swap.disk, swap.sector(slot))
+    };
+
+    let buffer = buf.handle().into_raw();
+
+    let work = if is_write {
+        Work::Write { sector, buffer }
+    } else {
+        Work::Read { sector, buffer }
+    };
+
+    let work = unsafe { BlockWork::new_boxed(Request { disk, work }, &queue) }?;
+
+    let work = queue.push(work);
+
+    match work.wait().map(|response| response.status()) {
+        Some(Ok(())) => Ok(()),
+        Some(Err(status)) => Err(SwapError::Device(status)),
+        None => Err(SwapError::NoResponse),
+    }
+}
+
+/// Run `f` with the address space of `marker` loaded. The caller keeps
+/// process state locked, so interrupts are disabled and the task stays
+fn with_address_space(marker: &PageMarker, f: impl FnOnce()) {
+    let active: PhysicalAddress;
+
+    unsafe {
+        asm! {
+            "mov {}, cr3",
+            out(reg) active,
+            options(nostack, preserves_flags)
+        }
+    }
+
+    let directory = marker.physical_offset();
+
+    if active == directory {
+        return f();
+    }
+
+    unsafe {
+        asm! {
+            "mov cr3, {}",
+            in(reg) directory,
+            options(nostack, preserves_flags)
+        }
+    }
+
+    f();
+
+    unsafe {
+        asm! {
+            "mov cr3, {}",
+            in(reg) active,
+            options(nostack, preserves_flags)
+        }
+    }
+}
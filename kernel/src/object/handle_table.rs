@@ -0,0 +1,333 @@
+use alloc::vec::Vec;
+use kernel_types::syscall::SyscallError;
+
+use crate::memory::AllocError;
+
+use super::{clone_raw, drop_raw, Kind, Object, ObjectContainer, RawHandle};
+
+bitflags::bitflags! {
+    /// The operations allowed on object via user handle
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct HandleRights: u8 {
+        const READ = 0x01;
+        const WRITE = 0x02;
+        const WAIT = 0x04;
+        const DUPLICATE = 0x08;
+        /// The handle can be moved to another process
+        const TRANSFER = 0x10;
+
+        const READ_WRITE = Self::READ.bits() | Self::WRITE.bits();
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct HandleEntry {
+    pub object: RawHandle,
+    pub kind: Kind,
+    pub rights: HandleRights,
+}
+
+/// The user handle is an index in the table shifted by one
+/// to keep zero as null handle
+pub type UserRawHandle = usize;
+
+/// Per process table of objects available to user space.
+/// Each entry owns one reference to the kernel object
+#[derive(Debug, Default)]
+pub struct HandleTable {
+    entries: Vec<Option<HandleEntry>>,
+}
+
+impl HandleTable {
+    pub const fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Move object reference into the table
+    pub fn insert(
+        &mut self,
+        object: RawHandle,
+        rights: HandleRights,
+    ) -> Result<UserRawHandle, AllocError> {
+        let kind = unsafe { (*(object as *const Object)).kind };
+
+        let entry = Some(HandleEntry {
+            object,
+            kind,
+            rights,
+        });
+
+        if let Some(index) = self.entries.iter().position(Option::is_none) {
+            self.entries[index] = entry;
+
+            return Ok(index + 1);
+        }
+
+        self.entries.try_reserve(1)?;
+        self.entries.push(entry);
+
+        Ok(self.entries.len())
+    }
+
+    pub fn get(
+        &self,
+        handle: UserRawHandle,
+        rights: HandleRights,
+    ) -> Result<HandleEntry, SyscallError> {
+        let entry = handle
+            .checked_sub(1)
+            .and_then(|index| self.entries.get(index))
+            .and_then(Option::as_ref)
+            .ok_or(SyscallError::InvalidHandle)?;
+
+        if !entry.rights.contains(rights) {
+            return Err(SyscallError::AccessDenied);
+        }
+
+        Ok(*entry)
+    }
+
+    /// Return object address if handle refers to `T` with given rights
+    pub fn get_of<T: ObjectContainer>(
+        &self,
+        handle: UserRawHandle,
+        rights: HandleRights,
+    ) -> Result<RawHandle, SyscallError> {
+        let entry = self.get(handle, rights)?;
+
+        if entry.kind != T::KIND {
+            return Err(SyscallError::InvalidObjectKind);
+        }
+
+        Ok(entry.object)
+    }
+
+    /// Remove handle from the table.
+    /// The object reference is returned to caller
+    pub fn remove(
+        &mut self,
+        handle: UserRawHandle,
+    ) -> Result<HandleEntry, SyscallError> {
+        let entry = self.get(handle, HandleRights::empty())?;
+
+        self.entries[handle - 1] = None;
+
+        Ok(entry)
+    }
+
+    /// Insert new handle to the same object.
+    /// The new handle cannot have more rights than the original one
+    pub fn duplicate(
+        &mut self,
+        handle: UserRawHandle,
+        rights: HandleRights,
+    ) -> Result<UserRawHandle, SyscallError> {
+        let entry = self.get(handle, HandleRights::DUPLICATE)?;
+
+        let object = unsafe { clone_raw(entry.object) };
+
+        self.insert(object, entry.rights & rights)
+            .inspect_err(|_| unsafe { drop_raw(object) })
+            .map_err(SyscallError::from)
+    }
+
+    /// Copy the table for forked process.
+    /// Each copied entry owns its own reference to the object
+    pub fn try_clone(&self) -> Result<Self, AllocError> {
+        let mut entries = Vec::new();
+        entries.try_reserve_exact(self.entries.len())?;
+
+        for entry in self.entries.iter() {
+            entries.push(entry.map(|entry| HandleEntry {
+                object: unsafe { clone_raw(entry.object) },
+                ..entry
+            }));
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// Move handle to the table of another process
+    pub fn transfer(
+        &mut self,
+        handle: UserRawHandle,
+        target: &mut HandleTable,
+    ) -> Result<UserRawHandle, SyscallError> {
+        let entry = self.get(handle, HandleRights::TRANSFER)?;
+
+        let moved_handle = target.insert(entry.object, entry.rights)?;
+
+        self.entries[handle - 1] = None;
+
+        Ok(moved_handle)
+    }
+}
+
+impl Drop for HandleTable {
+    fn drop(&mut self) {
+        for entry in self.entries.iter().flatten() {
+            unsafe { drop_raw(entry.object) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+    extern crate std;
+
+    use core::{panic::Location, sync::atomic::AtomicU16};
+
+    use kernel_types::collections::ListNode;
+
+    use crate::{
+        object::{AtomicStatus, Origin, Status},
+        task::{Event, MutexObject},
+    };
+
+    use super::*;
+
+    /// The object is never dropped, so it isn't registered in runtime
+    fn new_object(kind: Kind) -> Object {
+        Object {
+            node: ListNode::empty(),
+            parent: None,
+            kind,
+            status: AtomicStatus::new(Status::Working),
+            ref_count: AtomicU16::new(1),
+            origin: Origin {
+                process: None,
+                location: Location::caller(),
+            },
+        }
+    }
+
+    fn raw(object: &Object) -> RawHandle {
+        object as *const Object as RawHandle
+    }
+
+    /// Remove entries, so the table doesn't drop the objects
+    fn clear(mut table: HandleTable) {
+        for index in 0..table.entries.len() {
+            table.entries[index] = None;
+        }
+    }
+
+    #[test]
+    fn rights_test() {
+        let event = new_object(Kind::Event);
+        let mut table = HandleTable::new();
+
+        let handle = table.insert(raw(&event), HandleRights::READ).unwrap();
+
+        assert_eq!(handle, 1);
+        assert!(table.get(handle, HandleRights::READ).is_ok());
+        assert!(table.get(handle, HandleRights::empty()).is_ok());
+        assert!(matches!(
+            table.get(handle, HandleRights::READ_WRITE),
+            Err(SyscallError::AccessDenied)
+        ));
+        assert!(matches!(
+            table.get(0, HandleRights::empty()),
+            Err(SyscallError::InvalidHandle)
+        ));
+        assert!(matches!(
+            table.get(handle + 1, HandleRights::empty()),
+            Err(SyscallError::InvalidHandle)
+        ));
+
+        clear(table);
+    }
+
+    #[test]
+    fn kind_test() {
+        let event = new_object(Kind::Event);
+        let mut table = HandleTable::new();
+
+        let handle = table.insert(raw(&event), HandleRights::WAIT).unwrap();
+
+        assert_eq!(
+            table.get_of::<Event>(handle, HandleRights::WAIT).unwrap(),
+            raw(&event)
+        );
+        assert!(matches!(
+            table.get_of::<MutexObject>(handle, HandleRights::WAIT),
+            Err(SyscallError::InvalidObjectKind)
+        ));
+
+        clear(table);
+    }
+
+    #[test]
+    fn reuse_test() {
+        let event = new_object(Kind::Event);
+        let mutex = new_object(Kind::Mutex);
+        let mut table = HandleTable::new();
+
+        let first = table.insert(raw(&event), HandleRights::READ).unwrap();
+        let second = table.insert(raw(&mutex), HandleRights::READ).unwrap();
+
+        assert_eq!((first, second), (1, 2));
+
+        let removed = table.remove(first).unwrap();
+
+        assert_eq!(removed.object, raw(&event));
+        assert!(matches!(
+            table.remove(first),
+            Err(SyscallError::InvalidHandle)
+        ));
+
+        //the free slot is taken before the table grows
+        let reused = table.insert(raw(&event), HandleRights::WRITE).unwrap();
+
+        assert_eq!(reused, first);
+        assert_eq!(
+            table.get(reused, HandleRights::WRITE).unwrap().kind,
+            Kind::Event
+        );
+        assert_eq!(
+            table.get(second, HandleRights::READ).unwrap().kind,
+            Kind::Mutex
+        );
+
+        clear(table);
+    }
+
+    #[test]
+    fn transfer_test() {
+        let event = new_object(Kind::Event);
+        let mutex = new_object(Kind::Mutex);
+        let mut table = HandleTable::new();
+        let mut target = HandleTable::new();
+
+        let kept = table.insert(raw(&event), HandleRights::READ).unwrap();
+        let moved = table
+            .insert(raw(&mutex), HandleRights::READ | HandleRights::TRANSFER)
+            .unwrap();
+
+        assert!(matches!(
+            table.transfer(kept, &mut target),
+            Err(SyscallError::AccessDenied)
+        ));
+
+        let received = table.transfer(moved, &mut target).unwrap();
+
+        assert_eq!(received, 1);
+        assert_eq!(
+            target
+                .get_of::<MutexObject>(received, HandleRights::READ)
+                .unwrap(),
+            raw(&mutex)
+        );
+        assert!(matches!(
+            table.get(moved, HandleRights::empty()),
+            Err(SyscallError::InvalidHandle)
+        ));
+        assert!(table.get(kept, HandleRights::READ).is_ok());
+
+        clear(table);
+        clear(target);
+    }
+}
@@ -1,6 +1,9 @@
 mod handle;
+mod handle_table;
 pub mod runtime;
+pub mod tracking;
 mod user_handle;
+mod vtable;
 mod work;
 
 use core::{ptr::NonNull, sync::atomic::AtomicU16};
@@ -8,10 +11,17 @@ use core::{ptr::NonNull, sync::atomic::AtomicU16};
 use kernel_macro::ListNode;
 use kernel_types::collections::ListNode;
 
-use crate::memory::{self, slab_alloc, Slab, SlabBox, VirtualAddress};
+use crate::{
+    memory::{self, slab_alloc, ProcessId, Slab, SlabBox, VirtualAddress},
+    user::kernel_buf,
+};
 
 pub use handle::*;
+pub use handle_table::*;
+pub use tracking::Origin;
 pub use user_handle::*;
+pub use vtable::*;
+pub use work::WorkObject;
 
 pub struct AnyObject;
 
@@ -32,6 +42,26 @@ pub enum Kind {
     Event,
 
     KernelBuf,
+    Timer,
+}
+
+impl Kind {
+    pub const COUNT: usize = Kind::Timer as usize + 1;
+
+    pub const ALL: [Kind; Kind::COUNT] = [
+        Kind::BlockDeviceWork,
+        Kind::FsWork,
+        Kind::FileLookupWork,
+        Kind::FileWork,
+        Kind::IrqEvent,
+        Kind::Queue,
+        Kind::SuperBlock,
+        Kind::File,
+        Kind::Mutex,
+        Kind::Event,
+        Kind::KernelBuf,
+        Kind::Timer,
+    ];
 }
 
 #[derive(Debug, ListNode)]
@@ -45,6 +75,7 @@ pub struct Object {
     pub status: AtomicStatus,
 
     pub ref_count: AtomicU16,
+    pub origin: Origin,
 }
 
 pub enum DetachError {
@@ -64,23 +95,35 @@ pub enum Status {
 }
 
 impl Object {
+    #[track_caller]
     fn new_root(kind: Kind) -> Self {
+        let origin = Origin::caller();
+
+        tracking::track(kind, origin);
+
         Self {
             kind,
             parent: None,
             ref_count: AtomicU16::new(0),
             status: AtomicStatus::new(Status::Working),
             node: ListNode::empty(),
+            origin,
         }
     }
 
+    #[track_caller]
     fn new_child(kind: Kind, parent: RawHandle) -> Self {
+        let origin = Origin::caller();
+
+        tracking::track(kind, origin);
+
         Self {
             kind,
             parent: parent.into(),
             ref_count: AtomicU16::new(1),
             status: AtomicStatus::new(Status::Working),
             node: ListNode::empty(),
+            origin,
         }
     }
 
@@ -93,18 +136,31 @@ impl Object {
     }
 }
 
+impl Drop for Object {
+    fn drop(&mut self) {
+        //the work dropped without response keeps buffers lent to module
+        if matches!(self.kind, Kind::BlockDeviceWork | Kind::FileWork) {
+            kernel_buf::reclaim(self.raw_handle());
+        }
+
+        tracking::untrack(self.kind, self.origin);
+    }
+}
+
 pub trait ObjectContainer: Sized + Slab + 'static {
     const KIND: Kind;
     fn container_of(object: *mut Object) -> *mut Self;
     fn object(&self) -> &Object;
     fn object_mut(&mut self) -> &mut Object;
 
+    #[track_caller]
     fn new_object<T: ObjectContainer>(parent: &Handle<T>) -> Object {
         assert!(runtime::lookup(parent.clone()));
 
         Object::new_child(Self::KIND, parent.as_addr())
     }
 
+    #[track_caller]
     fn attach_to_parent<T: ObjectContainer>(&mut self, parent: &Handle<T>) {
         assert!(runtime::lookup(parent.clone()));
 
@@ -122,6 +178,7 @@ pub trait ObjectContainer: Sized + Slab + 'static {
         Ok(())
     }
 
+    #[track_caller]
     fn new_root_object() -> Object {
         Object::new_root(Self::KIND)
     }
@@ -129,6 +186,12 @@ pub trait ObjectContainer: Sized + Slab + 'static {
     fn handle(&self) -> Handle<Self> {
         self.object().handle()
     }
+
+    /// Describe object for user space.
+    /// Lent resources are described for the given process
+    fn info(&self, _process: Option<ProcessId>) -> ObjectInfo {
+        ObjectInfo::Object(self.object().into())
+    }
 }
 
 // alloc new root object
@@ -2,7 +2,7 @@ use core::sync::atomic::Ordering;
 
 use kernel_types::collections::LinkedList;
 
-use crate::{error::KernelError, task::SCHEDULER};
+use crate::{error::KernelError, smp, task::SCHEDULER};
 
 use super::{Handle, Object, ObjectContainer, RawHandle, Status};
 
@@ -44,14 +44,15 @@ pub fn block_on<T: ObjectContainer>(
     let status = T::object(&handle).status.load(Ordering::SeqCst);
 
     //this task is blocking on object
-    //and holding critical section
-    let awake_another = status == Status::Blocked;
+    //and holding critical section,
+    //so another waiter (of any cpu) is awaken
+    if status == Status::Blocked {
+        notify(handle.clone());
+    }
 
-    SCHEDULER
-        .switch_lock()
-        .block_on(handle, awake_another, |obj| {
-            obj.object().status.store(Status::Working, Ordering::SeqCst);
-        });
+    SCHEDULER.switch_lock().block_on(handle, false, |obj| {
+        obj.object().status.store(Status::Working, Ordering::SeqCst);
+    });
 
     Ok(())
 }
@@ -85,8 +86,22 @@ where
     }
 }
 
+/// Unblock one task waiting on handle.
+/// The tasks of current cpu are preferred
 pub fn notify<T: ObjectContainer>(handle: Handle<T>) {
-    SCHEDULER.access_lock().unblock_on(handle.as_addr());
+    let current = smp::cpu_id();
+
+    if SCHEDULER.access_lock().unblock_on(handle.as_addr()) {
+        return;
+    }
+
+    let unblocked = SCHEDULER.iter().filter(|(cpu, _)| *cpu != current).find(
+        |(_, scheduler)| scheduler.access_lock().unblock_on(handle.as_addr()),
+    );
+
+    if let Some((cpu, _)) = unblocked {
+        smp::reschedule(cpu);
+    }
 }
 
 pub fn lookup<T: ObjectContainer>(handle: Handle<T>) -> bool {
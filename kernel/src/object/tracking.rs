@@ -0,0 +1,112 @@
+//! Accounting of live kernel objects.
+//! Each object remembers the process and the code location created it,
+//! thus leaked objects are visible in `/sys/objects`
+
+use core::{
+    panic::Location,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use alloc::vec::Vec;
+
+use crate::{
+    io::InterruptableLazyCell,
+    memory::{self, ProcessId},
+};
+
+use super::Kind;
+
+/// The count of distinct creation sites to be tracked.
+/// Objects of other sites are accounted only in per kind counters
+const MAX_SITES: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Origin {
+    /// The process of task created object. `None` for kernel tasks
+    pub process: Option<ProcessId>,
+    pub location: &'static Location<'static>,
+}
+
+impl Origin {
+    #[track_caller]
+    pub fn caller() -> Self {
+        Self {
+            process: memory::running_process(),
+            location: Location::caller(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SiteStat {
+    pub kind: Kind,
+    pub origin: Origin,
+    pub live: usize,
+}
+
+struct Tracker {
+    kinds: [AtomicUsize; Kind::COUNT],
+    sites: InterruptableLazyCell<[Option<SiteStat>; MAX_SITES]>,
+}
+
+static TRACKER: Tracker = Tracker::new();
+
+impl Tracker {
+    const fn new() -> Self {
+        Self {
+            kinds: [const { AtomicUsize::new(0) }; Kind::COUNT],
+            sites: InterruptableLazyCell::new([None; MAX_SITES]),
+        }
+    }
+}
+
+pub fn track(kind: Kind, origin: Origin) {
+    TRACKER.kinds[kind as usize].fetch_add(1, Ordering::SeqCst);
+
+    let mut sites = TRACKER.sites.lock();
+
+    if let Some(site) = sites
+        .iter_mut()
+        .flatten()
+        .find(|site| site.kind == kind && site.origin == origin)
+    {
+        site.live += 1;
+    } else if let Some(slot) = sites.iter_mut().find(|site| site.is_none()) {
+        *slot = Some(SiteStat {
+            kind,
+            origin,
+            live: 1,
+        });
+    }
+}
+
+pub fn untrack(kind: Kind, origin: Origin) {
+    TRACKER.kinds[kind as usize].fetch_sub(1, Ordering::SeqCst);
+
+    let mut sites = TRACKER.sites.lock();
+
+    let Some(slot) = sites.iter_mut().find(|site| {
+        site.is_some_and(|site| site.kind == kind && site.origin == origin)
+    }) else {
+        return;
+    };
+
+    let site = slot.as_mut().unwrap();
+    site.live -= 1;
+
+    if site.live == 0 {
+        *slot = None;
+    }
+}
+
+/// The count of live objects of given kind
+pub fn live_count(kind: Kind) -> usize {
+    TRACKER.kinds[kind as usize].load(Ordering::SeqCst)
+}
+
+/// Snapshot of creation sites having live objects
+pub fn live_sites() -> Vec<SiteStat> {
+    let sites = *TRACKER.sites.lock();
+
+    sites.into_iter().flatten().collect()
+}
@@ -0,0 +1,129 @@
+use core::mem::ManuallyDrop;
+
+use crate::{
+    fs::{FileLookupWork, FileWork, FsWork, IndexNode, SuperBlock},
+    io::{block::BlockWork, IrqEvent},
+    memory::ProcessId,
+    task::{timer::TimerObject, Event, MutexObject},
+    user::{kernel_buf::KernelBuf, queue::Queue},
+};
+
+use super::{AnyObject, Handle, Kind, Object, ObjectContainer, RawHandle};
+
+/// The description of object available to user space
+pub type ObjectInfo = kernel_types::object::AnyObjectInfo;
+
+impl From<&Object> for kernel_types::object::ObjectInfo {
+    fn from(object: &Object) -> Self {
+        use core::sync::atomic::Ordering;
+
+        Self {
+            kind: object.kind as u8,
+            status: object.status.load(Ordering::SeqCst) as u8,
+            ref_count: object.ref_count.load(Ordering::SeqCst),
+        }
+    }
+}
+
+/// Operations on object which type is known only by its kind
+pub struct ObjectVTable {
+    /// Release one reference to object
+    pub drop: unsafe fn(RawHandle),
+    /// Acquire one more reference to object
+    pub clone: unsafe fn(RawHandle) -> RawHandle,
+    pub info: unsafe fn(RawHandle, Option<ProcessId>) -> ObjectInfo,
+}
+
+impl ObjectVTable {
+    pub const fn of<T: ObjectContainer>() -> Self {
+        Self {
+            drop: drop_object::<T>,
+            clone: clone_object::<T>,
+            info: object_info::<T>,
+        }
+    }
+}
+
+unsafe fn drop_object<T: ObjectContainer>(raw_handle: RawHandle) {
+    drop(Handle::<T>::from_addr_unchecked(raw_handle));
+}
+
+unsafe fn clone_object<T: ObjectContainer>(raw_handle: RawHandle) -> RawHandle {
+    let handle =
+        ManuallyDrop::new(Handle::<T>::from_addr_unchecked(raw_handle));
+
+    (*handle).clone().into_addr()
+}
+
+unsafe fn object_info<T: ObjectContainer>(
+    raw_handle: RawHandle,
+    process: Option<ProcessId>,
+) -> ObjectInfo {
+    let handle =
+        ManuallyDrop::new(Handle::<T>::from_addr_unchecked(raw_handle));
+
+    handle.info(process)
+}
+
+static BLOCK_DEVICE_WORK: ObjectVTable = ObjectVTable::of::<BlockWork>();
+static FS_WORK: ObjectVTable = ObjectVTable::of::<FsWork>();
+static FILE_LOOKUP_WORK: ObjectVTable = ObjectVTable::of::<FileLookupWork>();
+static FILE_WORK: ObjectVTable = ObjectVTable::of::<FileWork>();
+static IRQ_EVENT: ObjectVTable = ObjectVTable::of::<IrqEvent>();
+static QUEUE: ObjectVTable = ObjectVTable::of::<Queue<AnyObject>>();
+static SUPER_BLOCK: ObjectVTable = ObjectVTable::of::<SuperBlock>();
+static FILE: ObjectVTable = ObjectVTable::of::<IndexNode>();
+static MUTEX: ObjectVTable = ObjectVTable::of::<MutexObject>();
+static EVENT: ObjectVTable = ObjectVTable::of::<Event>();
+static KERNEL_BUF: ObjectVTable = ObjectVTable::of::<KernelBuf>();
+static TIMER: ObjectVTable = ObjectVTable::of::<TimerObject>();
+
+impl Kind {
+    pub fn vtable(&self) -> &'static ObjectVTable {
+        match self {
+            Kind::BlockDeviceWork => &BLOCK_DEVICE_WORK,
+            Kind::FsWork => &FS_WORK,
+            Kind::FileLookupWork => &FILE_LOOKUP_WORK,
+            Kind::FileWork => &FILE_WORK,
+            Kind::IrqEvent => &IRQ_EVENT,
+            Kind::Queue => &QUEUE,
+            Kind::SuperBlock => &SUPER_BLOCK,
+            Kind::File => &FILE,
+            Kind::Mutex => &MUTEX,
+            Kind::Event => &EVENT,
+            Kind::KernelBuf => &KERNEL_BUF,
+            Kind::Timer => &TIMER,
+        }
+    }
+}
+
+/// Release one reference to object of any kind
+///
+/// # Safety
+/// `raw_handle` should point to live object owned by caller
+pub unsafe fn drop_raw(raw_handle: RawHandle) {
+    let kind = (*(raw_handle as *const Object)).kind;
+
+    (kind.vtable().drop)(raw_handle)
+}
+
+/// Acquire one more reference to object of any kind
+///
+/// # Safety
+/// `raw_handle` should point to live object
+pub unsafe fn clone_raw(raw_handle: RawHandle) -> RawHandle {
+    let kind = (*(raw_handle as *const Object)).kind;
+
+    (kind.vtable().clone)(raw_handle)
+}
+
+/// # Safety
+/// `raw_handle` should point to live object
+pub unsafe fn info_raw(
+    raw_handle: RawHandle,
+    process: Option<ProcessId>,
+) -> ObjectInfo {
+    let kind = (*(raw_handle as *const Object)).kind;
+
+    (kind.vtable().info)(raw_handle, process)
+}
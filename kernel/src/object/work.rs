@@ -1,3 +1,12 @@
+use kernel_types::object::OpStatus;
+
+use super::ObjectContainer;
+
+/// The work which is completed by kernel when it can't be passed to module
+pub trait WorkObject: ObjectContainer {
+    fn fail(&self, status: OpStatus);
+}
+
 #[macro_export]
 macro_rules! impl_work {
     ($ty: ty,
@@ -9,6 +18,7 @@ macro_rules! impl_work {
         impl $ty {
             /// You should put object into the parent collections
             /// As droping this object will cause removing object from collection
+            #[track_caller]
             pub unsafe fn new_boxed(
                 request: $req,
                 parent: &$crate::object::Handle<$crate::user::queue::Queue<$ty>>,
@@ -80,6 +90,12 @@ macro_rules! impl_work {
             }
         }
 
+        impl $crate::object::WorkObject for $ty {
+            fn fail(&self, status: kernel_types::object::OpStatus) {
+                self.send_response(status.into());
+            }
+        }
+
         impl $crate::memory::Slab for $ty {
             const NAME: &str = $slab;
         }
@@ -88,7 +104,7 @@ macro_rules! impl_work {
 
 #[macro_export]
 macro_rules! impl_container {
-    ($ty: ty, obj_kind: $kind: ident, slab: $slab: expr) => {
+    ($ty: ty, obj_kind: $kind: ident, slab: $slab: expr $(, info: $info: expr)?) => {
         impl $crate::memory::Slab for $ty {
             const NAME: &str = $slab;
         }
@@ -107,6 +123,15 @@ macro_rules! impl_container {
             fn object_mut(&mut self) -> &mut $crate::object::Object {
                 &mut self.object
             }
+
+            $(
+            fn info(
+                &self,
+                process: Option<$crate::memory::ProcessId>,
+            ) -> $crate::object::ObjectInfo {
+                $info(self, process)
+            }
+            )?
         }
     };
 }
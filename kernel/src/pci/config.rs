@@ -0,0 +1,78 @@
+//! Configuration space access mechanism #1 via ports 0xCF8/0xCFC
+
+use core::fmt;
+
+use crate::common::io;
+
+const CONFIG_ADDRESS: u16 = 0xCF8;
+const CONFIG_DATA: u16 = 0xCFC;
+
+const ENABLE: u32 = 1 << 31;
+
+/// The address and data ports are used in pair
+static CONFIG_LOCK: spin::Mutex<()> = spin::Mutex::new(());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PciAddress {
+    pub bus: u8,
+    pub device: u8,
+    pub function: u8,
+}
+
+impl PciAddress {
+    pub const fn new(bus: u8, device: u8, function: u8) -> Self {
+        Self {
+            bus,
+            device,
+            function,
+        }
+    }
+
+    fn config_address(&self, offset: u8) -> u32 {
+        ENABLE
+            | (self.bus as u32) << 16
+            | (self.device as u32) << 11
+            | (self.function as u32) << 8
+            | (offset & 0xFC) as u32
+    }
+
+    pub fn read_u32(&self, offset: u8) -> u32 {
+        let _lock = CONFIG_LOCK.lock();
+
+        unsafe {
+            io::outl(CONFIG_ADDRESS, self.config_address(offset));
+            io::inl(CONFIG_DATA)
+        }
+    }
+
+    pub fn read_u16(&self, offset: u8) -> u16 {
+        (self.read_u32(offset) >> ((offset & 0b10) * 8)) as u16
+    }
+
+    pub fn read_u8(&self, offset: u8) -> u8 {
+        (self.read_u32(offset) >> ((offset & 0b11) * 8)) as u8
+    }
+
+    pub fn write_u32(&self, offset: u8, value: u32) {
+        let _lock = CONFIG_LOCK.lock();
+
+        unsafe {
+            io::outl(CONFIG_ADDRESS, self.config_address(offset));
+            io::outl(CONFIG_DATA, value);
+        }
+    }
+
+    pub fn write_u16(&self, offset: u8, value: u16) {
+        let shift = (offset & 0b10) * 8;
+
+        let dword = self.read_u32(offset) & !(0xFFFF << shift);
+
+        self.write_u32(offset, dword | (value as u32) << shift);
+    }
+}
+
+impl fmt::Display for PciAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02x}:{:02x}.{}", self.bus, self.device, self.function)
+    }
+}
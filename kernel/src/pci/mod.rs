@@ -0,0 +1,237 @@
+//! PCI bus enumeration. The devices found at boot are kept
+//! in registry, a module takes the unbound device matching
+//! its `PciMatch` and receives BARs and interrupt line
+
+mod config;
+
+use alloc::vec::Vec;
+
+use crate::{common::atomics::UnsafeLazyCell, drivers::ModuleId};
+
+pub use config::PciAddress;
+pub use kernel_types::drivers::pci::{
+    PciBar, PciDeviceInfo, PciMatch, PCI_BARS_COUNT,
+};
+
+/// The offsets in configuration space header
+const VENDOR_ID: u8 = 0x00;
+const DEVICE_ID: u8 = 0x02;
+const COMMAND: u8 = 0x04;
+const REVISION: u8 = 0x08;
+const PROG_IF: u8 = 0x09;
+const SUBCLASS: u8 = 0x0A;
+const CLASS: u8 = 0x0B;
+const HEADER_TYPE: u8 = 0x0E;
+const BAR0: u8 = 0x10;
+const IRQ_LINE: u8 = 0x3C;
+const IRQ_PIN: u8 = 0x3D;
+
+const NO_VENDOR: u16 = 0xFFFF;
+
+const MULTI_FUNCTION: u8 = 0x80;
+const HEADER_TYPE_MASK: u8 = 0x7F;
+const GENERAL_DEVICE: u8 = 0x00;
+const PCI_BRIDGE: u8 = 0x01;
+
+const DEVICES_PER_BUS: u8 = 32;
+const FUNCTIONS_PER_DEVICE: u8 = 8;
+
+const COMMAND_IO: u16 = 1;
+const COMMAND_MEMORY: u16 = 1 << 1;
+const COMMAND_BUS_MASTER: u16 = 1 << 2;
+
+const BAR_IO: u32 = 1;
+const BAR_TYPE_MASK: u32 = 0b110;
+const BAR_64BIT: u32 = 0b100;
+const BAR_PREFETCHABLE: u32 = 1 << 3;
+const BAR_IO_FLAGS: u32 = 0b11;
+const BAR_MEMORY_FLAGS: u32 = 0xF;
+
+#[derive(Debug, Clone)]
+pub struct PciDevice {
+    pub address: PciAddress,
+    pub info: PciDeviceInfo,
+    /// The module serving device
+    pub owner: Option<ModuleId>,
+}
+
+static DEVICES: UnsafeLazyCell<spin::Mutex<Vec<PciDevice>>> =
+    UnsafeLazyCell::empty();
+
+pub fn init() {
+    let mut devices = Vec::new();
+
+    for bus in 0..=u8::MAX {
+        for device in 0..DEVICES_PER_BUS {
+            scan_device(bus, device, &mut devices);
+        }
+    }
+
+    for device in devices.iter() {
+        let info = &device.info;
+
+        log::info!(
+            "PCI {}: {:04x}:{:04x} class {:02x}.{:02x}",
+            device.address,
+            info.vendor_id,
+            info.device_id,
+            info.class,
+            info.subclass
+        );
+    }
+
+    DEVICES.set(spin::Mutex::new(devices));
+}
+
+/// The snapshot of registry
+pub fn devices() -> Vec<PciDevice> {
+    DEVICES
+        .try_get()
+        .map(|devices| devices.lock().clone())
+        .unwrap_or_default()
+}
+
+/// Take the first unbound device matching `pattern`.
+/// I/O, memory decoding and bus mastering are enabled for device
+pub fn bind(pattern: &PciMatch, owner: ModuleId) -> Option<PciDeviceInfo> {
+    let mut devices = DEVICES.try_get()?.lock();
+
+    let device = devices.iter_mut().find(|device| {
+        device.owner.is_none() && pattern.matches(&device.info)
+    })?;
+
+    device.owner = Some(owner);
+
+    let address = device.address;
+    let command = address.read_u16(COMMAND);
+
+    address.write_u16(
+        COMMAND,
+        command | COMMAND_IO | COMMAND_MEMORY | COMMAND_BUS_MASTER,
+    );
+
+    log::debug!("PCI {address} is bound to module#{owner}");
+
+    Some(device.info)
+}
+
+fn scan_device(bus: u8, device: u8, devices: &mut Vec<PciDevice>) {
+    let address = PciAddress::new(bus, device, 0);
+
+    if address.read_u16(VENDOR_ID) == NO_VENDOR {
+        return;
+    }
+
+    let functions = if address.read_u8(HEADER_TYPE) & MULTI_FUNCTION != 0 {
+        FUNCTIONS_PER_DEVICE
+    } else {
+        1
+    };
+
+    for function in 0..functions {
+        let address = PciAddress::new(bus, device, function);
+
+        if address.read_u16(VENDOR_ID) == NO_VENDOR {
+            continue;
+        }
+
+        devices.push(PciDevice {
+            address,
+            info: read_info(address),
+            owner: None,
+        });
+    }
+}
+
+fn read_info(address: PciAddress) -> PciDeviceInfo {
+    let bars_count = match address.read_u8(HEADER_TYPE) & HEADER_TYPE_MASK {
+        GENERAL_DEVICE => PCI_BARS_COUNT,
+        PCI_BRIDGE => 2,
+        _ => 0,
+    };
+
+    PciDeviceInfo {
+        bus: address.bus,
+        device: address.device,
+        function: address.function,
+        vendor_id: address.read_u16(VENDOR_ID),
+        device_id: address.read_u16(DEVICE_ID),
+        class: address.read_u8(CLASS),
+        subclass: address.read_u8(SUBCLASS),
+        prog_if: address.read_u8(PROG_IF),
+        revision: address.read_u8(REVISION),
+        bars: read_bars(address, bars_count),
+        irq_line: address.read_u8(IRQ_LINE),
+        irq_pin: address.read_u8(IRQ_PIN),
+    }
+}
+
+/// Write all ones to register and read back the mask of size
+fn size_mask(address: PciAddress, offset: u8) -> u32 {
+    let value = address.read_u32(offset);
+
+    address.write_u32(offset, u32::MAX);
+    let mask = address.read_u32(offset);
+    address.write_u32(offset, value);
+
+    mask
+}
+
+fn read_bars(address: PciAddress, count: usize) -> [PciBar; PCI_BARS_COUNT] {
+    let mut bars = [PciBar::None; PCI_BARS_COUNT];
+
+    //the device must not decode the address while it's sized
+    let command = address.read_u16(COMMAND);
+    address.write_u16(COMMAND, command & !(COMMAND_IO | COMMAND_MEMORY));
+
+    let mut index = 0;
+
+    while index < count {
+        let offset = BAR0 + (index * 4) as u8;
+
+        let value = address.read_u32(offset);
+        let mask = size_mask(address, offset);
+
+        if value & BAR_IO != 0 {
+            let mask = (mask & !BAR_IO_FLAGS) as u16;
+
+            if mask != 0 {
+                bars[index] = PciBar::Io {
+                    port: (value & !BAR_IO_FLAGS) as u16,
+                    size: (!mask).wrapping_add(1) as u32,
+                };
+            }
+
+            index += 1;
+            continue;
+        }
+
+        let mut base = (value & !BAR_MEMORY_FLAGS) as u64;
+        let mut mask = (mask & !BAR_MEMORY_FLAGS) as u64 | 0xFFFF_FFFF << 32;
+
+        let is_64bit = value & BAR_TYPE_MASK == BAR_64BIT;
+
+        //the next register holds the upper half of address
+        if is_64bit && index + 1 < count {
+            let upper = offset + 4;
+
+            base |= (address.read_u32(upper) as u64) << 32;
+            mask =
+                (mask & 0xFFFF_FFFF) | (size_mask(address, upper) as u64) << 32;
+        }
+
+        if mask & 0xFFFF_FFFF != 0 {
+            bars[index] = PciBar::Memory {
+                address: base,
+                size: (!mask).wrapping_add(1),
+                prefetchable: value & BAR_PREFETCHABLE != 0,
+            };
+        }
+
+        index += if is_64bit { 2 } else { 1 };
+    }
+
+    address.write_u16(COMMAND, command);
+
+    bars
+}
@@ -29,7 +29,7 @@ use alloc::{
 };
 use common::logging;
 use kernel_types::{fs::DirEntriesInfo, get_eax};
-use memory::PagingProperties;
+use memory::{Page, PagingProperties};
 use object::Handle;
 use task::Mutex;
 use user::kernel_buf::KernelBuf;
@@ -37,6 +37,7 @@ use user::kernel_buf::KernelBuf;
 #[cfg(not(target_arch = "x86"))]
 compile_error!("Operation system is suitable for x86 CPU only");
 
+mod acpi;
 mod boot;
 mod drivers;
 #[allow(dead_code)]
@@ -50,6 +51,9 @@ mod memory;
 mod common;
 pub mod error;
 mod object;
+mod pci;
+mod power;
+mod smp;
 mod task;
 mod user;
 
@@ -79,15 +83,23 @@ pub fn main() {
     memory::init_kernel_space(properties);
     log::info!("memory is initialized");
 
+    if let Err(cause) = acpi::init() {
+        log::warn!("ACPI is not available: {cause}");
+    }
+
     io::init();
     log::info!("interrupts are initialized");
 
+    pci::init();
+
     fs::init();
 
     memory::enable_task_switching();
 
     log::info!("Task switching is enabled");
 
+    smp::init();
+
     drivers::init();
 
     // let mutex = Arc::new(Mutex::new(3usize).unwrap());
@@ -137,6 +149,8 @@ extern "C" fn init_task(_args: *const ()) {
 
     unsafe { fs::mount_dev_fs() }.expect("Failed to mount dev-fs");
 
+    unsafe { fs::mount_sys_fs() }.expect("Failed to mount sys-fs");
+
     let output = fs::open("/dev/vga").unwrap();
 
     let input = fs::open("/dev/keybrd").unwrap();
@@ -203,7 +217,7 @@ extern "C" fn init_task(_args: *const ()) {
             continue;
         };
 
-        let cat_buf = KernelBuf::new(128).unwrap();
+        let cat_buf = KernelBuf::new(Page::SIZE).unwrap();
 
         should_print_name = true;
 
@@ -233,6 +247,32 @@ extern "C" fn init_task(_args: *const ()) {
                     let _status = work.wait().unwrap();
                 }
             }
+            Command::Meminfo => {
+                let mut text = String::new();
+
+                if drivers::show_meminfo(&mut text).is_ok() {
+                    let _work =
+                        fs::write(output, text.as_str().try_into().unwrap())
+                            .unwrap()
+                            .wait()
+                            .unwrap();
+                }
+            }
+            Command::Swapon(device, disk, start_sector, pages) => {
+                let msg = match drivers::block_queue(&device) {
+                    Some(queue) => {
+                        match memory::swap_on(queue, disk, start_sector, pages)
+                        {
+                            Ok(()) => format!("Swap on {device} is enabled"),
+                            Err(cause) => format!("Failed to swap on: {cause}"),
+                        }
+                    }
+                    None => format!("No block device {device}"),
+                };
+
+                let _ = fs::write(output, msg.as_str().try_into().unwrap())
+                    .unwrap();
+            }
             Command::Pwd => {
                 modinfo_content.reset();
                 let _work =
@@ -289,6 +329,8 @@ extern "C" fn init_task(_args: *const ()) {
                 continue;
             }
             Command::Files => todo!(),
+            Command::Shutdown => power::shutdown(power::PowerAction::PowerOff),
+            Command::Reboot => power::shutdown(power::PowerAction::Reboot),
         }
     }
 }
@@ -374,8 +416,11 @@ fn normalize_components(path: &str) -> String {
 #[derive(Debug, PartialEq)]
 pub enum Command {
     Pwd,
-    Cd(String),                   // cd <path>
-    Modinfo,                      // modinfo
+    Cd(String), // cd <path>
+    Modinfo,    // modinfo
+    Meminfo,    // meminfo
+    // swapon <device> <disk> <start_sector> <pages>
+    Swapon(String, usize, u32, usize),
     Ls,                           // ls
     Echo(String, Option<String>), // echo "Text" <optional_file_name>
     Cat(String),                  // cat <file_name>
@@ -383,6 +428,8 @@ pub enum Command {
     Mkdir(String),
     Files,
     Clear,
+    Shutdown,
+    Reboot,
 }
 
 // Parser state for collecting bytes
@@ -447,6 +494,8 @@ impl CommandParser {
             }
             "cls" | "clear" => Command::Clear,
             "pwd" => Command::Pwd,
+            "shutdown" => Command::Shutdown,
+            "reboot" => Command::Reboot,
             "files" => Command::Files,
             "modinfo" => {
                 if tokens.len() == 1 {
@@ -455,6 +504,29 @@ impl CommandParser {
                     Command::Invalid
                 }
             }
+            "meminfo" => {
+                if tokens.len() == 1 {
+                    Command::Meminfo
+                } else {
+                    Command::Invalid
+                }
+            }
+            "swapon" => {
+                if tokens.len() != 5 {
+                    return Command::Invalid;
+                }
+
+                match (tokens[2].parse(), tokens[3].parse(), tokens[4].parse())
+                {
+                    (Ok(disk), Ok(start_sector), Ok(pages)) => Command::Swapon(
+                        String::from(tokens[1]),
+                        disk,
+                        start_sector,
+                        pages,
+                    ),
+                    _ => Command::Invalid,
+                }
+            }
             "ls" => {
                 if tokens.len() == 1 {
                     Command::Ls
@@ -0,0 +1,83 @@
+//! Orderly shutdown: modules are stopped, file systems
+//! are synced and the machine is powered off or reset
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::{acpi, common, drivers, fs, io, smp};
+
+/// How long modules may handle `exit` (in milliseconds)
+const MODULE_EXIT_TIMEOUT_MS: usize = 1000;
+
+/// QEMU `isa-debug-exit` device
+const QEMU_EXIT_PORT: u16 = 0xF4;
+
+const KEYBOARD_STATUS: u16 = 0x64;
+const KEYBOARD_COMMAND: u16 = 0x64;
+const KEYBOARD_INPUT_FULL: u8 = 1 << 1;
+/// Pulse the reset line of processor
+const KEYBOARD_RESET: u8 = 0xFE;
+
+static IS_GOING_DOWN: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerAction {
+    PowerOff,
+    Reboot,
+}
+
+/// Stop the system. The method returns only
+/// if shutdown is already started by another task
+pub fn shutdown(action: PowerAction) {
+    if IS_GOING_DOWN.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    log::info!("The system is going down: {action:?}");
+
+    //the block devices are served by modules,
+    //thus modules are stopped last
+    fs::unmount_all();
+
+    drivers::flush_block_devices();
+
+    drivers::exit_modules(MODULE_EXIT_TIMEOUT_MS);
+
+    unsafe { io::disable() };
+
+    //the other processors must not touch devices during reset
+    smp::halt_others();
+
+    match action {
+        PowerAction::PowerOff => power_off(),
+        PowerAction::Reboot => reboot(),
+    }
+
+    log::error!("Failed to {action:?}. Halting");
+
+    loop {
+        unsafe { core::arch::asm!("hlt") };
+    }
+}
+
+fn power_off() {
+    if let Err(cause) = acpi::power::power_off() {
+        log::warn!("ACPI power off failed: {cause}");
+    }
+
+    //the machine is still running if it's not ACPI or QEMU ignores S5
+    unsafe { common::io::outb(QEMU_EXIT_PORT, 0) };
+}
+
+fn reboot() {
+    if let Err(cause) = acpi::power::reset() {
+        log::warn!("ACPI reset failed: {cause}");
+    }
+
+    unsafe {
+        while common::io::inb(KEYBOARD_STATUS) & KEYBOARD_INPUT_FULL != 0 {
+            common::io::wait();
+        }
+
+        common::io::outb(KEYBOARD_COMMAND, KEYBOARD_RESET);
+    }
+}
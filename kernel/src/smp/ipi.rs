@@ -0,0 +1,196 @@
+//! Inter-processor interrupts
+
+use core::arch::asm;
+use core::ops::Range;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use crate::{
+    common::atomics::SpinLock,
+    io::{self, apic, InterruptStackFrame},
+    memory::{InterruptGate, Page, SegmentSelector, VirtualAddress},
+};
+
+use super::{
+    apic_id_of, cpu_id, is_online, mark_offline, online_count, MAX_CPUS,
+};
+
+/// Let the processor pick the queued task
+pub const RESCHEDULE_VECTOR: u8 = 0xF0;
+/// Invalidate the requested range of TLB
+pub const TLB_FLUSH_VECTOR: u8 = 0xF1;
+/// Stop the processor until reset
+pub const HALT_VECTOR: u8 = 0xF2;
+
+/// The ranges larger than this are flushed by reloading CR3
+const MAX_INVLPG_PAGES: usize = 32;
+
+/// Only one shootdown is in flight at a time
+static SHOOTDOWN: SpinLock = SpinLock::new();
+static SHOOTDOWN_START: AtomicUsize = AtomicUsize::new(0);
+static SHOOTDOWN_END: AtomicUsize = AtomicUsize::new(0);
+static SHOOTDOWN_PENDING: [AtomicBool; MAX_CPUS] =
+    [const { AtomicBool::new(false) }; MAX_CPUS];
+
+pub(super) fn init() {
+    io::set(
+        RESCHEDULE_VECTOR as usize,
+        InterruptGate::with_naked_handler(
+            on_reschedule,
+            SegmentSelector::KERNEL_CODE,
+            io::INTERRUPT,
+        ),
+    );
+
+    io::set(
+        TLB_FLUSH_VECTOR as usize,
+        InterruptGate::with_naked_handler(
+            on_tlb_flush,
+            SegmentSelector::KERNEL_CODE,
+            io::INTERRUPT,
+        ),
+    );
+
+    io::set(
+        HALT_VECTOR as usize,
+        InterruptGate::with_naked_handler(
+            on_halt,
+            SegmentSelector::KERNEL_CODE,
+            io::INTERRUPT,
+        ),
+    );
+}
+
+/// Stop other online processors, used before the machine is powered off.
+/// Return when all of them have stopped
+pub fn halt_others() {
+    let current = cpu_id();
+
+    let targets = (0..MAX_CPUS)
+        .filter(|&cpu| cpu != current && is_online(cpu))
+        .filter_map(|cpu| Some((cpu, apic_id_of(cpu)?)));
+
+    for (_, apic_id) in targets.clone() {
+        apic::send_ipi(apic_id, HALT_VECTOR);
+    }
+
+    for (cpu, _) in targets {
+        while is_online(cpu) {
+            //the halting processor may wait for our shootdown
+            serve_shootdown(current);
+            core::hint::spin_loop();
+        }
+    }
+}
+
+/// Wake the processor to pick the task pushed to its queue
+pub fn reschedule(cpu: usize) {
+    if cpu == cpu_id() || !is_online(cpu) {
+        return;
+    }
+
+    if let Some(apic_id) = apic_id_of(cpu) {
+        apic::send_ipi(apic_id, RESCHEDULE_VECTOR);
+    }
+}
+
+/// Invalidate `range` in TLB of other online processors.
+/// The caller is responsible for own TLB.
+/// Return when all processors have flushed their TLB
+pub fn flush_tlb(range: Range<VirtualAddress>) {
+    if online_count() < 2 {
+        return;
+    }
+
+    let restore_interrupts = unsafe { io::status() };
+
+    unsafe { io::disable() };
+
+    let current = cpu_id();
+
+    //another processor can wait for our flush holding the lock
+    while SHOOTDOWN.try_acquire().is_err() {
+        serve_shootdown(current);
+        core::hint::spin_loop();
+    }
+
+    SHOOTDOWN_START.store(range.start, Ordering::SeqCst);
+    SHOOTDOWN_END.store(range.end, Ordering::SeqCst);
+
+    let targets = (0..MAX_CPUS)
+        .filter(|&cpu| cpu != current && is_online(cpu))
+        .filter_map(|cpu| Some((cpu, apic_id_of(cpu)?)));
+
+    for (cpu, apic_id) in targets.clone() {
+        SHOOTDOWN_PENDING[cpu].store(true, Ordering::SeqCst);
+        apic::send_ipi(apic_id, TLB_FLUSH_VECTOR);
+    }
+
+    for (cpu, _) in targets {
+        while SHOOTDOWN_PENDING[cpu].load(Ordering::SeqCst) {
+            core::hint::spin_loop();
+        }
+    }
+
+    SHOOTDOWN.release();
+
+    if restore_interrupts {
+        unsafe { io::enable() };
+    }
+}
+
+/// Flush TLB of `cpu` if another processor waits for it.
+/// The loops spinning with disabled interrupts should call this,
+/// otherwise the initiator holding the awaited lock never completes
+pub fn serve_shootdown(cpu: usize) {
+    if !SHOOTDOWN_PENDING[cpu].load(Ordering::SeqCst) {
+        return;
+    }
+
+    let start = SHOOTDOWN_START.load(Ordering::SeqCst);
+    let end = SHOOTDOWN_END.load(Ordering::SeqCst);
+
+    if (end - start) / Page::SIZE > MAX_INVLPG_PAGES {
+        unsafe {
+            asm! {
+                "mov eax, cr3",
+                "mov cr3, eax",
+                out("eax") _,
+                options(nostack, preserves_flags)
+            }
+        }
+    } else {
+        for page in (start..end).step_by(Page::SIZE) {
+            unsafe {
+                asm! {
+                    "invlpg [{0}]",
+                    in(reg) page,
+                    options(nostack, preserves_flags)
+                }
+            }
+        }
+    }
+
+    SHOOTDOWN_PENDING[cpu].store(false, Ordering::SeqCst);
+}
+
+//the idle task picks the task after wake up, see `task::idle_task`
+extern "x86-interrupt" fn on_reschedule(_frame: InterruptStackFrame) {
+    apic::complete();
+}
+
+extern "x86-interrupt" fn on_tlb_flush(_frame: InterruptStackFrame) {
+    serve_shootdown(cpu_id());
+
+    apic::complete();
+}
+
+//the interrupts stay disabled by the gate, so only NMI wakes the processor
+extern "x86-interrupt" fn on_halt(_frame: InterruptStackFrame) {
+    apic::complete();
+
+    mark_offline(cpu_id());
+
+    loop {
+        unsafe { asm!("cli", "hlt", options(nomem, nostack)) };
+    }
+}
@@ -0,0 +1,91 @@
+//! Symmetric multiprocessing.
+//! The bootstrap processor (BSP) wakes application processors (AP)
+//! listed in ACPI MADT. Each processor has own GDT, TSS and scheduler,
+//! the processors talk to each other via IPIs
+
+mod ipi;
+mod per_cpu;
+mod startup;
+
+use core::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+
+use crate::io::apic;
+
+pub use ipi::{flush_tlb, halt_others, reschedule, serve_shootdown};
+pub use per_cpu::PerCpu;
+
+/// The maximal count of processors served by kernel
+pub const MAX_CPUS: usize = 8;
+
+const NO_APIC_ID: u8 = u8::MAX;
+
+/// The LAPIC id of each processor. The index is the processor id
+static CPU_APIC_IDS: [AtomicU8; MAX_CPUS] =
+    [const { AtomicU8::new(NO_APIC_ID) }; MAX_CPUS];
+
+static ONLINE: [AtomicBool; MAX_CPUS] =
+    [const { AtomicBool::new(false) }; MAX_CPUS];
+static ONLINE_COUNT: AtomicUsize = AtomicUsize::new(1);
+
+/// The index of current processor. The bootstrap processor is always 0
+pub fn cpu_id() -> usize {
+    if !apic::is_enabled() {
+        return 0;
+    }
+
+    let apic_id = apic::local_id();
+
+    CPU_APIC_IDS
+        .iter()
+        .position(|id| id.load(Ordering::Relaxed) == apic_id)
+        .unwrap_or(0)
+}
+
+/// The count of processors running tasks
+pub fn online_count() -> usize {
+    ONLINE_COUNT.load(Ordering::SeqCst)
+}
+
+/// `true` if processor is running tasks and serves IPIs
+pub fn is_online(cpu: usize) -> bool {
+    ONLINE
+        .get(cpu)
+        .is_some_and(|online| online.load(Ordering::SeqCst))
+}
+
+fn mark_online(cpu: usize) {
+    if !ONLINE[cpu].swap(true, Ordering::SeqCst) && cpu != 0 {
+        ONLINE_COUNT.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+fn mark_offline(cpu: usize) {
+    if ONLINE[cpu].swap(false, Ordering::SeqCst) && cpu != 0 {
+        ONLINE_COUNT.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// The LAPIC id of processor if it is known
+fn apic_id_of(cpu: usize) -> Option<u8> {
+    let apic_id = CPU_APIC_IDS.get(cpu)?.load(Ordering::Relaxed);
+
+    (apic_id != NO_APIC_ID).then_some(apic_id)
+}
+
+/// Start all application processors.
+/// The bootstrap processor should already be able to schedule tasks
+pub fn init() {
+    if !apic::is_enabled() {
+        log::info!("SMP is disabled: no APIC");
+        return;
+    }
+
+    CPU_APIC_IDS[0].store(apic::local_id(), Ordering::SeqCst);
+    mark_online(0);
+
+    ipi::init();
+
+    startup::start_processors();
+
+    log::info!("{} processor(s) are online", online_count());
+}
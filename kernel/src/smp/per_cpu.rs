@@ -0,0 +1,53 @@
+use core::ops::Deref;
+
+use crate::common::atomics::UnsafeLazyCell;
+
+use super::{cpu_id, MAX_CPUS};
+
+/// The variable having own value for each processor.
+/// The value of current processor is accessed through `Deref`
+pub struct PerCpu<T> {
+    cells: [UnsafeLazyCell<T>; MAX_CPUS],
+}
+
+impl<T> PerCpu<T> {
+    pub const fn empty() -> Self {
+        Self {
+            cells: [const { UnsafeLazyCell::empty() }; MAX_CPUS],
+        }
+    }
+
+    /// Init the value of current processor
+    pub fn set(&self, value: T) {
+        self.cells[cpu_id()].set(value);
+    }
+
+    pub fn get(&self) -> &T {
+        self.cells[cpu_id()].get()
+    }
+
+    pub fn try_get(&self) -> Option<&T> {
+        self.cells[cpu_id()].try_get()
+    }
+
+    /// The value of given processor
+    pub fn of(&self, cpu: usize) -> Option<&T> {
+        self.cells.get(cpu)?.try_get()
+    }
+
+    /// Visit values of all initialized processors
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> {
+        self.cells
+            .iter()
+            .enumerate()
+            .filter_map(|(cpu, cell)| Some((cpu, cell.try_get()?)))
+    }
+}
+
+impl<T> Deref for PerCpu<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.get()
+    }
+}
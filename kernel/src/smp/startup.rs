@@ -0,0 +1,213 @@
+//! The startup of application processors via INIT-SIPI-SIPI sequence
+
+use core::ops::Range;
+use core::ptr;
+use core::sync::atomic::Ordering;
+
+use crate::{
+    acpi,
+    io::{self, apic},
+    memory::{
+        self, AllocError, MemoryAllocationFlag, Page, PagingFeatures,
+        PhysicalAddress, VirtualAddress,
+    },
+    task::{self, clocks, TASK_STACK_SIZE},
+};
+
+use super::{is_online, mark_online, CPU_APIC_IDS, MAX_CPUS, NO_APIC_ID};
+
+/// The physical page where application processors start in real mode.
+/// Duplicates `TRAMPOLINE_BASE` in ap_boot.asm
+const TRAMPOLINE_BASE: PhysicalAddress = 0x8000;
+
+const TRAMPOLINE_PAGE: Range<PhysicalAddress> =
+    TRAMPOLINE_BASE..TRAMPOLINE_BASE + Page::SIZE;
+
+/// How long the processor may boot before it is considered dead
+const STARTUP_TIMEOUT_MS: u32 = 100;
+
+#[derive(Debug, thiserror_no_std::Error)]
+enum StartupError {
+    #[error("Failed to alloc stack: {0}")]
+    NoStack(#[from] AllocError),
+    #[error("Processor doesn't respond")]
+    Timeout,
+}
+
+/// Mirror of `ApParams` in ap_boot.asm
+#[repr(C)]
+struct StartupParams {
+    directory: u32,
+    stack: u32,
+    entry: u32,
+    cpu: u32,
+    paging: u32,
+}
+
+/// Duplicates `PAGING_PAE` and `PAGING_NX` in ap_boot.asm
+const PAGING_PAE: u32 = 0x1;
+const PAGING_NX: u32 = 0x2;
+
+extern "C" {
+    static AP_TRAMPOLINE_START: u8;
+    static AP_TRAMPOLINE_PARAMS: u8;
+    static AP_TRAMPOLINE_END: u8;
+}
+
+fn trampoline_code() -> &'static [u8] {
+    unsafe {
+        let start = &raw const AP_TRAMPOLINE_START;
+        let end = &raw const AP_TRAMPOLINE_END;
+
+        core::slice::from_raw_parts(start, end.offset_from(start) as usize)
+    }
+}
+
+fn params() -> *mut StartupParams {
+    let offset = unsafe {
+        (&raw const AP_TRAMPOLINE_PARAMS)
+            .offset_from(&raw const AP_TRAMPOLINE_START) as usize
+    };
+
+    (low_memory(TRAMPOLINE_BASE) + offset) as *mut StartupParams
+}
+
+/// The paging mode of application processors
+fn paging_flags() -> u32 {
+    let features = PagingFeatures::enabled();
+
+    let mut flags = 0;
+
+    if features.pae {
+        flags |= PAGING_PAE;
+    }
+
+    if features.nx {
+        flags |= PAGING_NX;
+    }
+
+    flags
+}
+
+fn low_memory(physical: PhysicalAddress) -> VirtualAddress {
+    physical + memory::kernel_virtual_offset()
+}
+
+pub(super) fn start_processors() {
+    let Some(madt) = acpi::madt::find() else {
+        log::warn!("No MADT. Application processors are not started");
+        return;
+    };
+
+    let code = trampoline_code();
+
+    assert!(code.len() <= Page::SIZE, "AP trampoline exceeds page");
+
+    unsafe {
+        ptr::copy_nonoverlapping(
+            code.as_ptr(),
+            low_memory(TRAMPOLINE_BASE) as *mut u8,
+            code.len(),
+        )
+    };
+
+    //the trampoline enables paging while running at low address
+    if let Err(cause) = memory::map_identity(TRAMPOLINE_PAGE) {
+        log::warn!("Failed to map AP trampoline: {cause}");
+        return;
+    }
+
+    let bsp = apic::local_id();
+
+    let processors = madt
+        .processors
+        .iter()
+        .filter(|processor| processor.enabled && processor.apic_id != bsp);
+
+    let mut cpu = 1;
+
+    for processor in processors {
+        if cpu == MAX_CPUS {
+            log::warn!("Only {MAX_CPUS} processors are supported");
+            break;
+        }
+
+        match start_processor(cpu, processor.apic_id) {
+            Ok(()) => cpu += 1,
+            Err(cause) => {
+                log::warn!(
+                    "LAPIC#{} is not started: {cause}",
+                    processor.apic_id
+                )
+            }
+        }
+    }
+
+    memory::unmap_identity(TRAMPOLINE_PAGE);
+}
+
+fn start_processor(cpu: usize, apic_id: u8) -> Result<(), StartupError> {
+    let stack = memory::virtual_alloc(
+        TASK_STACK_SIZE,
+        MemoryAllocationFlag::CONTINOUS
+            | MemoryAllocationFlag::ZEROED
+            | MemoryAllocationFlag::READ_WRITE
+            | MemoryAllocationFlag::GUARDED,
+    )?;
+
+    unsafe {
+        params().write_volatile(StartupParams {
+            directory: memory::kernel_directory() as u32,
+            stack: (stack + TASK_STACK_SIZE) as u32,
+            entry: ap_main as VirtualAddress as u32,
+            cpu: cpu as u32,
+            paging: paging_flags(),
+        })
+    };
+
+    CPU_APIC_IDS[cpu].store(apic_id, Ordering::SeqCst);
+
+    apic::send_init(apic_id);
+    clocks::pit_wait(10);
+
+    //the second Startup IPI is sent if the first one is lost
+    for _ in 0..2 {
+        apic::send_startup(apic_id, (TRAMPOLINE_BASE / Page::SIZE) as u8);
+        clocks::pit_wait(1);
+
+        if is_online(cpu) {
+            return Ok(());
+        }
+    }
+
+    for _ in 0..STARTUP_TIMEOUT_MS {
+        if is_online(cpu) {
+            return Ok(());
+        }
+
+        clocks::pit_wait(1);
+    }
+
+    CPU_APIC_IDS[cpu].store(NO_APIC_ID, Ordering::SeqCst);
+
+    Err(StartupError::Timeout)
+}
+
+/// The first kernel code executed by application processor.
+/// Paging is enabled and the stack is set by trampoline,
+/// interrupts are disabled
+extern "C" fn ap_main(cpu: usize) -> ! {
+    memory::init_cpu(cpu);
+
+    io::load_table();
+
+    apic::init_cpu();
+
+    task::init_cpu();
+
+    mark_online(cpu);
+
+    log::info!("CPU#{cpu} (LAPIC#{}) is online", apic::local_id());
+
+    task::run();
+}
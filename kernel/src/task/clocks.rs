@@ -1,34 +1,77 @@
 #![allow(unused)]
 
+use core::arch::x86::{__cpuid, _rdtsc};
 use core::sync::atomic::AtomicUsize;
 use core::sync::atomic::Ordering::SeqCst;
 use kernel_types::declare_constants;
 
+use crate::common::atomics::UnsafeLazyCell;
 use crate::common::io;
+use crate::common::rtc;
 use crate::common::time::Timestamp;
+use crate::io::apic;
 use crate::io::pic;
 
-pub enum ClockType {
-    //the time since epoch
-    Epoch,
-    //the time from system boot
-    Boot,
-    //the total time consumed to corresponding thread
-    ThreadConsumed,
-}
+pub use kernel_types::time::ClockType;
 
 ///This upper level function is supposed to be really slow
 ///Do not use this in high performance places
-pub fn now(_clocks: ClockType) -> Timestamp {
-    todo!()
+pub fn now(clock: ClockType) -> Timestamp {
+    match clock {
+        ClockType::Boot => since_boot(),
+        ClockType::Epoch => {
+            let boot_epoch = BOOT_EPOCH.load(SeqCst) as u64;
+
+            Timestamp::from_secs(boot_epoch) + since_boot()
+        }
+        ClockType::ThreadConsumed => {
+            let consumed = crate::current_task!().metrics.cpu_time;
+
+            Timestamp::from_millis(consumed as u64)
+        }
+    }
 }
 
 declare_constants!(
     pub usize,
-    TIMEOUT = 20, "in milliseconds";
     TICKS = 2000000;
 );
+
+/// The period of timer tick in milliseconds
+#[cfg(not(feature = "high-res-timer"))]
+pub const TIMEOUT: usize = 20;
+/// The period of timer tick in milliseconds.
+/// The timer is running at 1000 Hz to wake sleeping tasks in time
+#[cfg(feature = "high-res-timer")]
+pub const TIMEOUT: usize = 1;
+
 static TIME: AtomicUsize = AtomicUsize::new(0);
+/// The epoch seconds read from RTC at boot
+static BOOT_EPOCH: AtomicUsize = AtomicUsize::new(0);
+static TSC: UnsafeLazyCell<TscClock> = UnsafeLazyCell::empty();
+
+const PIT_FREQUENCY: u32 = 1_193_180;
+const PIT_DIVISOR: u32 = PIT_FREQUENCY / (1000 / TIMEOUT as u32);
+/// The period of PIT one-shot used to measure frequency of other timers
+pub const CALIBRATION_MS: u32 = 10;
+
+/// The time stamp counter with known frequency
+struct TscClock {
+    /// The count of cycles per millisecond
+    khz: u64,
+    /// The counter value at calibration
+    base: u64,
+}
+
+impl TscClock {
+    fn nanos(&self) -> u64 {
+        let cycles = unsafe { _rdtsc() }.wrapping_sub(self.base);
+
+        //split to not overflow on multiplication
+        (cycles / self.khz) * 1_000_000
+            + (cycles % self.khz) * 1_000_000 / self.khz
+    }
+}
 
 pub fn get_time_since_boot() -> usize {
     TIME.load(SeqCst)
@@ -49,18 +92,131 @@ macro_rules! ticks_size {
 }
 
 pub fn init() {
-    const DEFAULT_FREQUENCY: u32 = 1_193_180;
-    let divisor: u32 = DEFAULT_FREQUENCY / 50;
-    let low = (divisor & 0xFF) as u8;
-    let high = ((divisor >> 8) & 0xFF) as u8;
+    let low = (PIT_DIVISOR & 0xFF) as u8;
+    let high = ((PIT_DIVISOR >> 8) & 0xFF) as u8;
 
     unsafe {
-        io::outb(0x43, 0x36);
+        //channel 0, low/high byte, mode 2 (rate generator):
+        //unlike square wave mode, the counter decrements by one per clock
+        io::outb(0x43, 0x34);
         io::outb(0x40, low);
         io::outb(0x40, high);
     };
+
+    if let Some(tsc) = calibrate_tsc() {
+        log::info!("TSC frequency: {} kHz", tsc.khz);
+
+        TSC.set(tsc);
+    } else {
+        log::warn!("TSC is not available. PIT is used as clock source");
+    }
+
+    let time = unsafe { rtc::read_rtc_time() };
+
+    BOOT_EPOCH.store(time.to_unix_secs() as usize, SeqCst);
 }
 
 pub fn update_time() {
     let _ = TIME.fetch_add(TIMEOUT, SeqCst);
 }
+
+/// The nanoseconds since boot which never goes backward.
+/// The precision is of TSC if available or of the timer tick otherwise
+pub fn monotonic_nanos() -> u64 {
+    match TSC.try_get() {
+        Some(tsc) => tsc.nanos(),
+        None => tick_since_boot().as_nanos(),
+    }
+}
+
+pub fn monotonic_millis() -> usize {
+    (monotonic_nanos() / 1_000_000) as usize
+}
+
+fn since_boot() -> Timestamp {
+    Timestamp::from_nanos(monotonic_nanos())
+}
+
+/// The time since boot with precision of PIT counter if PIT raises the ticks.
+/// Otherwise the ticks come from the local APIC timer and PIT is unrelated
+fn tick_since_boot() -> Timestamp {
+    if apic::is_enabled() {
+        return Timestamp::from_millis(TIME.load(SeqCst) as u64);
+    }
+
+    loop {
+        let ticks = TIME.load(SeqCst);
+        let passed = tick_passed_micros();
+
+        //the tick has happened while reading counter
+        if ticks == TIME.load(SeqCst) {
+            return Timestamp::from_micros(ticks as u64 * 1_000 + passed);
+        }
+    }
+}
+
+/// The microseconds passed since the last timer tick
+fn tick_passed_micros() -> u64 {
+    let count = unsafe {
+        //latch the counter of channel 0
+        io::outb(0x43, 0x00);
+
+        let low = io::inb(0x40) as u32;
+        let high = io::inb(0x40) as u32;
+
+        (high << 8) | low
+    };
+
+    let passed = PIT_DIVISOR.saturating_sub(count) as u64;
+
+    passed * 1_000_000 / PIT_FREQUENCY as u64
+}
+
+fn has_tsc() -> bool {
+    const TSC_FLAG: u32 = 1 << 4;
+
+    let features = unsafe { __cpuid(1) };
+
+    features.edx & TSC_FLAG != 0
+}
+
+/// Busy wait using the one-shot of PIT channel 2.
+/// It is used to calibrate other timers, thus interrupts should be disabled.
+/// The longest wait is about 50 milliseconds
+pub fn pit_wait(milliseconds: u32) {
+    let latch = PIT_FREQUENCY * milliseconds / 1000;
+
+    assert!(latch <= u16::MAX as u32, "PIT wait is too long");
+
+    unsafe {
+        let gate = io::inb(0x61);
+        //enable gate of channel 2 and disconnect speaker
+        io::outb(0x61, (gate & !0x02) | 0x01);
+        //channel 2, low/high byte, mode 0 (interrupt on terminal count)
+        io::outb(0x43, 0xB0);
+        io::outb(0x42, (latch & 0xFF) as u8);
+        io::outb(0x42, ((latch >> 8) & 0xFF) as u8);
+
+        //wait for the output of channel 2
+        while io::inb(0x61) & 0x20 == 0 {}
+
+        io::outb(0x61, gate);
+    }
+}
+
+/// Count TSC cycles during the PIT wait
+fn calibrate_tsc() -> Option<TscClock> {
+    if !has_tsc() {
+        return None;
+    }
+
+    let start = unsafe { _rdtsc() };
+
+    pit_wait(CALIBRATION_MS);
+
+    let end = unsafe { _rdtsc() };
+
+    let khz = end.saturating_sub(start) / CALIBRATION_MS as u64;
+
+    (khz != 0).then_some(TscClock { khz, base: start })
+}
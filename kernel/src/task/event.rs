@@ -12,6 +12,7 @@ pub struct Event {
 }
 
 impl Event {
+    #[track_caller]
     pub fn new() -> Result<Handle<Event>, AllocError> {
         alloc_root_object(Self {
             signal: AtomicBool::new(false),
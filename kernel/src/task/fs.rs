@@ -5,7 +5,7 @@ use crate::{
     object::Handle,
 };
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 #[repr(C)]
 pub struct FilePool {
     opened_files_count: usize,
@@ -16,7 +16,7 @@ pub struct FilePool {
 
 impl FilePool {
     pub fn get(&self, index: usize) -> Option<Handle<IndexNode>> {
-        self.files[index].clone()
+        self.files.get(index).cloned().flatten()
     }
 
     pub fn set(&mut self, index: usize, handle: Handle<IndexNode>) {
@@ -1,5 +1,5 @@
 use core::arch::asm;
-use core::mem::MaybeUninit;
+use core::mem::{self, MaybeUninit};
 use core::ptr;
 use core::sync::atomic::{AtomicUsize, Ordering};
 
@@ -8,17 +8,20 @@ use num_enum::{IntoPrimitive, TryFromPrimitive};
 
 use kernel_types::{bitflags, declare_constants, Zeroed};
 
-use crate::common::atomics::UnsafeLazyCell;
-
 use crate::error::KernelError;
 use crate::fs::{FileOpenMode, MountPoint, PathNode};
 
+use crate::io::ports::{self, IoBitmap};
 use crate::io::{pic, CallbackInfo};
 use crate::memory::{
-    MemoryAllocationFlag, Page, SegmentSelector, VirtualAddress,
+    MemoryAllocationFlag, Page, PhysicalAddress, Process, ProcessId,
+    SegmentSelector, VirtualAddress,
 };
+use crate::smp::{self, PerCpu};
 use crate::task::scheduler::SchedulerLock;
-use crate::{memory, object};
+use crate::{drivers, memory, object, user};
+
+use alloc::vec::Vec;
 
 mod arch;
 pub mod clocks;
@@ -30,6 +33,7 @@ mod pid;
 mod priority;
 mod scheduler;
 mod state;
+pub mod timer;
 
 pub use context::*;
 pub use event::*;
@@ -144,6 +148,78 @@ impl TaskState {
     pub fn set_kernel_stack(&mut self, esp0: usize) {
         self.esp0 = esp0 as u32; //the u32 is native word
     }
+
+    /// The state entering `routine` on `stack` with interrupts disabled.
+    /// The processor switches to it through task gate
+    pub fn kernel_entry(
+        routine: extern "C" fn() -> !,
+        stack: VirtualAddress,
+        directory: PhysicalAddress,
+    ) -> Self {
+        let data = u16::from(SegmentSelector::KERNEL_DATA);
+
+        let mut state = Self::null();
+        state.eip = routine as usize as u32;
+        state.esp = stack as u32;
+        state.cr3 = directory as u32;
+        //only the reserved bit is set
+        state.flags = 0x2;
+        state.cs = SegmentSelector::KERNEL_CODE.into();
+        state.ss = data;
+        state.ds = data;
+        state.es = data;
+        state.fs = data;
+        state.gs = data;
+        state.set_io_map(NO_IO_BITMAP);
+
+        state
+    }
+}
+
+/// The offset of I/O bitmap beyond the segment limit denies all ports
+pub const NO_IO_BITMAP: u16 = 0xFFFF;
+
+/// Task state followed by I/O permission bitmap.
+/// The processor may read the byte after bitmap, so all its bits are set
+#[repr(C)]
+pub struct TaskStateSegment {
+    pub state: TaskState,
+    io_bitmap: IoBitmap,
+    io_bitmap_end: u8,
+    /// The process and generation of the copied bitmap
+    loaded: Option<(ProcessId, usize)>,
+}
+
+impl TaskStateSegment {
+    pub const LIMIT: usize = mem::offset_of!(Self, io_bitmap_end);
+
+    pub const fn null() -> Self {
+        Self {
+            state: TaskState::null(),
+            io_bitmap: IoBitmap::deny_all(),
+            io_bitmap_end: u8::MAX,
+            loaded: None,
+        }
+    }
+
+    /// Allow the ports of running process. The bitmap is
+    /// copied only if another process has been loaded before
+    pub fn load_io_bitmap(&mut self, bitmap: Option<(ProcessId, &IoBitmap)>) {
+        let Some((process, bitmap)) = bitmap else {
+            self.state.set_io_map(NO_IO_BITMAP);
+            return;
+        };
+
+        let loaded = Some((process, ports::generation()));
+
+        if self.loaded != loaded {
+            self.io_bitmap.copy_from(bitmap);
+            self.loaded = loaded;
+        }
+
+        self.state
+            .set_io_map(mem::offset_of!(Self, io_bitmap) as u16);
+    }
 }
 
 declare_constants!(
@@ -172,7 +248,8 @@ pub fn new_task(
         TASK_STACK_SIZE,
         MemoryAllocationFlag::CONTINOUS
             | MemoryAllocationFlag::ZEROED
-            | MemoryAllocationFlag::READ_WRITE,
+            | MemoryAllocationFlag::READ_WRITE
+            | MemoryAllocationFlag::GUARDED,
     )?;
 
     let task_id = NEXT_TASK_ID.fetch_add(1, Ordering::SeqCst);
@@ -221,14 +298,140 @@ pub fn new_task(
     Ok(task)
 }
 
+/// Queue the task on the next online cpu (round robin).
+/// The task never migrates to another cpu
 pub fn submit_task(task: &'static mut RunningTask) {
-    log::debug!("Submitting task#{}", task.id);
+    let cpu = next_cpu();
+
+    log::debug!("Submitting task#{} to CPU#{cpu}", task.id);
+
+    let scheduler = SCHEDULER.of(cpu).expect("CPU without scheduler");
+
+    scheduler.access_lock().push_task(task);
+
+    smp::reschedule(cpu);
+}
+
+fn next_cpu() -> usize {
+    loop {
+        let cpu = NEXT_CPU.fetch_add(1, Ordering::Relaxed) % smp::MAX_CPUS;
+
+        if cpu == 0 || smp::is_online(cpu) {
+            break cpu;
+        }
+    }
+}
+
+/// The scheduling statistics of single task
+pub struct TaskStat {
+    pub id: TaskId,
+    pub cpu: usize,
+    pub priority: TaskPriority,
+    pub status: &'static str,
+    pub cpu_time: usize,
+    pub vruntime: u64,
+    pub exec_count: usize,
+}
+
+impl From<&RunningTask> for TaskStat {
+    fn from(task: &RunningTask) -> Self {
+        let status = match task.status {
+            TaskStatus::Embryo => "embryo",
+            TaskStatus::Running => "running",
+            TaskStatus::Sleeping => "sleeping",
+            TaskStatus::Blocked(_) => "blocked",
+            TaskStatus::Killed => "killed",
+        };
+
+        Self {
+            id: task.id,
+            cpu: 0,
+            priority: task.priority,
+            status,
+            cpu_time: task.metrics.cpu_time,
+            vruntime: task.metrics.vruntime,
+            exec_count: task.metrics.exec_count,
+        }
+    }
+}
 
-    SCHEDULER.access_lock().push_task(task);
+pub fn stats() -> Vec<TaskStat> {
+    let mut stats = Vec::new();
+
+    for (cpu, scheduler) in SCHEDULER.iter() {
+        scheduler.access_lock().for_each_task(|task| {
+            stats.push(TaskStat {
+                cpu,
+                ..TaskStat::from(task)
+            })
+        });
+    }
+
+    stats
 }
 
-pub fn terminate(_code: i32) -> ! {
-    todo!()
+/// The processes of tasks known to schedulers
+pub fn processes() -> Vec<Process> {
+    let mut processes = Vec::<Process>::new();
+
+    for (_, scheduler) in SCHEDULER.iter() {
+        scheduler.access_lock().for_each_task(|task| {
+            let Some(process) = task.process.as_ref() else {
+                return;
+            };
+
+            if processes.iter().all(|known| known.id != process.id) {
+                processes.push(process.clone());
+            }
+        });
+    }
+
+    processes
+}
+
+/// `true` if task is known to any scheduler
+pub fn is_alive(id: TaskId) -> bool {
+    SCHEDULER.iter().any(|(_, scheduler)| {
+        let mut found = false;
+
+        scheduler
+            .access_lock()
+            .for_each_task(|task| found |= task.id == id);
+
+        found
+    })
+}
+
+/// Release the resources kept for process outside of its state,
+/// called when the last task of process is terminated
+fn release_process(process: &Process) {
+    //the shared pages are read through the address space of process
+    unsafe { memory::switch_to_task(current_task!()) };
+
+    memory::sync_file_maps(process);
+
+    user::kernel_buf::reclaim_process(process.id);
+
+    //the hook references buffers of process
+    drivers::release_irq(process);
+
+    ports::release(process);
+}
+
+pub fn terminate(code: i32) -> ! {
+    let task = current_task!();
+
+    log::debug!("task#{} is terminated: {code}", task.id);
+
+    if let Some(process) =
+        task.process.clone().filter(|process| process.detach_task())
+    {
+        release_process(&process);
+    }
+
+    SCHEDULER.switch_lock().terminate();
+
+    unreachable!("Terminated task is scheduled again");
 }
 
 //run the kernel main loop
@@ -247,11 +450,26 @@ pub fn init() -> CallbackInfo {
     let idle = new_task(idle_task, 42 as _, TaskPriority::Idle)
         .expect("Failed to alloc idle task");
 
-    SCHEDULER.set(SchedulerLock::new(idle));
+    let scheduler =
+        SchedulerLock::new(idle).expect("Failed to alloc scheduler");
+
+    SCHEDULER.set(scheduler);
 
     CallbackInfo::new(on_timer, ptr::null_mut())
 }
 
+/// Create the scheduler of application processor.
+/// The timer interrupt is shared with bootstrap processor
+pub fn init_cpu() {
+    let idle = new_task(idle_task, 42 as _, TaskPriority::Idle)
+        .expect("Failed to alloc idle task");
+
+    let scheduler =
+        SchedulerLock::new(idle).expect("Failed to alloc scheduler");
+
+    SCHEDULER.set(scheduler);
+}
+
 #[no_mangle]
 extern "C" fn idle_task(arg: *const ()) {
     let v: usize;
@@ -275,6 +493,9 @@ extern "C" fn idle_task(arg: *const ()) {
                 options(preserves_flags, nomem, nostack)
             }
         }
+
+        //the task can be submitted by another cpu
+        SCHEDULER.switch_lock().reschedule();
     }
 }
 
@@ -283,7 +504,15 @@ fn on_timer(
     _context: *const (),
     frame: &mut *mut TaskContext,
 ) -> bool {
-    clocks::update_time();
+    //the system time is counted by bootstrap processor
+    if smp::cpu_id() == 0 {
+        clocks::update_time();
+    }
+
+    let expired = SCHEDULER.access_lock().timers().advance();
+
+    timer::dispatch(expired);
+
     let old_context = current_task!().context_ptr();
 
     SCHEDULER.access_lock().on_tick();
@@ -316,5 +545,6 @@ fn on_timer(
     true
 }
 
-pub static SCHEDULER: UnsafeLazyCell<SchedulerLock> = UnsafeLazyCell::empty();
+pub static SCHEDULER: PerCpu<SchedulerLock> = PerCpu::empty();
 static NEXT_TASK_ID: AtomicUsize = AtomicUsize::new(1);
+static NEXT_CPU: AtomicUsize = AtomicUsize::new(0);
@@ -6,11 +6,7 @@ use core::{
 use crate::{
     impl_container,
     memory::AllocError,
-    object::{
-        self,
-        runtime::{self, critical_section},
-        Handle, Object, ObjectContainer,
-    },
+    object::{self, runtime, Handle, Object, ObjectContainer},
 };
 
 pub struct Mutex<T: Sized> {
@@ -32,6 +28,7 @@ pub struct MutexObject {
 }
 
 impl MutexObject {
+    #[track_caller]
     pub fn new() -> Result<Handle<Self>, AllocError> {
         object::alloc_root_object(MutexObject {
             object: MutexObject::new_root_object(),
@@ -101,6 +98,7 @@ unsafe impl<T: Sized + Send> Sync for Mutex<T> {}
 unsafe impl<T: Sized + Send> Send for Mutex<T> {}
 
 impl<T: Sized> Mutex<T> {
+    #[track_caller]
     pub fn new(value: T) -> Result<Mutex<T>, AllocError> {
         let mutex = MutexObject::new()?;
 
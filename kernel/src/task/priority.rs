@@ -26,6 +26,16 @@ impl TaskPriority {
             TaskPriority::Kernel => 200,
         }
     }
+
+    /// The share of processor time relative to other tasks
+    pub fn weight(self) -> usize {
+        match self {
+            TaskPriority::Idle => 1,
+            TaskPriority::User(level) => 256 + 16 * level as usize,
+            TaskPriority::Module(level) => 1024 + 64 * level as usize,
+            TaskPriority::Kernel => 4096,
+        }
+    }
 }
 
 impl core::fmt::Display for TaskPriority {
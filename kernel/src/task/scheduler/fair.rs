@@ -0,0 +1,250 @@
+use kernel_types::collections::LinkedList;
+
+use crate::task::RunningTask;
+
+use super::SchedulingPolicy;
+
+/// The period (in milliseconds) in which each ready task should run once
+const TARGET_LATENCY: usize = 120;
+/// The minimal time slice (one timer tick)
+const MIN_GRANULARITY: usize = crate::task::clocks::TIMEOUT;
+/// The virtual time a woken task can be ahead of the others.
+/// It allows interactive tasks to preempt cpu bound ones
+const WAKEUP_BONUS: usize = TARGET_LATENCY / 2;
+
+const BASE_WEIGHT: u64 = 1024;
+/// The virtual runtime is counted in nanoseconds,
+/// so a tick of heavy task isn't rounded down to zero
+const NANOS_PER_MILLI: u64 = 1_000_000;
+
+const fn virtual_time(millis: usize) -> u64 {
+    millis as u64 * NANOS_PER_MILLI
+}
+
+/// The policy tries to give each task the processor time
+/// proportional to the weight of its priority.
+/// The task with the least virtual runtime is running next,
+/// so the task of any class eventually runs (no starvation)
+pub struct FairPolicy {
+    tasks: LinkedList<'static, RunningTask>,
+    /// The sum of weights of queued tasks
+    total_weight: usize,
+    /// Monotonic lower bound of virtual runtime of ready tasks
+    min_vruntime: u64,
+}
+
+impl FairPolicy {
+    pub const fn new() -> Self {
+        Self {
+            tasks: LinkedList::empty(),
+            total_weight: 0,
+            min_vruntime: 0,
+        }
+    }
+
+    fn time_slice(&self, task: &RunningTask) -> usize {
+        let weight = task.priority.weight();
+        let total_weight = self.total_weight + weight;
+
+        usize::max(MIN_GRANULARITY, TARGET_LATENCY * weight / total_weight)
+    }
+
+    fn update_min_vruntime(&mut self, current: &RunningTask) {
+        let min_queued = self
+            .tasks
+            .iter()
+            .map(|task| task.metrics.vruntime)
+            .min()
+            .unwrap_or(current.metrics.vruntime);
+
+        let min_vruntime = u64::min(min_queued, current.metrics.vruntime);
+
+        self.min_vruntime = u64::max(self.min_vruntime, min_vruntime);
+    }
+}
+
+impl SchedulingPolicy for FairPolicy {
+    fn enqueue(&mut self, task: &'static mut RunningTask) {
+        //the task was sleeping or it is new one:
+        //it should not use the whole time it has missed
+        let floor =
+            self.min_vruntime.saturating_sub(virtual_time(WAKEUP_BONUS));
+
+        task.metrics.vruntime = u64::max(task.metrics.vruntime, floor);
+        task.metrics.elapsed = 0;
+
+        self.total_weight += task.priority.weight();
+        self.tasks.push_back(task.as_node());
+    }
+
+    fn pick_next(&mut self) -> Option<&'static mut RunningTask> {
+        let id = self
+            .tasks
+            .iter()
+            .min_by_key(|task| task.metrics.vruntime)
+            .map(|task| task.id)?;
+
+        let task = self.tasks.remove_by(|task| task.id == id)?;
+
+        self.total_weight -= task.priority.weight();
+
+        task.metrics.exec_count += 1;
+        task.metrics.base_duration = self.time_slice(task);
+
+        Some(task)
+    }
+
+    fn tick(&mut self, current: &mut RunningTask, elapsed: usize) -> bool {
+        let weight = current.priority.weight() as u64;
+
+        current.metrics.elapsed += elapsed;
+        current.metrics.vruntime +=
+            u64::max(1, virtual_time(elapsed) * BASE_WEIGHT / weight);
+
+        self.update_min_vruntime(current);
+
+        let Some(candidate) =
+            self.tasks.iter().map(|task| task.metrics.vruntime).min()
+        else {
+            return false;
+        };
+
+        let is_expired =
+            current.metrics.elapsed >= current.metrics.base_duration;
+
+        //the woken task is far behind the current
+        let is_outrun = candidate + virtual_time(MIN_GRANULARITY)
+            < current.metrics.vruntime;
+
+        is_expired || is_outrun
+    }
+
+    fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    fn for_each(&self, f: &mut dyn FnMut(&RunningTask)) {
+        self.tasks.iter().for_each(|task| f(task));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+    extern crate std;
+
+    use std::boxed::Box;
+
+    use kernel_types::collections::ListNode;
+
+    use crate::task::{Task, TaskId, TaskMetrics, TaskPriority, TaskStatus};
+
+    use super::*;
+
+    /// The task is leaked, it has no stack and is never running
+    fn new_task(
+        id: TaskId,
+        priority: TaskPriority,
+        vruntime: u64,
+    ) -> &'static mut RunningTask {
+        let task = Task {
+            kernel_stack_bottom: 0,
+            id,
+            priority,
+            status: TaskStatus::Embryo,
+            start_time: 0,
+            process: None,
+            opened_files: Default::default(),
+            metrics: TaskMetrics {
+                elapsed: 0,
+                lock_count: 0,
+                exec_count: 0,
+                jump_ratio: 0,
+                base_duration: priority.static_duration(),
+                cpu_time: 0,
+                vruntime,
+            },
+        };
+
+        Box::leak(Box::new(RunningTask {
+            node: ListNode::empty(),
+            task,
+        }))
+    }
+
+    #[test]
+    fn pick_least_vruntime_test() {
+        let mut policy = FairPolicy::new();
+
+        policy.enqueue(new_task(1, TaskPriority::User(0), virtual_time(30)));
+        policy.enqueue(new_task(2, TaskPriority::User(0), virtual_time(10)));
+        policy.enqueue(new_task(3, TaskPriority::Kernel, virtual_time(20)));
+
+        let order: [TaskId; 3] =
+            core::array::from_fn(|_| policy.pick_next().unwrap().id);
+
+        assert_eq!(order, [2, 3, 1]);
+        assert!(policy.pick_next().is_none());
+        assert!(policy.is_empty());
+    }
+
+    #[test]
+    fn weighted_vruntime_test() {
+        let mut policy = FairPolicy::new();
+
+        let heavy = new_task(1, TaskPriority::Kernel, 0);
+        let light = new_task(2, TaskPriority::User(0), 0);
+
+        policy.tick(heavy, 20);
+        policy.tick(light, 20);
+
+        //the vruntime grows inversely to the weight of priority
+        let ratio =
+            TaskPriority::Kernel.weight() / TaskPriority::User(0).weight();
+
+        assert_eq!(
+            heavy.metrics.vruntime * ratio as u64,
+            light.metrics.vruntime
+        );
+        assert_eq!(heavy.metrics.elapsed, 20);
+    }
+
+    #[test]
+    fn preempt_outrun_test() {
+        let mut policy = FairPolicy::new();
+
+        let current = new_task(1, TaskPriority::User(0), virtual_time(500));
+        current.metrics.base_duration = usize::MAX;
+
+        //the current task runs alone
+        assert!(!policy.tick(current, 0));
+
+        policy.enqueue(new_task(2, TaskPriority::User(0), virtual_time(500)));
+
+        //the queued task is not far behind and the slice isn't expired
+        assert!(!policy.tick(current, 0));
+
+        assert!(policy.tick(current, MIN_GRANULARITY * 2));
+    }
+
+    #[test]
+    fn wakeup_floor_test() {
+        let mut policy = FairPolicy::new();
+
+        let current = new_task(1, TaskPriority::User(0), virtual_time(2000));
+
+        policy.enqueue(new_task(2, TaskPriority::User(0), virtual_time(1000)));
+        policy.tick(current, 0);
+
+        //the woken task can't use the whole time it has missed
+        let woken = new_task(3, TaskPriority::User(0), 0);
+        policy.enqueue(woken);
+
+        let floor = virtual_time(1000) - virtual_time(WAKEUP_BONUS);
+
+        let picked = policy.pick_next().unwrap();
+
+        assert_eq!(picked.id, 3);
+        assert_eq!(picked.metrics.vruntime, floor);
+    }
+}
@@ -6,8 +6,9 @@ use core::{
 };
 
 use crate::{
+    common::atomics::RecursiveSpinLock,
     current_task, io, log_module,
-    memory::{self, SegmentSelector},
+    memory::{self, AllocError, SegmentSelector},
     task::{switch_context, RunningTask, TaskContext},
 };
 
@@ -15,6 +16,8 @@ use super::TaskScheduler;
 
 pub struct SchedulerLock {
     scheduler: UnsafeCell<TaskScheduler>,
+    //other cpus push tasks and unblock them remotely
+    lock: RecursiveSpinLock,
 }
 
 pub struct SchedulerGuard<'a> {
@@ -30,9 +33,13 @@ unsafe impl Sync for SchedulerLock {}
 
 impl<'a> Drop for SchedulerGuard<'a> {
     fn drop(&mut self) {
-        if self.allow_task_switching
-            && !ptr::eq(self.old_context, self.current.context_ptr())
-        {
+        let should_switch = self.allow_task_switching
+            && !ptr::eq(self.old_context, self.current.context_ptr());
+
+        //the switched task never returns here until it is scheduled again
+        self.lock.lock.release();
+
+        if should_switch {
             {
                 log::trace!("Task switching");
                 let frame: &TaskContext = unsafe { &*self.old_context };
@@ -67,20 +74,24 @@ impl<'a> core::ops::DerefMut for SchedulerGuard<'a> {
 
 ///all time-consuming methods are marked as unsafe
 impl SchedulerLock {
-    pub fn new(task: &'static mut RunningTask) -> Self {
-        Self {
-            scheduler: UnsafeCell::new(TaskScheduler::new(task)),
-        }
+    pub fn new(task: &'static mut RunningTask) -> Result<Self, AllocError> {
+        Ok(Self {
+            scheduler: UnsafeCell::new(TaskScheduler::new(task)?),
+            lock: RecursiveSpinLock::new(),
+        })
     }
 
     //let's say scheduler is locked somewhere
     //if it has been locked (interrupts disabled) then no other way
     //to obtain schedular in another thread (this thread is never executed)
+    //other cpus spin until the lock is released
     pub fn switch_lock(&self) -> SchedulerGuard<'_> {
         let restore_interrupts = unsafe { io::status() };
 
         unsafe { io::disable() };
 
+        self.lock.acquire();
+
         let old_context =
             unsafe { &*self.scheduler.get() }.current.context_ptr();
 
@@ -97,6 +108,8 @@ impl SchedulerLock {
 
         unsafe { io::disable() };
 
+        self.lock.acquire();
+
         let old_context = unsafe { &mut *self.scheduler.get() }
             .current_task()
             .context_ptr();
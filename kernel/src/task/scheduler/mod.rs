@@ -1,6 +1,8 @@
 #![allow(unused)]
 
+mod fair;
 mod lock;
+mod policy;
 mod queue;
 
 use core::arch::asm;
@@ -10,28 +12,36 @@ use core::sync::atomic::Ordering::Relaxed;
 use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use core::{mem, ptr};
 
+use alloc::boxed::Box;
+
 use kernel_types::collections::{
     BorrowingLinkedList, HashTable, LinkedList, ListNode,
 };
-use queue::TaskQueue;
 
+use crate::memory::AllocError;
+use crate::object;
 use crate::object::{Handle, ObjectContainer};
-use crate::task::{switch_context, RunningTask, TaskContext, TaskStatus};
+use crate::task::{
+    clocks, switch_context, RunningTask, TaskContext, TaskStatus,
+};
 use crate::{io, log_module, memory, ticks_size};
-use crate::{object, ticks_now};
 
-use super::{BlockedTask, RunningTaskBox, Task, TaskPriority};
+use super::timer::{self, TimerAction, TimerWheel};
+use super::{BlockedTask, RunningTaskBox, Task, TaskId, TaskPriority};
 
+pub use fair::FairPolicy;
 pub use lock::SchedulerLock;
+pub use policy::{PriorityPolicy, SchedulingPolicy};
 
 pub struct TaskScheduler {
-    // the queue of delayed tasks
-    // which will be running on next stage
-    delayed: &'static mut TaskQueue,
-    running: &'static mut TaskQueue,
+    policy: &'static mut dyn SchedulingPolicy,
 
     blocked: LinkedList<'static, BlockedTask>,
     sleeping: LinkedList<'static, RunningTask>,
+    /// The sleeping tasks which failed to arm the timer.
+    /// Their deadlines are checked on each tick
+    overdue_sleeping: LinkedList<'static, RunningTask>,
+    timers: TimerWheel,
 
     current: &'static mut RunningTask,
 
@@ -41,12 +51,22 @@ pub struct TaskScheduler {
 }
 
 impl TaskScheduler {
-    pub fn new(current: &'static mut RunningTask) -> Self {
+    pub fn new(current: &'static mut RunningTask) -> Result<Self, AllocError> {
+        let policy = Box::leak(Box::try_new(FairPolicy::new())?);
+
+        Ok(Self::with_policy(current, policy))
+    }
+
+    pub fn with_policy(
+        current: &'static mut RunningTask,
+        policy: &'static mut dyn SchedulingPolicy,
+    ) -> Self {
         Self {
             current,
-            running: TaskQueue::new_leaked(),
-            delayed: TaskQueue::new_leaked(),
+            policy,
             sleeping: LinkedList::empty(),
+            overdue_sleeping: LinkedList::empty(),
+            timers: TimerWheel::new(),
             blocked: LinkedList::empty(),
             idle_tasks: LinkedList::empty(),
         }
@@ -56,15 +76,13 @@ impl TaskScheduler {
     /// 1. It will never be terminated (busy loop)
     /// 2. It requires no time constraints
     pub fn push_task(&mut self, task: &'static mut RunningTask) {
-        task.metrics.elapsed = 0;
-        task.metrics.base_duration = task.priority.static_duration();
-
         if task.priority == TaskPriority::Idle {
             log::trace!("Pushing idle task");
+            task.metrics.elapsed = 0;
             self.idle_tasks.push_back(task);
         } else {
             log::trace!("Pushing task#{}", task.id);
-            self.delayed.push(task);
+            self.policy.enqueue(task);
         }
     }
 
@@ -77,10 +95,7 @@ impl TaskScheduler {
     ) {
         log::debug!("Blocking: 0x{:x}", handle.as_addr());
 
-        let mut next_task = self
-            .running
-            .take_next()
-            .unwrap_or_else(|| self.reschedule());
+        let mut next_task = self.next_task();
 
         mem::swap(&mut self.current, &mut next_task);
 
@@ -100,8 +115,9 @@ impl TaskScheduler {
         on_block(&handle);
     }
 
-    /// unblock task with highest priority on object handle
-    pub fn unblock_on(&mut self, handle: object::RawHandle) {
+    /// unblock task with highest priority on object handle.
+    /// Return `false` if no task is blocked on handle
+    pub fn unblock_on(&mut self, handle: object::RawHandle) -> bool {
         log::debug!("Unblocking: 0x{handle:x?}");
 
         let mut max_priority = Option::<TaskPriority>::None;
@@ -115,7 +131,7 @@ impl TaskScheduler {
 
         let Some(task_id) = maybe_task_id else {
             log::trace!("No tasks to unblock on 0x{handle:x?}");
-            return;
+            return false;
         };
 
         log::debug!("task#{task_id} will be unblocked");
@@ -127,103 +143,99 @@ impl TaskScheduler {
             .into_running();
 
         self.push_task(unblocked_task);
+
+        true
     }
 
     ///add current task to sleeping list
+    ///the task is woken by timer after `period` milliseconds
     pub fn sleep(&mut self, period: usize) {
-        let mut next_task = self
-            .running
-            .take_next()
-            .unwrap_or_else(|| self.reschedule());
+        let deadline = clocks::monotonic_millis() + period;
 
-        mem::swap(&mut next_task, &mut self.current);
+        let armed = self.timers.insert(
+            timer::ticks_of(period),
+            None,
+            TimerAction::Wake(self.current.id),
+        );
 
-        let sleeping = next_task.into_sleeping(period);
+        let mut next_task = self.next_task();
 
-        self.sleeping.push_back(sleeping.as_node());
-    }
+        mem::swap(&mut next_task, &mut self.current);
 
-    pub fn on_tick(&mut self) {
-        let mut iter = self.sleeping.iter_mut();
+        let sleeping = next_task.into_sleeping(deadline).as_node();
 
-        loop {
-            let Some(task) = iter.next() else {
-                break;
-            };
+        match armed {
+            Ok(_) => self.sleeping.push_back(sleeping),
+            Err(err) => {
+                log::warn!("Failed to alloc sleep timer: {err}");
 
-            //fixme: time overflow?
-            if task.start_time < ticks_now!() {
-                let awaked_task = iter.unlink_watched().unwrap();
+                self.overdue_sleeping.push_back(sleeping);
+            }
+        }
+    }
 
-                awaked_task.metrics.elapsed = 0;
+    pub fn timers(&mut self) -> &mut TimerWheel {
+        &mut self.timers
+    }
 
-                if awaked_task.priority == TaskPriority::Idle {
-                    self.idle_tasks.push_back(awaked_task);
-                } else {
-                    self.running.push(awaked_task);
-                }
-            }
+    /// Make the sleeping or blocked task running
+    pub fn wake(&mut self, task_id: TaskId) {
+        if let Some(task) = self.sleeping.remove_by(|task| task.id == task_id) {
+            self.push_task(task);
+        } else if let Some(task) =
+            self.overdue_sleeping.remove_by(|task| task.id == task_id)
+        {
+            self.push_task(task);
+        } else if let Some(task) =
+            self.blocked.remove_by(|task| task.id == task_id)
+        {
+            log::debug!("task#{task_id} is woken by timer");
+
+            self.push_task(task.into_running());
         }
+    }
 
+    pub fn on_tick(&mut self) {
         log::trace!("On tick. Task = {}", self.current.id);
 
-        self.current.metrics.elapsed += ticks_size!();
+        self.current.metrics.cpu_time += ticks_size!();
 
-        if let Some(candidate) = self.running.probe_next() {
-            assert!(
-                candidate.metrics.elapsed <= candidate.metrics.base_duration,
-            );
+        self.wake_overdue();
 
-            if candidate.priority > self.current.priority {
+        let should_switch = if self.current.priority == TaskPriority::Idle {
+            !self.policy.is_empty()
+        } else {
+            self.policy.tick(self.current, ticks_size!())
+        };
+
+        if should_switch {
+            if let Some(mut task) = self.policy.pick_next() {
                 log::debug!(
-                    "Replacing task {} with higher {}",
+                    "Replacing task {} with {}",
                     self.current.id,
-                    candidate.id
+                    task.id
                 );
 
-                let mut task = self.running.take_next().expect("Success probe");
-
-                mem::swap(&mut task, &mut self.current);
-
-                log::debug!("Old = {}. New = {}", task.id, self.current.id);
+                mem::swap(&mut self.current, &mut task);
 
-                //the problem: if task has too small execution time
-                //should it be executed at whole?
-                if task.priority == TaskPriority::Idle {
-                    task.metrics.elapsed = 0;
-                    self.idle_tasks.push_back(task);
-                } else if task.metrics.elapsed >= task.metrics.base_duration {
-                    task.metrics.elapsed = 0;
-                    self.delayed.push(task);
-                } else {
-                    self.running.push(task);
-                }
+                self.push_task(task);
             }
         }
 
-        if self.current.metrics.elapsed > self.current.metrics.base_duration {
-            self.current.metrics.elapsed = 0;
-
-            if let Some(mut task) = self.running.take_next() {
-                mem::swap(&mut self.current, &mut task);
-
-                self.push_task(task);
-            } else if self.running.is_empty() && !self.delayed.is_empty() {
-                let mut task = self.reschedule();
+        log::trace!("On tick end. Task = {}", self.current.id);
+    }
 
-                mem::swap(&mut self.current, &mut task);
+    /// Give the cpu to the queued task if the idle task is running
+    pub fn reschedule(&mut self) {
+        if self.current.priority != TaskPriority::Idle {
+            return;
+        }
 
-                self.push_task(task);
-            } else {
-                assert!(self.running.is_empty() && self.delayed.is_empty());
-                //do nothing as task is already running
-                //and we cannot take another task
+        if let Some(mut task) = self.policy.pick_next() {
+            mem::swap(&mut self.current, &mut task);
 
-                log::debug!("No more tasks");
-            }
+            self.push_task(task);
         }
-
-        log::trace!("On tick end. Task = {}", self.current.id);
     }
 
     /// terminate the current task by
@@ -241,27 +253,39 @@ impl TaskScheduler {
         self.current
     }
 
-    ///Try to fetch the next task
-    ///If task is killed then it will be pushed in list of killed tasks
-    ///If no task is available in list then idle task will return
-    fn next_task(&mut self) -> &'static mut RunningTask {
-        self.running
-            .take_next()
-            .unwrap_or_else(|| self.reschedule())
+    /// Visit all tasks known to scheduler
+    pub fn for_each_task(&self, mut f: impl FnMut(&RunningTask)) {
+        f(self.current);
+
+        self.policy.for_each(&mut f);
+
+        self.blocked.iter().for_each(|task| f(task));
+        self.sleeping.iter().for_each(|task| f(task));
+        self.overdue_sleeping.iter().for_each(|task| f(task));
+        self.idle_tasks.iter().for_each(|task| f(task));
     }
 
-    /// update the task ordering
-    /// and return current next task
-    #[must_use]
-    fn reschedule(&mut self) -> &'static mut RunningTask {
-        log::trace!("Rescheduling");
+    /// Wake the tasks sleeping without timer whose deadline has passed
+    fn wake_overdue(&mut self) {
+        if self.overdue_sleeping.is_empty() {
+            return;
+        }
 
-        mem::swap(&mut self.delayed, &mut self.running);
+        let now = clocks::monotonic_millis();
 
-        let Some(task) = self.running.take_next() else {
-            return self.idle_tasks.remove_first().expect("No idle task");
-        };
+        while let Some(task) = self
+            .overdue_sleeping
+            .remove_by(|task| task.start_time <= now)
+        {
+            self.push_task(task);
+        }
+    }
 
-        task
+    ///Try to fetch the next task
+    ///If no task is available in policy then idle task will return
+    fn next_task(&mut self) -> &'static mut RunningTask {
+        self.policy.pick_next().unwrap_or_else(|| {
+            self.idle_tasks.remove_first().expect("No idle task")
+        })
     }
 }
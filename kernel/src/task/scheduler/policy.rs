@@ -0,0 +1,88 @@
+use core::mem;
+
+use crate::task::RunningTask;
+
+use super::queue::TaskQueue;
+
+/// The strategy to order ready tasks.
+/// Idle tasks are never passed to policy,
+/// they are running only when policy has no task
+pub trait SchedulingPolicy {
+    /// Put ready task in the run queue
+    fn enqueue(&mut self, task: &'static mut RunningTask);
+
+    /// Take the task to be running next
+    fn pick_next(&mut self) -> Option<&'static mut RunningTask>;
+
+    /// Account `elapsed` milliseconds to the running task.
+    /// Return `true` if the task should be preempted
+    fn tick(&mut self, current: &mut RunningTask, elapsed: usize) -> bool;
+
+    fn is_empty(&self) -> bool;
+
+    fn for_each(&self, f: &mut dyn FnMut(&RunningTask));
+}
+
+/// Fixed priority policy with static time slices.
+/// The task with higher priority is always preferred
+pub struct PriorityPolicy {
+    // the queue of delayed tasks
+    // which will be running on next stage
+    delayed: &'static mut TaskQueue,
+    running: &'static mut TaskQueue,
+}
+
+impl PriorityPolicy {
+    pub fn new() -> Self {
+        Self {
+            running: TaskQueue::new_leaked(),
+            delayed: TaskQueue::new_leaked(),
+        }
+    }
+}
+
+impl SchedulingPolicy for PriorityPolicy {
+    fn enqueue(&mut self, task: &'static mut RunningTask) {
+        task.metrics.base_duration = task.priority.static_duration();
+
+        if task.metrics.elapsed >= task.metrics.base_duration {
+            task.metrics.elapsed = 0;
+            self.delayed.push(task);
+        } else {
+            self.running.push(task);
+        }
+    }
+
+    fn pick_next(&mut self) -> Option<&'static mut RunningTask> {
+        if self.running.is_empty() {
+            mem::swap(&mut self.delayed, &mut self.running);
+        }
+
+        self.running.take_next()
+    }
+
+    fn tick(&mut self, current: &mut RunningTask, elapsed: usize) -> bool {
+        current.metrics.elapsed += elapsed;
+
+        let has_higher = self
+            .running
+            .probe_next()
+            .is_some_and(|candidate| candidate.priority > current.priority);
+
+        let is_expired =
+            current.metrics.elapsed > current.metrics.base_duration;
+
+        has_higher
+            || (is_expired
+                && !(self.running.is_empty() && self.delayed.is_empty()))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.running.is_empty() && self.delayed.is_empty()
+    }
+
+    fn for_each(&self, f: &mut dyn FnMut(&RunningTask)) {
+        self.running.for_each(f);
+        self.delayed.for_each(f);
+    }
+}
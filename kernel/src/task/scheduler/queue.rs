@@ -45,7 +45,7 @@ impl TaskQueue {
     }
 
     pub fn probe_next(&self) -> Option<&'static RunningTask> {
-        for tasks in self.tasks.iter() {
+        for tasks in self.tasks.iter().rev() {
             let mut iter = tasks.iter();
 
             if let Some(task) = iter.next() {
@@ -59,4 +59,11 @@ impl TaskQueue {
     pub fn is_empty(&self) -> bool {
         self.tasks.iter().all(|list| list.is_empty())
     }
+
+    pub fn for_each(&self, f: &mut dyn FnMut(&RunningTask)) {
+        self.tasks
+            .iter()
+            .flat_map(|list| list.iter())
+            .for_each(|task| f(task));
+    }
 }
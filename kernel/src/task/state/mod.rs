@@ -28,6 +28,10 @@ pub struct TaskMetrics {
     pub jump_ratio: usize,
     /// The base execution duration for task
     pub base_duration: usize,
+    /// The total processor time consumed by task (in milliseconds)
+    pub cpu_time: usize,
+    /// The processor time scaled by weight of task priority (in nanoseconds)
+    pub vruntime: u64,
 }
 
 pub type TaskId = usize;
@@ -43,8 +47,7 @@ pub struct Task {
     pub kernel_stack_bottom: VirtualAddress,
     //the unique identifier of thread
     pub id: TaskId,
-    //the time when task should be started
-    //the value should be greater then 0
+    //the monotonic time (in milliseconds) when task should be started
     pub start_time: usize,
     //the process context for thread
     pub process: Option<Process>,
@@ -109,6 +112,8 @@ impl Task {
                 exec_count: 0,
                 jump_ratio: 0,
                 base_duration: priority.static_duration(),
+                cpu_time: 0,
+                vruntime: 0,
             },
         };
 
@@ -134,6 +139,8 @@ impl Task {
     }
 
     pub fn set_process(&mut self, process: Process) {
+        process.attach_task();
+
         self.process = process.into();
     }
 
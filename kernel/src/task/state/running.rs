@@ -42,9 +42,10 @@ impl RunningTask {
         self
     }
 
-    pub fn into_sleeping(&mut self, timeout: usize) -> &mut RunningTask {
+    /// `deadline` is the monotonic time in milliseconds to wake the task
+    pub fn into_sleeping(&mut self, deadline: usize) -> &mut RunningTask {
         self.task.status = TaskStatus::Sleeping;
-        self.task.start_time += timeout;
+        self.task.start_time = deadline;
 
         self
     }
@@ -0,0 +1,159 @@
+//! Deferred execution driven by the timer interrupt.
+//! Timers are kept in the hierarchical wheel of scheduler
+//! and counted in timer ticks (`clocks::TIMEOUT` milliseconds each)
+
+mod object;
+mod wheel;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use kernel_macro::ListNode;
+use kernel_types::collections::{BoxedNode, LinkedList, ListNode};
+
+use crate::{
+    memory::{self, AllocError, Slab, SlabBox},
+    object::{runtime, Handle},
+};
+
+use super::{clocks, Event, TaskId, SCHEDULER};
+
+pub use object::TimerObject;
+pub use wheel::TimerWheel;
+
+/// The kernel function invoked on timer expiration
+pub type TimerCallback = fn(*const ());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimerId(usize);
+
+static NEXT_TIMER_ID: AtomicUsize = AtomicUsize::new(1);
+
+impl TimerId {
+    /// The ids are unique among the wheels of all cpus
+    fn next() -> Self {
+        Self(NEXT_TIMER_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+pub enum TimerAction {
+    /// Invoke the callback in the timer interrupt.
+    /// Interrupts are disabled, thus the callback should never block
+    Callback {
+        callback: TimerCallback,
+        context: *const (),
+    },
+    /// Wake the task which is sleeping or blocked
+    Wake(TaskId),
+    /// Unblock one task waiting on event
+    Notify(Handle<Event>),
+}
+
+#[derive(ListNode)]
+pub struct Timer {
+    #[list_pivots]
+    node: ListNode<Timer>,
+    pub id: TimerId,
+    /// The tick when timer fires
+    pub expires: u64,
+    /// The count of ticks between fires of periodic timer
+    pub period: Option<u64>,
+    pub action: TimerAction,
+}
+
+impl Timer {
+    fn new(
+        id: TimerId,
+        expires: u64,
+        period: Option<u64>,
+        action: TimerAction,
+    ) -> Self {
+        Self {
+            node: ListNode::empty(),
+            id,
+            expires,
+            period,
+            action,
+        }
+    }
+}
+
+impl Slab for Timer {
+    const NAME: &str = "timer";
+}
+
+impl BoxedNode for Timer {
+    type Target = SlabBox<Timer>;
+
+    fn into_boxed(node: &mut Self::Item) -> Self::Target {
+        memory::into_boxed(node.into())
+    }
+}
+
+/// The count of ticks covering given milliseconds
+pub fn ticks_of(milliseconds: usize) -> u64 {
+    milliseconds.div_ceil(clocks::TIMEOUT) as u64
+}
+
+/// Arm the timer firing after `delay` milliseconds
+/// and then each `period` milliseconds if period is given
+pub fn start(
+    delay: usize,
+    period: Option<usize>,
+    action: TimerAction,
+) -> Result<TimerId, AllocError> {
+    SCHEDULER.access_lock().timers().insert(
+        ticks_of(delay),
+        period.map(ticks_of),
+        action,
+    )
+}
+
+/// Invoke `callback` once after `delay` milliseconds
+pub fn set_timeout(
+    delay: usize,
+    callback: TimerCallback,
+    context: *const (),
+) -> Result<TimerId, AllocError> {
+    start(delay, None, TimerAction::Callback { callback, context })
+}
+
+/// Invoke `callback` each `period` milliseconds
+pub fn set_interval(
+    period: usize,
+    callback: TimerCallback,
+    context: *const (),
+) -> Result<TimerId, AllocError> {
+    start(
+        period,
+        Some(period),
+        TimerAction::Callback { callback, context },
+    )
+}
+
+/// Disarm the timer armed on any cpu.
+/// Return `false` if timer has already fired
+pub fn cancel(id: TimerId) -> bool {
+    SCHEDULER
+        .iter()
+        .any(|(_, scheduler)| scheduler.access_lock().timers().remove(id))
+}
+
+/// Run the actions of timers expired on current cpu.
+/// The scheduler is not locked, as the actions can touch other cpus
+pub fn dispatch(mut expired: LinkedList<'static, Timer>) {
+    while let Some(timer) = expired.remove_first() {
+        match &timer.action {
+            TimerAction::Callback { callback, context } => callback(*context),
+            TimerAction::Wake(task_id) => {
+                SCHEDULER.access_lock().wake(*task_id)
+            }
+            TimerAction::Notify(event) => runtime::notify(event.clone()),
+        }
+
+        if timer.period.is_some() {
+            SCHEDULER.access_lock().timers().rearm(timer);
+        } else {
+            drop(timer.into_boxed());
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use crate::{
+    impl_container,
+    memory::AllocError,
+    object::{alloc_root_object, Handle, Object, ObjectContainer},
+    task::Event,
+};
+
+use super::{TimerAction, TimerId};
+
+/// The timer of user space which notifies the event on each fire
+pub struct TimerObject {
+    event: Handle<Event>,
+    /// The armed timer. The id of fired one-shot timer is stale
+    timer: spin::Mutex<Option<TimerId>>,
+    object: Object,
+}
+
+impl TimerObject {
+    #[track_caller]
+    pub fn new(
+        event: Handle<Event>,
+    ) -> Result<Handle<TimerObject>, AllocError> {
+        alloc_root_object(Self {
+            event,
+            timer: spin::Mutex::new(None),
+            object: Self::new_root_object(),
+        })
+    }
+
+    /// (Re)arm the timer. Zero period means one-shot timer
+    pub fn start(&self, delay: usize, period: usize) -> Result<(), AllocError> {
+        let mut timer = self.timer.lock();
+
+        if let Some(id) = timer.take() {
+            super::cancel(id);
+        }
+
+        let id = super::start(
+            delay,
+            (period > 0).then_some(period),
+            TimerAction::Notify(self.event.clone()),
+        )?;
+
+        *timer = Some(id);
+
+        Ok(())
+    }
+
+    /// Return `false` if timer is not armed
+    pub fn cancel(&self) -> bool {
+        self.timer.lock().take().is_some_and(super::cancel)
+    }
+}
+
+impl Drop for TimerObject {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+impl_container! {
+    TimerObject,
+    obj_kind: Timer,
+    slab: "timer_object"
+}
@@ -0,0 +1,225 @@
+use core::mem;
+
+use kernel_types::collections::{LinkedList, ListNode};
+
+use crate::memory::{self, AllocError, SlabBox};
+
+use super::{Timer, TimerAction, TimerId};
+
+const LEVEL_BITS: u32 = 6;
+const SLOTS: usize = 1 << LEVEL_BITS;
+const LEVELS: usize = 4;
+/// The farthest expiration (in ticks) that wheel can hold.
+/// Later timers are parked in the last slot and placed again on cascade
+const MAX_DELTA: u64 = 1 << (LEVEL_BITS * LEVELS as u32);
+
+type Slot = LinkedList<'static, Timer>;
+
+/// Hierarchical timing wheel.
+/// Each level has 64 slots, the slot of level `n` covers `64^n` ticks.
+/// Timers of upper levels are moved (cascaded) to lower levels
+/// when the lower level passes the whole round,
+/// so insertion and tick are O(1).
+/// Cancellation looks the timer up in all slots
+pub struct TimerWheel {
+    /// The count of ticks passed
+    now: u64,
+    levels: [[Slot; SLOTS]; LEVELS],
+}
+
+impl TimerWheel {
+    pub const fn new() -> Self {
+        Self {
+            now: 0,
+            levels: [const { [const { LinkedList::empty() }; SLOTS] }; LEVELS],
+        }
+    }
+
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+
+    /// Arm the timer firing after `delay` ticks
+    /// and then each `period` ticks if period is given
+    pub fn insert(
+        &mut self,
+        delay: u64,
+        period: Option<u64>,
+        action: TimerAction,
+    ) -> Result<TimerId, AllocError> {
+        let id = TimerId::next();
+
+        let timer = memory::slab_alloc(Timer::new(
+            id,
+            self.now + delay,
+            period.filter(|&period| period > 0),
+            action,
+        ))?;
+
+        self.place(SlabBox::leak(timer).as_node());
+
+        Ok(id)
+    }
+
+    /// Disarm the timer, it's searched in all slots.
+    /// Return `false` if timer has already fired (one-shot) or unknown
+    pub fn remove(&mut self, id: TimerId) -> bool {
+        for slot in self.levels.iter_mut().flatten() {
+            if let Some(timer) = slot.remove_by(|timer| timer.id == id) {
+                drop(timer.into_boxed());
+
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Move the wheel to the next tick.
+    /// Return timers expired on this tick
+    pub fn advance(&mut self) -> LinkedList<'static, Timer> {
+        self.now += 1;
+
+        //upper levels are cascaded first:
+        //their timers can be placed to lower levels being cascaded
+        for level in (1..LEVELS).rev() {
+            let mask = (1 << (LEVEL_BITS * level as u32)) - 1;
+
+            if self.now & mask == 0 {
+                self.cascade(level);
+            }
+        }
+
+        let index = (self.now as usize) & (SLOTS - 1);
+
+        mem::replace(&mut self.levels[0][index], LinkedList::empty())
+    }
+
+    /// Put back the periodic timer which has just fired
+    pub fn rearm(&mut self, timer: &'static mut ListNode<Timer>) {
+        let period = timer.period.expect("Rearming one-shot timer");
+
+        timer.expires = u64::max(timer.expires + period, self.now + 1);
+
+        self.place(timer);
+    }
+
+    fn cascade(&mut self, level: usize) {
+        let index = self.index(self.now, level);
+
+        let mut slot =
+            mem::replace(&mut self.levels[level][index], LinkedList::empty());
+
+        while let Some(timer) = slot.remove_first() {
+            self.place(timer);
+        }
+    }
+
+    fn place(&mut self, timer: &'static mut ListNode<Timer>) {
+        //the timer in past is fired on the next tick
+        let delta = timer
+            .expires
+            .saturating_sub(self.now)
+            .clamp(1, MAX_DELTA - 1);
+
+        let level = (0..LEVELS)
+            .find(|&level| delta < 1 << (LEVEL_BITS * (level as u32 + 1)))
+            .unwrap_or(LEVELS - 1);
+
+        let index = self.index(self.now + delta, level);
+
+        self.levels[level][index].push_back(timer);
+    }
+
+    fn index(&self, tick: u64, level: usize) -> usize {
+        ((tick >> (LEVEL_BITS * level as u32)) as usize) & (SLOTS - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+    extern crate std;
+
+    use std::boxed::Box;
+
+    use super::*;
+
+    /// The timer is leaked instead of slab allocation
+    fn arm(wheel: &mut TimerWheel, expires: u64) -> TimerId {
+        let id = TimerId::next();
+
+        let timer = Box::leak(Box::new(Timer::new(
+            id,
+            expires,
+            None,
+            TimerAction::Wake(0),
+        )));
+
+        wheel.place(timer.as_node());
+
+        id
+    }
+
+    /// Advance the wheel until timer `id` expires. Return the tick
+    fn fire_tick(wheel: &mut TimerWheel, id: TimerId, limit: u64) -> u64 {
+        while wheel.now() < limit {
+            let expired = wheel.advance();
+
+            if expired.iter().any(|timer| timer.id == id) {
+                return wheel.now();
+            }
+        }
+
+        panic!("Timer has not expired in {limit} ticks");
+    }
+
+    #[test]
+    fn expiry_test() {
+        let mut wheel = TimerWheel::new();
+
+        let first = arm(&mut wheel, 3);
+        let second = arm(&mut wheel, 5);
+
+        assert_eq!(fire_tick(&mut wheel, first, 10), 3);
+        assert_eq!(fire_tick(&mut wheel, second, 10), 5);
+    }
+
+    #[test]
+    fn cascade_test() {
+        let mut wheel = TimerWheel::new();
+
+        let level_one = SLOTS as u64 * 2 + 5;
+        let level_two = (SLOTS * SLOTS) as u64 + 7;
+
+        let first = arm(&mut wheel, level_one);
+        let second = arm(&mut wheel, level_two);
+
+        assert_eq!(fire_tick(&mut wheel, first, level_two), level_one);
+        assert_eq!(fire_tick(&mut wheel, second, level_two), level_two);
+    }
+
+    #[test]
+    fn past_expiry_test() {
+        let mut wheel = TimerWheel::new();
+
+        for _ in 0..10 {
+            assert!(wheel.advance().iter().next().is_none());
+        }
+
+        let late = arm(&mut wheel, 2);
+
+        assert_eq!(fire_tick(&mut wheel, late, 20), 11);
+    }
+
+    #[test]
+    fn far_expiry_test() {
+        let mut wheel = TimerWheel::new();
+
+        //the timer is parked in the last level and placed again
+        let far = MAX_DELTA + 3;
+        let id = arm(&mut wheel, far);
+
+        assert_eq!(fire_tick(&mut wheel, id, far), far);
+    }
+}
@@ -0,0 +1,270 @@
+//! Access to user memory from syscalls.
+//! Each range is checked against the regions of the calling process
+//! and its page tables. The copying itself survives page faults
+
+use core::mem::{self, MaybeUninit};
+
+use kernel_types::{
+    drivers::pci::{PciMatch, RawPciMatch},
+    fs::{FileResponse, RawFileResponse},
+    io::{
+        block::{self, BlockDeviceInfo, RawBlockDeviceInfo},
+        char::{CharModuleInfo, RawCharModuleInfo},
+        IrqHandler, MemBuf, MemoryRemap, PortRange, RawIrqHandler,
+    },
+    memory::{AnonymousMap, FileMap, ProtectRange},
+    string::{MutString, RawMutString},
+    syscall::SyscallError,
+    task::{RawTaskParams, TaskParams},
+    time::{RawTimerSpec, TimeSpec, TimerSpec},
+};
+
+use crate::{
+    current_task,
+    memory::{self, MemoryRegionFlag, Page, VirtualAddress},
+};
+
+extern "C" {
+    /// Return non-zero value if page fault is caught during copy
+    fn user_copy(target: *mut u8, source: *const u8, len: usize) -> u32;
+
+    static user_copy_fault_ip: u8;
+    static user_copy_fixup: u8;
+}
+
+/// The type which is valid for any bit pattern,
+/// so it's read from user memory as is
+///
+/// # Safety
+/// Any `size_of::<Self>()` bytes should be a valid value
+pub unsafe trait Pod: Copy {}
+
+macro_rules! impl_pod {
+    ($($ty: ty),+ $(,)?) => {
+        $(unsafe impl Pod for $ty {})+
+    };
+}
+
+impl_pod!(
+    u8,
+    u16,
+    u32,
+    usize,
+    PortRange,
+    AnonymousMap,
+    FileMap,
+    ProtectRange,
+    MemoryRemap,
+    MemBuf,
+    TimeSpec,
+    RawMutString,
+    RawTaskParams,
+    RawTimerSpec,
+    RawPciMatch,
+    RawIrqHandler,
+    RawBlockDeviceInfo,
+    RawCharModuleInfo,
+    block::RawResponse,
+    RawFileResponse,
+);
+
+/// The type read from user memory through its raw form. The raw
+/// discriminants and lengths are checked when the value is built
+pub trait FromRaw: TryFrom<Self::Raw, Error = SyscallError> {
+    type Raw: Pod;
+}
+
+macro_rules! impl_from_raw {
+    ($($ty: ty => $raw: ty),+ $(,)?) => {
+        $(impl FromRaw for $ty {
+            type Raw = $raw;
+        })+
+    };
+}
+
+impl_from_raw!(
+    MutString<'_> => RawMutString,
+    TaskParams => RawTaskParams,
+    TimerSpec => RawTimerSpec,
+    PciMatch => RawPciMatch,
+    IrqHandler => RawIrqHandler,
+    BlockDeviceInfo => RawBlockDeviceInfo,
+    CharModuleInfo => RawCharModuleInfo,
+    block::Response => block::RawResponse,
+    FileResponse => RawFileResponse,
+);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    Execute,
+}
+
+impl Access {
+    fn as_region_flag(&self) -> MemoryRegionFlag {
+        match self {
+            Access::Read => MemoryRegionFlag::READ,
+            Access::Write => MemoryRegionFlag::WRITE,
+            Access::Execute => MemoryRegionFlag::EXEC,
+        }
+    }
+}
+
+/// Check that `[offset; offset + len)` lies in mapped memory of current process
+pub fn check_range(
+    offset: VirtualAddress,
+    len: usize,
+    access: Access,
+) -> Result<(), SyscallError> {
+    if len == 0 {
+        return Ok(());
+    }
+
+    if offset == 0 {
+        return Err(SyscallError::InvalidData);
+    }
+
+    let Some(end) = offset.checked_add(len) else {
+        return Err(SyscallError::InvalidData);
+    };
+
+    if end > memory::kernel_virtual_offset() {
+        return Err(SyscallError::InvalidData);
+    }
+
+    let Some(process) = current_task!().process.clone() else {
+        return Err(SyscallError::KernelSpaceCall);
+    };
+
+    let mut page_offset = offset - offset % Page::SIZE;
+
+    while page_offset < end {
+        let address = page_offset.max(offset);
+
+        let state = process.state.lock();
+
+        let Some(region) = state.find_region(address) else {
+            return Err(SyscallError::InvalidData);
+        };
+
+        if !region.flag.contains(access.as_region_flag()) {
+            return Err(SyscallError::InvalidData);
+        }
+
+        let is_present = state.marker.lookup_physical(page_offset).is_some();
+        let is_writable = state.marker.is_writable(page_offset);
+
+        drop(state);
+
+        //the page of demand region is populated before copying.
+        //kernel writes ignore read-only pages, so shared page is copied too
+        if !is_present || (access == Access::Write && !is_writable) {
+            memory::resolve_fault(
+                &process,
+                address,
+                is_present,
+                access == Access::Write,
+            )
+            .map_err(|_| SyscallError::InvalidData)?;
+        }
+
+        page_offset += Page::SIZE;
+    }
+
+    Ok(())
+}
+
+/// Copy bytes of user memory to kernel
+pub fn copy_from_user(
+    target: &mut [u8],
+    source: VirtualAddress,
+) -> Result<(), SyscallError> {
+    check_range(source, target.len(), Access::Read)?;
+
+    let status = unsafe {
+        user_copy(target.as_mut_ptr(), source as *const u8, target.len())
+    };
+
+    if status != 0 {
+        return Err(SyscallError::InvalidData);
+    }
+
+    Ok(())
+}
+
+/// Copy kernel bytes to user memory
+pub fn copy_to_user(
+    target: VirtualAddress,
+    source: &[u8],
+) -> Result<(), SyscallError> {
+    check_range(target, source.len(), Access::Write)?;
+
+    let status =
+        unsafe { user_copy(target as *mut u8, source.as_ptr(), source.len()) };
+
+    if status != 0 {
+        return Err(SyscallError::InvalidData);
+    }
+
+    Ok(())
+}
+
+/// Read value from user memory.
+/// The value is copied bitwise, so it should be plain data
+pub fn read<T: Pod>(offset: VirtualAddress) -> Result<T, SyscallError> {
+    let mut value = MaybeUninit::<T>::uninit();
+
+    let bytes = unsafe {
+        core::slice::from_raw_parts_mut(
+            value.as_mut_ptr().cast::<u8>(),
+            mem::size_of::<T>(),
+        )
+    };
+
+    copy_from_user(bytes, offset)?;
+
+    Ok(unsafe { value.assume_init() })
+}
+
+/// Read the raw form of value and check it
+pub fn read_valid<T: FromRaw>(
+    offset: VirtualAddress,
+) -> Result<T, SyscallError> {
+    T::try_from(read::<T::Raw>(offset)?)
+}
+
+/// Read `index` element of user array
+pub fn read_at<T: Pod>(
+    offset: VirtualAddress,
+    index: usize,
+) -> Result<T, SyscallError> {
+    let element_offset = mem::size_of::<T>()
+        .checked_mul(index)
+        .and_then(|shift| offset.checked_add(shift))
+        .ok_or(SyscallError::InvalidData)?;
+
+    read(element_offset)
+}
+
+/// Move value to user memory
+pub fn write<T>(offset: VirtualAddress, value: T) -> Result<(), SyscallError> {
+    let value = mem::ManuallyDrop::new(value);
+
+    let bytes = unsafe {
+        core::slice::from_raw_parts(
+            (&*value as *const T).cast::<u8>(),
+            mem::size_of::<T>(),
+        )
+    };
+
+    copy_to_user(offset, bytes)
+}
+
+/// Return the instruction to continue with
+/// if page fault is caught at `ip` during user copy
+pub fn fault_fixup(ip: VirtualAddress) -> Option<VirtualAddress> {
+    let fault_ip = &raw const user_copy_fault_ip as VirtualAddress;
+
+    (ip == fault_ip).then(|| &raw const user_copy_fixup as VirtualAddress)
+}
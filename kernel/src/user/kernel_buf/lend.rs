@@ -0,0 +1,118 @@
+use core::ops::Range;
+
+use alloc::vec::Vec;
+
+use crate::{
+    memory::{self, Process, ProcessId, VirtualAddress},
+    object::{Handle, RawHandle},
+};
+
+use super::{KernelBuf, Lease};
+
+/// The window of process address space where kernel buffers are mapped
+pub const LEND_AREA: Range<VirtualAddress> = 0x8_000_000..0xA_000_000;
+
+struct LentBuf {
+    work: RawHandle,
+    buf: Handle<KernelBuf>,
+    process: Process,
+    offset: VirtualAddress,
+}
+
+struct Lendings {
+    bufs: spin::Mutex<Vec<LentBuf>>,
+}
+
+unsafe impl Send for Lendings {}
+unsafe impl Sync for Lendings {}
+
+static LENDINGS: Lendings = Lendings {
+    bufs: spin::Mutex::new(Vec::new()),
+};
+
+/// Map buffer pages into the process serving `work` till [`reclaim`].
+/// Return `false` when buffer cannot be lent and should be copied
+pub fn lend(
+    work: RawHandle,
+    buf: Handle<KernelBuf>,
+    process: &Process,
+) -> bool {
+    if !buf.can_lend() {
+        return false;
+    }
+
+    if LENDINGS.bufs.lock().try_reserve(1).is_err() {
+        return false;
+    }
+
+    let offset = match memory::lend_pages(
+        process,
+        buf.kernel_offset(),
+        buf.capacity(),
+        LEND_AREA,
+    ) {
+        Ok(offset) => offset,
+        Err(cause) => {
+            log::warn!("Failed to lend kernel buf: {cause}");
+            return false;
+        }
+    };
+
+    buf.set_lease(Some(Lease {
+        process: process.id,
+        offset,
+    }));
+
+    let lent_buf = LentBuf {
+        work,
+        buf,
+        process: process.clone(),
+        offset,
+    };
+
+    let mut bufs = LENDINGS.bufs.lock();
+
+    if bufs.try_reserve(1).is_err() {
+        drop(bufs);
+        release(lent_buf);
+
+        return false;
+    }
+
+    bufs.push(lent_buf);
+
+    true
+}
+
+/// Unmap all buffers lent for `work`
+pub fn reclaim(work: RawHandle) {
+    reclaim_by(|lent| lent.work == work);
+}
+
+/// Unmap all buffers lent to `process`, the module
+/// may exit without responding to the work
+pub fn reclaim_process(process: ProcessId) {
+    reclaim_by(|lent| lent.process.id == process);
+}
+
+fn reclaim_by(is_reclaimed: impl Fn(&LentBuf) -> bool) {
+    loop {
+        let mut bufs = LENDINGS.bufs.lock();
+
+        let Some(index) = bufs.iter().position(&is_reclaimed) else {
+            break;
+        };
+
+        let lent_buf = bufs.swap_remove(index);
+
+        drop(bufs);
+
+        release(lent_buf);
+    }
+}
+
+fn release(lent_buf: LentBuf) {
+    memory::reclaim_pages(&lent_buf.process, lent_buf.offset);
+
+    lent_buf.buf.set_lease(None);
+}
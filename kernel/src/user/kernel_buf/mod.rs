@@ -1,15 +1,33 @@
+mod lend;
+
 use alloc::vec::Vec;
-use kernel_types::syscall::SyscallError;
+use kernel_types::{io::MemBuf, syscall::SyscallError};
+
+use crate::{
+    impl_container,
+    memory::{AllocError, Page, ProcessId, VirtualAddress},
+    task::Mutex,
+};
 
-use crate::{impl_container, memory::AllocError, task::Mutex};
+use crate::object::{
+    alloc_root_object, Handle, Object, ObjectContainer, ObjectInfo,
+};
 
-use crate::object::{alloc_root_object, Handle, Object, ObjectContainer};
+pub use lend::*;
 
 #[derive(Debug)]
 pub struct KernelBuf {
     object: Object,
     capacity: usize,
     buf: Mutex<Vec<u8>>,
+    //the buffer pages are mapped into the process serving work
+    lease: spin::Mutex<Option<Lease>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Lease {
+    pub process: ProcessId,
+    pub offset: VirtualAddress,
 }
 
 #[derive(Debug, thiserror_no_std::Error)]
@@ -21,12 +39,14 @@ pub enum CopyError {
 }
 
 impl KernelBuf {
+    #[track_caller]
     pub fn new(size: usize) -> Result<Handle<KernelBuf>, AllocError> {
         let buf = Vec::try_with_capacity(size)?;
 
         let handle = alloc_root_object(Self {
             capacity: size,
             buf: Mutex::new(buf)?,
+            lease: spin::Mutex::new(None),
             object: Self::new_root_object(),
         })?;
 
@@ -100,14 +120,113 @@ impl KernelBuf {
         }
     }
 
+    /// Append `len` bytes written by `fill` to the buffer
+    pub fn fill_from<E, F>(&self, len: usize, fill: F) -> Result<(), E>
+    where
+        E: From<CopyError>,
+        F: FnOnce(&mut [u8]) -> Result<(), E>,
+    {
+        let mut buf = self.buf.lock();
+
+        if buf.capacity() - buf.len() < len {
+            return Err(CopyError::NoSpaceAvailable.into());
+        }
+
+        let start = buf.len();
+
+        let bytes = unsafe {
+            core::slice::from_raw_parts_mut(buf.as_mut_ptr().add(start), len)
+        };
+
+        fill(bytes)?;
+
+        unsafe { buf.set_len(start + len) };
+
+        Ok(())
+    }
+
     pub fn reset(&self) {
         self.buf.lock().clear();
     }
+
+    /// Only page aligned buffers can be mapped into user space.
+    /// The others are transfered by copying
+    pub fn can_lend(&self) -> bool {
+        let offset = self.buf.lock().as_ptr() as VirtualAddress;
+
+        self.capacity >= Page::SIZE
+            && self.capacity % Page::SIZE == 0
+            && offset % Page::SIZE == 0
+            && self.lease.lock().is_none()
+    }
+
+    pub fn kernel_offset(&self) -> VirtualAddress {
+        self.buf.lock().as_ptr() as VirtualAddress
+    }
+
+    pub fn lease(&self) -> Option<Lease> {
+        *self.lease.lock()
+    }
+
+    pub fn set_lease(&self, lease: Option<Lease>) {
+        *self.lease.lock() = lease;
+    }
+
+    /// The address of lent pages in the address space of `process`
+    pub fn user_offset(&self, process: ProcessId) -> Option<VirtualAddress> {
+        self.lease()
+            .filter(|lease| lease.process == process)
+            .map(|lease| lease.offset)
+    }
+
+    /// Only the process holding the lease sees the buffer pointer
+    fn describe(&self, process: Option<ProcessId>) -> ObjectInfo {
+        let ptr = process
+            .and_then(|process| self.user_offset(process))
+            .map_or(core::ptr::null_mut(), |offset| offset as *mut u8);
+
+        ObjectInfo::Buf(MemBuf {
+            ptr,
+            len: self.len(),
+            capacity: self.capacity(),
+        })
+    }
+
+    /// Account `len` bytes written by `process` directly to the lent pages.
+    /// Return `false` if `offset` is not the end of lent buffer and data should be copied
+    pub fn commit_lent(
+        &self,
+        process: ProcessId,
+        offset: VirtualAddress,
+        len: usize,
+    ) -> Result<bool, CopyError> {
+        let Some(lent_offset) = self.user_offset(process) else {
+            return Ok(false);
+        };
+
+        let mut buf = self.buf.lock();
+
+        if offset != lent_offset + buf.len() {
+            return Ok(false);
+        }
+
+        if buf.capacity() - buf.len() < len {
+            return Err(CopyError::NoSpaceAvailable);
+        }
+
+        let new_len = buf.len() + len;
+
+        //the bytes are already written via user mapping
+        unsafe { buf.set_len(new_len) };
+
+        Ok(true)
+    }
 }
 
 impl<'a> TryFrom<&'a str> for Handle<KernelBuf> {
     type Error = CopyError;
 
+    #[track_caller]
     fn try_from(value: &'a str) -> Result<Self, Self::Error> {
         let buf = KernelBuf::new(value.len())?;
 
@@ -132,5 +251,6 @@ impl From<CopyError> for SyscallError {
 impl_container! {
     KernelBuf,
     obj_kind: KernelBuf,
-    slab: "kernel_buf"
+    slab: "kernel_buf",
+    info: KernelBuf::describe
 }
@@ -1,3 +1,18 @@
+use core::arch::asm;
+
+use alloc::boxed::Box;
+use kernel_types::syscall::SyscallError;
+
+use crate::{
+    current_task,
+    drivers::current_module,
+    io::{self, InterruptStackFrame},
+    log_module,
+    memory::{self, PageFault, ProcessId, VirtualAddress},
+    task,
+};
+
+pub mod access;
 pub mod channel;
 pub mod kernel_buf;
 pub mod queue;
@@ -11,3 +26,93 @@ pub fn exec<PATH: AsRef<str>>(_path: PATH) -> Result<(), ()> {
 pub fn exit(_code: i32) -> ! {
     todo!()
 }
+
+/// The exit code of task stopped by unresolved fault
+pub const SEGMENTATION_FAULT: i32 = 11;
+
+/// Deliver the unresolved fault to process. There are
+/// no signal handlers, so the faulting task is terminated
+pub fn fault(address: VirtualAddress, cause: PageFault) -> ! {
+    log_module!(
+        "[Task#{}] Segmentation fault at 0x{address:X}: {cause}\n",
+        current_task!().id
+    );
+
+    task::terminate(SEGMENTATION_FAULT)
+}
+
+/// The user registers restored in forked task
+struct ForkContext {
+    ip: VirtualAddress,
+    esp: VirtualAddress,
+    flags: usize,
+}
+
+/// Clone current task into new process sharing memory copy-on-write.
+/// The child task resumes after the syscall interrupted at `frame`
+pub fn fork(frame: &InterruptStackFrame) -> Result<ProcessId, SyscallError> {
+    let Some(process) = current_task!().process.clone() else {
+        return Err(SyscallError::KernelSpaceCall);
+    };
+
+    //the devices and ports of module can't be shared
+    if current_module().is_some() {
+        return Err(SyscallError::NotSupported);
+    }
+
+    let context = Box::try_new(ForkContext {
+        ip: frame.ip(),
+        esp: frame.esp(),
+        flags: frame.flags(),
+    })
+    .map_err(|_| SyscallError::NoMemory)?;
+
+    //the pages are protected in the active address space of parent
+    let child = process.fork()?;
+    let child_id = child.id;
+
+    unsafe {
+        memory::switch_to_kernel();
+    }
+
+    let raw_context = Box::into_raw(context);
+
+    let spawned = task::new_task(
+        run_forked_task,
+        raw_context as *const (),
+        current_task!().priority,
+    );
+
+    unsafe {
+        memory::switch_to_task(current_task!());
+    }
+
+    let task =
+        spawned.inspect_err(|_| drop(unsafe { Box::from_raw(raw_context) }))?;
+
+    task.set_process(child);
+    task.opened_files = current_task!().opened_files.clone();
+
+    task::submit_task(task);
+
+    Ok(child_id)
+}
+
+extern "C" fn run_forked_task(raw_context: *const ()) {
+    let context = unsafe { Box::from_raw(raw_context as *mut ForkContext) };
+
+    unsafe { io::disable() }; //disable interrupts to configure kernel task
+
+    let ForkContext { ip, esp, flags } = *context;
+    drop(context);
+
+    unsafe {
+        asm! {
+            "jmp resume_user_routine",
+            in("eax") ip,
+            in("ecx") esp,
+            in("edx") flags,
+            options(nostack, noreturn)
+        }
+    }
+}
@@ -53,6 +53,7 @@ impl<T> Queue<T>
 where
     T: ObjectContainer + 'static,
 {
+    #[track_caller]
     pub fn new_unbounded() -> Result<Handle<Self>, memory::AllocError> {
         let data = InterruptableLazyCell::new(LinkedList::empty());
 
@@ -68,6 +69,7 @@ where
         Ok(handle)
     }
 
+    #[track_caller]
     pub fn new_bounded(
         capacity: usize,
     ) -> Result<Handle<Self>, memory::AllocError> {
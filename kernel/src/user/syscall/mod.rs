@@ -1,129 +1,324 @@
-use alloc::boxed::Box;
+use core::mem;
+
+use alloc::{boxed::Box, vec::Vec};
 use kernel_types::{
     drivers::UserModule,
-    fs::{
-        FileInfo, FileLookupRequest, FileRequest, FileResponse, FsRequest, Work,
-    },
+    fs::{FileLookupRequest, FileRequest, FileResponse, FsRequest, Work},
     io::{
         block::BlockDeviceInfo, char::CharModuleInfo, IoOperation, IrqHandler,
-        IrqMessage, MemBuf, MemoryRemap,
+        IrqMessage, MemBuf, MemoryRemap, PortOperation, PortRange,
     },
+    memory::{AnonymousMap, FileMap, MapFlags, ProtectRange},
+    object::OpStatus,
     string::MutString,
     syscall::{Request, SyscallError},
     task::TaskParams,
+    time::{ClockType, TimeSpec, TimerSpec},
 };
 
 use crate::{
     current_task,
     drivers::{self, current_module, run_process_task},
-    fs::{FileLookupWork, FileWork, FsWork, IndexNode},
+    fs::{FileLookupWork, FileWork, FsWork},
     io::{
         self,
         block::{self, BlockWork},
         pic::PicLine,
-        IrqEvent,
+        InterruptStackFrame, IrqEvent, IrqHook,
     },
     log_module,
-    memory::{self, AllocError, VirtualAddress},
-    object::{runtime, AnyObject, Handle, Object, ObjectContainer, UserHandle},
-    task::{self, Event, MutexObject, TaskPriority},
+    memory::{self, AllocError, MemoryRegionFlag, VirtualAddress},
+    object::{
+        clone_raw, drop_raw, info_raw, runtime, AnyObject, Handle, HandleEntry,
+        HandleRights, HandleTable, ObjectContainer, UserHandle, UserRawHandle,
+        WorkObject,
+    },
+    pci::{self, PciBar, PciMatch},
+    power::{self, PowerAction},
+    task::{self, timer::TimerObject, Event, MutexObject, TaskPriority},
     user,
 };
 
-use super::{kernel_buf::KernelBuf, queue::Queue};
-
-pub fn validate_ref<'a, T: Sized>(
-    offset: VirtualAddress,
-) -> Result<&'a T, SyscallError> {
-    let ptr = offset as *const T;
-
-    if ptr.is_null() {
-        return Err(SyscallError::InvalidData);
-    }
+use super::{
+    access::{self, Access},
+    kernel_buf::{self, KernelBuf},
+    queue::Queue,
+};
 
-    Ok(unsafe { &*ptr })
-}
+/// The longest module message printed at once
+const MAX_PRINTK_LEN: usize = 256;
 
 pub fn handle(
     request: Request,
     edx: usize,
     ecx: usize,
+    frame: &InterruptStackFrame,
 ) -> Result<(), SyscallError> {
     match request {
         Request::PrintK => {
-            let string: &MutString = validate_ref(edx)?;
+            let string = access::read_valid::<MutString>(edx)?;
+
+            let mut bytes = [0u8; MAX_PRINTK_LEN];
+            let len = usize::min(string.len(), bytes.len());
+
+            access::copy_from_user(
+                &mut bytes[..len],
+                string.unwrap() as VirtualAddress,
+            )?;
+
+            let string = core::str::from_utf8(&bytes[..len])
+                .map_err(|_| SyscallError::InvalidData)?;
 
             log_module!("M! {string}");
         }
-        Request::MemRemap => {
-            let remap = validate_ref::<MemoryRemap>(edx)?;
+        Request::GetTime => {
+            let Ok(clock) = ClockType::try_from(edx as u32) else {
+                return Err(SyscallError::InvalidData);
+            };
+
+            let time = task::clocks::now(clock);
+
+            access::write(ecx, TimeSpec::from(time))?;
+        }
+        Request::Shutdown | Request::Reboot => {
+            //only the modules loaded by kernel are trusted to stop the system
+            if current_module().is_none() {
+                return Err(SyscallError::AccessDenied);
+            }
+
+            let action = match request {
+                Request::Reboot => PowerAction::Reboot,
+                _ => PowerAction::PowerOff,
+            };
+
+            unsafe { memory::switch_to_kernel() };
+
+            power::shutdown(action);
+
+            unsafe { memory::switch_to_task(current_task!()) };
+
+            return Err(SyscallError::BusyResource);
+        }
+        Request::MapAnonymous => {
+            let map = access::read::<AnonymousMap>(edx)?;
 
             let Some(process) = current_task!().process.clone() else {
                 return Err(SyscallError::KernelSpaceCall);
             };
 
-            let remap = remap.clone();
+            let kernel_space = unsafe { memory::KernelSpaceGuard::enter() };
 
-            unsafe { memory::switch_to_kernel() };
+            let address = memory::map_anonymous(
+                &process,
+                map.len,
+                map.protection.into(),
+            )?;
+
+            kernel_space.leave();
+
+            access::write(ecx, address)?;
+        }
+        Request::Unmap => {
+            let Some(process) = current_task!().process.clone() else {
+                return Err(SyscallError::KernelSpaceCall);
+            };
+
+            //the shared file pages are read through process address space
+            if let Err(cause) = memory::sync_file_map(&process, edx) {
+                log::warn!("Failed to write back file mapping: {cause}");
+            }
+
+            let kernel_space = unsafe { memory::KernelSpaceGuard::enter() };
+
+            memory::unmap_region(&process, edx).map_err(|cause| {
+                log::warn!("Failed to unmap: {cause}");
+                SyscallError::InvalidData
+            })?;
+
+            kernel_space.leave();
+        }
+        Request::MapFile => {
+            let map = access::read::<FileMap>(edx)?;
+
+            if map.flags.contains(MapFlags::SHARED)
+                == map.flags.contains(MapFlags::PRIVATE)
+            {
+                return Err(SyscallError::InvalidData);
+            }
+
+            let Some(process) = current_task!().process.clone() else {
+                return Err(SyscallError::KernelSpaceCall);
+            };
+
+            //the fault of module may wait for the module itself
+            if drivers::is_module_process(&process) {
+                return Err(SyscallError::AccessDenied);
+            }
+
+            let Some(node) = current_task!().opened_files.get(map.file) else {
+                return Err(SyscallError::InvalidHandle);
+            };
+
+            let mut flag = MemoryRegionFlag::from(map.protection);
+
+            if map.flags.contains(MapFlags::SHARED) {
+                flag |= MemoryRegionFlag::SHARED;
+            }
+
+            let kernel_space = unsafe { memory::KernelSpaceGuard::enter() };
+
+            let address =
+                memory::map_file(&process, node, map.offset, map.len, flag)?;
+
+            kernel_space.leave();
+
+            access::write(ecx, address)?;
+        }
+        Request::MemRemap => {
+            let remap = access::read::<MemoryRemap>(edx)?;
+
+            let Some(process) = current_task!().process.clone() else {
+                return Err(SyscallError::KernelSpaceCall);
+            };
+
+            let kernel_space = unsafe { memory::KernelSpaceGuard::enter() };
 
             memory::remap(&process, remap.into())
                 .inspect_err(|cause| log::warn!("Failed to remap: {cause}"))?;
 
-            unsafe { memory::switch_to_task(current_task!()) };
+            kernel_space.leave();
             // let state = procces.state.try_lock().unwrap();
             // state.find_region(remap.virtual_start)
 
             // procces.state
         }
+        Request::Protect => {
+            let range = access::read::<ProtectRange>(edx)?;
+
+            let Some(process) = current_task!().process.clone() else {
+                return Err(SyscallError::KernelSpaceCall);
+            };
+
+            let kernel_space = unsafe { memory::KernelSpaceGuard::enter() };
+
+            memory::protect(
+                &process,
+                range.address,
+                range.len,
+                range.protection.into(),
+            )
+            .map_err(|cause| {
+                log::warn!("Failed to protect: {cause}");
+                SyscallError::InvalidData
+            })?;
+
+            kernel_space.leave();
+        }
+        Request::Fork => {
+            let child = user::fork(frame)?;
+
+            //the parent gets own copy of page before the write
+            access::write(ecx, child)?;
+        }
         Request::RegBlockDevice => {
-            let blk_dev = validate_ref::<BlockDeviceInfo>(edx)?.clone();
+            let blk_dev = access::read_valid::<BlockDeviceInfo>(edx)?;
 
-            unsafe { memory::switch_to_kernel() };
+            let kernel_space = unsafe { memory::KernelSpaceGuard::enter() };
 
             drivers::api::reg_blk_module(&blk_dev)?;
 
-            unsafe { memory::switch_to_task(current_task!()) };
+            kernel_space.leave();
         }
         Request::RegCharDevice => {
-            let chr_dev = validate_ref::<CharModuleInfo>(edx)?.clone();
+            let chr_dev = access::read_valid::<CharModuleInfo>(edx)?;
 
-            unsafe { memory::switch_to_kernel() };
+            let kernel_space = unsafe { memory::KernelSpaceGuard::enter() };
 
             drivers::api::reg_chr_module(&chr_dev)?;
 
-            unsafe { memory::switch_to_task(current_task!()) };
+            kernel_space.leave();
         }
         Request::RegFs => {}
-        Request::IoOperation => {
+        Request::RequestIoPorts => {
             let len = ecx;
-            let _ = validate_ref::<IoOperation>(edx)?;
 
-            let ops = unsafe {
-                core::slice::from_raw_parts(edx as *const IoOperation, len)
+            let ranges_size = mem::size_of::<PortRange>()
+                .checked_mul(len)
+                .ok_or(SyscallError::InvalidData)?;
+
+            access::check_range(edx, ranges_size, Access::Read)?;
+
+            let ranges = (0..len)
+                .map(|index| access::read_at::<PortRange>(edx, index))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let Some(process) = current_task!().process.clone() else {
+                return Err(SyscallError::KernelSpaceCall);
             };
 
-            io::start_op_tx();
+            let kernel_space = unsafe { memory::KernelSpaceGuard::enter() };
+
+            io::ports::grant_declared(&process, &ranges)?;
+
+            //the bitmap of running task is reloaded
+            kernel_space.leave();
+        }
+        Request::PciBind => {
+            let pattern = access::read_valid::<PciMatch>(edx)?;
+
+            let Some(process) = current_task!().process.clone() else {
+                return Err(SyscallError::KernelSpaceCall);
+            };
 
-            for op in ops.iter() {
-                unsafe { io::interpretate_op(op) };
+            //only modules drive devices
+            if current_module().is_none()
+                && !drivers::is_module_process(&process)
+            {
+                return Err(SyscallError::AccessDenied);
             }
 
-            io::end_op_tx();
+            let device = pci::bind(&pattern, process.id)
+                .ok_or(SyscallError::DeviceIsNotFound)?;
+
+            //the module drives I/O ports decoded by device
+            let ranges = device
+                .bars
+                .iter()
+                .filter_map(|bar| match *bar {
+                    PciBar::Io { port, size } => {
+                        Some(PortRange::new(port, size as u16))
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+
+            if !ranges.is_empty() {
+                let kernel_space = unsafe { memory::KernelSpaceGuard::enter() };
+
+                io::ports::grant(&process, &ranges)?;
+
+                kernel_space.leave();
+            }
+
+            access::write(ecx, device)?;
         }
         Request::GetModuleInfo => {
-            let ptr = edx as *mut UserModule;
-
-            unsafe { memory::switch_to_kernel() };
+            let kernel_space = unsafe { memory::KernelSpaceGuard::enter() };
 
             let Some(module) = drivers::current_module() else {
                 return Err(SyscallError::ModuleIsNotFound);
             };
 
-            let module = module.as_user_module();
+            let mut module = module.as_user_module();
 
-            unsafe { memory::switch_to_task(current_task!()) };
+            publish_raw(
+                &mut module.queue,
+                HandleRights::WAIT | HandleRights::DUPLICATE,
+            )?;
+
+            kernel_space.leave();
 
-            unsafe { ptr.write(module) };
+            access::write::<UserModule>(edx, module)?;
         }
         Request::TerminateCurrentTask => {
             task::terminate(edx as i32);
@@ -132,78 +327,106 @@ pub fn handle(
             user::exit(edx as i32);
         }
         Request::QueueBlockingGet => {
-            unsafe { memory::switch_to_kernel() };
+            //the work handlers load task address space before the write
+            let _kernel_space = unsafe { memory::KernelSpaceGuard::enter() };
 
-            let queue: UserHandle<Queue<AnyObject>> =
-                unsafe { UserHandle::from_addr_unchecked(ecx) };
+            let queue =
+                lookup_handle::<Queue<AnyObject>>(ecx, HandleRights::WAIT)?;
 
             match queue.kind() {
                 crate::object::Kind::BlockDeviceWork => unsafe {
-                    blocking_pop(&queue, |work: Handle<BlockWork>| {
-                        let request = work.take_request().into();
+                    blocking_pop_work(&queue, |work: Handle<BlockWork>| {
+                        let mut request: block::Request =
+                            work.take_request().into();
+
+                        match &mut request.work {
+                            block::Work::Read { buffer, .. } => {
+                                lend_kernel_buf(work.as_addr(), buffer);
+                                publish_raw(buffer, HandleRights::READ_WRITE)?;
+                            }
+                            block::Work::Write { buffer, .. } => {
+                                lend_kernel_buf(work.as_addr(), buffer);
+                                publish_raw(buffer, HandleRights::READ)?;
+                            }
+                            block::Work::Passthrough { .. } => {}
+                        }
 
                         let user_work = Work {
-                            request,
-                            handle: work.into_raw(),
+                            request: Some(request),
+                            handle: publish_work(work)?,
                         };
 
                         memory::switch_to_task(current_task!());
 
-                        let ptr = edx as *mut Work<block::Request>;
-
-                        ptr.write(user_work);
+                        access::write::<Work<block::Request>>(edx, user_work)
                     })?;
                 },
                 crate::object::Kind::FsWork => unsafe {
-                    blocking_pop(&queue, |work: Handle<FsWork>| {
+                    blocking_pop_work(&queue, |work: Handle<FsWork>| {
                         let request = work.take_request().into();
 
                         let user_work = Work {
-                            handle: work.into_raw(),
+                            handle: publish_work(work)?,
                             request,
                         };
 
                         memory::switch_to_task(current_task!());
 
-                        let ptr = edx as *mut Work<FsRequest>;
-
-                        ptr.write(user_work);
+                        access::write::<Work<FsRequest>>(edx, user_work)
                     })?;
                 },
 
                 crate::object::Kind::FileLookupWork => unsafe {
-                    blocking_pop(&queue, |work: Handle<FileLookupWork>| {
-                        let request = work.take_request().into();
-
-                        let user_work = Work {
-                            request,
-                            handle: work.into_raw(),
-                        };
-
-                        memory::switch_to_task(current_task!());
-
-                        let ptr = edx as *mut Work<FileLookupRequest>;
-
-                        ptr.write(user_work);
-                    })?;
+                    blocking_pop_work(
+                        &queue,
+                        |work: Handle<FileLookupWork>| {
+                            let request = work.take_request().into();
+
+                            let user_work = Work {
+                                request,
+                                handle: publish_work(work)?,
+                            };
+
+                            memory::switch_to_task(current_task!());
+
+                            access::write::<Work<FileLookupRequest>>(
+                                edx, user_work,
+                            )
+                        },
+                    )?;
                 },
 
                 crate::object::Kind::FileWork => unsafe {
-                    blocking_pop(&queue, |work: Handle<FileWork>| {
-                        let request = work.take_request().into();
+                    blocking_pop_work(&queue, |work: Handle<FileWork>| {
+                        let mut request: FileRequest =
+                            work.take_request().into();
 
                         log::debug!("File Work: {request:?}");
 
+                        match &mut request {
+                            FileRequest::Read { file, buf, .. } => {
+                                lend_kernel_buf(work.as_addr(), buf);
+                                publish_raw(file, HandleRights::READ)?;
+                                publish_raw(buf, HandleRights::READ_WRITE)?;
+                            }
+                            FileRequest::Write { file, buf, .. } => {
+                                lend_kernel_buf(work.as_addr(), buf);
+                                publish_raw(file, HandleRights::READ)?;
+                                publish_raw(buf, HandleRights::READ)?;
+                            }
+                            FileRequest::Command { file, .. } => {
+                                publish_raw(file, HandleRights::READ)?;
+                            }
+                        }
+
                         let user_work = Work {
-                            handle: work.into_raw(),
-                            request,
+                            handle: publish_work(work)?,
+                            request: Some(request),
                         };
 
                         memory::switch_to_task(current_task!());
 
-                        let ptr = edx as *mut Work<FileRequest>;
-
-                        ptr.write(user_work);
+                        access::write::<Work<FileRequest>>(edx, user_work)
                     })?;
                 },
 
@@ -211,7 +434,7 @@ pub fn handle(
                     log::debug!(
                         "task !!. Stack size before: {}. ESP= 0x{:x?}",
                         current_task!().stack_size(),
-                        unsafe { current_task!().context().esp }
+                        current_task!().context().esp
                     );
 
                     let Some(handle) = queue.cast::<IrqEvent>().blocking_pop()
@@ -222,7 +445,7 @@ pub fn handle(
                     log::debug!(
                         "task !!. Stack size after: {}. ESP = 0x{:x?}",
                         current_task!().stack_size(),
-                        unsafe { current_task!().context().esp }
+                        current_task!().context().esp
                     );
 
                     let event = handle.into_owned().unwrap();
@@ -239,12 +462,10 @@ pub fn handle(
                     log::debug!(
                         "task !!. Stack size somewhere: {}. ESP = 0x{:x?}",
                         current_task!().stack_size(),
-                        unsafe { current_task!().context().esp }
+                        current_task!().context().esp
                     );
 
-                    let ptr = edx as *mut IrqMessage;
-
-                    ptr.write(message);
+                    access::write::<IrqMessage>(edx, message)?;
                 },
 
                 _ => {
@@ -252,139 +473,73 @@ pub fn handle(
                 }
             }
         }
-        Request::FreeKernelObject => unsafe {
-            let raw_object = edx as *const Object;
-
-            let raw_handle = edx;
-            let kind = (*raw_object).kind;
-
-            match kind {
-                crate::object::Kind::BlockDeviceWork => {
-                    let _ = Handle::<block::BlockWork>::from_addr_unchecked(
-                        raw_handle,
-                    );
-                }
-                crate::object::Kind::FsWork => {
-                    let _ = Handle::<FsWork>::from_addr_unchecked(raw_handle);
-                }
-                crate::object::Kind::FileLookupWork => {
-                    let _ = Handle::<FileLookupWork>::from_addr_unchecked(
-                        raw_handle,
-                    );
-                }
-                crate::object::Kind::FileWork => {
-                    let _ = Handle::<FileWork>::from_addr_unchecked(raw_handle);
-                }
-                crate::object::Kind::IrqEvent => {
-                    let _ = Handle::<IrqEvent>::from_addr_unchecked(raw_handle);
-                }
+        Request::FreeKernelObject => {
+            let HandleEntry { object, .. } =
+                with_handles(|handles| handles.remove(edx))?;
 
-                crate::object::Kind::File => {
-                    let _ =
-                        Handle::<IndexNode>::from_addr_unchecked(raw_handle);
-                }
-
-                crate::object::Kind::Queue => {
-                    let _ = Handle::<Queue<AnyObject>>::from_addr_unchecked(
-                        raw_handle,
-                    );
-                }
-
-                crate::object::Kind::KernelBuf => {
-                    let handle =
-                        Handle::<KernelBuf>::from_addr_unchecked(raw_handle);
-
-                    drop(handle);
-                }
-
-                crate::object::Kind::Event => {
-                    let handle =
-                        Handle::<Event>::from_addr_unchecked(raw_handle);
-
-                    drop(handle);
-                }
-
-                crate::object::Kind::SuperBlock => todo!(),
-                crate::object::Kind::Mutex => todo!(),
-            }
-        },
+            unsafe { drop_raw(object) };
+        }
         Request::CloneHandle => {
-            //clone handle
-            let handle = Handle::<FsWork>::from_addr(edx).unwrap();
+            let handle = with_handles(|handles| {
+                handles.duplicate(edx, HandleRights::all())
+            })?;
 
-            //prevent droping handle
-            let _ = unsafe { handle.into_addr() };
+            access::write(ecx, handle)?;
         }
         Request::GetObjectInfo => {
-            let kind = validate_ref::<Object>(edx)?.kind;
+            let HandleEntry { object, kind, .. } =
+                lookup_object(edx, HandleRights::READ)?;
 
             log::debug!("obj kind: {kind:?}");
 
-            match kind {
-                crate::object::Kind::KernelBuf => {
-                    let handle = unsafe {
-                        UserHandle::<KernelBuf>::from_addr_unchecked(edx)
-                    };
-
-                    let ptr = ecx as *mut MemBuf;
-
-                    unsafe {
-                        ptr.write(MemBuf {
-                            ptr: core::ptr::null_mut(),
-                            len: handle.len(),
-                            capacity: handle.capacity(),
-                        });
-                    }
-                }
-                crate::object::Kind::File => {
-                    let handle = unsafe {
-                        UserHandle::<IndexNode>::from_addr_unchecked(edx)
-                    };
-
-                    let ptr = ecx as *mut FileInfo;
+            let process = current_task!().process.as_ref().map(|p| p.id);
 
-                    unsafe {
-                        ptr.write(FileInfo {
-                            ctx: handle.ctx,
-                            offset: 0,
-                        });
-                    }
-                }
-
-                _ => todo!(),
-            }
+            //the tag is written too, so the caller checks the kind
+            access::write(ecx, unsafe { info_raw(object, process) })?;
         }
         Request::KernelCopy => {
             let kernel_buf =
-                unsafe { UserHandle::<KernelBuf>::from_addr_unchecked(edx) };
+                lookup_handle::<KernelBuf>(edx, HandleRights::READ)?;
 
-            let mem_buf: &MemBuf = validate_ref(ecx)?;
+            let mem_buf = access::read::<MemBuf>(ecx)?;
 
-            let bytes = unsafe {
-                core::slice::from_raw_parts_mut(mem_buf.ptr, mem_buf.capacity)
-            };
+            let bytes = kernel_buf.as_slice();
+            let len = usize::min(mem_buf.capacity, bytes.len());
 
-            kernel_buf.copy_to(bytes)?;
+            access::copy_to_user(mem_buf.ptr as VirtualAddress, &bytes[..len])?;
         }
         Request::UserCopy => {
-            let mem_buf = validate_ref::<MemBuf>(ecx)?.clone();
+            let mem_buf = access::read::<MemBuf>(ecx)?;
 
             let kernel_buf =
-                unsafe { UserHandle::<KernelBuf>::from_addr_unchecked(edx) };
-
-            let bytes = unsafe {
-                core::slice::from_raw_parts(mem_buf.ptr, mem_buf.len)
+                lookup_handle::<KernelBuf>(edx, HandleRights::WRITE)?;
+
+            let is_committed = match current_task!().process.as_ref() {
+                Some(process) => kernel_buf.commit_lent(
+                    process.id,
+                    mem_buf.ptr as VirtualAddress,
+                    mem_buf.len,
+                )?,
+                None => false,
             };
 
-            kernel_buf.copy_from(bytes)?;
+            if !is_committed {
+                kernel_buf.fill_from(mem_buf.len, |bytes| {
+                    access::copy_from_user(bytes, mem_buf.ptr as VirtualAddress)
+                })?;
+            }
         }
         Request::QueueTryGet => todo!(),
         Request::SpawnTask => {
-            let params = validate_ref::<TaskParams>(ecx)?.clone();
+            let params = access::read_valid::<TaskParams>(ecx)?;
 
-            unsafe {
-                memory::switch_to_kernel();
-            }
+            access::check_range(
+                params.routine as VirtualAddress,
+                1,
+                Access::Execute,
+            )?;
+
+            let kernel_space = unsafe { memory::KernelSpaceGuard::enter() };
 
             let priority = if current_module().is_some() {
                 TaskPriority::Module(params.nice)
@@ -401,105 +556,143 @@ pub fn handle(
                 priority,
             )?;
 
-            routine_task.process = current_task!().process.clone();
+            if let Some(process) = current_task!().process.clone() {
+                routine_task.set_process(process);
+            }
 
             task::submit_task(routine_task);
 
-            unsafe {
-                memory::switch_to_task(current_task!());
-            }
-
-            let ptr = edx as *mut VirtualAddress;
+            kernel_space.leave();
 
-            unsafe {
-                ptr.write(current_task!().id);
-            }
+            access::write(edx, current_task!().id)?;
         }
         Request::SetIrqHandler => {
-            let handler = validate_ref::<IrqHandler>(edx)?.clone();
+            let handler = access::read_valid::<IrqHandler>(edx)?;
 
             let Ok(pic_line) = PicLine::try_from(handler.line) else {
                 return Err(SyscallError::InvalidData);
             };
 
-            unsafe {
-                memory::switch_to_kernel();
-            }
+            let hook = handler.hook.map(translate_irq_hook).transpose()?;
 
-            let queue = crate::io::set_irq(pic_line.into(), handler.hook)?;
+            let kernel_space = unsafe { memory::KernelSpaceGuard::enter() };
 
-            unsafe {
-                memory::switch_to_task(current_task!());
-            }
+            let queue = crate::io::set_irq(pic_line.into(), hook)?;
+
+            let queue = publish_handle(
+                unsafe { queue.into_addr() },
+                HandleRights::WAIT | HandleRights::DUPLICATE,
+            )?;
 
-            let ptr = ecx as *mut VirtualAddress;
+            kernel_space.leave();
 
-            unsafe { ptr.write(queue.into_addr()) };
+            access::write(ecx, queue)?;
         }
 
         Request::EventNew => {
-            unsafe { memory::switch_to_kernel() };
+            let kernel_space = unsafe { memory::KernelSpaceGuard::enter() };
 
             let event = Event::new()?;
 
-            unsafe { memory::switch_to_task(current_task!()) };
+            let event = publish_handle(
+                unsafe { event.into_addr() },
+                HandleRights::WAIT
+                    | HandleRights::WRITE
+                    | HandleRights::DUPLICATE
+                    | HandleRights::TRANSFER,
+            )?;
 
-            let ptr = edx as *mut VirtualAddress;
+            kernel_space.leave();
 
-            unsafe {
-                ptr.write(event.into_addr());
-            }
+            access::write(edx, event)?;
         }
         Request::EventBlock => {
             log::debug!("Event block");
 
-            let event =
-                unsafe { UserHandle::<Event>::from_addr_unchecked(edx) };
+            let event = lookup_handle::<Event>(edx, HandleRights::WAIT)?;
 
             runtime::block_on(event.to_owned())?;
         }
         Request::EventNotifyOne | Request::EventNotifyAll => {
             log::debug!("Event notify");
 
-            let event =
-                unsafe { UserHandle::<Event>::from_addr_unchecked(edx) };
+            let event = lookup_handle::<Event>(edx, HandleRights::WRITE)?;
 
             runtime::notify(event.to_owned());
         }
+        Request::TimerNew => {
+            let event = lookup_handle::<Event>(edx, HandleRights::WRITE)?;
+
+            let kernel_space = unsafe { memory::KernelSpaceGuard::enter() };
+
+            let timer = TimerObject::new(event.to_owned())?;
+
+            let timer = publish_handle(
+                unsafe { timer.into_addr() },
+                HandleRights::WRITE
+                    | HandleRights::DUPLICATE
+                    | HandleRights::TRANSFER,
+            )?;
+
+            kernel_space.leave();
+
+            access::write(ecx, timer)?;
+        }
+        Request::TimerStart => {
+            let spec = access::read_valid::<TimerSpec>(ecx)?;
+
+            let timer = lookup_handle::<TimerObject>(edx, HandleRights::WRITE)?;
+
+            timer.start(
+                spec.delay.as_millis() as usize,
+                spec.period.as_millis() as usize,
+            )?;
+        }
+        Request::TimerCancel => {
+            let timer = lookup_handle::<TimerObject>(edx, HandleRights::WRITE)?;
+
+            timer.cancel();
+        }
         Request::MutexNew => {
             let mutex = MutexObject::new()?;
 
-            let ptr = edx as *mut kernel_types::object::RawHandle;
-            unsafe { ptr.write(mutex.into_raw()) };
+            let mutex = publish_handle(
+                unsafe { mutex.into_addr() },
+                HandleRights::WAIT
+                    | HandleRights::DUPLICATE
+                    | HandleRights::TRANSFER,
+            )?;
+
+            access::write(edx, mutex)?;
         }
         Request::MutexAcquire => {
             log::debug!("MutexAcquire: 0x{edx:x}");
 
-            let mutex =
-                unsafe { UserHandle::<MutexObject>::from_addr_unchecked(edx) };
+            let mutex = lookup_handle::<MutexObject>(edx, HandleRights::WAIT)?;
 
             mutex.acquire();
         }
         Request::MutexRelease => {
             log::debug!("MutexRelease: 0x{edx:x}");
 
-            let mutex =
-                unsafe { UserHandle::<MutexObject>::from_addr_unchecked(edx) };
+            let mutex = lookup_handle::<MutexObject>(edx, HandleRights::WAIT)?;
 
             mutex.release();
         }
         Request::SetWorkResponse => {
-            let raw_object = edx as *const Object;
-
-            let raw_handle = edx;
-            let kind = unsafe { (*raw_object).kind };
+            let HandleEntry {
+                object: raw_handle,
+                kind,
+                ..
+            } = lookup_object(edx, HandleRights::WRITE)?;
 
             match kind {
-                crate::object::Kind::FsWork => todo!(),
-                crate::object::Kind::FileLookupWork => todo!(),
+                crate::object::Kind::FsWork
+                | crate::object::Kind::FileLookupWork => {
+                    return Err(SyscallError::NotSupported)
+                }
                 crate::object::Kind::BlockDeviceWork => unsafe {
-                    let response =
-                        validate_ref::<block::Response>(ecx)?.clone();
+                    let response = access::read_valid::<block::Response>(ecx)?;
 
                     let handle = UserHandle::<BlockWork>::from_addr_unchecked(
                         raw_handle,
@@ -507,16 +700,20 @@ pub fn handle(
 
                     log::debug!("Setting: {response:?} for {raw_handle}");
 
+                    kernel_buf::reclaim(raw_handle);
+
                     handle.send_response(response);
                 },
                 crate::object::Kind::FileWork => unsafe {
-                    let response = validate_ref::<FileResponse>(ecx)?.clone();
+                    let response = access::read_valid::<FileResponse>(ecx)?;
 
                     let handle =
                         UserHandle::<FileWork>::from_addr_unchecked(raw_handle);
 
                     log::debug!("Setting: {response:?} for {raw_handle}");
 
+                    kernel_buf::reclaim(raw_handle);
+
                     handle.send_response(response);
                 },
 
@@ -533,19 +730,187 @@ impl From<AllocError> for SyscallError {
         SyscallError::NoMemory
     }
 }
+fn lend_kernel_buf(
+    work: VirtualAddress,
+    buf: &kernel_types::object::RawHandle,
+) {
+    let Some(process) = current_task!().process.clone() else {
+        return;
+    };
+
+    let buf =
+        unsafe { UserHandle::<KernelBuf>::from_addr_unchecked(buf.syscall()) };
+
+    //the buffer is copied on failure
+    let _ = kernel_buf::lend(work, buf.to_owned(), &process);
+}
+
 unsafe fn blocking_pop<T, F>(
     queue: &Queue<AnyObject>,
     mut op: F,
 ) -> Result<(), SyscallError>
 where
     T: ObjectContainer,
-    F: FnMut(Handle<T>),
+    F: FnMut(Handle<T>) -> Result<(), SyscallError>,
 {
     let Some(handle) = queue.cast::<T>().blocking_pop() else {
         return Err(SyscallError::QueueIsEmpty);
     };
 
-    op(handle);
+    op(handle)
+}
+
+/// Pop the work and pass it to module by `op`. The work which
+/// isn't passed is failed, so its sender doesn't wait forever
+unsafe fn blocking_pop_work<T, F>(
+    queue: &Queue<AnyObject>,
+    mut op: F,
+) -> Result<(), SyscallError>
+where
+    T: WorkObject,
+    F: FnMut(Handle<T>) -> Result<(), SyscallError>,
+{
+    unsafe {
+        blocking_pop(queue, |work: Handle<T>| {
+            let pending = work.clone();
+
+            op(work).inspect_err(|_| {
+                kernel_buf::reclaim(pending.as_addr());
+
+                pending.fail(OpStatus::Failed);
+            })
+        })
+    }
+}
+
+fn with_handles<T, F>(f: F) -> Result<T, SyscallError>
+where
+    F: FnOnce(&mut HandleTable) -> Result<T, SyscallError>,
+{
+    let Some(process) = current_task!().process.clone() else {
+        return Err(SyscallError::KernelSpaceCall);
+    };
+
+    let mut state = process.state.lock();
+
+    f(&mut state.handles)
+}
+
+/// Translate user handle to kernel object checking handle rights
+fn lookup_object(
+    handle: UserRawHandle,
+    rights: HandleRights,
+) -> Result<HandleEntry, SyscallError> {
+    with_handles(|handles| handles.get(handle, rights))
+}
+
+fn lookup_handle<T: ObjectContainer>(
+    handle: UserRawHandle,
+    rights: HandleRights,
+) -> Result<UserHandle<T>, SyscallError> {
+    let object = with_handles(|handles| handles.get_of::<T>(handle, rights))?;
+
+    Ok(unsafe { UserHandle::from_addr_unchecked(object) })
+}
+
+/// Move object reference to the handle table of current process
+fn publish_handle(
+    object: VirtualAddress,
+    rights: HandleRights,
+) -> Result<UserRawHandle, SyscallError> {
+    with_handles(|handles| Ok(handles.insert(object, rights)?))
+}
+
+fn publish_work<T: ObjectContainer>(
+    work: Handle<T>,
+) -> Result<kernel_types::object::RawHandle, SyscallError> {
+    let handle = publish_handle(work.as_addr(), HandleRights::READ_WRITE)?;
+
+    //the reference is owned by handle table now
+    let _ = unsafe { work.into_addr() };
+
+    Ok(unsafe { kernel_types::object::RawHandle::new_unchecked(handle) })
+}
+
+/// Replace kernel object address in request with user handle
+fn publish_raw(
+    raw: &mut kernel_types::object::RawHandle,
+    rights: HandleRights,
+) -> Result<(), SyscallError> {
+    let handle = publish_handle(unsafe { raw.syscall() }, rights)?;
+
+    let kernel_raw = core::mem::replace(raw, unsafe {
+        kernel_types::object::RawHandle::new_unchecked(handle)
+    });
+
+    //the reference is owned by handle table now
+    let _ = unsafe { kernel_raw.leak() };
 
     Ok(())
 }
+
+/// Replace user handles in operation with kernel objects
+/// and check user pointers to be written.
+/// The operation owns a reference to each kernel object
+fn translate_io_op(mut op: IoOperation) -> Result<IoOperation, SyscallError> {
+    if let IoOperation::PortOperation(port_op) = &mut op {
+        match port_op {
+            PortOperation::ReadBytesToBuf { buf, .. }
+            | PortOperation::ReadWordsToBuf { buf, .. } => {
+                let object = with_handles(|handles| {
+                    handles.get_of::<KernelBuf>(*buf, HandleRights::WRITE)
+                })?;
+
+                //the handle may be freed while operation is in use
+                *buf = unsafe { clone_raw(object) };
+            }
+            PortOperation::ReadByte { value, .. } => {
+                access::check_range(
+                    *value as VirtualAddress,
+                    mem::size_of::<u8>(),
+                    Access::Write,
+                )?;
+            }
+            PortOperation::ReadWord { value, .. } => {
+                access::check_range(
+                    *value as VirtualAddress,
+                    mem::size_of::<u16>(),
+                    Access::Write,
+                )?;
+            }
+            PortOperation::WriteByte { .. }
+            | PortOperation::WriteWord { .. } => {}
+        }
+    }
+
+    Ok(op)
+}
+
+/// Irq hook is executed outside of process address space,
+/// so it cannot write to user memory.
+/// The hook may touch only ports granted to module
+fn translate_irq_hook(op: IoOperation) -> Result<IrqHook, SyscallError> {
+    let IoOperation::PortOperation(port_op) = &op else {
+        return Ok(unsafe { IrqHook::from_raw(translate_io_op(op)?) });
+    };
+
+    let (port, width) = match port_op {
+        PortOperation::ReadByte { .. } | PortOperation::ReadWord { .. } => {
+            return Err(SyscallError::InvalidData);
+        }
+        PortOperation::WriteByte { port, .. }
+        | PortOperation::ReadBytesToBuf { port, .. } => (*port, 1),
+        PortOperation::WriteWord { port, .. }
+        | PortOperation::ReadWordsToBuf { port, .. } => (*port, 2),
+    };
+
+    let Some(process) = current_task!().process.clone() else {
+        return Err(SyscallError::KernelSpaceCall);
+    };
+
+    if !io::ports::is_granted(&process, port, width) {
+        return Err(SyscallError::AccessDenied);
+    }
+
+    Ok(unsafe { IrqHook::from_raw(translate_io_op(op)?) })
+}
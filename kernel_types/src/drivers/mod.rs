@@ -1,5 +1,6 @@
 mod header;
 mod module;
+pub mod pci;
 
 use core::marker::Tuple;
 use core::mem;
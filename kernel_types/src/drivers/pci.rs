@@ -0,0 +1,146 @@
+use crate::syscall::SyscallError;
+
+/// The resource decoded by device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(C)]
+pub enum PciBar {
+    /// The register is not implemented or
+    /// it holds the upper half of 64-bit address
+    #[default]
+    None,
+    Io {
+        port: u16,
+        size: u32,
+    },
+    Memory {
+        address: u64,
+        size: u64,
+        prefetchable: bool,
+    },
+}
+
+impl PciBar {
+    pub fn is_none(&self) -> bool {
+        matches!(self, Self::None)
+    }
+}
+
+pub const PCI_BARS_COUNT: usize = 6;
+
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct PciDeviceInfo {
+    pub bus: u8,
+    pub device: u8,
+    pub function: u8,
+
+    pub vendor_id: u16,
+    pub device_id: u16,
+    pub class: u8,
+    pub subclass: u8,
+    pub prog_if: u8,
+    pub revision: u8,
+
+    pub bars: [PciBar; PCI_BARS_COUNT],
+    /// The legacy interrupt line routed by firmware (0xFF if none)
+    pub irq_line: u8,
+    /// The interrupt pin INTA..INTD (0 if device doesn't use interrupts)
+    pub irq_pin: u8,
+}
+
+/// The devices served by driver. `None` matches any value
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct PciMatch {
+    pub vendor_id: Option<u16>,
+    pub device_id: Option<u16>,
+    pub class: Option<u8>,
+    pub subclass: Option<u8>,
+}
+
+impl PciMatch {
+    pub const fn device(vendor_id: u16, device_id: u16) -> Self {
+        Self {
+            vendor_id: Some(vendor_id),
+            device_id: Some(device_id),
+            class: None,
+            subclass: None,
+        }
+    }
+
+    pub const fn class(class: u8, subclass: u8) -> Self {
+        Self {
+            vendor_id: None,
+            device_id: None,
+            class: Some(class),
+            subclass: Some(subclass),
+        }
+    }
+
+    pub fn matches(&self, device: &PciDeviceInfo) -> bool {
+        self.vendor_id.is_none_or(|id| id == device.vendor_id)
+            && self.device_id.is_none_or(|id| id == device.device_id)
+            && self.class.is_none_or(|class| class == device.class)
+            && self
+                .subclass
+                .is_none_or(|subclass| subclass == device.subclass)
+    }
+}
+
+/// The raw form of `PciMatch` passed to kernel.
+/// The field is compared only if its bit is set in `fields`
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct RawPciMatch {
+    pub vendor_id: u16,
+    pub device_id: u16,
+    pub class: u8,
+    pub subclass: u8,
+    pub fields: u8,
+}
+
+impl RawPciMatch {
+    pub const VENDOR_ID: u8 = 1 << 0;
+    pub const DEVICE_ID: u8 = 1 << 1;
+    pub const CLASS: u8 = 1 << 2;
+    pub const SUBCLASS: u8 = 1 << 3;
+
+    const ALL_FIELDS: u8 =
+        Self::VENDOR_ID | Self::DEVICE_ID | Self::CLASS | Self::SUBCLASS;
+}
+
+impl From<&PciMatch> for RawPciMatch {
+    fn from(pattern: &PciMatch) -> Self {
+        let field = |is_set: bool, bit: u8| if is_set { bit } else { 0 };
+
+        Self {
+            vendor_id: pattern.vendor_id.unwrap_or_default(),
+            device_id: pattern.device_id.unwrap_or_default(),
+            class: pattern.class.unwrap_or_default(),
+            subclass: pattern.subclass.unwrap_or_default(),
+            fields: field(pattern.vendor_id.is_some(), Self::VENDOR_ID)
+                | field(pattern.device_id.is_some(), Self::DEVICE_ID)
+                | field(pattern.class.is_some(), Self::CLASS)
+                | field(pattern.subclass.is_some(), Self::SUBCLASS),
+        }
+    }
+}
+
+impl TryFrom<RawPciMatch> for PciMatch {
+    type Error = SyscallError;
+
+    fn try_from(raw: RawPciMatch) -> Result<Self, Self::Error> {
+        if raw.fields & !RawPciMatch::ALL_FIELDS != 0 {
+            return Err(SyscallError::InvalidData);
+        }
+
+        let is_set = |bit: u8| raw.fields & bit != 0;
+
+        Ok(Self {
+            vendor_id: is_set(RawPciMatch::VENDOR_ID).then_some(raw.vendor_id),
+            device_id: is_set(RawPciMatch::DEVICE_ID).then_some(raw.device_id),
+            class: is_set(RawPciMatch::CLASS).then_some(raw.class),
+            subclass: is_set(RawPciMatch::SUBCLASS).then_some(raw.subclass),
+        })
+    }
+}
@@ -1,4 +1,5 @@
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
 pub struct FileInfo {
     pub offset: usize,
     pub ctx: *const (),
@@ -1,24 +1,63 @@
 use crate::{
     from_variant,
     object::{OpStatus, RawHandle},
+    syscall::SyscallError,
 };
 
 use super::NodeId;
 
 #[derive(Debug)]
 pub enum FileRequest {
-    Command { file: RawHandle, command: u32 },
-    Read { file: RawHandle, buf: RawHandle },
-    Write { file: RawHandle, buf: RawHandle },
+    Command {
+        file: RawHandle,
+        command: u32,
+    },
+    /// `offset` is the position in file (ignored by character devices)
+    Read {
+        file: RawHandle,
+        buf: RawHandle,
+        offset: usize,
+    },
+    Write {
+        file: RawHandle,
+        buf: RawHandle,
+        offset: usize,
+    },
 }
 
+/// The tags follow the order of variants
 #[derive(Debug, Clone)]
+#[repr(C, u32)]
 pub enum FileResponse {
     File(NodeId),
     OpStatus(OpStatus),
     Completed,
 }
 
+/// The raw form of `FileResponse`. The value
+/// is the node or status, depending on the tag
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct RawFileResponse {
+    pub tag: u32,
+    pub value: u32,
+}
+
+impl TryFrom<RawFileResponse> for FileResponse {
+    type Error = SyscallError;
+
+    fn try_from(raw: RawFileResponse) -> Result<Self, Self::Error> {
+        match raw.tag {
+            0 => Ok(FileResponse::File(raw.value)),
+            1 => OpStatus::try_from(raw.value)
+                .map(FileResponse::OpStatus)
+                .map_err(|_| SyscallError::InvalidData),
+            2 => Ok(FileResponse::Completed),
+            _ => Err(SyscallError::InvalidData),
+        }
+    }
+}
+
 from_variant!(FileResponse, OpStatus);
 
 impl FileResponse {
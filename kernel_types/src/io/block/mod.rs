@@ -1,8 +1,16 @@
 use crate::{
-    from_variant,
+    declare_constants, from_variant,
     object::{OpStatus, RawHandle},
+    syscall::SyscallError,
 };
 
+use super::{DeviceName, RawDeviceName};
+
+declare_constants! {
+    pub u32,
+    CMD_FLUSH = 0x01, "Write cached data of device to the medium";
+}
+
 #[derive(Debug)]
 pub enum Work {
     Read {
@@ -27,18 +35,82 @@ pub struct Request {
 
 #[derive(Debug, Clone)]
 pub struct BlockDeviceInfo {
-    pub name: heapless::String<12>,
+    pub name: DeviceName,
     pub sector_size: usize,
     //deseriable queue size
     pub queue_size: usize,
+    /// The count of disks addressed by `Request::disk`
+    pub disks: usize,
+}
+
+/// The raw form of `BlockDeviceInfo` passed to kernel
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct RawBlockDeviceInfo {
+    pub name: RawDeviceName,
+    pub sector_size: usize,
+    pub queue_size: usize,
+    pub disks: usize,
+}
+
+impl From<&BlockDeviceInfo> for RawBlockDeviceInfo {
+    fn from(info: &BlockDeviceInfo) -> Self {
+        Self {
+            name: RawDeviceName::from(&info.name),
+            sector_size: info.sector_size,
+            queue_size: info.queue_size,
+            disks: info.disks,
+        }
+    }
+}
+
+impl TryFrom<RawBlockDeviceInfo> for BlockDeviceInfo {
+    type Error = SyscallError;
+
+    fn try_from(raw: RawBlockDeviceInfo) -> Result<Self, Self::Error> {
+        if raw.disks == 0 {
+            return Err(SyscallError::InvalidData);
+        }
+
+        Ok(Self {
+            name: raw.name.try_into()?,
+            sector_size: raw.sector_size,
+            queue_size: raw.queue_size,
+            disks: raw.disks,
+        })
+    }
 }
 
+/// The tags follow the order of variants
 #[derive(Debug, Clone)]
+#[repr(C, u32)]
 pub enum Response {
     Completed,
     OpStatus(OpStatus),
 }
 
+/// The raw form of `Response`, the status is set by `OpStatus` variant
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct RawResponse {
+    pub tag: u32,
+    pub status: u32,
+}
+
+impl TryFrom<RawResponse> for Response {
+    type Error = SyscallError;
+
+    fn try_from(raw: RawResponse) -> Result<Self, Self::Error> {
+        match raw.tag {
+            0 => Ok(Response::Completed),
+            1 => OpStatus::try_from(raw.status)
+                .map(Response::OpStatus)
+                .map_err(|_| SyscallError::InvalidData),
+            _ => Err(SyscallError::InvalidData),
+        }
+    }
+}
+
 impl Response {
     pub fn status(self) -> Result<(), OpStatus> {
         match self {
@@ -1,5 +1,37 @@
+use crate::syscall::SyscallError;
+
+use super::{DeviceName, RawDeviceName};
+
 #[derive(Debug, Clone)]
 pub struct CharModuleInfo {
-    pub name: heapless::String<12>,
+    pub name: DeviceName,
     pub ctx: *mut (),
 }
+
+/// The raw form of `CharModuleInfo` passed to kernel
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct RawCharModuleInfo {
+    pub name: RawDeviceName,
+    pub ctx: *mut (),
+}
+
+impl From<&CharModuleInfo> for RawCharModuleInfo {
+    fn from(info: &CharModuleInfo) -> Self {
+        Self {
+            name: RawDeviceName::from(&info.name),
+            ctx: info.ctx,
+        }
+    }
+}
+
+impl TryFrom<RawCharModuleInfo> for CharModuleInfo {
+    type Error = SyscallError;
+
+    fn try_from(raw: RawCharModuleInfo) -> Result<Self, Self::Error> {
+        Ok(Self {
+            name: raw.name.try_into()?,
+            ctx: raw.ctx,
+        })
+    }
+}
@@ -0,0 +1,102 @@
+use crate::syscall::SyscallError;
+
+/// The longest name of device module
+pub const DEVICE_NAME_LEN: usize = 12;
+
+pub type DeviceName = heapless::String<DEVICE_NAME_LEN>;
+
+/// The device name passed to kernel.
+/// The length and encoding are checked when the name is taken back
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct RawDeviceName {
+    pub len: usize,
+    pub bytes: [u8; DEVICE_NAME_LEN],
+}
+
+impl From<&DeviceName> for RawDeviceName {
+    fn from(name: &DeviceName) -> Self {
+        let mut bytes = [0; DEVICE_NAME_LEN];
+        bytes[..name.len()].copy_from_slice(name.as_bytes());
+
+        Self {
+            len: name.len(),
+            bytes,
+        }
+    }
+}
+
+impl TryFrom<RawDeviceName> for DeviceName {
+    type Error = SyscallError;
+
+    fn try_from(raw: RawDeviceName) -> Result<Self, Self::Error> {
+        let bytes =
+            raw.bytes.get(..raw.len).ok_or(SyscallError::InvalidData)?;
+
+        let name = core::str::from_utf8(bytes)
+            .map_err(|_| SyscallError::InvalidData)?;
+
+        let mut string = DeviceName::new();
+        string
+            .push_str(name)
+            .map_err(|_| SyscallError::InvalidData)?;
+
+        Ok(string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+    extern crate std;
+
+    use super::*;
+
+    fn raw_name(bytes: &[u8], len: usize) -> RawDeviceName {
+        let mut raw = RawDeviceName {
+            len,
+            bytes: [0; DEVICE_NAME_LEN],
+        };
+
+        raw.bytes[..bytes.len()].copy_from_slice(bytes);
+
+        raw
+    }
+
+    #[test]
+    fn valid_name_test() {
+        let mut name = DeviceName::new();
+        name.push_str("ata").unwrap();
+
+        let raw = RawDeviceName::from(&name);
+
+        assert_eq!(DeviceName::try_from(raw).unwrap(), name);
+
+        let full = raw_name(b"abcdefghijkl", DEVICE_NAME_LEN);
+
+        assert_eq!(
+            DeviceName::try_from(full).unwrap().as_str(),
+            "abcdefghijkl"
+        );
+        assert!(DeviceName::try_from(raw_name(b"", 0)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn invalid_name_test() {
+        //the length exceeds the buffer
+        assert!(matches!(
+            DeviceName::try_from(raw_name(b"ata", DEVICE_NAME_LEN + 1)),
+            Err(SyscallError::InvalidData)
+        ));
+        assert!(matches!(
+            DeviceName::try_from(raw_name(b"ata", usize::MAX)),
+            Err(SyscallError::InvalidData)
+        ));
+
+        //the name is not UTF-8
+        assert!(matches!(
+            DeviceName::try_from(raw_name(&[b'a', 0xFF], 2)),
+            Err(SyscallError::InvalidData)
+        ));
+    }
+}
@@ -1,4 +1,8 @@
-use super::IoOperation;
+use core::mem::MaybeUninit;
+
+use crate::syscall::SyscallError;
+
+use super::{IoOperation, RawIoOperation};
 
 #[derive(Debug, Clone)]
 #[repr(C)]
@@ -7,6 +11,48 @@ pub struct IrqHandler {
     pub hook: Option<IoOperation>,
 }
 
+/// The raw form of `IrqHandler` passed to kernel.
+/// The hook is set only if `has_hook` is 1
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct RawIrqHandler {
+    pub line: u8,
+    pub has_hook: u8,
+    pub hook: RawIoOperation,
+}
+
+impl From<&IrqHandler> for RawIrqHandler {
+    fn from(handler: &IrqHandler) -> Self {
+        let hook = match handler.hook {
+            Some(op) => RawIoOperation::from(op),
+            None => RawIoOperation(MaybeUninit::uninit()),
+        };
+
+        Self {
+            line: handler.line,
+            has_hook: handler.hook.is_some() as u8,
+            hook,
+        }
+    }
+}
+
+impl TryFrom<RawIrqHandler> for IrqHandler {
+    type Error = SyscallError;
+
+    fn try_from(raw: RawIrqHandler) -> Result<Self, Self::Error> {
+        let hook = match raw.has_hook {
+            0 => None,
+            1 => Some(IoOperation::try_from(raw.hook)?),
+            _ => return Err(SyscallError::InvalidData),
+        };
+
+        Ok(Self {
+            line: raw.line,
+            hook,
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 #[repr(transparent)]
 pub struct IrqMessage {
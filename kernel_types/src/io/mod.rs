@@ -1,13 +1,17 @@
 pub mod block;
 pub mod char;
+mod device_name;
 mod handle;
+mod irq_handler;
 mod mem_buf;
 pub mod op;
+mod ports;
 mod remap;
-mod irq_handler;
 
+pub use device_name::*;
 pub use handle::*;
+pub use irq_handler::*;
 pub use mem_buf::*;
 pub use op::*;
+pub use ports::*;
 pub use remap::*;
-pub use irq_handler::*;
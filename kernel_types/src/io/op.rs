@@ -1,4 +1,11 @@
-#[derive(Debug, Clone)]
+use core::mem::{self, MaybeUninit};
+
+use crate::syscall::SyscallError;
+
+/// The operations have `repr(C, u32)` layout, so kernel
+/// checks their tags in raw form. The tags follow the order of variants
+#[derive(Debug, Clone, Copy)]
+#[repr(C, u32)]
 pub enum MemoryOperation {
     Write {},
 }
@@ -9,7 +16,8 @@ pub enum PortValue {
     Word(u16),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy)]
+#[repr(C, u32)]
 pub enum PortOperation {
     WriteByte { port: u16, value: u8 },
     WriteWord { port: u16, value: u16 },
@@ -21,12 +29,65 @@ pub enum PortOperation {
     ReadWordsToBuf { port: u16, buf: usize },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy)]
+#[repr(C, u32)]
 pub enum IoOperation {
     PortOperation(PortOperation),
     MemoryOperation(MemoryOperation),
 }
 
+const PORT_OPERATIONS: u32 = 6;
+const MEMORY_OPERATIONS: u32 = 1;
+/// The operation follows the tag of its kind and is aligned as
+/// the most aligned operation (the pointers of port operation)
+const OPERATION_TAG_OFFSET: usize = {
+    let align = mem::align_of::<PortOperation>();
+
+    if align > mem::size_of::<u32>() {
+        align
+    } else {
+        mem::size_of::<u32>()
+    }
+};
+
+/// The raw form of `IoOperation` passed to kernel. The fields
+/// of operations are valid for any bits, thus only tags are checked
+#[derive(Debug, Clone, Copy)]
+#[repr(transparent)]
+pub struct RawIoOperation(pub MaybeUninit<IoOperation>);
+
+impl From<IoOperation> for RawIoOperation {
+    fn from(op: IoOperation) -> Self {
+        Self(MaybeUninit::new(op))
+    }
+}
+
+impl TryFrom<RawIoOperation> for IoOperation {
+    type Error = SyscallError;
+
+    fn try_from(raw: RawIoOperation) -> Result<Self, Self::Error> {
+        let tags = raw.0.as_ptr().cast::<u8>();
+        let (kind, op) = unsafe {
+            (
+                tags.cast::<u32>().read(),
+                tags.add(OPERATION_TAG_OFFSET).cast::<u32>().read(),
+            )
+        };
+
+        let is_valid = match kind {
+            0 => op < PORT_OPERATIONS,
+            1 => op < MEMORY_OPERATIONS,
+            _ => false,
+        };
+
+        if !is_valid {
+            return Err(SyscallError::InvalidData);
+        }
+
+        Ok(unsafe { raw.0.assume_init() })
+    }
+}
+
 impl From<PortOperation> for IoOperation {
     fn from(value: PortOperation) -> Self {
         Self::PortOperation(value)
@@ -38,3 +99,69 @@ impl From<MemoryOperation> for IoOperation {
         Self::MemoryOperation(value)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+    extern crate std;
+
+    use super::*;
+
+    fn raw_with_tags(kind: u32, op: u32) -> RawIoOperation {
+        let mut raw = RawIoOperation::from(IoOperation::PortOperation(
+            PortOperation::ReadWordsToBuf { port: 0, buf: 0 },
+        ));
+
+        let tags = raw.0.as_mut_ptr().cast::<u8>();
+
+        unsafe {
+            tags.cast::<u32>().write(kind);
+            tags.add(OPERATION_TAG_OFFSET).cast::<u32>().write(op);
+        }
+
+        raw
+    }
+
+    #[test]
+    fn valid_operation_test() {
+        let op = PortOperation::WriteWord {
+            port: 0x1F0,
+            value: 0xABCD,
+        };
+
+        let raw = RawIoOperation::from(IoOperation::from(op));
+
+        assert!(matches!(
+            IoOperation::try_from(raw),
+            Ok(IoOperation::PortOperation(PortOperation::WriteWord {
+                port: 0x1F0,
+                value: 0xABCD
+            }))
+        ));
+
+        let raw =
+            RawIoOperation::from(IoOperation::from(MemoryOperation::Write {}));
+
+        assert!(matches!(
+            IoOperation::try_from(raw),
+            Ok(IoOperation::MemoryOperation(MemoryOperation::Write {}))
+        ));
+
+        assert!(IoOperation::try_from(raw_with_tags(0, 5)).is_ok());
+    }
+
+    #[test]
+    fn invalid_tags_test() {
+        for (kind, op) in [(0, PORT_OPERATIONS), (1, MEMORY_OPERATIONS), (2, 0)]
+        {
+            assert!(matches!(
+                IoOperation::try_from(raw_with_tags(kind, op)),
+                Err(SyscallError::InvalidData)
+            ));
+        }
+
+        assert!(
+            IoOperation::try_from(raw_with_tags(u32::MAX, u32::MAX)).is_err()
+        );
+    }
+}
@@ -0,0 +1,88 @@
+/// The continuous range of I/O ports granted to module
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct PortRange {
+    pub base: u16,
+    pub count: u16,
+}
+
+impl PortRange {
+    pub const fn new(base: u16, count: u16) -> Self {
+        Self { base, count }
+    }
+
+    /// The port after the last one in range
+    pub const fn end(&self) -> u32 {
+        self.base as u32 + self.count as u32
+    }
+
+    pub const fn contains(&self, port: u16) -> bool {
+        port >= self.base && (port as u32) < self.end()
+    }
+
+    pub const fn overlaps(&self, other: &Self) -> bool {
+        (self.base as u32) < other.end() && (other.base as u32) < self.end()
+    }
+}
+
+/// The legacy ports declared by module in `io_ports` symbol.
+/// The loader keeps them, only these ports are granted on request
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct PortsDeclaration {
+    pub ranges: *const PortRange,
+    pub count: usize,
+}
+
+//the declaration points to immutable static ranges
+unsafe impl Sync for PortsDeclaration {}
+
+impl PortsDeclaration {
+    pub const fn new(ranges: &'static [PortRange]) -> Self {
+        Self {
+            ranges: ranges.as_ptr(),
+            count: ranges.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+    extern crate std;
+
+    use super::*;
+
+    #[test]
+    fn contains_test() {
+        let range = PortRange::new(0x1F0, 8);
+
+        assert!(range.contains(0x1F0));
+        assert!(range.contains(0x1F7));
+        assert!(!range.contains(0x1EF));
+        assert!(!range.contains(0x1F8));
+
+        //the end is past the last port
+        let last = PortRange::new(u16::MAX, 1);
+
+        assert_eq!(last.end(), u16::MAX as u32 + 1);
+        assert!(last.contains(u16::MAX));
+
+        assert!(!PortRange::new(0x60, 0).contains(0x60));
+    }
+
+    #[test]
+    fn overlaps_test() {
+        let range = PortRange::new(0x1F0, 8);
+
+        assert!(range.overlaps(&range));
+        assert!(range.overlaps(&PortRange::new(0x1F7, 2)));
+        assert!(range.overlaps(&PortRange::new(0x1E0, 0x20)));
+        assert!(PortRange::new(0x1E0, 0x11).overlaps(&range));
+
+        //the adjacent ranges share no port
+        assert!(!range.overlaps(&PortRange::new(0x1F8, 1)));
+        assert!(!range.overlaps(&PortRange::new(0x1E0, 0x10)));
+        assert!(!range.overlaps(&PortRange::new(0x1F4, 0)));
+    }
+}
@@ -1,6 +1,6 @@
 /// remap physical memory with read-write permissions
 /// physical memory can be controlled only via single user
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy)]
 #[repr(C)]
 pub struct MemoryRemap {
     pub physical_start: usize,
@@ -14,10 +14,12 @@ pub mod collections;
 pub mod drivers;
 pub mod fs;
 pub mod io;
+pub mod memory;
 pub mod object;
 pub mod string;
 pub mod syscall;
 pub mod task;
+pub mod time;
 
 extern crate alloc;
 
@@ -0,0 +1,61 @@
+//! Mappings of memory into process address space
+
+bitflags::bitflags! {
+    /// The access to mapped pages
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[repr(transparent)]
+    pub struct Protection: u32 {
+        const READ = 0x01;
+        const WRITE = 0x02;
+        const EXEC = 0x04;
+
+        const READ_WRITE = Self::READ.bits() | Self::WRITE.bits();
+    }
+}
+
+/// The request to map zeroed pages not backed by any file
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct AnonymousMap {
+    /// The size of mapping, rounded up to pages
+    pub len: usize,
+    pub protection: Protection,
+}
+
+/// The request to change the access to mapped pages
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct ProtectRange {
+    /// The start of range, aligned to pages
+    pub address: usize,
+    /// The size of range, rounded up to pages
+    pub len: usize,
+    /// The new access, no access makes guard pages
+    pub protection: Protection,
+}
+
+bitflags::bitflags! {
+    /// Whether the writes to file mapping reach the file
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[repr(transparent)]
+    pub struct MapFlags: u32 {
+        /// The writes are flushed back to file
+        const SHARED = 0x01;
+        /// The writes stay in the private copy of process
+        const PRIVATE = 0x02;
+    }
+}
+
+/// The request to map the part of opened file
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct FileMap {
+    /// The index of file opened by task
+    pub file: usize,
+    /// The position in file, aligned to pages
+    pub offset: usize,
+    /// The size of mapping, rounded up to pages
+    pub len: usize,
+    pub protection: Protection,
+    pub flags: MapFlags,
+}
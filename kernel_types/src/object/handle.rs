@@ -58,15 +58,17 @@ impl RawHandle {
     }
 
     pub fn try_clone(&self) -> Result<Self, syscall::SyscallError> {
+        let mut handle: usize = 0;
+
         unsafe {
             syscall! {
                 syscall::Request::CloneHandle,
-                edx:
-                    self.syscall()
+                ecx: &mut handle,
+                edx: self.syscall()
             }?;
         }
 
-        Ok(unsafe { RawHandle::new_unchecked(self.syscall()) })
+        Ok(unsafe { RawHandle::new_unchecked(handle) })
     }
 }
 
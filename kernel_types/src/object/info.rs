@@ -0,0 +1,20 @@
+use crate::{fs::FileInfo, io::MemBuf};
+
+/// Common description of any kernel object
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct ObjectInfo {
+    pub kind: u8,
+    pub status: u8,
+    pub ref_count: u16,
+}
+
+/// The description written by `GetObjectInfo`.
+/// The tag tells which description the object has
+#[derive(Debug, Clone, Copy)]
+#[repr(C, u32)]
+pub enum AnyObjectInfo {
+    Buf(MemBuf),
+    File(FileInfo),
+    Object(ObjectInfo),
+}
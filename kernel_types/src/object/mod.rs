@@ -1,7 +1,9 @@
 mod handle;
+mod info;
 mod queue;
 mod status;
 
 pub use handle::*;
+pub use info::*;
 pub use queue::*;
 pub use status::*;
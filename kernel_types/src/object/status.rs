@@ -1,4 +1,5 @@
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, num_enum::TryFromPrimitive)]
+#[repr(u32)]
 pub enum OpStatus {
     Failed,
     NotFound,
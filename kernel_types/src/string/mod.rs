@@ -5,6 +5,7 @@ use core::str::from_utf8_unchecked;
 use core::{ptr, slice};
 
 use crate::collections::{FastHasher, HashCode, HashKey, PolynomialHasher};
+use crate::syscall::SyscallError;
 
 //alternative to linux qstr
 #[derive(PartialEq, Eq, Clone)]
@@ -75,6 +76,28 @@ pub struct MutString<'a> {
     _marker: PhantomData<&'a mut u8>,
 }
 
+/// The raw form of `MutString` read by kernel
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct RawMutString {
+    pub len: usize,
+    pub capacity: usize,
+    pub data: usize,
+}
+
+impl TryFrom<RawMutString> for MutString<'_> {
+    type Error = SyscallError;
+
+    /// The data isn't accessed, the caller checks it before reading
+    fn try_from(raw: RawMutString) -> Result<Self, Self::Error> {
+        if raw.len > raw.capacity {
+            return Err(SyscallError::InvalidData);
+        }
+
+        Ok(unsafe { Self::new(raw.len, raw.capacity, raw.data as *mut u8) })
+    }
+}
+
 impl<'a> MutString<'a> {
     pub unsafe fn with_capacity(capacity: usize, data: *mut u8) -> Self {
         let len = 0;
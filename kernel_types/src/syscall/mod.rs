@@ -14,6 +14,22 @@ pub enum Request {
     PrintK = 0x02,
     /// map physical memory to virtual memory in driver
     MemRemap = 0x03,
+    /// read clock given by `ClockType`
+    GetTime = 0x04,
+    /// stop modules, sync file systems and power off the machine
+    Shutdown = 0x05,
+    /// stop modules, sync file systems and reset the machine
+    Reboot = 0x06,
+    /// map zeroed memory given by `AnonymousMap` into process
+    MapAnonymous = 0x07,
+    /// unmap the whole region starting at given address
+    Unmap = 0x08,
+    /// map the part of opened file given by `FileMap` into process
+    MapFile = 0x09,
+    /// clone the calling task into new process sharing memory copy-on-write
+    Fork = 0x0A,
+    /// change the access to pages given by `ProtectRange`
+    Protect = 0x0B,
 
     RegBlockDevice = MIN_MODULE_REQUEST_CODE,
     RegCharDevice,
@@ -21,7 +37,8 @@ pub enum Request {
 
     CloneHandle,
 
-    IoOperation,
+    /// allow module to access I/O ports given by `PortRange` list
+    RequestIoPorts,
 
     //copy from kernel space to user space
     KernelCopy,
@@ -52,6 +69,14 @@ pub enum Request {
     EventBlock,
     EventNotifyOne,
     EventNotifyAll,
+
+    //timer operations
+    TimerNew,
+    TimerStart,
+    TimerCancel,
+
+    /// take unbound PCI device matching `PciMatch`
+    PciBind,
 }
 
 impl Request {
@@ -82,6 +107,12 @@ pub enum SyscallError {
 
     NoSpaceInBuffer = 10,
     InvalidObjectKind = 11,
+    /// The handle is not present in process handle table
+    InvalidHandle = 12,
+    /// The handle has no rights for operation
+    AccessDenied = 13,
+    /// No free device matches request
+    DeviceIsNotFound = 14,
 
     #[num_enum(default)]
     Failed = 0x42,
@@ -1,5 +1,7 @@
 pub type FnTask = extern "C" fn(*const ());
 
+use crate::syscall::SyscallError;
+
 #[derive(Debug, Clone)]
 #[repr(C)]
 pub struct TaskParams {
@@ -7,3 +9,30 @@ pub struct TaskParams {
     pub routine: FnTask,
     pub nice: u16,
 }
+
+/// The raw form of `TaskParams`, the routine may be null
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct RawTaskParams {
+    pub args: usize,
+    pub routine: usize,
+    pub nice: u16,
+}
+
+impl TryFrom<RawTaskParams> for TaskParams {
+    type Error = SyscallError;
+
+    fn try_from(raw: RawTaskParams) -> Result<Self, Self::Error> {
+        if raw.routine == 0 {
+            return Err(SyscallError::InvalidData);
+        }
+
+        Ok(Self {
+            args: raw.args as *const (),
+            routine: unsafe {
+                core::mem::transmute::<usize, FnTask>(raw.routine)
+            },
+            nice: raw.nice,
+        })
+    }
+}
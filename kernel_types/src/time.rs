@@ -0,0 +1,133 @@
+use core::time::Duration;
+
+use crate::syscall::SyscallError;
+
+const NANOS_PER_SEC: u32 = 1_000_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, num_enum::TryFromPrimitive)]
+#[repr(u32)]
+pub enum ClockType {
+    /// The time since epoch
+    Epoch,
+    /// The time from system boot
+    Boot,
+    /// The total time consumed by current thread
+    ThreadConsumed,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(C)]
+pub struct TimeSpec {
+    pub secs: u64,
+    pub nanos: u32,
+}
+
+impl TimeSpec {
+    pub const fn as_millis(&self) -> u64 {
+        self.secs * 1_000 + self.nanos as u64 / 1_000_000
+    }
+
+    /// `true` if nanoseconds are less than second
+    /// and the time is representable in milliseconds
+    pub const fn is_valid(&self) -> bool {
+        self.nanos < NANOS_PER_SEC && self.secs < u64::MAX / 1_000
+    }
+}
+
+impl From<Duration> for TimeSpec {
+    fn from(value: Duration) -> Self {
+        Self {
+            secs: value.as_secs(),
+            nanos: value.subsec_nanos(),
+        }
+    }
+}
+
+/// The arming parameters of timer object
+#[derive(Debug, Default, Clone, Copy)]
+#[repr(C)]
+pub struct TimerSpec {
+    /// The time before the first fire
+    pub delay: TimeSpec,
+    /// The time between fires. Zero for one-shot timer
+    pub period: TimeSpec,
+}
+
+/// The raw form of `TimerSpec`, the times aren't checked yet
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct RawTimerSpec {
+    pub delay: TimeSpec,
+    pub period: TimeSpec,
+}
+
+impl TryFrom<RawTimerSpec> for TimerSpec {
+    type Error = SyscallError;
+
+    fn try_from(raw: RawTimerSpec) -> Result<Self, Self::Error> {
+        if !raw.delay.is_valid() || !raw.period.is_valid() {
+            return Err(SyscallError::InvalidData);
+        }
+
+        Ok(Self {
+            delay: raw.delay,
+            period: raw.period,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+    extern crate std;
+
+    use super::*;
+
+    const fn time(secs: u64, nanos: u32) -> TimeSpec {
+        TimeSpec { secs, nanos }
+    }
+
+    #[test]
+    fn valid_spec_test() {
+        let raw = RawTimerSpec {
+            delay: time(1, NANOS_PER_SEC - 1),
+            period: time(0, 0),
+        };
+
+        let spec = TimerSpec::try_from(raw).unwrap();
+
+        assert_eq!(spec.delay, raw.delay);
+        assert_eq!(spec.period, raw.period);
+        assert_eq!(spec.delay.as_millis(), 1_999);
+    }
+
+    #[test]
+    fn invalid_spec_test() {
+        let valid = time(1, 0);
+
+        let invalid = [
+            time(0, NANOS_PER_SEC),
+            time(0, u32::MAX),
+            time(u64::MAX / 1_000, 0),
+            time(u64::MAX, 0),
+        ];
+
+        for invalid_time in invalid {
+            for raw in [
+                RawTimerSpec {
+                    delay: invalid_time,
+                    period: valid,
+                },
+                RawTimerSpec {
+                    delay: valid,
+                    period: invalid_time,
+                },
+            ] {
+                assert!(matches!(
+                    TimerSpec::try_from(raw),
+                    Err(SyscallError::InvalidData)
+                ));
+            }
+        }
+    }
+}